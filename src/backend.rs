@@ -0,0 +1,302 @@
+//! Read-only repository queries behind a swappable backend. Everything that mutates
+//! the repo (staging, committing with hooks, pull/push with credential helpers) stays
+//! on the git CLI so it behaves exactly like the user's own git.
+//!
+//! Only the queries on [`GitBackend`] have a libgit2 implementation, and only with the
+//! non-default `libgit2` feature; the rest of the reading (patches, added lines, status)
+//! is git CLI output in every build, so `git` on PATH stays a requirement.
+
+use anyhow::{Context, Result, anyhow, bail};
+use once_cell::sync::Lazy;
+use std::env;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crate::diff_parse::RawNumstat;
+use crate::session;
+use crate::{FileChange, categorize, git_output, git_stream, log_command};
+
+pub trait GitBackend: Send + Sync {
+    fn repo_root(&self) -> Result<PathBuf>;
+    fn has_staged_changes(&self) -> Result<bool>;
+    fn staged_changes(&self) -> Result<Vec<FileChange>>;
+    /// Short name of the checked-out branch, or None when HEAD is detached.
+    fn current_branch(&self) -> Result<Option<String>>;
+    /// Upstream of the current branch as `remote/branch`.
+    fn upstream_branch(&self) -> Result<Option<String>>;
+    fn ahead_behind(&self, upstream: &str) -> Result<(u32, u32)>;
+}
+
+static BACKEND: Lazy<Box<dyn GitBackend>> = Lazy::new(|| {
+    let requested = env::var("SCOMMIT_GIT_BACKEND").ok();
+    match select(requested.as_deref(), cfg!(feature = "libgit2")) {
+        #[cfg(feature = "libgit2")]
+        "libgit2" => Box::new(libgit2::Libgit2Backend),
+        _ => Box::new(CliBackend),
+    }
+});
+
+pub fn get() -> &'static dyn GitBackend {
+    BACKEND.as_ref()
+}
+
+/// libgit2 when compiled in, unless SCOMMIT_GIT_BACKEND=cli asks for the git CLI.
+fn select(requested: Option<&str>, libgit2_built: bool) -> &'static str {
+    match requested {
+        Some("cli") => "cli",
+        Some("libgit2") if !libgit2_built => {
+            eprintln!(
+                "SCOMMIT_GIT_BACKEND=libgit2 needs the `libgit2` feature; using the git CLI."
+            );
+            "cli"
+        }
+        _ if libgit2_built => "libgit2",
+        _ => "cli",
+    }
+}
+
+pub struct CliBackend;
+
+impl GitBackend for CliBackend {
+    fn repo_root(&self) -> Result<PathBuf> {
+        let out = git_output(&["rev-parse", "--show-toplevel"])?;
+        let path = out.trim();
+        if path.is_empty() {
+            bail!("Could not resolve repository root");
+        }
+        Ok(PathBuf::from(path))
+    }
+
+    fn has_staged_changes(&self) -> Result<bool> {
+        let mut cmd = session::command("git");
+        cmd.args(["diff", "--cached", "--quiet"]);
+        let started = Instant::now();
+        let status = cmd.status().context("checking staged changes")?;
+        log_command(&cmd, Some(status), started);
+        Ok(!status.success())
+    }
+
+    fn staged_changes(&self) -> Result<Vec<FileChange>> {
+        let diff_args = crate::config::get().diff.args();
+        let mut args = vec!["diff", "--cached", "--raw", "--numstat", "-z"];
+        args.extend(diff_args.iter().map(String::as_str));
+        let mut parser = RawNumstat::default();
+        git_stream(&args, b'\0', |field| {
+            parser.push(field);
+            ControlFlow::Continue(())
+        })?;
+        Ok(parser
+            .finish()
+            .into_iter()
+            .map(|entry| FileChange {
+                category: categorize(&entry.path),
+                path: entry.path,
+                status: entry.status,
+                added: entry.added,
+                deleted: entry.deleted,
+            })
+            .collect())
+    }
+
+    fn current_branch(&self) -> Result<Option<String>> {
+        let mut cmd = session::command("git");
+        cmd.args(["symbolic-ref", "--quiet", "--short", "HEAD"]);
+        let started = Instant::now();
+        let output = cmd.output().context("running git symbolic-ref")?;
+        log_command(&cmd, Some(output.status), started);
+        let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok((output.status.success() && !name.is_empty()).then_some(name))
+    }
+
+    fn upstream_branch(&self) -> Result<Option<String>> {
+        let mut cmd = session::command("git");
+        cmd.args(["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"]);
+        let started = Instant::now();
+        let output = cmd.output();
+        log_command(&cmd, output.as_ref().ok().map(|o| o.status), started);
+
+        match output {
+            Ok(out) if out.status.success() => {
+                let s = String::from_utf8_lossy(&out.stdout).trim().to_string();
+                if s.is_empty() { Ok(None) } else { Ok(Some(s)) }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn ahead_behind(&self, upstream: &str) -> Result<(u32, u32)> {
+        let range = format!("HEAD...{}", upstream);
+        let out = git_output(&["rev-list", "--left-right", "--count", &range])?;
+        let mut parts = out.split_whitespace();
+        let ahead = parts
+            .next()
+            .ok_or_else(|| anyhow!("unexpected rev-list output"))?
+            .parse()
+            .unwrap_or(0);
+        let behind = parts.next().unwrap_or("0").parse().unwrap_or(0);
+        Ok((ahead, behind))
+    }
+}
+
+#[cfg(feature = "libgit2")]
+mod libgit2 {
+    use anyhow::{Context, Result, anyhow};
+    use git2::{BranchType, Delta, DiffFindOptions, DiffOptions, ErrorCode, Repository};
+    use std::env;
+    use std::path::{Path, PathBuf};
+
+    use super::GitBackend;
+    use crate::session;
+    use crate::{FileChange, FileStatus, categorize};
+
+    /// Opens the repository per call: `git2::Repository` isn't `Sync`, and opening is
+    /// cheap next to the diff work itself.
+    pub struct Libgit2Backend;
+
+    fn open() -> Result<Repository> {
+        match session::root() {
+            Some(root) => Repository::open(root),
+            None => Repository::open_from_env(),
+        }
+        .context("opening repository with libgit2")
+    }
+
+    fn staged_diff(repo: &Repository) -> Result<git2::Diff<'_>> {
+        let head_tree = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(e) if e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound => {
+                None
+            }
+            Err(e) => return Err(e.into()),
+        };
+        // libgit2 ignores GIT_INDEX_FILE, which hooks and --print-only rely on.
+        let index = match env::var_os("GIT_INDEX_FILE") {
+            Some(path) => Some(git2::Index::open(Path::new(&path))?),
+            None => None,
+        };
+        // The same -M/-C detection and -w the CLI backend asks git for.
+        let config = &crate::config::get().diff;
+        let mut options = DiffOptions::new();
+        options.ignore_whitespace(config.ignore_whitespace);
+        let mut diff =
+            repo.diff_tree_to_index(head_tree.as_ref(), index.as_ref(), Some(&mut options))?;
+        let similarity = u16::from(config.similarity.min(100));
+        diff.find_similar(Some(
+            DiffFindOptions::new()
+                .renames(true)
+                .rename_threshold(similarity)
+                .copies(config.copies)
+                .copy_threshold(similarity),
+        ))?;
+        Ok(diff)
+    }
+
+    impl GitBackend for Libgit2Backend {
+        fn repo_root(&self) -> Result<PathBuf> {
+            let repo = open()?;
+            repo.workdir()
+                .map(|p| p.to_path_buf())
+                .ok_or_else(|| anyhow!("Could not resolve repository root (bare repository)"))
+        }
+
+        fn has_staged_changes(&self) -> Result<bool> {
+            let repo = open()?;
+            Ok(staged_diff(&repo)?.deltas().len() > 0)
+        }
+
+        fn staged_changes(&self) -> Result<Vec<FileChange>> {
+            let repo = open()?;
+            let diff = staged_diff(&repo)?;
+            let mut changes = Vec::new();
+            for (idx, delta) in diff.deltas().enumerate() {
+                let path_of = |file: git2::DiffFile<'_>| -> Result<String> {
+                    let bytes = file.path_bytes().unwrap_or_default();
+                    std::str::from_utf8(bytes)
+                        .map(str::to_string)
+                        .map_err(|e| crate::diff_parse::non_utf8_path(bytes, e.valid_up_to()))
+                };
+                let old = path_of(delta.old_file())?;
+                let new = path_of(delta.new_file())?;
+                let (status, path) = match delta.status() {
+                    Delta::Added => (FileStatus::Added, new),
+                    Delta::Deleted => (FileStatus::Deleted, old),
+                    Delta::Renamed => (
+                        FileStatus::Renamed {
+                            from: old,
+                            to: new.clone(),
+                        },
+                        new,
+                    ),
+                    Delta::Copied => (
+                        FileStatus::Copied {
+                            from: old,
+                            to: new.clone(),
+                        },
+                        new,
+                    ),
+                    Delta::Typechange => (FileStatus::TypeChanged, new),
+                    _ => (FileStatus::Modified, new),
+                };
+                let (added, deleted) = match git2::Patch::from_diff(&diff, idx)? {
+                    Some(patch) => {
+                        let (_, added, deleted) = patch.line_stats()?;
+                        (added as u32, deleted as u32)
+                    }
+                    None => (0, 0),
+                };
+                changes.push(FileChange {
+                    category: categorize(&path),
+                    path,
+                    status,
+                    added,
+                    deleted,
+                });
+            }
+            Ok(changes)
+        }
+
+        fn current_branch(&self) -> Result<Option<String>> {
+            let repo = open()?;
+            let head = repo.find_reference("HEAD")?;
+            Ok(head
+                .symbolic_target()?
+                .and_then(|t| t.strip_prefix("refs/heads/"))
+                .map(str::to_string))
+        }
+
+        fn upstream_branch(&self) -> Result<Option<String>> {
+            let Some(name) = self.current_branch()? else {
+                return Ok(None);
+            };
+            let repo = open()?;
+            let branch = repo.find_branch(&name, BranchType::Local)?;
+            let upstream = match branch.upstream() {
+                Ok(upstream) => upstream,
+                Err(_) => return Ok(None),
+            };
+            Ok(upstream.name()?.map(str::to_string))
+        }
+
+        fn ahead_behind(&self, upstream: &str) -> Result<(u32, u32)> {
+            let repo = open()?;
+            let local = repo.head()?.peel_to_commit()?.id();
+            let remote = repo.revparse_single(upstream)?.peel_to_commit()?.id();
+            let (ahead, behind) = repo.graph_ahead_behind(local, remote)?;
+            Ok((ahead as u32, behind as u32))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_prefers_libgit2_only_when_built() {
+        assert_eq!(select(None, false), "cli");
+        assert_eq!(select(None, true), "libgit2");
+        assert_eq!(select(Some("cli"), true), "cli");
+        assert_eq!(select(Some("libgit2"), false), "cli");
+    }
+}
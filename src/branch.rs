@@ -0,0 +1,256 @@
+//! `scommit branch`: suggest a name for the branch the work in progress belongs on, from
+//! the uncommitted changes or a description of the work, shaped by `[branches] pattern`.
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use std::env;
+use std::fs;
+
+use crate::output::say;
+use crate::reword::CommitView;
+use crate::{
+    Cli, ai_model, build_commit_message, chat_completion, collect_staged_changes, compose_message,
+    compute_stats, config, git, git_output, git_path, sanitize_json_blob, secrets,
+    staged_added_lines, watch,
+};
+
+const SYSTEM_PROMPT: &str = "You name git branches. Given a short description of upcoming work, reply with a JSON object {\"type\": string, \"scope\": string, \"description\": string}: type is a conventional commit type (feat, fix, docs, refactor, perf, test, build, ci or chore), scope is one word for the area of the code or \"\" when unclear, and description is two to five lowercase words saying what the work does.";
+
+/// Longest slug kept for a description; branch names get typed and read in lists.
+const MAX_SLUG: usize = 40;
+
+#[derive(Debug, Default, Deserialize)]
+struct Suggestion {
+    #[serde(default, rename = "type")]
+    kind: String,
+    #[serde(default)]
+    scope: String,
+    #[serde(default)]
+    description: String,
+}
+
+pub fn run(cli: &Cli, description: &[String], create: bool) -> Result<()> {
+    let suggestion = if description.is_empty() {
+        from_changes(cli)?
+    } else {
+        from_description(cli, &description.join(" "))
+    };
+    let name = render(&config::get().branches.pattern, &suggestion);
+    if name.is_empty() || git_output(&["check-ref-format", "--branch", &name]).is_err() {
+        bail!("'{name}' is not a valid branch name; check [branches] pattern");
+    }
+    let name = unused(&name);
+    if !create {
+        println!("{name}");
+        return Ok(());
+    }
+    if cli.dry_run {
+        say!("Would create and switch to branch {name}");
+        return Ok(());
+    }
+    git(&["switch", "--create", &name])
+}
+
+/// Name the uncommitted changes (staged, unstaged and untracked) as a commit of them
+/// would be named, without touching the index.
+fn from_changes(cli: &Cli) -> Result<Suggestion> {
+    let describe = "describe the work instead, e.g. `scommit branch add login form`";
+    if git_output(&["rev-parse", "--verify", "--quiet", "HEAD"]).is_err() {
+        bail!("there is no commit to compare the changes with yet; {describe}");
+    }
+    let index = git_path("scommit/branch-index")?;
+    fs::remove_file(&index).ok();
+    let tree = watch::snapshot(&index);
+    fs::remove_file(&index).ok();
+    let tree = tree?;
+    if tree == git_output(&["rev-parse", "HEAD^{tree}"])?.trim() {
+        bail!("there are no uncommitted changes to name a branch after; {describe}");
+    }
+
+    let _view = CommitView::enter("HEAD", &tree)?;
+    let changes = collect_staged_changes()?;
+    let stats = compute_stats(&changes);
+    // As for commits, the diff only goes to the AI provider past the secrets check.
+    let (subject, _) = match secrets::check(&staged_added_lines()?, false) {
+        Ok(()) => compose_message(cli, &changes, &stats, None)?,
+        Err(_) => build_commit_message(&changes, &stats),
+    };
+    Ok(parse_subject(&subject))
+}
+
+fn from_description(cli: &Cli, text: &str) -> Suggestion {
+    if let Some(model) = ai_model(cli) {
+        match suggest(&model, text) {
+            Ok(suggestion) if !suggestion.description.trim().is_empty() => return suggestion,
+            Ok(_) => eprintln!("The AI suggestion was empty; naming the branch from your words."),
+            Err(e) => eprintln!("AI suggestion failed ({e}); naming the branch from your words."),
+        }
+    }
+    let kind = guess_type(text);
+    // "fix the login loop" → fix/login-loop: the type and articles add nothing.
+    let description: Vec<&str> = text
+        .split_whitespace()
+        .enumerate()
+        .filter(|(i, word)| {
+            let word = word.to_ascii_lowercase();
+            let filler = (*i == 0 && word == kind) || matches!(word.as_str(), "a" | "an" | "the");
+            !filler
+        })
+        .map(|(_, word)| word)
+        .collect();
+    Suggestion {
+        kind: kind.to_string(),
+        scope: String::new(),
+        description: description.join(" "),
+    }
+}
+
+fn suggest(model: &str, text: &str) -> Result<Suggestion> {
+    let key = env::var("OPENAI_API_KEY")?;
+    let reply = chat_completion(&key, model, SYSTEM_PROMPT, text, true, 100)?
+        .ok_or_else(|| anyhow!("no reply"))?;
+    let blob = sanitize_json_blob(&reply).ok_or_else(|| anyhow!("AI reply wasn't JSON"))?;
+    serde_json::from_str(&blob).context("decoding AI branch suggestion")
+}
+
+/// `feat(auth)!: add login form` → type feat, scope auth, description "add login form".
+fn parse_subject(subject: &str) -> Suggestion {
+    if let Some((prefix, description)) = subject.split_once(':')
+        && !prefix.contains(' ')
+    {
+        let prefix = prefix.trim_end_matches('!');
+        let (kind, scope) = match prefix.split_once('(') {
+            Some((kind, scope)) => (kind, scope.trim_end_matches(')')),
+            None => (prefix, ""),
+        };
+        return Suggestion {
+            kind: kind.to_string(),
+            scope: scope.to_string(),
+            description: description.trim().to_string(),
+        };
+    }
+    Suggestion {
+        kind: guess_type(subject).to_string(),
+        scope: String::new(),
+        description: subject.to_string(),
+    }
+}
+
+/// A conventional type for plain words, from the verb they start with.
+fn guess_type(text: &str) -> &'static str {
+    let first = text
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match first.as_str() {
+        "fix" | "fixes" | "fixed" | "repair" | "resolve" | "handle" | "correct" => "fix",
+        "doc" | "docs" | "document" | "documentation" | "readme" => "docs",
+        "refactor" | "rename" | "move" | "extract" | "simplify" | "clean" | "cleanup"
+        | "restructure" | "reorganize" => "refactor",
+        "test" | "tests" | "cover" => "test",
+        "speed" | "optimize" | "optimise" | "cache" | "perf" => "perf",
+        "bump" | "upgrade" | "chore" => "chore",
+        _ => "feat",
+    }
+}
+
+/// Lowercase ASCII words joined by `-`, stopping before `max` characters.
+pub fn slug(text: &str, max: usize) -> String {
+    let mut slug = String::new();
+    for word in text
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        if slug.len() + word.len() + 1 > max {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word.to_ascii_lowercase());
+    }
+    slug
+}
+
+/// Fill in `{type}`, `{scope}` and `{slug}`. A part that comes out empty takes its
+/// separators with it, so `{type}/{scope}-{slug}` without a scope is `feat/add-login`.
+fn render(pattern: &str, suggestion: &Suggestion) -> String {
+    let mut description = slug(&suggestion.description, MAX_SLUG);
+    if description.is_empty() {
+        description.push_str("changes");
+    }
+    let filled = pattern
+        .replace("{type}", &slug(&suggestion.kind, MAX_SLUG))
+        .replace("{scope}", &slug(&suggestion.scope, MAX_SLUG))
+        .replace("{slug}", &description);
+    filled
+        .split('/')
+        .map(|segment| {
+            segment
+                .split('-')
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// `name`, or `name-2`, `name-3`, ... when a branch already has it.
+fn unused(name: &str) -> String {
+    let taken = |candidate: &str| {
+        git_output(&[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("refs/heads/{candidate}"),
+        ])
+        .is_ok()
+    };
+    if !taken(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|n| format!("{name}-{n}"))
+        .find(|candidate| !taken(candidate))
+        .unwrap_or_else(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn names_follow_the_pattern() {
+        let pattern = "{type}/{scope}-{slug}";
+        assert_eq!(
+            render(pattern, &parse_subject("feat(auth): add login form")),
+            "feat/auth-add-login-form"
+        );
+        assert_eq!(
+            render(pattern, &parse_subject("fix!: handle empty input")),
+            "fix/handle-empty-input"
+        );
+        assert_eq!(
+            render(pattern, &parse_subject("Rename the config loader")),
+            "refactor/rename-the-config-loader"
+        );
+        assert_eq!(
+            render(
+                pattern,
+                &from_description(
+                    &Cli::parse_from(["scommit", "--no-ai"]),
+                    "Fix the login loop"
+                )
+            ),
+            "fix/login-loop"
+        );
+        assert_eq!(
+            render("wip/{slug}", &parse_subject("docs: explain --dry-run")),
+            "wip/explain-dry-run"
+        );
+    }
+}
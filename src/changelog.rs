@@ -0,0 +1,259 @@
+//! `scommit changelog`: a CHANGELOG section for a commit range, grouped by
+//! conventional-commit type, optionally reworded by the AI provider.
+
+use anyhow::{Context, Result, anyhow};
+use std::fs;
+use std::path::Path;
+
+use crate::error;
+use crate::output::say;
+use crate::{Cli, ai_model, chat_completion, git_output, tag};
+
+/// Section headings in the order they appear, with the commit types filed under each.
+const GROUPS: &[(&str, &[&str])] = &[
+    ("Features", &["feat"]),
+    ("Bug Fixes", &["fix"]),
+    ("Performance", &["perf"]),
+    ("Refactoring", &["refactor"]),
+    ("Documentation", &["docs"]),
+    ("Tests", &["test"]),
+    ("Build and CI", &["build", "ci"]),
+    ("Chores", &["chore", "style", "revert"]),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    hash: String,
+    kind: Option<String>,
+    scope: Option<String>,
+    breaking: bool,
+    description: String,
+}
+
+impl Entry {
+    /// Splits `type(scope)!: description`; anything else is kept whole, without a type.
+    fn parse(hash: &str, subject: &str, body: &str) -> Entry {
+        let conventional = subject.split_once(": ").and_then(|(prefix, description)| {
+            let breaking = prefix.ends_with('!');
+            let prefix = prefix.trim_end_matches('!');
+            let (kind, scope) = match prefix.split_once('(') {
+                Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?.to_string())),
+                None => (prefix, None),
+            };
+            let valid = !kind.is_empty() && kind.chars().all(|c| c.is_ascii_lowercase());
+            valid.then(|| (kind.to_string(), scope, breaking, description.to_string()))
+        });
+        let (kind, scope, breaking, description) = match conventional {
+            Some((kind, scope, breaking, description)) => {
+                (Some(kind), scope, breaking, description)
+            }
+            None => (None, None, false, subject.to_string()),
+        };
+        Entry {
+            hash: hash.to_string(),
+            kind,
+            scope,
+            breaking: breaking || body.contains("BREAKING CHANGE"),
+            description,
+        }
+    }
+
+    fn line(&self) -> String {
+        match &self.scope {
+            Some(scope) => format!("- **{scope}:** {} ({})", self.description, self.hash),
+            None => format!("- {} ({})", self.description, self.hash),
+        }
+    }
+}
+
+/// The range to describe: as given, or everything since the last tag.
+pub fn resolve_range(range: Option<&str>) -> String {
+    match (range, tag::last_tag()) {
+        (Some(range), _) => range.to_string(),
+        (None, Some(last)) => format!("{last}..HEAD"),
+        (None, None) => "HEAD".to_string(),
+    }
+}
+
+/// The heading: `--version` if given, else the tag the range ends at (with its date), else
+/// "Unreleased".
+fn heading(range: &str, version: Option<&str>) -> String {
+    if let Some(version) = version {
+        return format!("## {version} ({})", chrono::Local::now().format("%Y-%m-%d"));
+    }
+    let end = range.rsplit("..").next().unwrap_or(range);
+    if tag::tag_exists(end) {
+        let date = git_output(&["log", "-1", "--format=%cs", end]).unwrap_or_default();
+        return format!("## {end} ({})", date.trim());
+    }
+    "## Unreleased".to_string()
+}
+
+pub fn entries(range: &str) -> Result<Vec<Entry>> {
+    let out = git_output(&["log", "--no-merges", "--format=%h%x00%s%x00%b%x1e", range])
+        .with_context(|| format!("reading the log for {range}"))?;
+    Ok(out
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(3, '\0');
+            let (hash, subject, body) = (fields.next()?, fields.next()?, fields.next()?);
+            Some(Entry::parse(hash, subject.trim(), body))
+        })
+        .collect())
+}
+
+fn render(heading: &str, entries: &[Entry]) -> String {
+    format!("{heading}\n{}", groups(entries))
+}
+
+/// The `###` groups: breaking changes first, then each group with entries, then commits
+/// that don't follow the convention under "Other".
+pub fn groups(entries: &[Entry]) -> String {
+    let mut section = String::new();
+    let mut push_group = |title: &str, lines: Vec<String>| {
+        if !lines.is_empty() {
+            section.push_str(&format!("\n### {title}\n\n{}\n", lines.join("\n")));
+        }
+    };
+    push_group(
+        "Breaking Changes",
+        entries
+            .iter()
+            .filter(|e| e.breaking)
+            .map(Entry::line)
+            .collect(),
+    );
+    for (title, kinds) in GROUPS {
+        push_group(
+            title,
+            entries
+                .iter()
+                .filter(|e| !e.breaking)
+                .filter(|e| e.kind.as_deref().is_some_and(|k| kinds.contains(&k)))
+                .map(Entry::line)
+                .collect(),
+        );
+    }
+    let known = |kind: &str| GROUPS.iter().any(|(_, kinds)| kinds.contains(&kind));
+    push_group(
+        "Other",
+        entries
+            .iter()
+            .filter(|e| !e.breaking && !e.kind.as_deref().is_some_and(known))
+            .map(Entry::line)
+            .collect(),
+    );
+    section
+}
+
+/// Ask the AI provider to reword the entries for readers of a changelog; the structure,
+/// headings and hashes stay.
+fn polish(cli: &Cli, section: &str) -> Result<Option<String>> {
+    let Some(model) = ai_model(cli) else {
+        return Err(error::ai_unavailable(anyhow!(
+            "--polish needs the AI provider: set OPENAI_API_KEY (CI mode keeps the AI off)"
+        )));
+    };
+    let key = std::env::var("OPENAI_API_KEY")?;
+    let system = "You edit CHANGELOG sections. Reword each bullet so a user of the project understands the change: plain language, no internal jargon. Keep every markdown heading, the bullet order, the **scope:** prefixes and the (hash) suffixes exactly. Do not add, merge or drop bullets. Reply with the markdown section only, no code fences.";
+    let reply = chat_completion(&key, &model, system, section, false, 1200)?;
+    Ok(reply
+        .map(|text| text.trim().to_string())
+        .filter(|text| text.starts_with("## "))
+        .map(|text| text + "\n"))
+}
+
+/// Put `section` above the newest entry of the changelog at `path` (after its title),
+/// creating the file if needed.
+pub fn prepend(path: &Path, section: &str) -> Result<()> {
+    let existing = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => "# Changelog\n".to_string(),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+    };
+    let at = existing
+        .match_indices("## ")
+        .map(|(i, _)| i)
+        .find(|&i| i == 0 || existing.as_bytes()[i - 1] == b'\n');
+    let updated = match at {
+        Some(at) => format!("{}{section}\n{}", &existing[..at], &existing[at..]),
+        None => format!("{}\n\n{section}", existing.trim_end()),
+    };
+    fs::write(path, updated).with_context(|| format!("writing {}", path.display()))
+}
+
+/// The section for `range` and how many commits it lists; None when the range is empty.
+pub fn section(
+    cli: &Cli,
+    range: &str,
+    version: Option<&str>,
+    polish_wording: bool,
+) -> Result<Option<(String, usize)>> {
+    let entries = entries(range)?;
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    let mut section = render(&heading(range, version), &entries);
+    if polish_wording {
+        match polish(cli, &section) {
+            Ok(Some(polished)) => section = polished,
+            Ok(None) => eprintln!("The AI reply wasn't a changelog section; kept the commit list."),
+            Err(e) => eprintln!("AI polishing failed ({e}); kept the commit list."),
+        }
+    }
+    Ok(Some((section, entries.len())))
+}
+
+pub fn run(
+    cli: &Cli,
+    range: Option<&str>,
+    version: Option<&str>,
+    polish_wording: bool,
+    file: Option<&Path>,
+) -> Result<()> {
+    let range = resolve_range(range);
+    let Some((section, count)) = section(cli, &range, version, polish_wording)? else {
+        say!("No commits in {range}.");
+        return Ok(());
+    };
+    match file {
+        Some(path) => {
+            prepend(path, &section)?;
+            say!("Added {count} commit(s) to {}", path.display());
+        }
+        None => print!("{section}"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_grouped_by_type_with_breaking_changes_first() {
+        let entries = vec![
+            Entry::parse("a1", "feat(cli): add --tag", ""),
+            Entry::parse("b2", "fix: handle empty input", ""),
+            Entry::parse("c3", "feat(api)!: drop v1 endpoints", ""),
+            Entry::parse("d4", "Update README", ""),
+            Entry::parse("e5", "refactor: split parser", "BREAKING CHANGE: new API"),
+            Entry::parse("f6", "ci: cache cargo", ""),
+        ];
+        assert_eq!(
+            render("## Unreleased", &entries),
+            "## Unreleased\n\
+             \n### Breaking Changes\n\n\
+             - **api:** drop v1 endpoints (c3)\n\
+             - split parser (e5)\n\
+             \n### Features\n\n\
+             - **cli:** add --tag (a1)\n\
+             \n### Bug Fixes\n\n\
+             - handle empty input (b2)\n\
+             \n### Build and CI\n\n\
+             - cache cargo (f6)\n\
+             \n### Other\n\n\
+             - Update README (d4)\n"
+        );
+    }
+}
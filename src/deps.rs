@@ -0,0 +1,728 @@
+//! Concrete dependency changes in staged manifests (`Cargo.toml`, `package.json`,
+//! `go.mod`, `requirements.txt`): "bump serde 1.0.190 → 1.0.205" says more in a body
+//! than "update Cargo.toml". Lockfiles get the same treatment with their resolved
+//! versions, so a lockfile-only commit can be described without the AI.
+
+use std::collections::BTreeMap;
+
+use crate::{FileChange, FileStatus, LOCKFILES, git_output, short_name};
+
+/// A dependency's version requirement per name; dev/build dependencies are suffixed with
+/// their section (`tempfile (dev)`) so they don't collide with runtime ones.
+type Manifest = BTreeMap<String, String>;
+
+type Parser = fn(&str) -> Option<Manifest>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepChange {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl DepChange {
+    /// "add x 1.2", "remove x", "bump x 1.0 → 1.1".
+    pub fn describe(&self) -> String {
+        match (&self.old, &self.new) {
+            (None, Some(new)) => format!("add {} {new}", self.name),
+            (Some(_), None) => format!("remove {}", self.name),
+            (Some(old), Some(new)) => format!("bump {} {old} → {new}", self.name),
+            (None, None) => self.name.clone(),
+        }
+    }
+}
+
+/// Dependency changes per staged manifest, in file order; manifests that didn't change
+/// any dependency (or can't be parsed) are left out.
+pub fn changed(changes: &[FileChange]) -> Vec<(String, Vec<DepChange>)> {
+    compare_staged(changes, parser)
+}
+
+/// A manifest's own version changing, the mark of a release-prep commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionBump {
+    pub path: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// The version bump in the staged manifests (`Cargo.toml`, `package.json`,
+/// `pyproject.toml`); with several, the one closest to the repo root.
+pub fn version_bump(changes: &[FileChange]) -> Option<VersionBump> {
+    let mut bumps: Vec<VersionBump> = changes
+        .iter()
+        .filter(|c| matches!(c.status, FileStatus::Modified))
+        .filter_map(|change| {
+            let path = change.path.as_str();
+            let read = |spec: String| git_output(&["show", &spec]).ok();
+            let old = declared_version(path, &read(format!("HEAD:{path}"))?)?;
+            let new = declared_version(path, &read(format!(":{path}"))?)?;
+            (old != new).then(|| VersionBump {
+                path: path.to_string(),
+                old,
+                new,
+            })
+        })
+        .collect();
+    bumps.sort_by_key(|bump| bump.path.matches('/').count());
+    bumps.into_iter().next()
+}
+
+/// "chore(release): bump version 1.2.0 → 1.3.0".
+pub fn release_subject(bump: &VersionBump) -> String {
+    format!("chore(release): bump version {} → {}", bump.old, bump.new)
+}
+
+/// Cargo.toml's `[package]` (or `[workspace.package]`) version, package.json's
+/// `version`, pyproject.toml's `[project]` or `[tool.poetry]` version.
+pub fn declared_version(path: &str, text: &str) -> Option<String> {
+    let at = |value: Option<&toml::Value>, keys: &[&str]| {
+        keys.iter()
+            .try_fold(value?, |v, key| v.get(key))?
+            .as_str()
+            .map(str::to_string)
+    };
+    match short_name(path).as_str() {
+        "Cargo.toml" => {
+            let doc = toml::Value::Table(text.parse().ok()?);
+            at(Some(&doc), &["package", "version"])
+                .or_else(|| at(Some(&doc), &["workspace", "package", "version"]))
+        }
+        "pyproject.toml" => {
+            let doc = toml::Value::Table(text.parse().ok()?);
+            at(Some(&doc), &["project", "version"])
+                .or_else(|| at(Some(&doc), &["tool", "poetry", "version"]))
+        }
+        "package.json" => {
+            let doc: serde_json::Value = serde_json::from_str(text).ok()?;
+            doc.get("version")?.as_str().map(str::to_string)
+        }
+        _ => None,
+    }
+}
+
+/// Resolved version changes per staged lockfile, like [`changed`].
+pub fn locked(changes: &[FileChange]) -> Vec<(String, Vec<DepChange>)> {
+    compare_staged(changes, lock_parser)
+}
+
+/// Whether every staged file is a lockfile: an AI call on a 10,000-line lockfile diff is
+/// slow and says less than the version deltas do.
+pub fn lockfile_only(changes: &[FileChange]) -> bool {
+    !changes.is_empty()
+        && changes
+            .iter()
+            .all(|c| LOCKFILES.contains(&short_name(&c.path).as_str()))
+}
+
+/// "bump serde 1.0.190 → 1.0.205", "bump serde, regex & libc", or a count of what
+/// moved when there's more than that.
+pub fn lockfile_subject(deps: &[DepChange]) -> String {
+    let bumped: Vec<&str> = deps
+        .iter()
+        .filter(|d| d.old.is_some() && d.new.is_some())
+        .map(|d| d.name.as_str())
+        .collect();
+    let focus = match deps {
+        [] => "refresh lockfile".to_string(),
+        [dep] => dep.describe(),
+        _ if bumped.len() == deps.len() && deps.len() <= 3 => {
+            let (last, rest) = bumped.split_last().expect("at least two");
+            format!("bump {} & {last}", rest.join(", "))
+        }
+        _ => {
+            let added = deps.iter().filter(|d| d.old.is_none()).count();
+            let removed = deps.iter().filter(|d| d.new.is_none()).count();
+            let counts: Vec<String> = [
+                (bumped.len(), "bumped"),
+                (added, "added"),
+                (removed, "removed"),
+            ]
+            .iter()
+            .filter(|(n, _)| *n > 0)
+            .map(|(n, what)| format!("{n} {what}"))
+            .collect();
+            format!(
+                "update {} locked dependencies ({})",
+                deps.len(),
+                counts.join(", ")
+            )
+        }
+    };
+    let mut subject = format!("chore(deps): {focus}");
+    if subject.len() > 72 {
+        let cut = (0..=72)
+            .rev()
+            .find(|&i| subject.is_char_boundary(i))
+            .unwrap_or(0);
+        subject.truncate(cut);
+    }
+    subject
+}
+
+fn compare_staged(
+    changes: &[FileChange],
+    parser: fn(&str) -> Option<Parser>,
+) -> Vec<(String, Vec<DepChange>)> {
+    let mut found = Vec::new();
+    for change in changes {
+        let Some(parse) = parser(&change.path) else {
+            continue;
+        };
+        let old_path = match &change.status {
+            FileStatus::Added | FileStatus::Copied { .. } => None,
+            FileStatus::Renamed { from, .. } => Some(from.as_str()),
+            _ => Some(change.path.as_str()),
+        };
+        let read = |spec: String| git_output(&["show", &spec]).ok();
+        let before = old_path
+            .and_then(|path| read(format!("HEAD:{path}")))
+            .and_then(|text| parse(&text))
+            .unwrap_or_default();
+        let after = match change.status {
+            FileStatus::Deleted => Manifest::new(),
+            _ => match read(format!(":{}", change.path)).and_then(|text| parse(&text)) {
+                Some(manifest) => manifest,
+                None => continue,
+            },
+        };
+        let deps = compare(&before, &after);
+        if !deps.is_empty() {
+            found.push((change.path.clone(), deps));
+        }
+    }
+    found
+}
+
+fn parser(path: &str) -> Option<Parser> {
+    Some(match short_name(path).as_str() {
+        "Cargo.toml" => cargo_toml,
+        "package.json" => package_json,
+        "go.mod" => go_mod,
+        "requirements.txt" => requirements_txt,
+        name if name.starts_with("requirements") && name.ends_with(".txt") => requirements_txt,
+        _ => return None,
+    })
+}
+
+fn lock_parser(path: &str) -> Option<Parser> {
+    Some(match short_name(path).as_str() {
+        "Cargo.lock" | "poetry.lock" => toml_packages,
+        "package-lock.json" => package_lock,
+        "yarn.lock" => yarn_lock,
+        "pnpm-lock.yaml" => pnpm_lock,
+        "Pipfile.lock" => pipfile_lock,
+        "Gemfile.lock" => gemfile_lock,
+        "composer.lock" => composer_lock,
+        "go.sum" => go_sum,
+        _ => return None,
+    })
+}
+
+fn compare(before: &Manifest, after: &Manifest) -> Vec<DepChange> {
+    let mut deps = Vec::new();
+    for (name, new) in after {
+        match before.get(name) {
+            None => deps.push(DepChange {
+                name: name.clone(),
+                old: None,
+                new: Some(new.clone()),
+            }),
+            Some(old) if old != new => deps.push(DepChange {
+                name: name.clone(),
+                old: Some(old.clone()),
+                new: Some(new.clone()),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (name, old) in before {
+        if !after.contains_key(name) {
+            deps.push(DepChange {
+                name: name.clone(),
+                old: Some(old.clone()),
+                new: None,
+            });
+        }
+    }
+    deps
+}
+
+fn cargo_toml(text: &str) -> Option<Manifest> {
+    let doc: toml::Table = text.parse().ok()?;
+    let mut deps = Manifest::new();
+    let mut collect = |table: &toml::Table| {
+        for (section, suffix) in [
+            ("dependencies", ""),
+            ("dev-dependencies", " (dev)"),
+            ("build-dependencies", " (build)"),
+        ] {
+            let Some(entries) = table.get(section).and_then(|v| v.as_table()) else {
+                continue;
+            };
+            for (name, spec) in entries {
+                deps.insert(format!("{name}{suffix}"), cargo_version(spec));
+            }
+        }
+    };
+    collect(&doc);
+    if let Some(workspace) = doc.get("workspace").and_then(|v| v.as_table()) {
+        collect(workspace);
+    }
+    for target in doc
+        .get("target")
+        .and_then(|v| v.as_table())
+        .into_iter()
+        .flat_map(|targets| targets.values())
+        .filter_map(|v| v.as_table())
+    {
+        collect(target);
+    }
+    Some(deps)
+}
+
+/// `"1.0"`, `{ version = "1.0", ... }`, or where it comes from when there is no version.
+fn cargo_version(spec: &toml::Value) -> String {
+    if let Some(version) = spec.as_str() {
+        return version.to_string();
+    }
+    let table = spec.as_table();
+    let field = |key: &str| table.and_then(|t| t.get(key)).and_then(|v| v.as_str());
+    if let Some(version) = field("version") {
+        version.to_string()
+    } else if let Some(git) = field("git") {
+        let rev = field("rev").or(field("tag")).or(field("branch"));
+        rev.map_or_else(|| git.to_string(), |rev| format!("{git}#{rev}"))
+    } else if let Some(path) = field("path") {
+        format!("path {path}")
+    } else if table.and_then(|t| t.get("workspace")).is_some() {
+        "workspace".to_string()
+    } else {
+        "*".to_string()
+    }
+}
+
+fn package_json(text: &str) -> Option<Manifest> {
+    let doc: serde_json::Value = serde_json::from_str(text).ok()?;
+    let mut deps = Manifest::new();
+    for (section, suffix) in [
+        ("dependencies", ""),
+        ("devDependencies", " (dev)"),
+        ("peerDependencies", " (peer)"),
+        ("optionalDependencies", " (optional)"),
+    ] {
+        let Some(entries) = doc.get(section).and_then(|v| v.as_object()) else {
+            continue;
+        };
+        for (name, version) in entries {
+            let version = version.as_str().unwrap_or("*");
+            deps.insert(format!("{name}{suffix}"), version.to_string());
+        }
+    }
+    Some(deps)
+}
+
+/// `require` lines, single or in a block, with or without `// indirect`.
+fn go_mod(text: &str) -> Option<Manifest> {
+    let mut deps = Manifest::new();
+    let mut in_block = false;
+    for line in text.lines() {
+        let line = line.trim();
+        let entry = if in_block {
+            if line == ")" {
+                in_block = false;
+                continue;
+            }
+            line
+        } else if line == "require (" {
+            in_block = true;
+            continue;
+        } else if let Some(entry) = line.strip_prefix("require ") {
+            entry
+        } else {
+            continue;
+        };
+        let entry = entry.split("//").next().unwrap_or(entry);
+        let mut parts = entry.split_whitespace();
+        let (Some(module), Some(version)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        deps.insert(module.to_string(), version.to_string());
+    }
+    Some(deps)
+}
+
+/// `name==1.2`, `name>=1,<2`, `name[extra]~=1.0`; options, includes and URLs are skipped.
+fn requirements_txt(text: &str) -> Option<Manifest> {
+    let mut deps = Manifest::new();
+    for line in text.lines() {
+        let line = line.split(" #").next().unwrap_or("").trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('-') || line.contains("://")
+        {
+            continue;
+        }
+        let line = line.split(';').next().unwrap_or(line).trim();
+        let split = line
+            .find(|c: char| "=<>!~ ".contains(c))
+            .unwrap_or(line.len());
+        let (name, spec) = line.split_at(split);
+        let name = name.split('[').next().unwrap_or(name);
+        let spec = spec.trim().trim_start_matches("==").trim();
+        let version = if spec.is_empty() { "*" } else { spec };
+        deps.insert(
+            name.to_ascii_lowercase().replace('_', "-"),
+            version.to_string(),
+        );
+    }
+    Some(deps)
+}
+
+/// Lockfiles can hold several versions of one package; they are listed together.
+fn insert_locked(deps: &mut Manifest, name: &str, version: &str) {
+    let entry = deps.entry(name.to_string()).or_default();
+    let mut versions: Vec<&str> = entry.split(", ").filter(|v| !v.is_empty()).collect();
+    if !versions.contains(&version) {
+        versions.push(version);
+        versions.sort_unstable();
+        *entry = versions.join(", ");
+    }
+}
+
+/// `Cargo.lock` and `poetry.lock`: `[[package]]` tables with a name and version.
+fn toml_packages(text: &str) -> Option<Manifest> {
+    let doc: toml::Table = text.parse().ok()?;
+    let mut deps = Manifest::new();
+    for package in doc.get("package")?.as_array()? {
+        let field = |key: &str| package.get(key).and_then(|v| v.as_str());
+        if let (Some(name), Some(version)) = (field("name"), field("version")) {
+            insert_locked(&mut deps, name, version);
+        }
+    }
+    Some(deps)
+}
+
+/// `packages` keyed by `node_modules/...` path (lockfile v2/v3), else the v1
+/// `dependencies` tree.
+fn package_lock(text: &str) -> Option<Manifest> {
+    let doc: serde_json::Value = serde_json::from_str(text).ok()?;
+    let mut deps = Manifest::new();
+    if let Some(packages) = doc.get("packages").and_then(|v| v.as_object()) {
+        for (path, package) in packages {
+            let Some((_, name)) = path.rsplit_once("node_modules/") else {
+                continue;
+            };
+            if let Some(version) = package.get("version").and_then(|v| v.as_str()) {
+                insert_locked(&mut deps, name, version);
+            }
+        }
+        return Some(deps);
+    }
+    fn walk(deps: &mut Manifest, tree: &serde_json::Value) {
+        let Some(entries) = tree.get("dependencies").and_then(|v| v.as_object()) else {
+            return;
+        };
+        for (name, package) in entries {
+            if let Some(version) = package.get("version").and_then(|v| v.as_str()) {
+                insert_locked(deps, name, version);
+            }
+            walk(deps, package);
+        }
+    }
+    walk(&mut deps, &doc);
+    Some(deps)
+}
+
+/// The package name in a spec like `@scope/name@^1.0` or `name@npm:other@1`.
+fn strip_range(spec: &str) -> &str {
+    let spec = spec.trim().trim_matches('"');
+    match spec[1.min(spec.len())..].find('@') {
+        Some(at) => &spec[..at + 1],
+        None => spec,
+    }
+}
+
+/// Unindented `"a@^1", a@^1.1:` headers, each followed by an indented `version`.
+fn yarn_lock(text: &str) -> Option<Manifest> {
+    let mut deps = Manifest::new();
+    let mut current: Option<String> = None;
+    for line in text.lines() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        if !line.starts_with(' ') {
+            current = line
+                .trim_end_matches(':')
+                .split(", ")
+                .next()
+                .map(|spec| strip_range(spec).to_string());
+            continue;
+        }
+        let Some(name) = &current else { continue };
+        let line = line.trim();
+        if let Some(version) = line
+            .strip_prefix("version ")
+            .or_else(|| line.strip_prefix("version: "))
+        {
+            insert_locked(&mut deps, name, version.trim().trim_matches('"'));
+            current = None;
+        }
+    }
+    Some(deps)
+}
+
+/// Keys under `packages:` like `/name@1.2.3:`, `'@scope/name@1.2.3(peer@1)':` or the
+/// older `/name/1.2.3:`.
+fn pnpm_lock(text: &str) -> Option<Manifest> {
+    let mut deps = Manifest::new();
+    let mut in_packages = false;
+    for line in text.lines() {
+        if !line.starts_with(' ') && !line.trim().is_empty() {
+            in_packages = line.trim_end() == "packages:";
+            continue;
+        }
+        let Some(key) = line.strip_prefix("  ") else {
+            continue;
+        };
+        if !in_packages || key.starts_with(' ') || !key.trim_end().ends_with(':') {
+            continue;
+        }
+        let key = key.trim_end().trim_end_matches(':').trim_matches('\'');
+        let key = key.strip_prefix('/').unwrap_or(key);
+        let key = key.split('(').next().unwrap_or(key);
+        let split = key[1.min(key.len())..]
+            .rfind('@')
+            .map(|at| at + 1)
+            .or_else(|| key.rfind('/'));
+        if let Some(at) = split {
+            insert_locked(&mut deps, &key[..at], &key[at + 1..]);
+        }
+    }
+    Some(deps)
+}
+
+/// `default` and `develop` sections of `{"name": {"version": "==1.2"}}`.
+fn pipfile_lock(text: &str) -> Option<Manifest> {
+    let doc: serde_json::Value = serde_json::from_str(text).ok()?;
+    let mut deps = Manifest::new();
+    for (section, suffix) in [("default", ""), ("develop", " (dev)")] {
+        let Some(entries) = doc.get(section).and_then(|v| v.as_object()) else {
+            continue;
+        };
+        for (name, package) in entries {
+            if let Some(version) = package.get("version").and_then(|v| v.as_str()) {
+                let version = version.trim_start_matches("==");
+                insert_locked(&mut deps, &format!("{name}{suffix}"), version);
+            }
+        }
+    }
+    Some(deps)
+}
+
+/// `    name (1.2.3)` lines of the `specs:` lists; deeper lines are their requirements.
+fn gemfile_lock(text: &str) -> Option<Manifest> {
+    let mut deps = Manifest::new();
+    for line in text.lines() {
+        let Some(spec) = line.strip_prefix("    ") else {
+            continue;
+        };
+        if spec.starts_with(' ') {
+            continue;
+        }
+        if let Some((name, version)) = spec.split_once(" (") {
+            insert_locked(&mut deps, name, version.trim_end_matches(')'));
+        }
+    }
+    Some(deps)
+}
+
+/// `packages` and `packages-dev` arrays of `{"name", "version"}`.
+fn composer_lock(text: &str) -> Option<Manifest> {
+    let doc: serde_json::Value = serde_json::from_str(text).ok()?;
+    let mut deps = Manifest::new();
+    for (section, suffix) in [("packages", ""), ("packages-dev", " (dev)")] {
+        for package in doc
+            .get(section)
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let field = |key: &str| package.get(key).and_then(|v| v.as_str());
+            if let (Some(name), Some(version)) = (field("name"), field("version")) {
+                insert_locked(&mut deps, &format!("{name}{suffix}"), version);
+            }
+        }
+    }
+    Some(deps)
+}
+
+/// `module version[/go.mod] hash` lines.
+fn go_sum(text: &str) -> Option<Manifest> {
+    let mut deps = Manifest::new();
+    for line in text.lines() {
+        let mut parts = line.split_whitespace();
+        if let (Some(module), Some(version)) = (parts.next(), parts.next()) {
+            insert_locked(&mut deps, module, version.trim_end_matches("/go.mod"));
+        }
+    }
+    Some(deps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn describe(before: Manifest, after: Manifest) -> Vec<String> {
+        compare(&before, &after)
+            .iter()
+            .map(DepChange::describe)
+            .collect()
+    }
+
+    #[test]
+    fn manifests_are_compared_per_dependency() {
+        let before = cargo_toml(
+            "[dependencies]\nserde = \"1.0.190\"\nregex = \"1\"\n\
+             [dev-dependencies]\ntempfile = \"3\"\n",
+        )
+        .unwrap();
+        let after = cargo_toml(
+            "[dependencies]\nserde = { version = \"1.0.205\", features = [\"derive\"] }\n\
+             anyhow = \"1\"\n[dev-dependencies]\ntempfile = \"3\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            describe(before, after),
+            vec![
+                "add anyhow 1",
+                "bump serde 1.0.190 → 1.0.205",
+                "remove regex"
+            ]
+        );
+
+        let before = package_json(r#"{"dependencies":{"react":"^18.2.0"}}"#).unwrap();
+        let after = package_json(
+            r#"{"dependencies":{"react":"^18.3.1"},"devDependencies":{"vite":"^5.0.0"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            describe(before, after),
+            vec!["bump react ^18.2.0 → ^18.3.1", "add vite (dev) ^5.0.0"]
+        );
+
+        let before = go_mod("module x\n\nrequire (\n\tgithub.com/a/b v1.2.0\n)\n").unwrap();
+        let after = go_mod(
+            "module x\n\nrequire (\n\tgithub.com/a/b v1.3.0\n\tgolang.org/x/sys v0.1.0 // indirect\n)\n",
+        )
+        .unwrap();
+        assert_eq!(
+            describe(before, after),
+            vec![
+                "bump github.com/a/b v1.2.0 → v1.3.0",
+                "add golang.org/x/sys v0.1.0"
+            ]
+        );
+
+        let before = requirements_txt("Django==4.2\n# tools\nrequests>=2.31\n").unwrap();
+        let after = requirements_txt("django==5.0.1\n-r base.txt\nrequests>=2.31\n").unwrap();
+        assert_eq!(describe(before, after), vec!["bump django 4.2 → 5.0.1"]);
+    }
+
+    #[test]
+    fn declared_versions_come_from_the_package_tables() {
+        let cargo =
+            "[package]\nname = \"x\"\nversion = \"0.4.0\"\n\n[dependencies]\nserde = \"1\"\n";
+        assert_eq!(
+            declared_version("Cargo.toml", cargo).as_deref(),
+            Some("0.4.0")
+        );
+        let inherited = "[package]\nname = \"x\"\nversion.workspace = true\n";
+        assert_eq!(declared_version("crates/x/Cargo.toml", inherited), None);
+        let workspace = "[workspace.package]\nversion = \"2.0.0\"\n";
+        assert_eq!(
+            declared_version("Cargo.toml", workspace).as_deref(),
+            Some("2.0.0")
+        );
+        let poetry = "[tool.poetry]\nname = \"x\"\nversion = \"1.1\"\n";
+        assert_eq!(
+            declared_version("pyproject.toml", poetry).as_deref(),
+            Some("1.1")
+        );
+        let npm = r#"{"name":"x","version":"3.2.1","dependencies":{}}"#;
+        assert_eq!(
+            declared_version("web/package.json", npm).as_deref(),
+            Some("3.2.1")
+        );
+        assert_eq!(declared_version("go.mod", "module x\n"), None);
+
+        let bump = VersionBump {
+            path: "Cargo.toml".into(),
+            old: "0.4.0".into(),
+            new: "0.5.0".into(),
+        };
+        assert_eq!(
+            release_subject(&bump),
+            "chore(release): bump version 0.4.0 → 0.5.0"
+        );
+    }
+
+    #[test]
+    fn lockfiles_are_compared_by_resolved_version() {
+        let before = toml_packages(
+            "[[package]]\nname = \"libc\"\nversion = \"0.2.150\"\n\n\
+             [[package]]\nname = \"syn\"\nversion = \"1.0.109\"\n",
+        )
+        .unwrap();
+        let after = toml_packages(
+            "[[package]]\nname = \"libc\"\nversion = \"0.2.155\"\n\n\
+             [[package]]\nname = \"syn\"\nversion = \"1.0.109\"\n\n\
+             [[package]]\nname = \"syn\"\nversion = \"2.0.60\"\n",
+        )
+        .unwrap();
+        let deps = compare(&before, &after);
+        assert_eq!(
+            deps.iter().map(DepChange::describe).collect::<Vec<_>>(),
+            vec![
+                "bump libc 0.2.150 → 0.2.155",
+                "bump syn 1.0.109 → 1.0.109, 2.0.60"
+            ]
+        );
+        assert_eq!(lockfile_subject(&deps), "chore(deps): bump libc & syn");
+        assert_eq!(
+            lockfile_subject(&deps[..1]),
+            "chore(deps): bump libc 0.2.150 → 0.2.155"
+        );
+
+        let npm = package_lock(
+            r#"{"packages":{"":{"name":"app"},"node_modules/@babel/core":{"version":"7.24.0"},
+                "node_modules/a/node_modules/ms":{"version":"2.0.0"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(npm["@babel/core"], "7.24.0");
+        assert_eq!(npm["ms"], "2.0.0");
+
+        let yarn = yarn_lock(
+            "\"@types/node@*\", \"@types/node@^20\":\n  version \"20.11.5\"\n\nms@^2.1.1:\n  version \"2.1.3\"\n",
+        )
+        .unwrap();
+        assert_eq!(yarn["@types/node"], "20.11.5");
+        assert_eq!(yarn["ms"], "2.1.3");
+
+        let pnpm = pnpm_lock(
+            "lockfileVersion: '6.0'\n\npackages:\n\n  /ms@2.1.3:\n    resolution: {}\n\n  \
+             /@babel/core@7.24.0(supports-color@8.1.1):\n    dev: true\n",
+        )
+        .unwrap();
+        assert_eq!(pnpm["ms"], "2.1.3");
+        assert_eq!(pnpm["@babel/core"], "7.24.0");
+
+        let gems = gemfile_lock(
+            "GEM\n  specs:\n    rack (3.0.8)\n    rails (7.1.2)\n      rack (>= 2.2.4)\n",
+        )
+        .unwrap();
+        assert_eq!(gems["rack"], "3.0.8");
+
+        let sums = go_sum("github.com/a/b v1.3.0 h1:abc=\ngithub.com/a/b v1.3.0/go.mod h1:def=\n")
+            .unwrap();
+        assert_eq!(sums["github.com/a/b"], "v1.3.0");
+    }
+}
@@ -0,0 +1,546 @@
+//! Parsers for git's NUL-delimited (`-z`) diff output, and for the paths in patches.
+//! With `-z` git prints paths verbatim, so spaces, tabs and newlines in them survive,
+//! and nothing has to be unquoted or split on whitespace. Patch headers can't use `-z`:
+//! there git C-quotes a path in double quotes when it holds a quote, a backslash or a
+//! control character (and any non-ASCII byte, unless `core.quotepath` is off, which
+//! `git_output` sets), so they go through [`unquote`]. `-z` output is read as strict
+//! UTF-8: a path that isn't (see [`non_utf8_path`]) stops the run.
+
+use anyhow::anyhow;
+use std::borrow::Cow;
+
+use crate::FileStatus;
+
+/// One file from `git diff --raw --numstat -z`.
+#[derive(Debug, Clone)]
+pub struct DiffEntry {
+    /// The new path for renames and copies.
+    pub path: String,
+    pub status: FileStatus,
+    /// Lines added and deleted; 0/0 for binary files.
+    pub added: u32,
+    pub deleted: u32,
+}
+
+/// Parses `git diff --raw --numstat -z` output a NUL-terminated field at a time, as git
+/// streams it: every `--raw` record, then every `--numstat` record. The raw records give
+/// the status and paths, the numstat records the line counts, matched up by the new path.
+/// Only the entries themselves are kept.
+///
+/// A raw record is `:<modes> <shas> <status> NUL path NUL`, or for a rename or copy
+/// `... NUL from NUL to NUL`. A numstat record is `added TAB deleted TAB path NUL`, or
+/// `added TAB deleted TAB NUL from NUL to NUL`.
+#[derive(Debug, Default)]
+pub struct RawNumstat {
+    entries: Vec<DiffEntry>,
+    pending: Pending,
+    /// Where the next numstat record is expected; git prints both lists in one order.
+    next: usize,
+}
+
+#[derive(Debug, Default)]
+enum Pending {
+    #[default]
+    Record,
+    RawPath(char),
+    RawTo(char, String),
+    NumstatFrom(u32, u32),
+    NumstatTo(u32, u32),
+}
+
+impl RawNumstat {
+    pub fn push(&mut self, field: &str) {
+        match std::mem::take(&mut self.pending) {
+            Pending::Record => {
+                if let Some(header) = field.strip_prefix(':') {
+                    let letters = header.rsplit(' ').next().unwrap_or_default();
+                    self.pending = Pending::RawPath(letters.chars().next().unwrap_or('M'));
+                } else {
+                    let mut parts = field.splitn(3, '\t');
+                    let (Some(added), Some(deleted), Some(path)) =
+                        (parts.next(), parts.next(), parts.next())
+                    else {
+                        return;
+                    };
+                    let (added, deleted) =
+                        (added.parse().unwrap_or(0), deleted.parse().unwrap_or(0));
+                    if path.is_empty() {
+                        self.pending = Pending::NumstatFrom(added, deleted);
+                    } else {
+                        self.count(path, added, deleted);
+                    }
+                }
+            }
+            Pending::RawPath(kind @ ('R' | 'C')) => {
+                self.pending = Pending::RawTo(kind, field.to_string());
+            }
+            Pending::RawPath(kind) => {
+                let status = match kind {
+                    'A' => FileStatus::Added,
+                    'D' => FileStatus::Deleted,
+                    'T' => FileStatus::TypeChanged,
+                    _ => FileStatus::Modified,
+                };
+                self.entry(field.to_string(), status);
+            }
+            Pending::RawTo(kind, from) => {
+                let to = field.to_string();
+                let status = if kind == 'R' {
+                    FileStatus::Renamed {
+                        from,
+                        to: to.clone(),
+                    }
+                } else {
+                    FileStatus::Copied {
+                        from,
+                        to: to.clone(),
+                    }
+                };
+                self.entry(to, status);
+            }
+            Pending::NumstatFrom(added, deleted) => {
+                self.pending = Pending::NumstatTo(added, deleted);
+            }
+            Pending::NumstatTo(added, deleted) => self.count(field, added, deleted),
+        }
+    }
+
+    pub fn finish(self) -> Vec<DiffEntry> {
+        self.entries
+    }
+
+    fn entry(&mut self, path: String, status: FileStatus) {
+        self.entries.push(DiffEntry {
+            path,
+            status,
+            added: 0,
+            deleted: 0,
+        });
+    }
+
+    fn count(&mut self, path: &str, added: u32, deleted: u32) {
+        let found = (self.next..self.entries.len())
+            .chain(0..self.next)
+            .find(|&i| self.entries[i].path == path);
+        if let Some(i) = found {
+            (self.entries[i].added, self.entries[i].deleted) = (added, deleted);
+            self.next = i + 1;
+        }
+    }
+}
+
+/// `git diff --numstat -z` output as (path, added, deleted), keyed by the new path for
+/// renames and copies; binary files count 0/0.
+pub fn parse_numstat(out: &str) -> Vec<(String, u32, u32)> {
+    let mut fields = out.split('\0');
+    let mut files = Vec::new();
+    while let Some(field) = fields.next() {
+        files.extend(numstat_record(field, &mut fields));
+    }
+    files
+}
+
+/// The numstat record starting at `field`, taking the rename's two paths from `rest`.
+fn numstat_record<'a>(
+    field: &str,
+    rest: &mut impl Iterator<Item = &'a str>,
+) -> Option<(String, u32, u32)> {
+    let mut parts = field.splitn(3, '\t');
+    let (added, deleted, path) = (parts.next()?, parts.next()?, parts.next()?);
+    let path = if path.is_empty() {
+        rest.next();
+        rest.next()?
+    } else {
+        path
+    };
+    Some((
+        path.to_string(),
+        added.parse().unwrap_or(0),
+        deleted.parse().unwrap_or(0),
+    ))
+}
+
+/// The error for NUL-separated `records` that stop being UTF-8 at byte `at`: scommit
+/// holds paths as strings, and a lossy copy would name another file in a pathspec.
+pub fn non_utf8_path(records: &[u8], at: usize) -> anyhow::Error {
+    let start = records[..at]
+        .iter()
+        .rposition(|&b| b == 0)
+        .map_or(0, |i| i + 1);
+    let end = records[at..]
+        .iter()
+        .position(|&b| b == 0)
+        .map_or(records.len(), |i| at + i);
+    anyhow!(
+        "git listed a path that isn't valid UTF-8: {:?}; scommit only works with UTF-8 \
+         paths, so rename it or commit it with git itself",
+        String::from_utf8_lossy(&records[start..end])
+    )
+}
+
+/// A path as git prints it outside `-z` output: as is, or C-quoted in double quotes with
+/// backslash escapes and octal bytes. Bytes that aren't UTF-8 come out as U+FFFD.
+pub fn unquote(path: &str) -> Cow<'_, str> {
+    let Some(inner) = path
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    else {
+        return Cow::Borrowed(path);
+    };
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut rest = inner.bytes().peekable();
+    while let Some(byte) = rest.next() {
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        let Some(escaped) = rest.next() else {
+            break;
+        };
+        bytes.push(match escaped {
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b't' => b'\t',
+            b'n' => b'\n',
+            b'v' => 0x0b,
+            b'f' => 0x0c,
+            b'r' => b'\r',
+            b'0'..=b'3' => {
+                let mut value = escaped - b'0';
+                for _ in 0..2 {
+                    match rest.peek() {
+                        Some(digit @ b'0'..=b'7') => {
+                            value = value * 8 + (digit - b'0');
+                            rest.next();
+                        }
+                        _ => break,
+                    }
+                }
+                value
+            }
+            other => other,
+        });
+    }
+    Cow::Owned(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// `path` as git would print it with `core.quotepath` off: as is, unless it holds a
+/// quote, a backslash or a control character (a newline would split a listing).
+pub fn quote(path: &str) -> Cow<'_, str> {
+    if path
+        .chars()
+        .any(|c| c.is_control() || c == '"' || c == '\\')
+    {
+        Cow::Owned(c_quote(path))
+    } else {
+        Cow::Borrowed(path)
+    }
+}
+
+/// `path` in double quotes with C escapes, the inverse of [`unquote`].
+pub fn c_quote(path: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in path.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => {
+                let mut buf = [0; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    quoted.push_str(&format!("\\{byte:03o}"));
+                }
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The path on a patch's `--- ` or `+++ ` line (given without that prefix), without its
+/// `a/` or `b/`; None for `/dev/null`.
+pub fn patch_path(line: &str) -> Option<String> {
+    if line == "/dev/null" {
+        return None;
+    }
+    let path = unquote(line.trim_end_matches('\t'));
+    Some(
+        path.strip_prefix("a/")
+            .or_else(|| path.strip_prefix("b/"))
+            .unwrap_or(&path)
+            .to_string(),
+    )
+}
+
+/// The new path in a `diff --git a/<old> b/<new>` header.
+pub fn header_path(header: &str) -> Cow<'_, str> {
+    if header.ends_with('"')
+        && let Some(start) = header.rfind(" \"b/")
+    {
+        return Cow::Owned(unquote(&header[start + 1..])[2..].to_string());
+    }
+    Cow::Borrowed(header.rsplit(" b/").next().unwrap_or(header))
+}
+
+/// One file's part of a patch: the start of its text, up to a cap, and what it takes to
+/// summarize all of it.
+#[derive(Debug, Clone, Default)]
+pub struct Section {
+    /// The `diff --git` line.
+    pub header: String,
+    /// The new path, from the header.
+    pub path: String,
+    /// The header and the lines after it that fit under the cap, each ending in `\n`.
+    pub text: String,
+    /// Whether lines past the cap were left out of `text`.
+    pub truncated: bool,
+    pub added: u32,
+    pub deleted: u32,
+    pub binary: bool,
+    /// Whether an added line runs past 500 characters, as minified code does.
+    pub long_lines: bool,
+}
+
+/// Splits a patch into [`Section`]s a line at a time, so only the current file's capped
+/// text is held however big the patch is.
+#[derive(Debug)]
+pub struct Sections {
+    cap: usize,
+    current: Option<Section>,
+    /// Old and new lines the current hunk has still to show; while either is left, a
+    /// line is content even if it reads like a `---`/`+++` header.
+    hunk_left: (u32, u32),
+}
+
+impl Sections {
+    /// Sections keeping at most `cap` bytes of text each (the header always).
+    pub fn new(cap: usize) -> Sections {
+        Sections {
+            cap,
+            current: None,
+            hunk_left: (0, 0),
+        }
+    }
+
+    /// Take one line of the patch, without its newline; a new header hands back the
+    /// section it ends.
+    pub fn push(&mut self, line: &str) -> Option<Section> {
+        if line.starts_with("diff --git ") {
+            let section = Section {
+                header: line.to_string(),
+                path: header_path(line).into_owned(),
+                text: format!("{line}\n"),
+                ..Section::default()
+            };
+            self.hunk_left = (0, 0);
+            return self.current.replace(section);
+        }
+        // Anything before the first header isn't part of a file.
+        let section = self.current.as_mut()?;
+        let (old, new) = &mut self.hunk_left;
+        if *old > 0 || *new > 0 {
+            match line.as_bytes().first() {
+                Some(b'+') => {
+                    section.added += 1;
+                    section.long_lines |= line.len() > 500;
+                    *new = new.saturating_sub(1);
+                }
+                Some(b'-') => {
+                    section.deleted += 1;
+                    *old = old.saturating_sub(1);
+                }
+                // "\ No newline at end of file"
+                Some(b'\\') => {}
+                _ => {
+                    *old = old.saturating_sub(1);
+                    *new = new.saturating_sub(1);
+                }
+            }
+        } else if let Some(lengths) = hunk_lengths(line) {
+            self.hunk_left = lengths;
+        } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            section.binary = true;
+        }
+        if section.truncated || section.text.len() + line.len() + 1 > self.cap {
+            section.truncated = true;
+        } else {
+            section.text.push_str(line);
+            section.text.push('\n');
+        }
+        None
+    }
+
+    /// The last section, once the patch has ended.
+    pub fn finish(self) -> Option<Section> {
+        self.current
+    }
+}
+
+/// The old and new line counts in a `@@ -a,b +c,d @@` hunk header; a count left out
+/// is 1.
+fn hunk_lengths(line: &str) -> Option<(u32, u32)> {
+    let mut ranges = line.strip_prefix("@@ -")?.split(' ');
+    let old = ranges.next()?;
+    let new = ranges.next()?.strip_prefix('+')?;
+    let count = |range: &str| match range.split_once(',') {
+        Some((_, n)) => n.parse().ok(),
+        None => Some(1),
+    };
+    Some((count(old)?, count(new)?))
+}
+
+/// Every section of a whole patch in memory.
+#[cfg(test)]
+pub fn sections(diff: &str, cap: usize) -> Vec<Section> {
+    let mut sections = Sections::new(cap);
+    let mut all: Vec<Section> = diff
+        .lines()
+        .filter_map(|line| sections.push(line))
+        .collect();
+    all.extend(sections.finish());
+    all
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_and_numstat_records_join_on_the_new_path() {
+        let out = ":000000 100644 0000000 ba077a4 A\0b.txt\0\
+                   :100644 100644 587be6b b77b4eb M\0dir with space/keep\0\
+                   :100644 100644 422c2b7 422c2b7 R100\0o ld.txt\0n\tew.txt\0\
+                   :100644 100644 1111111 1111111 C075\0a.txt\0:copy.txt\0\
+                   :100644 000000 2222222 0000000 D\0gone\nline.txt\0\
+                   1\t0\tb.txt\0\
+                   1\t1\tdir with space/keep\0\
+                   0\t0\t\0o ld.txt\0n\tew.txt\0\
+                   2\t0\t\0a.txt\0:copy.txt\0\
+                   -\t-\tgone\nline.txt\0";
+        let parse = |out: &str| {
+            let mut parser = RawNumstat::default();
+            out.split('\0').for_each(|field| parser.push(field));
+            parser.finish()
+        };
+        let entries = parse(out);
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|e| (e.path.as_str(), e.added, e.deleted))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("b.txt", 1, 0),
+                ("dir with space/keep", 1, 1),
+                ("n\tew.txt", 0, 0),
+                (":copy.txt", 2, 0),
+                ("gone\nline.txt", 0, 0),
+            ]
+        );
+        assert!(matches!(entries[0].status, FileStatus::Added));
+        assert!(
+            matches!(&entries[2].status, FileStatus::Renamed { from, .. } if from == "o ld.txt")
+        );
+        assert!(matches!(&entries[3].status, FileStatus::Copied { from, .. } if from == "a.txt"));
+        assert!(matches!(entries[4].status, FileStatus::Deleted));
+
+        assert_eq!(
+            parse_numstat("3\t1\tsrc/lib.rs\0-\t-\tlogo.png\0\t\0"),
+            [
+                ("src/lib.rs".to_string(), 3, 1),
+                ("logo.png".to_string(), 0, 0)
+            ]
+        );
+        assert!(parse("").is_empty());
+    }
+
+    #[test]
+    fn quoted_paths_round_trip() {
+        assert_eq!(unquote("plain name.rs"), "plain name.rs");
+        assert_eq!(unquote(r#""caf\303\251 \"x\".md""#), "café \"x\".md");
+        assert_eq!(unquote(r#""a\tb\\c""#), "a\tb\\c");
+        assert_eq!(unquote(r#""bad\351.txt""#), "bad\u{fffd}.txt");
+        for path in [
+            "tab\there",
+            "quote\"d",
+            "back\\slash",
+            "new\nline",
+            "bell\u{7}",
+        ] {
+            assert_eq!(unquote(&c_quote(path)), path);
+        }
+        assert_eq!(quote("café.md"), "café.md");
+        assert_eq!(quote("a\nb"), "\"a\\nb\"");
+
+        assert_eq!(
+            patch_path(r#""b/caf\303\251.md""#).as_deref(),
+            Some("café.md")
+        );
+        assert_eq!(patch_path("a/src/lib.rs").as_deref(), Some("src/lib.rs"));
+        assert_eq!(patch_path("/dev/null"), None);
+        assert_eq!(header_path("diff --git a/x y.rs b/x y.rs"), "x y.rs");
+        assert_eq!(header_path(r#"diff --git "a/t\tb" "b/t\tb""#), "t\tb");
+        let listing = b"M\0ok.txt\0A\0caf\xe9.txt\0";
+        assert_eq!(
+            non_utf8_path(listing, 14).to_string(),
+            "git listed a path that isn't valid UTF-8: \"caf\u{fffd}.txt\"; scommit only \
+             works with UTF-8 paths, so rename it or commit it with git itself"
+        );
+    }
+
+    #[test]
+    fn sections_cap_their_text_but_count_every_line() {
+        let diff = "diff --git a/src/a.rs b/src/a.rs\n\
+                    --- a/src/a.rs\n\
+                    +++ b/src/a.rs\n\
+                    @@ -1,2 +1,3 @@\n\
+                    -old\n\
+                    +new\n\
+                    +more\n\
+                    diff --git a/logo.png b/logo.png\n\
+                    Binary files a/logo.png and b/logo.png differ\n";
+        let all = sections(diff, 70);
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].path, "src/a.rs");
+        assert_eq!((all[0].added, all[0].deleted), (2, 1));
+        assert!(all[0].truncated);
+        assert_eq!(
+            all[0].text,
+            "diff --git a/src/a.rs b/src/a.rs\n--- a/src/a.rs\n+++ b/src/a.rs\n"
+        );
+        assert!(all[1].binary);
+
+        let whole = sections(diff, usize::MAX);
+        assert_eq!(
+            whole.iter().map(|s| s.text.as_str()).collect::<String>(),
+            diff
+        );
+    }
+    #[test]
+    fn hunk_lines_that_look_like_headers_are_still_counted() {
+        // A removed `-- SQL comment` and an added `++ counter` line.
+        let diff = "diff --git a/db/schema.sql b/db/schema.sql\n\
+                    --- a/db/schema.sql\n\
+                    +++ b/db/schema.sql\n\
+                    @@ -1,3 +1,3 @@\n\
+                    --- SQL comment\n\
+                    +++ counter\n\
+                    \x20CREATE TABLE users (\n\
+                    -  id int\n\
+                    +  id bigint\n\
+                    \\ No newline at end of file\n\
+                    diff --git a/a.txt b/a.txt\n\
+                    --- a/a.txt\n\
+                    +++ b/a.txt\n\
+                    @@ -1 +1 @@\n\
+                    -x\n\
+                    +y\n";
+        let all = sections(diff, usize::MAX);
+        assert_eq!((all[0].added, all[0].deleted), (2, 2));
+        assert_eq!((all[1].added, all[1].deleted), (1, 1));
+        assert_eq!(hunk_lengths("@@ -0,0 +1,12 @@ fn main"), Some((0, 12)));
+    }
+}
@@ -0,0 +1,411 @@
+//! `scommit doctor`: checks what most "scommit doesn't work here" reports come down to
+//! (git itself, the repo's state, the upstream, the AI provider, the config files, the
+//! hooks and any WASM plugins) and prints a fix under anything that's off.
+
+use anyhow::{Result, bail};
+use std::env;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::{self, Config};
+use crate::hooks::{self, HookState, MessageHook};
+use crate::{
+    Cli, ahead_behind, ai_model, current_branch, default_remote, git_output, output, plugins,
+    repo_root, repo_state, upstream_branch,
+};
+
+/// `amend!` commits, which `scommit reword` relies on for older commits, came in 2.32.
+const REWORD_GIT: (u32, u32) = (2, 32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+struct Check {
+    status: Status,
+    what: String,
+    fix: Option<String>,
+}
+
+fn ok(what: impl Into<String>) -> Check {
+    Check {
+        status: Status::Ok,
+        what: what.into(),
+        fix: None,
+    }
+}
+
+fn warn(what: impl Into<String>, fix: impl Into<String>) -> Check {
+    Check {
+        status: Status::Warn,
+        what: what.into(),
+        fix: Some(fix.into()),
+    }
+}
+
+fn fail(what: impl Into<String>, fix: impl Into<String>) -> Check {
+    Check {
+        status: Status::Fail,
+        what: what.into(),
+        fix: Some(fix.into()),
+    }
+}
+
+/// Runs before the config is loaded, so a broken config file is reported rather than
+/// stopping the run.
+pub fn run(cli: &Cli) -> Result<()> {
+    let root = repo_root().ok();
+    let mut sections = vec![("Git", check_git())];
+    if let Some(root) = &root {
+        env::set_current_dir(root)?;
+        sections.push(("Repository", check_repo()));
+        sections.push(("Upstream", check_upstream()));
+    } else {
+        sections.push((
+            "Repository",
+            vec![fail(
+                "not inside a git repository",
+                "run scommit from a repository's worktree, or create one with `git init`",
+            )],
+        ));
+    }
+    sections.push(("AI provider", check_provider(cli)));
+    sections.push(("Config", check_config(root.as_deref())));
+    if let Some(root) = &root
+        && let Ok(loaded) = Config::load(root)
+    {
+        config::init(loaded);
+    }
+    if root.is_some() {
+        sections.push(("Hooks", check_hooks()));
+    }
+    let plugins = check_plugins(root.as_deref());
+    if !plugins.is_empty() {
+        sections.push(("Plugins", plugins));
+    }
+
+    let mut failed = 0;
+    let mut warned = 0;
+    for (title, checks) in &sections {
+        println!("{}", output::bold(title));
+        for check in checks {
+            let mark = match check.status {
+                Status::Ok => output::green("✓"),
+                Status::Warn => {
+                    warned += 1;
+                    output::yellow("!")
+                }
+                Status::Fail => {
+                    failed += 1;
+                    output::red("✗")
+                }
+            };
+            println!("  {mark} {}", check.what);
+            if let Some(fix) = &check.fix {
+                println!("    {}", output::dim(&format!("fix: {fix}")));
+            }
+        }
+    }
+    println!();
+    if failed > 0 {
+        bail!("{failed} check(s) failed and {warned} need attention; see the fixes above");
+    }
+    match warned {
+        0 => println!("Everything looks fine."),
+        n => println!("Nothing is broken; {n} check(s) need attention."),
+    }
+    Ok(())
+}
+
+fn check_git() -> Vec<Check> {
+    let version = match git_output(&["--version"]) {
+        Ok(out) => out.trim().to_string(),
+        Err(_) => {
+            return vec![fail(
+                "git isn't installed or isn't on PATH",
+                "install git from https://git-scm.com and make sure `git --version` works",
+            )];
+        }
+    };
+    let mut checks = vec![match parse_version(&version) {
+        Some(v) if v < REWORD_GIT => warn(
+            version.clone(),
+            format!(
+                "upgrade to git {}.{} or newer; `scommit reword` of older commits needs it",
+                REWORD_GIT.0, REWORD_GIT.1
+            ),
+        ),
+        _ => ok(version),
+    }];
+    // `git var` resolves the identity as `git commit` will: environment, config, guesses.
+    checks.push(match git_output(&["var", "GIT_COMMITTER_IDENT"]) {
+        Ok(ident) => {
+            let ident = ident.trim();
+            let who = ident.find('>').map_or(ident, |end| &ident[..=end]);
+            ok(format!("committing as {who}"))
+        }
+        Err(_) => fail(
+            "git doesn't know who you are, so it can't commit",
+            "git config --global user.name \"Your Name\" && git config --global user.email you@example.com",
+        ),
+    });
+    checks
+}
+
+/// (major, minor) from "git version 2.43.0" (or "git version 2.39.3 (Apple Git-146)").
+fn parse_version(output: &str) -> Option<(u32, u32)> {
+    let version = output.split_whitespace().nth(2)?;
+    let mut parts = version.split('.');
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+fn check_repo() -> Vec<Check> {
+    let mut checks = Vec::new();
+    if git_output(&["rev-parse", "--verify", "--quiet", "HEAD"]).is_err() {
+        checks.push(ok("no commits yet; the first run makes the root commit"));
+    }
+    match current_branch() {
+        Ok(Some(branch)) => checks.push(ok(format!("on branch {branch}"))),
+        Ok(None) => checks.push(warn(
+            "HEAD is detached; commits made here are easily lost",
+            "`git switch -c NAME` first, or pass --branch NAME (or --allow-detached)",
+        )),
+        Err(e) => checks.push(fail(
+            format!("couldn't read HEAD: {e:#}"),
+            "check `git status` for the underlying problem",
+        )),
+    }
+    match repo_state::in_progress() {
+        Ok(Some(op)) => {
+            let conflicts = repo_state::unmerged_paths().unwrap_or_default().len();
+            let what = match conflicts {
+                0 => format!("a {} is in progress", op.name()),
+                n => format!("a {} is in progress with {n} conflicted file(s)", op.name()),
+            };
+            checks.push(warn(
+                what,
+                format!(
+                    "finish it with `git {0} --continue` (or `scommit --continue`), or abort it with `git {0} --abort`",
+                    op.name()
+                ),
+            ));
+        }
+        Ok(None) => checks.push(ok("no merge, rebase, cherry-pick or revert in progress")),
+        Err(e) => checks.push(fail(
+            format!("couldn't check for in-progress operations: {e:#}"),
+            "check `git status` for the underlying problem",
+        )),
+    }
+    checks
+}
+
+fn check_upstream() -> Vec<Check> {
+    let Ok(Some(branch)) = current_branch() else {
+        return vec![ok("no branch checked out, so no upstream to check")];
+    };
+    match upstream_branch() {
+        Ok(Some(upstream)) => match ahead_behind(&upstream) {
+            Ok((ahead, behind)) => vec![ok(format!(
+                "{branch} tracks {upstream} ({ahead} ahead, {behind} behind)"
+            ))],
+            Err(_) => vec![warn(
+                format!("{branch} tracks {upstream}, which doesn't exist"),
+                format!(
+                    "`git fetch`, or point it elsewhere with `git branch --set-upstream-to REMOTE/{branch}`"
+                ),
+            )],
+        },
+        Ok(None) => match default_remote() {
+            Ok(Some(remote)) => vec![warn(
+                format!("{branch} has no upstream, so scommit asks before its first push"),
+                format!("`git push -u {remote} {branch}`, or run scommit with --set-upstream"),
+            )],
+            _ => vec![warn(
+                "no remote is configured, so there is nowhere to push",
+                "`git remote add origin URL`, or commit with --no-push",
+            )],
+        },
+        Err(e) => vec![fail(
+            format!("couldn't read the upstream: {e:#}"),
+            "check `git status -sb` for the underlying problem",
+        )],
+    }
+}
+
+fn check_provider(cli: &Cli) -> Vec<Check> {
+    if cli.no_ai {
+        return vec![ok("AI off (--no-ai); messages come from the heuristic")];
+    }
+    if env::var("OPENAI_API_KEY").is_err() {
+        return vec![warn(
+            "OPENAI_API_KEY isn't set, so messages come from the heuristic",
+            "export OPENAI_API_KEY=... (or pass --no-ai to make that explicit)",
+        )];
+    }
+    let Some(model) = ai_model(cli) else {
+        return vec![ok(
+            "AI off in CI mode; pass --allow-ai to use it there (messages come from the heuristic)",
+        )];
+    };
+    let key = env::var("OPENAI_API_KEY").unwrap_or_default();
+    let response = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .and_then(|client| {
+            client
+                .get(format!("https://api.openai.com/v1/models/{model}"))
+                .bearer_auth(key)
+                .send()
+        });
+    match response {
+        Ok(response) => vec![classify(response.status().as_u16(), &model)],
+        Err(e) => vec![fail(
+            format!("couldn't reach api.openai.com: {e}"),
+            "check the network connection and any proxy settings (HTTPS_PROXY)",
+        )],
+    }
+}
+
+/// What the provider's answer to "GET /v1/models/{model}" says about the setup.
+fn classify(status: u16, model: &str) -> Check {
+    match status {
+        200..=299 => ok(format!(
+            "api.openai.com accepts the key; {model} is available"
+        )),
+        401 => fail(
+            "api.openai.com rejected OPENAI_API_KEY",
+            "create a new key at https://platform.openai.com/api-keys and export it",
+        ),
+        403 | 404 => fail(
+            format!("{model} isn't available to this key"),
+            "pick one from `scommit models` with --model or SCOMMIT_MODEL (the default is gpt-4o-mini)",
+        ),
+        429 => warn(
+            "api.openai.com is rate limiting this key or its quota is used up",
+            "check the usage limits and billing of the key's account",
+        ),
+        status => warn(
+            format!("api.openai.com answered HTTP {status}"),
+            "try again later; scommit falls back to the heuristic message meanwhile",
+        ),
+    }
+}
+
+fn check_config(root: Option<&Path>) -> Vec<Check> {
+    let mut files: Vec<_> = config::user_config_path().into_iter().collect();
+    files.extend(root.map(|root| root.join(config::REPO_CONFIG)));
+    let mut checks = Vec::new();
+    for path in files {
+        match Config::check_file(&path) {
+            Ok(true) => checks.push(ok(format!("{} is valid", path.display()))),
+            Ok(false) => {}
+            // toml's messages span lines; keep each check on one.
+            Err(e) => checks.push(fail(
+                format!("{e:#}").split_whitespace().collect::<Vec<_>>().join(" "),
+                format!(
+                    "correct or remove the setting in {}; the Configuration section of the README lists every key",
+                    path.display()
+                ),
+            )),
+        }
+    }
+    if checks.is_empty() {
+        checks.push(ok("no config files; using the defaults"));
+    }
+    checks
+}
+
+/// Nothing when there are no plugin files, so the section only shows up where it matters.
+fn check_plugins(root: Option<&Path>) -> Vec<Check> {
+    plugins::describe(root)
+        .into_iter()
+        .map(|(path, hooks)| match hooks {
+            Ok(hooks) => ok(format!("{}: {}", path.display(), hooks.join(", "))),
+            Err(_) if !cfg!(feature = "plugins") => warn(
+                format!(
+                    "{} is ignored: scommit was built without the plugins feature",
+                    path.display()
+                ),
+                "reinstall with `cargo install --path . --features plugins`",
+            ),
+            Err(e) => fail(
+                format!("{} doesn't load: {e:#}", path.display()),
+                "fix or remove it; the WASM plugins section of the README lists the exports it needs",
+            ),
+        })
+        .collect()
+}
+
+fn check_hooks() -> Vec<Check> {
+    let mut checks = Vec::new();
+    let mut installed = false;
+    for hook in MessageHook::ALL {
+        let path = hooks::hook_path(hook.name())
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|_| hook.name().to_string());
+        checks.push(match hooks::hook_state(hook) {
+            Ok(HookState::Installed) => {
+                installed = true;
+                ok(format!("{} hook installed", hook.name()))
+            }
+            Ok(HookState::Missing) => ok(format!(
+                "{} hook not installed (optional: `scommit hook install --hook {}`)",
+                hook.name(),
+                hook.name()
+            )),
+            Ok(HookState::Foreign) => ok(format!(
+                "{} is another tool's hook; scommit's isn't installed",
+                path
+            )),
+            Ok(HookState::NotExecutable) => fail(
+                format!("{path} isn't executable, so git skips it"),
+                format!("chmod +x {path}"),
+            ),
+            Err(e) => fail(
+                format!("couldn't read the {} hook: {e:#}", hook.name()),
+                "check `git rev-parse --git-path hooks`",
+            ),
+        });
+    }
+    if installed && !hooks::on_path() {
+        checks.push(fail(
+            "scommit isn't on PATH, so the installed hooks do nothing",
+            "add the directory containing the scommit binary to PATH",
+        ));
+    }
+    match hooks::enforce_pre_commit(&config::get(), false) {
+        Ok(()) => {}
+        Err(e) => checks.push(fail(
+            format!("{e:#}"),
+            "install a pre-commit hook, or drop hooks.require_pre_commit from the config",
+        )),
+    }
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn git_versions_and_provider_answers() {
+        assert_eq!(parse_version("git version 2.43.0"), Some((2, 43)));
+        assert_eq!(
+            parse_version("git version 2.39.3 (Apple Git-146)"),
+            Some((2, 39))
+        );
+        assert_eq!(parse_version("git version 2.45.1.windows.1"), Some((2, 45)));
+        assert_eq!(parse_version("nonsense"), None);
+
+        assert_eq!(classify(200, "gpt-4o-mini").status, Status::Ok);
+        assert_eq!(classify(401, "gpt-4o-mini").status, Status::Fail);
+        let missing = classify(404, "gpt-9");
+        assert_eq!(missing.status, Status::Fail);
+        assert!(missing.what.contains("gpt-9"));
+        assert_eq!(classify(429, "gpt-4o-mini").status, Status::Warn);
+        assert_eq!(classify(503, "gpt-4o-mini").status, Status::Warn);
+    }
+}
@@ -0,0 +1,174 @@
+//! The plan --dry-run prints: every git command the real run would execute after the
+//! message is settled, with the decisions (rebase, new upstream, tag) spelled out.
+
+use anyhow::Result;
+
+use crate::output::{self, say};
+use crate::{
+    Cli, CommitOptions, ahead_behind, current_branch, default_remote, describe_signing, hooks,
+    new_branch_name, prefetch, pull_args, push_args, push_target, tag, upstream_branch,
+};
+
+pub fn print_plan(cli: &Cli, subject: &str, opts: &CommitOptions) -> Result<()> {
+    say!("\n{}", output::bold("Would run:"));
+    let new_branch = new_branch_name(cli, subject)?;
+    if let Some(name) = &new_branch {
+        step(&["switch", "--create", name], None);
+    }
+
+    let mut hook_names = Vec::new();
+    for name in ["pre-commit", "prepare-commit-msg", "commit-msg"] {
+        if (!opts.no_verify || name == "prepare-commit-msg")
+            && hooks::installed_hook(name)?.is_some()
+        {
+            hook_names.push(name);
+        }
+    }
+    let msg_path = hooks::message_file()?.to_string_lossy().into_owned();
+    let mut commit = vec!["commit".to_string()];
+    commit.extend(opts.git_args());
+    commit.extend(["-F".to_string(), msg_path]);
+    let mut notes = Vec::new();
+    if !hook_names.is_empty() {
+        notes.push(format!("after the {} hook(s)", hook_names.join(", ")));
+    }
+    if let Some(signing) = describe_signing(&opts.sign) {
+        notes.push(format!("signed: {signing}"));
+    }
+    let notes = notes.join("; ");
+    step(&commit, Some(notes.as_str()).filter(|n| !n.is_empty()));
+
+    let remote = print_push(cli, new_branch)?;
+    if let Some(spec) = &cli.tag {
+        let name = match spec.as_str() {
+            "auto" => tag::suggest_version(Some(subject))?,
+            name => name.to_string(),
+        };
+        step(
+            &["tag", "-a", &name, "-F", "-"],
+            Some("annotated, listing the commits since the last tag"),
+        );
+        match remote {
+            Some(remote) => step(&["push", &remote, &format!("refs/tags/{name}")], None),
+            None => say!("  {}", output::dim("# the tag is not pushed")),
+        }
+    }
+    Ok(())
+}
+
+/// The pull and push steps, mirroring `push_branch`. Returns the remote pushed to.
+fn print_push(cli: &Cli, new_branch: Option<String>) -> Result<Option<String>> {
+    if cli.no_push {
+        say!("  {}", output::dim("# no push (--no-push)"));
+        return Ok(None);
+    }
+    let target = push_target(cli)?;
+    // A branch created by --branch has no upstream yet.
+    let prefetched = prefetch::take_upstream().transpose()?;
+    let upstream = match (&new_branch, &prefetched) {
+        (Some(_), _) => None,
+        (None, Some(prefetched)) => prefetched.name.clone(),
+        (None, None) => upstream_branch()?,
+    };
+    let remote = match (&target, &upstream) {
+        (Some((remote, _)), _) => remote.clone(),
+        (None, Some(upstream)) => upstream
+            .split_once('/')
+            .map_or(upstream.as_str(), |(r, _)| r)
+            .to_string(),
+        (None, None) => return print_first_push(cli, new_branch),
+    };
+
+    if let Some(upstream) = &upstream
+        && !cli.force_with_lease
+    {
+        // Nothing was committed, so the counts from before still hold.
+        let (_, behind) = match prefetched.and_then(|p| p.ahead_behind) {
+            Some(counts) => counts,
+            None => ahead_behind(upstream)?,
+        };
+        let note = format!("behind {upstream} by {behind} commit(s)");
+        match (behind, cli.skip_pull) {
+            (0, _) => say!(
+                "  {}",
+                output::dim(&format!("# up to date with {upstream}; no rebase"))
+            ),
+            (_, false) => step(&pull_args(cli), Some(&note)),
+            (_, true) => say!(
+                "  {}",
+                output::dim(&format!(
+                    "# {note}; not rebasing (--skip-pull), so the push may be rejected"
+                ))
+            ),
+        }
+    }
+    let ask = (cli.force_with_lease && !cli.yes).then_some("after asking to force-push");
+    let mut args = push_args(cli, target.as_ref());
+    if cli.set_upstream && upstream.is_none() {
+        args.insert(1, "--set-upstream".to_string());
+    }
+    step(&args, ask);
+    Ok(Some(remote))
+}
+
+/// A branch without an upstream, as in `push_new_branch`.
+fn print_first_push(cli: &Cli, new_branch: Option<String>) -> Result<Option<String>> {
+    let branch = match new_branch {
+        Some(branch) => Some(branch),
+        None => current_branch()?,
+    };
+    let (Some(branch), Some(remote)) = (branch, default_remote()?) else {
+        say!(
+            "  {}",
+            output::dim("# no push: no upstream and no remote to push to")
+        );
+        return Ok(None);
+    };
+    let mut args = push_args(cli, Some(&(remote.clone(), branch)));
+    args.insert(1, "--set-upstream".to_string());
+    let ask = (!cli.set_upstream && !cli.yes).then_some("after asking: the branch has no upstream");
+    step(&args, ask);
+    Ok(Some(remote))
+}
+
+/// One `git ...` line, quoted so it can be pasted into a shell, with an optional note.
+fn step<S: AsRef<str>>(args: &[S], note: Option<&str>) {
+    let line: Vec<String> = args.iter().map(|a| shell_quote(a.as_ref())).collect();
+    match note {
+        Some(note) => say!(
+            "  git {}  {}",
+            line.join(" "),
+            output::dim(&format!("# {note}"))
+        ),
+        None => say!("  git {}", line.join(" ")),
+    }
+}
+
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@+,%^~".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_quote_leaves_plain_args_alone() {
+        assert_eq!(
+            shell_quote("--push-option=ci.skip"),
+            "--push-option=ci.skip"
+        );
+        assert_eq!(shell_quote("HEAD:feature/x"), "HEAD:feature/x");
+        assert_eq!(shell_quote("--push-option=a b"), "'--push-option=a b'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+}
@@ -0,0 +1,132 @@
+//! What went wrong, for programs rather than people. Failures carry a [`ScommitError`]
+//! as anyhow context (`.context(ScommitError::CommitFailed)`) under the human-readable
+//! chain; it picks the exit code and is the `failure` object of the `--json` record.
+
+use serde::Serialize;
+use std::fmt;
+
+use crate::exit::Code;
+
+/// The kind of failure, with what an integration needs to react to it. Serialized with
+/// a `kind` tag, e.g. `{"kind": "push_rejected", "stderr": "..."}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScommitError {
+    /// `git add` failed; nothing was committed.
+    StagingFailed,
+    /// Nothing is staged to commit.
+    NoChanges,
+    /// The AI provider was needed and couldn't be used: no key, CI mode, or a failed
+    /// request.
+    AiUnavailable { reason: String },
+    /// A hook, a plugin or `git commit` failed; nothing was committed.
+    CommitFailed,
+    /// The remote refused the push (or the tag push), or it couldn't be reached; the
+    /// commit is kept locally.
+    PushRejected { stderr: String },
+    /// `git pull --rebase` before the push failed without a conflict (the remote
+    /// couldn't be reached, say); the commit is kept locally.
+    PullFailed { stderr: String },
+    /// The pre-push rebase stopped on conflicts in these files; nothing was pushed.
+    RebaseConflict { files: Vec<String> },
+    /// A safety check stopped the run.
+    Refused { check: SafetyCheck },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SafetyCheck {
+    Secrets,
+    SensitiveFiles,
+    LargeFiles,
+    ConflictMarkers,
+    ProtectedBranch,
+    /// `hooks.require_pre_commit` without an installed pre-commit hook.
+    PreCommitHook,
+    /// The AI review raised findings and `--review-block` is set.
+    Review,
+}
+
+impl ScommitError {
+    pub fn code(&self) -> Code {
+        match self {
+            ScommitError::StagingFailed | ScommitError::AiUnavailable { .. } => Code::Error,
+            ScommitError::NoChanges => Code::NothingToCommit,
+            ScommitError::CommitFailed => Code::CommitFailed,
+            ScommitError::PushRejected { .. } => Code::PushFailed,
+            ScommitError::PullFailed { .. } => Code::PullFailed,
+            ScommitError::RebaseConflict { .. } => Code::RebaseConflict,
+            ScommitError::Refused { .. } => Code::Refused,
+        }
+    }
+}
+
+impl fmt::Display for ScommitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScommitError::StagingFailed => f.write_str("staging failed; nothing was committed"),
+            // The reason is the whole story; there is no further cause to show.
+            ScommitError::AiUnavailable { reason } => f.write_str(reason),
+            other => other.code().fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ScommitError {}
+
+/// `err` as an AI failure, keeping its message.
+pub fn ai_unavailable(err: impl Into<anyhow::Error>) -> anyhow::Error {
+    ScommitError::AiUnavailable {
+        reason: format!("{:#}", err.into()),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{Context, anyhow};
+
+    #[test]
+    fn kinds_serialize_tagged_and_keep_the_cli_wording() {
+        let err = ScommitError::PushRejected {
+            stderr: "! [rejected] main -> main (fetch first)\n".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_string(&err).unwrap(),
+            r#"{"kind":"push_rejected","stderr":"! [rejected] main -> main (fetch first)\n"}"#
+        );
+        assert_eq!(err.to_string(), "push failed; the commit is kept locally");
+        let err = ScommitError::PullFailed {
+            stderr: "fatal: unable to access 'https://example.com/r.git/'\n".to_string(),
+        };
+        assert_eq!(serde_json::to_value(&err).unwrap()["kind"], "pull_failed");
+        assert_eq!(err.code() as u8, 10);
+        assert_eq!(
+            serde_json::to_value(ScommitError::Refused {
+                check: SafetyCheck::ProtectedBranch
+            })
+            .unwrap(),
+            serde_json::json!({"kind": "refused", "check": "protected_branch"})
+        );
+
+        let err = ai_unavailable(anyhow!("OpenAI API error: 401 Unauthorized"));
+        assert_eq!(format!("{err:#}"), "OpenAI API error: 401 Unauthorized");
+        let wrapped = err.context("while explaining a1b2c3d");
+        assert_eq!(
+            wrapped
+                .downcast_ref::<ScommitError>()
+                .map(ScommitError::code),
+            Some(Code::Error)
+        );
+        let err = Err::<(), _>(anyhow!("boom"))
+            .context(ScommitError::RebaseConflict {
+                files: vec!["src/lib.rs".to_string()],
+            })
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ScommitError>().unwrap().code(),
+            Code::RebaseConflict
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! Exit codes wrapper scripts can branch on. Failures get theirs from the
+//! [`ScommitError`] they carry; outcomes that aren't errors, like finding nothing to
+//! commit, are noted as the run goes.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::error::ScommitError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Code {
+    Success = 0,
+    /// Any failure without a more specific code. (2 is clap's for bad arguments.)
+    Error = 1,
+    NothingToCommit = 3,
+    /// Committed, but with the heuristic message because AI generation failed.
+    AiFallback = 4,
+    /// A hook or `git commit` failed; nothing was committed.
+    CommitFailed = 5,
+    /// Committed locally, but the push (or the tag push) failed.
+    PushFailed = 6,
+    /// The pre-push rebase stopped on conflicts and needs the user.
+    RebaseConflict = 7,
+    /// A safety check refused: secrets, sensitive or large files, conflict markers,
+    /// a protected branch or a required hook.
+    Refused = 8,
+    /// Declined at a prompt; nothing was committed.
+    Cancelled = 9,
+    /// Committed locally, but the `git pull --rebase` before the push failed.
+    PullFailed = 10,
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Code::Success => "success",
+            Code::Error => "failed",
+            Code::NothingToCommit => "nothing to commit",
+            Code::AiFallback => "AI generation failed; used the heuristic message",
+            Code::CommitFailed => "commit failed; nothing was committed",
+            Code::PushFailed => "push failed; the commit is kept locally",
+            Code::RebaseConflict => "rebase stopped on conflicts; nothing was pushed",
+            Code::Refused => "refused by a safety check",
+            Code::Cancelled => "cancelled",
+            Code::PullFailed => "pull before the push failed; the commit is kept locally",
+        })
+    }
+}
+
+static NOTED: AtomicU8 = AtomicU8::new(Code::Success as u8);
+
+/// Record a non-error outcome. An AI fallback doesn't hide a later "nothing committed".
+pub fn note(code: Code) {
+    let current = NOTED.load(Ordering::SeqCst);
+    if current == Code::Success as u8 || current == Code::AiFallback as u8 {
+        NOTED.store(code as u8, Ordering::SeqCst);
+    }
+}
+
+/// The code for a finished run.
+pub fn code(result: Result<(), &anyhow::Error>) -> u8 {
+    match result {
+        Ok(()) => NOTED.load(Ordering::SeqCst),
+        Err(err) => err
+            .downcast_ref::<ScommitError>()
+            .map_or(Code::Error, ScommitError::code) as u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{Context, anyhow};
+
+    #[test]
+    fn code_finds_the_tag_under_other_context() {
+        let err = Err::<(), _>(anyhow!("git push exited with 1"))
+            .context(ScommitError::PushRejected {
+                stderr: String::new(),
+            })
+            .context("while publishing")
+            .unwrap_err();
+        assert_eq!(code(Err(&err)), 6);
+        assert_eq!(code(Err(&anyhow!("boom"))), 1);
+        assert_eq!(code(Ok(())), 0);
+    }
+}
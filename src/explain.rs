@@ -0,0 +1,127 @@
+//! `scommit explain <rev>`: a plain-language account of an existing commit (what it
+//! changed, what it was likely for and where it could bite) for code archaeology and
+//! reviews. The commit's diff goes through the same redaction and secrets checks as a
+//! new commit's before it reaches the AI provider.
+
+use anyhow::{Context, Result, anyhow, bail};
+use std::env;
+
+use crate::error::{self, SafetyCheck, ScommitError};
+use crate::output::{self, say};
+use crate::reword::CommitView;
+use crate::{
+    Cli, Privacy, Stats, ai_model, chat_completion, collect_staged_changes, compute_stats, config,
+    diff_excerpt, git_output, redact, secrets, staged_added_lines, whitespace,
+};
+
+/// Past commits are read for understanding, so the model gets as much diff as a review.
+const MAX_DIFF_CHARS: usize = 12_000;
+
+const SYSTEM_PROMPT: &str = "You explain an existing git commit to a developer doing code archaeology or a review. Reply in plain text with three short parts, each starting on its own line with its label: \"What changed:\" (the behaviour and structure that changed, not a file-by-file list), \"Likely intent:\" (why someone would make this change, from the message and the code; say when you are guessing) and \"Risk areas:\" (what could break or deserves a closer look, or \"None stand out.\"). Be concrete and brief. Don't repeat the diff.";
+
+pub fn run(cli: &Cli, rev: &str) -> Result<()> {
+    let sha = git_output(&["rev-parse", "--verify", &format!("{rev}^{{commit}}")])
+        .with_context(|| format!("{rev} isn't a commit"))?
+        .trim()
+        .to_string();
+    let Some(model) = ai_model(cli) else {
+        return Err(error::ai_unavailable(anyhow!(
+            "explaining a commit needs the AI provider: set OPENAI_API_KEY (CI mode keeps the AI off unless --allow-ai)"
+        )));
+    };
+    if cli.privacy == Privacy::Metadata {
+        bail!(
+            "--privacy metadata keeps the diff from the AI provider, so there is nothing to explain"
+        );
+    }
+    let parents = git_output(&["rev-list", "--parents", "-n", "1", &sha])?;
+    let note = match parents.split_whitespace().count() {
+        1 => bail!("{rev} is the root commit; scommit explains commits against their parent"),
+        2 => "",
+        _ => " (a merge; compared with its first parent)",
+    };
+    let header = git_output(&[
+        "log",
+        "-1",
+        "--format=%h %s%n%an, %ad",
+        "--date=short",
+        &sha,
+    ])?;
+    let message = git_output(&["log", "-1", "--format=%B", &sha])?;
+
+    let (diff, stats) = {
+        let _view = CommitView::enter(&format!("{sha}^1"), &sha)?;
+        let changes = collect_staged_changes()?;
+        if changes.is_empty() {
+            bail!("{rev} changes nothing; there is no diff to explain");
+        }
+        // Committed secrets are still secrets; don't send them on.
+        secrets::check(&staged_added_lines()?, cli.allow_secrets).context(
+            ScommitError::Refused {
+                check: SafetyCheck::Secrets,
+            },
+        )?;
+        let formatting = whitespace::formatting_only(&changes);
+        let diff = diff_excerpt(MAX_DIFF_CHARS, &config::get().redact, &changes, &formatting)?;
+        let stats = compute_stats(&changes);
+        (diff, stats)
+    };
+
+    let prompt = redact::apply_patterns(
+        &build_prompt(&message, &stats, note, &diff),
+        &config::get().redact,
+    )?;
+    let key = env::var("OPENAI_API_KEY")?;
+    let Some(explanation) = chat_completion(&key, &model, SYSTEM_PROMPT, &prompt, false, 700)?
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+    else {
+        bail!("the AI provider returned no explanation");
+    };
+    let (title, byline) = header
+        .trim()
+        .split_once('\n')
+        .unwrap_or((header.trim(), ""));
+    say!("{}\n{}\n", output::bold(title), output::dim(byline));
+    println!("{explanation}");
+    Ok(())
+}
+
+fn build_prompt(message: &str, stats: &Stats, note: &str, diff: &str) -> String {
+    format!(
+        "Commit message:\n{}\n\n{} file(s) changed (+{}/-{}){note}.\n\nDiff:\n{diff}",
+        message.trim(),
+        stats.files,
+        stats.added,
+        stats.deleted,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompt_carries_the_message_size_and_merge_note() {
+        let stats = Stats {
+            files: 2,
+            added: 30,
+            deleted: 4,
+            ..Stats::default()
+        };
+        let prompt = build_prompt(
+            "fix: retry on timeout\n\nThe client gave up too early.\n",
+            &stats,
+            " (a merge; compared with its first parent)",
+            "diff --git a/src/a.rs b/src/a.rs\n",
+        );
+        assert!(prompt.starts_with(
+            "Commit message:\nfix: retry on timeout\n\nThe client gave up too early.\n\n"
+        ));
+        assert!(
+            prompt
+                .contains("2 file(s) changed (+30/-4) (a merge; compared with its first parent).")
+        );
+        assert!(prompt.ends_with("Diff:\ndiff --git a/src/a.rs b/src/a.rs\n"));
+    }
+}
@@ -0,0 +1,405 @@
+use anyhow::{Context, Result, bail};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::output::say;
+use crate::{
+    CommitOptions, create_commit, diff_parse, git, git_output, git_with_input, upstream_branch,
+};
+
+/// How far back `--fixup auto` looks when the branch has no upstream.
+const AUTO_DEPTH: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Hunk {
+    old_start: u32,
+    old_count: u32,
+    new_start: u32,
+    new_count: u32,
+    lines: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct FilePatch {
+    path: String,
+    hunks: Vec<Hunk>,
+    // Only plain in-place modifications can be blamed and split by hunk.
+    absorbable: bool,
+}
+
+/// Subject for a `fixup!` commit targeting `rev`, as `git commit --fixup` would write it.
+pub fn fixup_subject(rev: &str) -> Result<String> {
+    let subject = git_output(&["log", "-1", "--format=%s", rev])
+        .with_context(|| format!("resolving fixup target {rev}"))?;
+    let subject = subject.trim();
+    if subject.is_empty() {
+        bail!("fixup target {rev} has an empty subject");
+    }
+    Ok(format!("fixup! {subject}"))
+}
+
+/// Match each staged hunk to the unpublished commit that last touched its lines and
+/// create one `fixup!` commit per target. Hunks that can't be attributed stay staged.
+/// Returns the number of fixup commits created (or planned, in dry-run mode).
+pub fn absorb(dry_run: bool, opts: &CommitOptions) -> Result<usize> {
+    let candidates = candidate_commits()?;
+    if candidates.is_empty() {
+        say!("No unpublished commits to fix up; leaving changes staged.");
+        return Ok(0);
+    }
+
+    let diff = git_output(&[
+        "diff",
+        "--cached",
+        "--unified=0",
+        "--no-color",
+        "--no-ext-diff",
+        "--src-prefix=a/",
+        "--dst-prefix=b/",
+    ])?;
+    let files = parse_patch(&diff);
+
+    // target sha -> (file index, hunk index) in diff order
+    let mut groups: BTreeMap<usize, (String, Vec<(usize, usize)>)> = BTreeMap::new();
+    let order: HashMap<&str, usize> = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, sha)| (sha.as_str(), i))
+        .collect();
+    let mut leftover = 0usize;
+
+    for (fi, file) in files.iter().enumerate() {
+        for (hi, hunk) in file.hunks.iter().enumerate() {
+            let target = if file.absorbable {
+                blame_target(&file.path, hunk, &order)?
+            } else {
+                None
+            };
+            match target {
+                Some(sha) => {
+                    groups
+                        .entry(order[sha.as_str()])
+                        .or_insert_with(|| (sha, Vec::new()))
+                        .1
+                        .push((fi, hi));
+                }
+                None => leftover += 1,
+            }
+        }
+        if file.hunks.is_empty() {
+            leftover += 1;
+        }
+    }
+
+    if groups.is_empty() {
+        say!("No staged hunk maps to a single unpublished commit; nothing absorbed.");
+        return Ok(0);
+    }
+
+    // Oldest target first so the fixups read in history order.
+    let plan: Vec<(String, Vec<(usize, usize)>)> = groups.into_values().rev().collect();
+
+    if dry_run {
+        say!("DRY RUN");
+        for (sha, hunks) in &plan {
+            say!("{} ({} hunk(s))", fixup_subject(sha)?, hunks.len());
+        }
+        if leftover > 0 {
+            say!("{leftover} hunk(s)/file(s) would remain staged.");
+        }
+        return Ok(plan.len());
+    }
+
+    let full_tree = git_output(&["write-tree"])?.trim().to_string();
+    let result = commit_groups(&files, &plan, opts);
+    // Whatever happened, put the original staged content back; relative to the new HEAD
+    // the index then only contains the hunks that were not absorbed.
+    git(&["read-tree", &full_tree])?;
+    result?;
+
+    if leftover > 0 {
+        say!("{leftover} hunk(s)/file(s) could not be attributed and remain staged.");
+    }
+    say!(
+        "Created {} fixup commit(s); fold them in with `git rebase -i --autosquash`.",
+        plan.len()
+    );
+    Ok(plan.len())
+}
+
+fn commit_groups(
+    files: &[FilePatch],
+    plan: &[(String, Vec<(usize, usize)>)],
+    opts: &CommitOptions,
+) -> Result<()> {
+    git(&["read-tree", "HEAD"])?;
+    // Per file: (original old_start, line delta) of hunks already committed.
+    let mut applied: HashMap<usize, Vec<(u32, i64)>> = HashMap::new();
+
+    for (sha, hunks) in plan {
+        let patch = render_group(files, hunks, &applied);
+        git_with_input(
+            &[
+                "apply",
+                "--cached",
+                "--unidiff-zero",
+                "--whitespace=nowarn",
+                "-",
+            ],
+            &patch,
+        )
+        .with_context(|| format!("applying hunks for fixup of {sha}"))?;
+
+        let subject = fixup_subject(sha)?;
+        create_commit(&subject, "", opts)?;
+
+        for &(fi, hi) in hunks {
+            let h = &files[fi].hunks[hi];
+            applied
+                .entry(fi)
+                .or_default()
+                .push((h.old_start, h.new_count as i64 - h.old_count as i64));
+        }
+    }
+    Ok(())
+}
+
+fn render_group(
+    files: &[FilePatch],
+    hunks: &[(usize, usize)],
+    applied: &HashMap<usize, Vec<(u32, i64)>>,
+) -> String {
+    let mut by_file: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for &(fi, hi) in hunks {
+        by_file.entry(fi).or_default().push(hi);
+    }
+
+    let mut out = String::new();
+    for (fi, his) in by_file {
+        let file = &files[fi];
+        out.push_str(&format!(
+            "diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}\n",
+            file.path
+        ));
+        let prior = applied.get(&fi).map(Vec::as_slice).unwrap_or(&[]);
+        let mut delta_in_patch = 0i64;
+        for hi in his {
+            let h = &file.hunks[hi];
+            let shift: i64 = prior
+                .iter()
+                .filter(|(start, _)| *start < h.old_start)
+                .map(|(_, d)| d)
+                .sum();
+            let old = h.old_start as i64 + shift;
+            let new = if h.old_count == 0 {
+                old + delta_in_patch + 1
+            } else if h.new_count == 0 {
+                old + delta_in_patch - 1
+            } else {
+                old + delta_in_patch
+            };
+            out.push_str(&format!(
+                "@@ -{},{} +{},{} @@\n",
+                old, h.old_count, new, h.new_count
+            ));
+            for line in &h.lines {
+                out.push_str(line);
+                out.push('\n');
+            }
+            delta_in_patch += h.new_count as i64 - h.old_count as i64;
+        }
+    }
+    out
+}
+
+fn candidate_commits() -> Result<Vec<String>> {
+    let out = match upstream_branch()? {
+        Some(upstream) => {
+            let range = format!("{upstream}..HEAD");
+            git_output(&["rev-list", "--no-merges", &range])?
+        }
+        None => git_output(&[
+            "rev-list",
+            "--no-merges",
+            "-n",
+            &AUTO_DEPTH.to_string(),
+            "HEAD",
+        ])?,
+    };
+    Ok(out
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect())
+}
+
+/// The single candidate commit that owns every line a hunk replaces. Pure insertions
+/// follow the unpublished commit among their neighbouring lines, if there is just one.
+fn blame_target(
+    path: &str,
+    hunk: &Hunk,
+    candidates: &HashMap<&str, usize>,
+) -> Result<Option<String>> {
+    let insertion = hunk.old_count == 0;
+    let (start, end) = if !insertion {
+        (hunk.old_start, hunk.old_start + hunk.old_count - 1)
+    } else if hunk.old_start == 0 {
+        (1, 1)
+    } else {
+        (hunk.old_start, hunk.old_start + 1)
+    };
+
+    let range = format!("{start},{end}");
+    let out = match git_output(&["blame", "--porcelain", "-L", &range, "HEAD", "--", path]) {
+        Ok(out) => out,
+        // Range past the end of the file (insertion at EOF): retry the last line only.
+        Err(_) if insertion && start > 0 => {
+            let range = format!("{start},{start}");
+            git_output(&["blame", "--porcelain", "-L", &range, "HEAD", "--", path])?
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut owners = blame_commits(&out);
+    if insertion {
+        owners.retain(|sha| candidates.contains_key(sha.as_str()));
+    }
+    if owners.len() != 1 {
+        return Ok(None);
+    }
+    let sha = owners.into_iter().next().unwrap_or_default();
+    Ok(candidates.contains_key(sha.as_str()).then_some(sha))
+}
+
+fn blame_commits(porcelain: &str) -> HashSet<String> {
+    porcelain
+        .lines()
+        .filter(|l| !l.starts_with('\t'))
+        .filter_map(|l| {
+            let first = l.split(' ').next()?;
+            (first.len() >= 40 && first.chars().all(|c| c.is_ascii_hexdigit()))
+                .then(|| first.to_string())
+        })
+        .collect()
+}
+
+fn parse_patch(diff: &str) -> Vec<FilePatch> {
+    let mut files: Vec<FilePatch> = Vec::new();
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            files.push(FilePatch {
+                path: String::new(),
+                hunks: Vec::new(),
+                absorbable: true,
+            });
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+
+        if let Some(header) = line.strip_prefix("@@ ") {
+            match parse_hunk_header(header) {
+                Some(hunk) => file.hunks.push(hunk),
+                None => file.absorbable = false,
+            }
+        } else if let Some(hunk) = file.hunks.last_mut() {
+            hunk.lines.push(line.to_string());
+        } else if let Some(path) = line.strip_prefix("+++ ").and_then(diff_parse::patch_path) {
+            file.path = path;
+        } else if line == "+++ /dev/null"
+            || line == "--- /dev/null"
+            || line.starts_with("Binary files")
+            || line.starts_with("old mode")
+            || line.starts_with("new mode")
+            || line.starts_with("rename ")
+            || line.starts_with("copy ")
+            || line.starts_with("similarity")
+        {
+            file.absorbable = false;
+        }
+    }
+    for file in &mut files {
+        if file.path.is_empty() {
+            file.absorbable = false;
+        }
+    }
+    files
+}
+
+fn parse_hunk_header(header: &str) -> Option<Hunk> {
+    let mut parts = header.split_whitespace();
+    let (old_start, old_count) = parse_range(parts.next()?.strip_prefix('-')?)?;
+    let (new_start, new_count) = parse_range(parts.next()?.strip_prefix('+')?)?;
+    Some(Hunk {
+        old_start,
+        old_count,
+        new_start,
+        new_count,
+        lines: Vec::new(),
+    })
+}
+
+fn parse_range(range: &str) -> Option<(u32, u32)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -3 +3 @@ fn a() {
+-    old();
++    new();
+@@ -10,0 +11,2 @@ fn b() {
++    one();
++    two();
+diff --git a/notes.md b/notes.md
+new file mode 100644
+index 0000000..3333333
+--- /dev/null
++++ b/notes.md
+@@ -0,0 +1 @@
++hello
+";
+
+    #[test]
+    fn parse_patch_reads_hunks_and_flags_new_files() {
+        let files = parse_patch(DIFF);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "src/lib.rs");
+        assert!(files[0].absorbable);
+        assert_eq!(files[0].hunks.len(), 2);
+        assert_eq!(
+            (files[0].hunks[1].old_start, files[0].hunks[1].old_count),
+            (10, 0)
+        );
+        assert_eq!(files[0].hunks[1].lines, vec!["+    one();", "+    two();"]);
+        assert!(!files[1].absorbable);
+    }
+
+    #[test]
+    fn render_group_shifts_hunks_after_earlier_fixups() {
+        let files = parse_patch(DIFF);
+        // The first hunk was committed already; it did not change the line count,
+        // so pretend it grew the file by two lines to exercise the shift.
+        let applied = HashMap::from([(0usize, vec![(3u32, 2i64)])]);
+        let patch = render_group(&files, &[(0, 1)], &applied);
+        assert!(patch.contains("@@ -12,0 +13,2 @@\n+    one();\n+    two();\n"));
+    }
+
+    #[test]
+    fn blame_commits_collects_header_shas_only() {
+        let sha = "a".repeat(40);
+        let porcelain = format!("{sha} 3 3 1\nauthor Someone\nsummary x\n\t{sha}\n");
+        let owners = blame_commits(&porcelain);
+        assert_eq!(owners.len(), 1);
+        assert!(owners.contains(&sha));
+    }
+}
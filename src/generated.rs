@@ -0,0 +1,147 @@
+//! Generated files: protobuf and other codegen output, files that say they are
+//! generated in their header, and bundles checked in as one huge line. Their diffs are
+//! noise, so they are kept out of the AI prompt and collapsed to one "regenerate" bullet.
+
+use anyhow::Result;
+use std::collections::HashSet;
+
+use crate::{Category, FileChange, common_dir, git_blobs, linguist};
+
+/// Output names of common code generators.
+const KNOWN_SUFFIXES: &[&str] = &[
+    ".pb.go",
+    ".pb.gw.go",
+    "_pb2.py",
+    "_pb2_grpc.py",
+    "_pb2.pyi",
+    ".pb.cc",
+    ".pb.h",
+    "_pb.js",
+    "_pb.d.ts",
+    "_grpc_pb.js",
+    ".g.dart",
+    ".freezed.dart",
+    ".designer.cs",
+    ".generated.ts",
+    ".generated.go",
+];
+
+/// Header markers generators write: Phabricator's tag and Go's `Code generated ... DO NOT
+/// EDIT.` convention (which most other generators copied).
+const MARKERS: &[&str] = &["@generated", "DO NOT EDIT"];
+
+/// Markers only count near the top, where generators put them; further down they are
+/// more likely code that talks about generated files.
+const HEADER_LINES: usize = 5;
+
+/// A file this big on one or two lines is a bundle or dump, not something anyone wrote.
+const ONE_LINE_BYTES: usize = 20_000;
+
+/// Which of `paths` are generated, by `linguist-generated`, name, or staged content.
+/// Paths that aren't in the index (deletions) are only judged by attribute and name.
+pub fn detect(paths: &[&str]) -> Result<HashSet<String>> {
+    let mut found: HashSet<String> = linguist::lookup(paths)?
+        .into_iter()
+        .filter(|(_, attrs)| attrs.generated)
+        .map(|(path, _)| path)
+        .collect();
+    let (named, unknown): (Vec<&str>, Vec<&str>) = paths
+        .iter()
+        .copied()
+        .filter(|path| !found.contains(*path))
+        .partition(|path| KNOWN_SUFFIXES.iter().any(|suffix| path.ends_with(suffix)));
+    found.extend(named.into_iter().map(str::to_string));
+    let specs: Vec<String> = unknown.iter().map(|path| format!(":{path}")).collect();
+    for (path, blob) in unknown.iter().zip(git_blobs(&specs)?) {
+        if blob.is_some_and(|content| looks_generated(&content)) {
+            found.insert(path.to_string());
+        }
+    }
+    Ok(found)
+}
+
+/// Generated files are never code, tests or config of this repo.
+pub fn apply(changes: &mut [FileChange]) -> Result<()> {
+    let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let found = detect(&paths)?;
+    for change in changes {
+        if found.contains(&change.path) {
+            change.category = Category::Other;
+        }
+    }
+    Ok(())
+}
+
+/// "- regenerate api/user.pb.go (+120/-80)", or a count when there are more than three.
+pub fn summary(files: &[&FileChange]) -> Option<String> {
+    let (added, deleted) = files
+        .iter()
+        .fold((0, 0), |(a, d), c| (a + c.added, d + c.deleted));
+    let what = match files {
+        [] => return None,
+        [one] => one.path.clone(),
+        [rest @ .., last] if files.len() <= 3 => {
+            let rest: Vec<&str> = rest.iter().map(|c| c.path.as_str()).collect();
+            format!("{} & {}", rest.join(", "), last.path)
+        }
+        _ => match common_dir(files.iter().map(|c| c.path.as_str())) {
+            Some(dir) => format!("{} files in {dir}/", files.len()),
+            None => format!("{} generated files", files.len()),
+        },
+    };
+    Some(format!("- regenerate {what} (+{added}/-{deleted})"))
+}
+
+fn looks_generated(content: &[u8]) -> bool {
+    let header: Vec<&[u8]> = content.split(|&b| b == b'\n').take(HEADER_LINES).collect();
+    let marked = header.iter().any(|line| {
+        let line = String::from_utf8_lossy(line);
+        MARKERS.iter().any(|marker| line.contains(marker))
+    });
+    let lines = content.trim_ascii_end().split(|&b| b == b'\n').count();
+    marked || (content.len() >= ONE_LINE_BYTES && lines <= 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileStatus;
+
+    #[test]
+    fn generated_content_and_summary() {
+        assert!(looks_generated(
+            b"// Code generated by protoc-gen-go. DO NOT EDIT.\npackage api\n"
+        ));
+        assert!(looks_generated(b"# @generated by tool\nx = 1\n"));
+        assert!(looks_generated(&[b'x'; ONE_LINE_BYTES]));
+        assert!(!looks_generated(b"fn main() {}\n"));
+        let late = format!("{}// DO NOT EDIT\n", "line\n".repeat(HEADER_LINES));
+        assert!(!looks_generated(late.as_bytes()));
+
+        let change = |path: &str| FileChange {
+            path: path.to_string(),
+            status: FileStatus::Modified,
+            added: 10,
+            deleted: 2,
+            category: Category::Other,
+        };
+        let one = [change("api/user.pb.go")];
+        assert_eq!(
+            summary(&one.iter().collect::<Vec<_>>()).unwrap(),
+            "- regenerate api/user.pb.go (+10/-2)"
+        );
+        let many = [
+            change("api/v1/a.pb.go"),
+            change("api/v1/b.pb.go"),
+            change("api/v1/x/c.pb.go"),
+            change("api/v1/d.pb.go"),
+        ];
+        assert_eq!(
+            summary(&many.iter().collect::<Vec<_>>()).unwrap(),
+            "- regenerate 4 files in api/v1/ (+40/-8)"
+        );
+    }
+}
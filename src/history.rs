@@ -0,0 +1,262 @@
+//! The local history of generated messages, one JSON line per message scommit committed
+//! or printed, in `.git/scommit/history.jsonl`. `scommit log` browses it and brings a
+//! message back, e.g. after an amend replaced one worth keeping.
+
+use anyhow::{Context, Result, bail};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::error::ScommitError;
+use crate::output::{self, say};
+use crate::{Cli, config, confirm, exit, git, git_output, git_path, save_message};
+
+/// Hash of the last prompt sent for a commit message in this run.
+static PROMPT: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Unix seconds.
+    pub time: i64,
+    /// Hash of the model, system prompt and prompt the AI provider was asked with.
+    pub prompt: Option<String>,
+    /// Written by the AI provider rather than the heuristic.
+    pub ai: bool,
+    /// The commit the message went into; None when it was only printed or saved.
+    pub sha: Option<String>,
+    pub message: String,
+}
+
+impl Entry {
+    fn subject(&self) -> &str {
+        self.message.lines().next().unwrap_or_default()
+    }
+}
+
+/// Called as a commit message prompt goes to the AI provider.
+pub fn note_prompt(model: &str, system: &str, prompt: &str) {
+    let hash = fnv1a(&[model, system, prompt]);
+    if let Ok(mut last) = PROMPT.lock() {
+        *last = Some(hash);
+    }
+}
+
+/// Append a message to the history, dropping the oldest entries past `[history] keep`.
+pub fn record(message: &str, sha: Option<&str>) -> Result<()> {
+    let keep = config::get().history.keep;
+    if keep == 0 {
+        return Ok(());
+    }
+    let entry = Entry {
+        time: chrono::Local::now().timestamp(),
+        prompt: PROMPT.lock().ok().and_then(|last| last.clone()),
+        ai: output::inspect(|r| r.ai).unwrap_or(false),
+        sha: sha.map(str::to_string),
+        message: message.to_string(),
+    };
+    let path = path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("opening {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)
+        .with_context(|| format!("writing {}", path.display()))?;
+    drop(file);
+
+    let entries = load()?;
+    if entries.len() > keep {
+        let lines: Vec<String> = entries[entries.len() - keep..]
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<_, _>>()?;
+        fs::write(&path, lines.join("\n") + "\n")
+            .with_context(|| format!("writing {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// `scommit log`: list the history newest first, or print entry `number` (1 = newest),
+/// write it to --output, or with `restore` make it HEAD's message.
+pub fn run(cli: &Cli, number: Option<usize>, limit: usize, restore: bool) -> Result<()> {
+    let entries = load()?;
+    let Some(number) = number else {
+        if restore {
+            bail!("name the entry to restore, e.g. `scommit log 2 --restore`");
+        }
+        if entries.is_empty() {
+            say!("No messages recorded yet.");
+            return Ok(());
+        }
+        for (i, entry) in entries.iter().rev().take(limit).enumerate() {
+            println!("{}", line(i + 1, entry));
+        }
+        if entries.len() > limit {
+            say!(
+                "{}",
+                output::dim(&format!("({} older; see --limit)", entries.len() - limit))
+            );
+        }
+        return Ok(());
+    };
+    let Some(entry) = number
+        .checked_sub(1)
+        .and_then(|i| entries.iter().rev().nth(i))
+    else {
+        bail!(
+            "there is no entry {number}; the history has {}",
+            entries.len()
+        );
+    };
+    if restore {
+        return restore_to_head(cli, entry);
+    }
+    match &cli.output {
+        Some(path) => {
+            let (subject, body) = entry
+                .message
+                .split_once("\n\n")
+                .unwrap_or((entry.message.trim_end(), ""));
+            save_message(path, subject, body)
+        }
+        None => {
+            print!("{}", entry.message);
+            Ok(())
+        }
+    }
+}
+
+/// `  3  2026-10-14 09:12  ai         1a2b3c4  feat: add parser`
+fn line(number: usize, entry: &Entry) -> String {
+    let when = chrono::DateTime::from_timestamp(entry.time, 0)
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default();
+    let source = if entry.ai { "ai" } else { "heuristic" };
+    let sha = entry.sha.as_deref().map_or_else(
+        || "printed".to_string(),
+        |sha| sha[..7.min(sha.len())].to_string(),
+    );
+    format!(
+        "{number:>3}  {}  {source:<9}  {sha:<7}  {}",
+        output::dim(&when),
+        entry.subject()
+    )
+}
+
+fn restore_to_head(cli: &Cli, entry: &Entry) -> Result<()> {
+    let head = git_output(&["rev-parse", "HEAD"])
+        .context("there is no commit to restore the message to")?
+        .trim()
+        .to_string();
+    if cli.dry_run {
+        say!("Would amend {} with: {}", &head[..7], entry.subject());
+        return Ok(());
+    }
+    let published = git_output(&["branch", "--remotes", "--contains", &head])?;
+    if !published.trim().is_empty()
+        && !cli.yes
+        && !confirm(
+            "amend-pushed",
+            &format!(
+                "{} is already pushed; amending rewrites published history. Continue?",
+                &head[..7]
+            ),
+        )?
+    {
+        exit::note(exit::Code::Cancelled);
+        return Ok(());
+    }
+    let msg_path = git_path("SCOMMIT_RESTORE")?;
+    fs::write(&msg_path, &entry.message)
+        .with_context(|| format!("writing {}", msg_path.display()))?;
+    let result = git(&[
+        "commit",
+        "--amend",
+        "--only",
+        "--quiet",
+        "-F",
+        &msg_path.to_string_lossy(),
+    ]);
+    fs::remove_file(&msg_path).ok();
+    result.context(ScommitError::CommitFailed)?;
+    say!("Restored HEAD's message: {}", entry.subject());
+    Ok(())
+}
+
+/// The commits recorded messages went into.
+pub fn committed() -> HashSet<String> {
+    load()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|entry| entry.sha)
+        .collect()
+}
+
+fn path() -> Result<PathBuf> {
+    git_path("scommit/history.jsonl")
+}
+
+/// Oldest first. Lines that don't parse (a torn write, a newer format) are skipped.
+fn load() -> Result<Vec<Entry>> {
+    let path = path()?;
+    let raw = match fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+    };
+    Ok(parse(&raw))
+}
+
+fn parse(raw: &str) -> Vec<Entry> {
+    raw.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// 64-bit FNV-1a as hex: stable across builds, unlike std's hasher. The parts are
+/// separated so ("ab", "c") and ("a", "bc") differ.
+fn fnv1a(parts: &[&str]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for byte in part.bytes().chain([0]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_round_trip_and_list() {
+        let entry = Entry {
+            time: 1_760_000_000,
+            prompt: Some(fnv1a(&["gpt-4o-mini", "system", "prompt"])),
+            ai: true,
+            sha: Some("1a2b3c4d5e6f".to_string()),
+            message: "feat: add parser\n\n- add src/parse.rs\n".to_string(),
+        };
+        let raw = format!("{}\n{{\"torn\n", serde_json::to_string(&entry).unwrap());
+        assert_eq!(parse(&raw), vec![entry.clone()]);
+        assert_ne!(fnv1a(&["ab", "c"]), fnv1a(&["a", "bc"]));
+        assert_eq!(entry.prompt.as_deref().map(str::len), Some(16));
+
+        let listed = line(1, &entry);
+        assert!(listed.starts_with("  1  "));
+        assert!(listed.ends_with("  ai         1a2b3c4  feat: add parser"));
+    }
+}
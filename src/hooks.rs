@@ -0,0 +1,471 @@
+use anyhow::{Context, Result, bail};
+use clap::{Subcommand, ValueEnum};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crate::config::Config;
+use crate::output::say;
+use crate::session;
+use crate::{
+    Cli, build_commit_message, collect_staged_changes, commit_message_text, compose_message,
+    compute_stats, git_output, git_path, log_command, output, secrets, staged_added_lines,
+};
+
+/// `scommit hook ...`: run as one of git's own hooks instead of driving the commit.
+#[derive(Subcommand, Debug)]
+pub enum HookAction {
+    /// Pre-fill the message of a plain `git commit` (called by git, not by hand)
+    PrepareCommitMsg {
+        /// The message file git will open in the editor
+        file: PathBuf,
+        /// Where the message came from: message, template, merge, squash or commit
+        source: Option<String>,
+        /// The commit being amended, for source `commit`
+        sha: Option<String>,
+    },
+    /// Replace a placeholder message ("wip", "fix", ...) with a generated one (called by
+    /// git, not by hand)
+    CommitMsg {
+        /// The message file git is about to commit
+        file: PathBuf,
+    },
+    /// Install scommit's message hooks in this repository (honors core.hooksPath)
+    Install {
+        /// Which hook to install (repeatable)
+        #[arg(
+            long = "hook",
+            value_enum,
+            value_name = "HOOK",
+            default_value = "prepare-commit-msg"
+        )]
+        hooks: Vec<MessageHook>,
+        /// Replace an existing hook that scommit didn't write
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove the hooks scommit installed; other hooks are left alone
+    Uninstall {
+        /// Which hook to remove (repeatable; default: both)
+        #[arg(long = "hook", value_enum, value_name = "HOOK")]
+        hooks: Vec<MessageHook>,
+    },
+    /// Show where git looks for hooks and which of scommit's are installed
+    Status,
+}
+
+/// The hooks scommit can be installed as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MessageHook {
+    /// Pre-fill the editor of a plain `git commit` with the generated message
+    PrepareCommitMsg,
+    /// Replace placeholder messages like `git commit -m wip` with the generated one
+    CommitMsg,
+}
+
+impl MessageHook {
+    pub const ALL: [MessageHook; 2] = [MessageHook::PrepareCommitMsg, MessageHook::CommitMsg];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MessageHook::PrepareCommitMsg => "prepare-commit-msg",
+            MessageHook::CommitMsg => "commit-msg",
+        }
+    }
+
+    /// What the installed script says it is for.
+    fn purpose(self) -> &'static str {
+        match self {
+            MessageHook::PrepareCommitMsg => "pre-fills plain `git commit` messages",
+            MessageHook::CommitMsg => "replaces placeholder messages like `wip`",
+        }
+    }
+
+    /// Marks hook scripts scommit wrote, so reinstalling never clobbers someone else's.
+    fn marker(self) -> String {
+        format!("scommit hook {}", self.name())
+    }
+}
+
+/// Messages that say nothing about the change; the commit-msg hook replaces them.
+const PLACEHOLDERS: &[&str] = &[
+    "wip", "fix", "fixes", "update", "updates", "changes", "stuff", "tmp", "temp", "misc", "save",
+    "commit", "asdf", "x", ".", "-", "...",
+];
+
+pub fn run_action(cli: &Cli, action: &HookAction) -> Result<()> {
+    match action {
+        HookAction::PrepareCommitMsg { file, source, .. } => {
+            // A hook that fails aborts the user's commit; a missing suggestion shouldn't.
+            if let Err(err) = prepare_commit_msg(cli, file, source.as_deref()) {
+                eprintln!("scommit: no message suggested ({err:#})");
+            }
+            Ok(())
+        }
+        HookAction::CommitMsg { file } => {
+            if let Err(err) = commit_msg(cli, file) {
+                eprintln!("scommit: message left as it was ({err:#})");
+            }
+            Ok(())
+        }
+        HookAction::Install { hooks, force } => {
+            for hook in hooks {
+                install(*hook, *force)?;
+            }
+            Ok(())
+        }
+        HookAction::Uninstall { hooks } => {
+            let hooks = if hooks.is_empty() {
+                &MessageHook::ALL[..]
+            } else {
+                hooks
+            };
+            for hook in hooks {
+                uninstall(*hook)?;
+            }
+            Ok(())
+        }
+        HookAction::Status => status(),
+    }
+}
+
+/// Write the generated message above git's comment lines, but only when git would
+/// otherwise open an empty message: not for -m/-F, merges, squashes or amends.
+fn prepare_commit_msg(cli: &Cli, file: &Path, source: Option<&str>) -> Result<()> {
+    let existing =
+        fs::read_to_string(file).with_context(|| format!("reading {}", file.display()))?;
+    if !wants_message(source, &existing) {
+        return Ok(());
+    }
+    let changes = collect_staged_changes()?;
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let stats = compute_stats(&changes);
+    // The staged diff only goes to the AI API if it passes the same secrets check as a
+    // normal run; otherwise fall back to the local heuristic.
+    let (subject, body) = match secrets::check(&staged_added_lines()?, false) {
+        Ok(()) => compose_message(cli, &changes, &stats, None)?,
+        Err(err) => {
+            eprintln!("scommit: {err:#}");
+            build_commit_message(&changes, &stats)
+        }
+    };
+    fs::write(
+        file,
+        format!("{}{existing}", commit_message_text(&subject, &body)),
+    )
+    .with_context(|| format!("writing {}", file.display()))
+}
+
+/// Swap a placeholder message for the generated one; anything else is the author's
+/// message and stays.
+fn commit_msg(cli: &Cli, file: &Path) -> Result<()> {
+    let existing =
+        fs::read_to_string(file).with_context(|| format!("reading {}", file.display()))?;
+    if !is_placeholder(&existing) {
+        return Ok(());
+    }
+    let changes = collect_staged_changes()?;
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let stats = compute_stats(&changes);
+    let (subject, body) = match secrets::check(&staged_added_lines()?, false) {
+        Ok(()) => compose_message(cli, &changes, &stats, None)?,
+        Err(err) => {
+            eprintln!("scommit: {err:#}");
+            build_commit_message(&changes, &stats)
+        }
+    };
+    fs::write(file, commit_message_text(&subject, &body))
+        .with_context(|| format!("writing {}", file.display()))?;
+    eprintln!(
+        "scommit: replaced \"{}\" with \"{subject}\"",
+        existing.lines().next().unwrap_or_default().trim()
+    );
+    Ok(())
+}
+
+/// One line from PLACEHOLDERS (any case), once comments and blank lines are dropped.
+fn is_placeholder(message: &str) -> bool {
+    let lines: Vec<&str> = message
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .collect();
+    match lines.as_slice() {
+        [only] => PLACEHOLDERS.iter().any(|p| only.eq_ignore_ascii_case(p)),
+        _ => false,
+    }
+}
+
+/// Only a plain `git commit` (or a template that is nothing but comments) leaves the
+/// user facing an empty message.
+fn wants_message(source: Option<&str>, existing: &str) -> bool {
+    matches!(source, None | Some("template"))
+        && existing
+            .lines()
+            .all(|l| l.trim().is_empty() || l.starts_with('#'))
+}
+
+fn install(hook: MessageHook, force: bool) -> Result<()> {
+    let path = hook_path(hook.name())?;
+    if let Ok(current) = fs::read_to_string(&path)
+        && !current.contains(&hook.marker())
+        && !force
+    {
+        bail!(
+            "{} already exists and wasn't written by scommit; pass --force to replace it",
+            path.display()
+        );
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+    let script = format!(
+        "#!/bin/sh\n# Installed by `scommit hook install`: {}.\n# Skipped quietly where scommit isn't on PATH (e.g. some GUI clients).\ncommand -v scommit >/dev/null 2>&1 || exit 0\nexec {} \"$@\"\n",
+        hook.purpose(),
+        hook.marker()
+    );
+    fs::write(&path, script).with_context(|| format!("writing {}", path.display()))?;
+    make_executable(&path)?;
+    say!("Installed the {} hook at {}", hook.name(), path.display());
+    Ok(())
+}
+
+fn uninstall(hook: MessageHook) -> Result<()> {
+    let path = hook_path(hook.name())?;
+    match fs::read_to_string(&path) {
+        Ok(current) if current.contains(&hook.marker()) => {
+            fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))?;
+            say!("Removed the {} hook from {}", hook.name(), path.display());
+        }
+        Ok(_) => say!("Left {} alone: scommit didn't write it", path.display()),
+        Err(_) => say!("No {} hook installed", hook.name()),
+    }
+    Ok(())
+}
+
+fn status() -> Result<()> {
+    let hooks_path = git_output(&["config", "--get", "core.hooksPath"]).unwrap_or_default();
+    let dir = hook_path("")?;
+    match hooks_path.trim() {
+        "" => say!("Hooks directory: {}", dir.display()),
+        configured => say!(
+            "Hooks directory: {} (core.hooksPath = {configured})",
+            dir.display()
+        ),
+    }
+    for hook in MessageHook::ALL {
+        let state = match hook_state(hook)? {
+            HookState::Missing => "not installed".to_string(),
+            HookState::Installed => format!("installed by scommit; {}", hook.purpose()),
+            HookState::NotExecutable => {
+                "installed by scommit but not executable, so git skips it".to_string()
+            }
+            HookState::Foreign => {
+                "another hook is installed (replace it with `install --force`)".to_string()
+            }
+        };
+        say!("  {:<19} {state}", hook.name());
+    }
+    if !on_path() {
+        say!("scommit isn't on PATH here, so installed hooks do nothing.");
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookState {
+    Missing,
+    Installed,
+    /// Written by scommit, but git skips hooks it can't execute.
+    NotExecutable,
+    /// Some other hook is in the way.
+    Foreign,
+}
+
+pub fn hook_state(hook: MessageHook) -> Result<HookState> {
+    let path = hook_path(hook.name())?;
+    Ok(match fs::read_to_string(&path) {
+        Err(_) => HookState::Missing,
+        Ok(current) if current.contains(&hook.marker()) && is_executable(&path) => {
+            HookState::Installed
+        }
+        Ok(current) if current.contains(&hook.marker()) => HookState::NotExecutable,
+        Ok(_) => HookState::Foreign,
+    })
+}
+
+/// Whether a hook's `command -v scommit` would find us.
+pub fn on_path() -> bool {
+    Command::new("scommit")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("making {} executable", path.display()))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Where git would look for hook `name`, honoring core.hooksPath and worktrees.
+pub fn hook_path(name: &str) -> Result<PathBuf> {
+    git_path(&format!("hooks/{name}"))
+}
+
+/// The hook's path if it is installed and git would actually run it.
+pub fn installed_hook(name: &str) -> Result<Option<PathBuf>> {
+    let path = hook_path(name)?;
+    Ok(is_executable(&path).then_some(path))
+}
+
+/// Scratch file holding the message while the message hooks run.
+pub fn message_file() -> Result<PathBuf> {
+    git_path("SCOMMIT_EDITMSG")
+}
+
+/// Run the configured `hooks.commands` through `sh`, in order, stopping at the first
+/// failure with that command's output.
+pub fn run_commands(commands: &[String]) -> Result<()> {
+    for command in commands {
+        if !output::quiet() {
+            eprintln!("Running: {command}");
+        }
+        let mut sh = session::command("sh");
+        sh.arg("-c").arg(command);
+        run_echoing(sh, &format!("`{command}`"))?;
+    }
+    Ok(())
+}
+
+fn run_echoing(mut command: Command, label: &str) -> Result<()> {
+    let started = Instant::now();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("running {label} ({})", command.get_program().display()))?;
+
+    let captured = Arc::new(Mutex::new(String::new()));
+    let readers: Vec<_> = [
+        child
+            .stdout
+            .take()
+            .map(|s| Box::new(s) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|s| Box::new(s) as Box<dyn Read + Send>),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|stream| {
+        let captured = Arc::clone(&captured);
+        thread::spawn(move || {
+            for line in BufReader::new(stream).lines().map_while(|l| l.ok()) {
+                if !output::quiet() {
+                    writeln!(std::io::stderr(), "{line}").ok();
+                }
+                if let Ok(mut buf) = captured.lock() {
+                    buf.push_str(&line);
+                    buf.push('\n');
+                }
+            }
+        })
+    })
+    .collect();
+    for reader in readers {
+        reader.join().ok();
+    }
+
+    let status = child.wait()?;
+    log_command(&command, Some(status), started);
+    if !status.success() {
+        let output = captured
+            .lock()
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default();
+        let code = status
+            .code()
+            .map(|c| format!("exit {c}"))
+            .unwrap_or_else(|| "killed".to_string());
+        if output.is_empty() {
+            bail!("{label} failed ({code})");
+        }
+        bail!("{label} failed ({code}):\n{output}");
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Apply `hooks.require_pre_commit`: the hook must exist and may not be bypassed.
+pub fn enforce_pre_commit(config: &Config, no_verify: bool) -> Result<()> {
+    if !config.hooks.require_pre_commit {
+        return Ok(());
+    }
+    if no_verify {
+        bail!("--no-verify refused: hooks.require_pre_commit is set in the scommit config");
+    }
+    if installed_hook("pre-commit")?.is_none() {
+        bail!(
+            "hooks.require_pre_commit is set but no executable pre-commit hook exists at {}",
+            hook_path("pre-commit")?.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wants_message_only_for_empty_plain_commits() {
+        let comments = "\n# Please enter the commit message for your changes.\n";
+        assert!(wants_message(None, comments));
+        assert!(wants_message(Some("template"), comments));
+        assert!(!wants_message(Some("template"), "feat: \n\n# Why:\n"));
+        assert!(!wants_message(Some("message"), ""));
+        assert!(!wants_message(Some("merge"), comments));
+        assert!(!wants_message(Some("commit"), comments));
+    }
+
+    #[test]
+    fn only_placeholder_messages_are_replaced() {
+        assert!(is_placeholder("wip\n"));
+        assert!(is_placeholder("WIP\n\n# Please enter the commit message\n"));
+        assert!(is_placeholder(" fix \n"));
+        assert!(!is_placeholder("fix: handle empty input\n"));
+        assert!(!is_placeholder("wip\n\nhalf of the parser\n"));
+        assert!(!is_placeholder("# only comments\n"));
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::{Context, Result};
+use std::fs;
+
+use crate::output::{self, say};
+use crate::repo_state::split_message;
+use crate::{
+    Cli, FileChange, FileStatus, Stats, ai_model, compose_message, diff_parse, hooks,
+    launch_editor, prompt_line,
+};
+
+/// Show the generated message with a change summary and ask before committing. Returns
+/// the (possibly edited) message, or None if the user quit. With --edit the editor takes
+/// the place of the prompt; without a terminal, or with --yes or --quiet, nothing is
+/// asked so scripted runs behave as before.
+pub fn confirm_message(
+    cli: &Cli,
+    changes: &[FileChange],
+    stats: &Stats,
+    subject: String,
+    body: String,
+) -> Result<Option<(String, String)>> {
+    if cli.edit {
+        return edit_message(&subject, &body);
+    }
+    if cli.yes || cli.quiet || output::ci() || !output::can_prompt() {
+        return Ok(Some((subject, body)));
+    }
+
+    let (mut subject, mut body) = (subject, body);
+    print_summary(changes, stats);
+    loop {
+        say!("\n{}\n", output::message(&subject, &body));
+        let answer = prompt_line("action", "[c]ommit, [e]dit, [r]egenerate, [q]uit? ")?;
+        match answer.to_ascii_lowercase().as_str() {
+            "c" | "commit" => return Ok(Some((subject, body))),
+            "e" | "edit" => match edit_message(&subject, &body)? {
+                Some(edited) => (subject, body) = edited,
+                None => say!("Empty message; keeping the previous one."),
+            },
+            "r" | "regenerate" => {
+                if ai_model(cli).is_none() {
+                    say!(
+                        "AI generation is off (--no-ai or no OPENAI_API_KEY); the heuristic message won't change."
+                    );
+                    continue;
+                }
+                let hint = prompt_line(
+                    "hint",
+                    "Hint for the new message (optional, e.g. \"focus on the API change\"): ",
+                )?;
+                let hint = (!hint.is_empty()).then_some(hint.as_str());
+                (subject, body) = compose_message(cli, changes, stats, hint)?;
+            }
+            "q" | "quit" => return Ok(None),
+            _ => say!("Please answer c, e, r or q."),
+        }
+    }
+}
+
+/// Open the message in the user's editor, like `git commit` does with COMMIT_EDITMSG.
+/// Comment lines are dropped; None means the user saved an empty message.
+pub fn edit_message(subject: &str, body: &str) -> Result<Option<(String, String)>> {
+    let path = hooks::message_file()?;
+    let mut buffer = format!("{subject}\n\n{body}\n");
+    buffer.push_str(
+        "\n# Edit the commit message above. Lines starting with '#' are ignored,\n\
+         # and an empty message aborts the commit.\n",
+    );
+    fs::write(&path, buffer).with_context(|| format!("writing {}", path.display()))?;
+    launch_editor(&[&path.to_string_lossy()])?;
+    let edited =
+        fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    Ok(split_message(&edited))
+}
+
+/// One line per file: status, path, +/- counts and category badge.
+pub fn print_summary(changes: &[FileChange], stats: &Stats) {
+    say!(
+        "{} file(s), {}",
+        stats.files,
+        output::line_counts(stats.added, stats.deleted)
+    );
+    for change in changes {
+        let status = match &change.status {
+            FileStatus::Added => output::green("A"),
+            FileStatus::Modified => output::yellow("M"),
+            FileStatus::Deleted => output::red("D"),
+            FileStatus::Renamed { from, .. } => {
+                format!("{} {} ->", output::yellow("R"), diff_parse::quote(from))
+            }
+            FileStatus::Copied { from, .. } => {
+                format!("{} {} ->", output::green("C"), diff_parse::quote(from))
+            }
+            FileStatus::TypeChanged => output::yellow("T"),
+        };
+        say!(
+            "  {status} {} ({}) {}",
+            diff_parse::quote(&change.path),
+            output::line_counts(change.added, change.deleted),
+            output::badge(change.category)
+        );
+    }
+}
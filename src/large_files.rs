@@ -0,0 +1,153 @@
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use std::fs;
+
+use crate::config::LargeFilesConfig;
+use crate::output::{self, say};
+use crate::{FileChange, confirm, diff_parse, git_output};
+
+/// Paths routed through git-lfs by .gitattributes; their blobs are pointers, not content.
+fn lfs_tracked(paths: &[&str]) -> Vec<String> {
+    if paths.is_empty() {
+        return Vec::new();
+    }
+    let mut args = vec!["check-attr", "filter", "--"];
+    args.extend_from_slice(paths);
+    git_output(&args)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.strip_suffix(": filter: lfs"))
+        .map(|path| diff_parse::unquote(path).into_owned())
+        .collect()
+}
+
+/// Warn about (or, with `block`, refuse) files over the configured size before they are
+/// staged; once pushed, a huge blob stays in the history for good.
+pub fn check(config: &LargeFilesConfig, paths: &[String], allow: bool) -> Result<()> {
+    let limit = config.threshold_mb * 1024 * 1024;
+    let large: Vec<(String, u64)> = paths
+        .iter()
+        .filter_map(|path| {
+            let size = fs::symlink_metadata(path).ok()?.len();
+            (size > limit).then(|| (path.clone(), size))
+        })
+        .collect();
+    let paths: Vec<&str> = large.iter().map(|(p, _)| p.as_str()).collect();
+    let lfs = lfs_tracked(&paths);
+    let large: Vec<&(String, u64)> = large.iter().filter(|(p, _)| !lfs.contains(p)).collect();
+    if large.is_empty() {
+        return Ok(());
+    }
+
+    let mut report = format!("files over {} MB:\n", config.threshold_mb);
+    for (path, size) in &large {
+        report.push_str(&format!("  {path} ({:.1} MB)\n", *size as f64 / 1048576.0));
+    }
+    report.push_str("Add them to .gitignore, or track them with git-lfs: git lfs track ");
+    let quoted: Vec<String> = large.iter().map(|(p, _)| format!("\"{p}\"")).collect();
+    report.push_str(&quoted.join(" "));
+
+    if config.block && !allow {
+        bail!("refusing to commit {report}\nPass --allow-large-files to commit them anyway.");
+    }
+    eprintln!("Warning: {report}");
+    Ok(())
+}
+
+/// Whether the staged set is over `max_staged_files`/`max_staged_lines`; a stray vendor or
+/// build directory usually shows up here first.
+fn oversized(config: &LargeFilesConfig, changes: &[FileChange]) -> bool {
+    let lines: u64 = changes
+        .iter()
+        .map(|c| u64::from(c.added) + u64::from(c.deleted))
+        .sum();
+    (config.max_staged_files > 0 && changes.len() > config.max_staged_files)
+        || (config.max_staged_lines > 0 && lines > config.max_staged_lines)
+}
+
+/// Directories with the most staged files, biggest first.
+fn busiest_dirs(changes: &[FileChange], limit: usize) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for change in changes {
+        let dir = match change.path.rsplit_once('/') {
+            Some((dir, _)) => dir.to_string(),
+            None => ".".to_string(),
+        };
+        *counts.entry(dir).or_default() += 1;
+    }
+    let mut dirs: Vec<(String, usize)> = counts.into_iter().collect();
+    dirs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    dirs.truncate(limit);
+    dirs
+}
+
+/// Flag an unusually large staged set, listing the biggest directories and files, and
+/// (when `ask` and on a terminal) ask before going on. Returns false if the user declined.
+pub fn confirm_staged_size(
+    config: &LargeFilesConfig,
+    changes: &[FileChange],
+    ask: bool,
+) -> Result<bool> {
+    if !oversized(config, changes) {
+        return Ok(true);
+    }
+    let lines: u64 = changes
+        .iter()
+        .map(|c| u64::from(c.added) + u64::from(c.deleted))
+        .sum();
+    say!(
+        "About to commit {} file(s) and {lines} changed line(s). Biggest directories:",
+        changes.len()
+    );
+    for (dir, count) in busiest_dirs(changes, 5) {
+        say!("  {dir}/ ({count} files)");
+    }
+    let mut biggest: Vec<&FileChange> = changes.iter().collect();
+    biggest.sort_by_key(|c| std::cmp::Reverse(c.added + c.deleted));
+    say!("Biggest files:");
+    for change in biggest.iter().take(5) {
+        say!("  {} (+{}/-{})", change.path, change.added, change.deleted);
+    }
+    if !ask || !output::can_prompt() {
+        return Ok(true);
+    }
+    confirm("large-commit", "Commit all of this?")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Category, FileStatus};
+
+    fn change(path: &str, lines: u32) -> FileChange {
+        FileChange {
+            path: path.to_string(),
+            status: FileStatus::Added,
+            added: lines,
+            deleted: 0,
+            category: Category::Other,
+        }
+    }
+
+    #[test]
+    fn oversized_staging_is_flagged_and_summarized_by_directory() {
+        let config = LargeFilesConfig {
+            max_staged_files: 3,
+            max_staged_lines: 100,
+            ..LargeFilesConfig::default()
+        };
+        let small = vec![change("src/a.rs", 10), change("README.md", 5)];
+        assert!(!oversized(&config, &small));
+        assert!(oversized(&config, &[change("dump.sql", 500)]));
+
+        let vendored: Vec<FileChange> = (0..4)
+            .map(|i| change(&format!("vendor/lib/f{i}.js"), 1))
+            .chain(std::iter::once(change("src/a.rs", 1)))
+            .collect();
+        assert!(oversized(&config, &vendored));
+        assert_eq!(
+            busiest_dirs(&vendored, 5),
+            vec![("vendor/lib".to_string(), 4), ("src".to_string(), 1)]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use std::path::Path;
+
+use crate::AddedLine;
+use crate::config::LeftoversConfig;
+
+/// Built-in patterns by file extension; `*` applies to every file.
+fn builtin_patterns(ext: &str) -> &'static [&'static str] {
+    match ext {
+        "*" => &[
+            "TODO(remove)",
+            "FIXME(remove)",
+            "DO NOT COMMIT",
+            "DONOTCOMMIT",
+        ],
+        "rs" => &["dbg!(", "println!(\"DEBUG"],
+        "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" | "vue" | "svelte" => {
+            &["console.log(", "debugger;"]
+        }
+        "py" => &[
+            "breakpoint()",
+            "pdb.set_trace()",
+            "import pdb",
+            "import ipdb",
+        ],
+        "rb" => &["binding.pry", "byebug", "binding.irb"],
+        "php" => &["var_dump(", "dd(", "dump("],
+        "go" => &["spew.Dump("],
+        "java" | "kt" => &["System.out.println(\"DEBUG", "e.printStackTrace()"],
+        "ex" | "exs" => &["IO.inspect("],
+        _ => &[],
+    }
+}
+
+/// Patterns for `ext`: the configured list if there is one (an empty list switches the
+/// built-ins off), otherwise the built-ins.
+fn patterns_for(config: &LeftoversConfig, ext: &str) -> Vec<String> {
+    match config.patterns.get(ext) {
+        Some(patterns) => patterns.clone(),
+        None => builtin_patterns(ext)
+            .iter()
+            .map(|p| p.to_string())
+            .collect(),
+    }
+}
+
+/// `(path, line, pattern)` for each added line containing a debug leftover.
+fn find(config: &LeftoversConfig, added: &[AddedLine]) -> Vec<(String, u32, String)> {
+    let any = patterns_for(config, "*");
+    let mut hits = Vec::new();
+    for line in added {
+        let ext = Path::new(&line.path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("");
+        let patterns = patterns_for(config, ext);
+        if let Some(pattern) = patterns
+            .iter()
+            .chain(any.iter())
+            .find(|p| line.text.contains(p.as_str()))
+        {
+            hits.push((line.path.clone(), line.line, pattern.clone()));
+        }
+    }
+    hits
+}
+
+/// Warn about debugging statements and "remove before commit" markers in added lines.
+pub fn check(config: &LeftoversConfig, added: &[AddedLine]) {
+    let hits = find(config, added);
+    if hits.is_empty() {
+        return;
+    }
+    eprintln!("Warning: possible debug leftovers in the staged changes:");
+    for (path, line, pattern) in hits {
+        eprintln!("  {path}:{line}  {pattern}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn added(path: &str, line: u32, text: &str) -> AddedLine {
+        AddedLine {
+            path: path.to_string(),
+            line,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn find_uses_language_patterns_and_config_overrides() {
+        let lines = vec![
+            added("src/lib.rs", 3, "    dbg!(&state);"),
+            added("web/app.ts", 9, "console.log(user)"),
+            added("app/models/user.rb", 2, "binding.pry"),
+            added("notes.md", 1, "Use console.log( to debug"),
+            added("src/main.rs", 7, "// TODO(remove) hack"),
+        ];
+        let mut config = LeftoversConfig::default();
+        let found: Vec<(String, u32)> = find(&config, &lines)
+            .into_iter()
+            .map(|(path, line, _)| (path, line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("src/lib.rs".to_string(), 3),
+                ("web/app.ts".to_string(), 9),
+                ("app/models/user.rb".to_string(), 2),
+                ("src/main.rs".to_string(), 7),
+            ]
+        );
+
+        config.patterns.insert("ts".to_string(), Vec::new());
+        config
+            .patterns
+            .insert("md".to_string(), vec!["console.log(".to_string()]);
+        let paths: Vec<String> = find(&config, &lines).into_iter().map(|h| h.0).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "src/lib.rs",
+                "app/models/user.rb",
+                "notes.md",
+                "src/main.rs"
+            ]
+        );
+    }
+}
@@ -0,0 +1,3700 @@
+//! scommit: stage, describe and commit changes with a generated message.
+//!
+//! The `scommit` binary is a thin wrapper around [`cli_main`]. Other tools (editor
+//! plugins, bots) can call the pipeline directly: [`staged_changes`] lists what is staged
+//! in a repository, each file with its [`Category`] and line counts, and
+//! [`generate_message`] describes it the way `scommit --print-only` would, with the AI
+//! provider when `OPENAI_API_KEY` is set and the heuristic otherwise.
+//!
+//! ```no_run
+//! let message = scommit::generate_message("path/to/repo".as_ref(), &scommit::Options::default())?;
+//! println!("{}", message.text());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::{Parser, Subcommand, ValueEnum};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod backend;
+mod branch;
+mod changelog;
+mod completions;
+mod config;
+mod deps;
+mod diff_parse;
+mod doctor;
+mod dry_run;
+mod error;
+mod exit;
+mod explain;
+mod fixup;
+mod generated;
+mod history;
+mod hooks;
+mod interactive;
+mod large_files;
+mod leftovers;
+mod linguist;
+mod manpage;
+mod missing_tests;
+mod models;
+mod notify;
+mod output;
+mod plugins;
+mod porcelain;
+mod post_process;
+mod pr;
+mod prefetch;
+mod prose;
+mod redact;
+mod release;
+mod repo_state;
+mod review;
+mod reword;
+mod secrets;
+#[cfg(feature = "tui")]
+mod select;
+mod sensitive_files;
+mod serve;
+mod session;
+mod split;
+mod squash;
+mod staging;
+mod stats;
+mod submodules;
+mod summarize;
+mod symbols;
+mod tag;
+mod templates;
+mod timings;
+#[cfg(feature = "tui")]
+mod tui;
+mod undo;
+mod watch;
+mod whitespace;
+
+use config::Config;
+use diff_parse::Section;
+pub use error::{SafetyCheck, ScommitError};
+use output::say;
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Smart git commit helper")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Preview actions without committing or pushing
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Skip the git add -A step and use existing staged changes
+    #[arg(long)]
+    no_stage: bool,
+
+    /// Stage new untracked files without listing them for review first
+    #[arg(long, conflicts_with = "no_stage")]
+    include_untracked: bool,
+
+    /// Only stage and commit paths matching GLOB (repeatable), e.g. --only 'src/**'
+    #[arg(long, value_name = "GLOB")]
+    only: Vec<String>,
+
+    /// Leave paths matching GLOB out of staging and the commit (repeatable), e.g. --exclude '*.snap'
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Skip pushing to the upstream remote
+    #[arg(long)]
+    no_push: bool,
+
+    /// Skip pulling/rebasing even if branch is behind upstream
+    #[arg(long)]
+    skip_pull: bool,
+
+    /// Push with --force-with-lease (after an amend/rebase); skips the pre-push rebase
+    #[arg(long)]
+    force_with_lease: bool,
+
+    /// Answer yes to confirmation prompts and commit the generated message without asking
+    #[arg(long, short = 'y', global = true)]
+    yes: bool,
+
+    /// Remote to push to (default: pushRemote/remote.pushDefault/upstream remote/origin)
+    #[arg(long, value_name = "REMOTE")]
+    remote: Option<String>,
+
+    /// Remote branch to push HEAD to instead of the configured upstream
+    #[arg(long, value_name = "BRANCH")]
+    push_to: Option<String>,
+
+    /// When the branch has no upstream, push with -u instead of asking
+    #[arg(long, short = 'u')]
+    set_upstream: bool,
+
+    /// After pushing, open a pull/merge request for the branch (see `scommit pr`)
+    #[arg(long, conflicts_with = "no_push")]
+    pr: bool,
+
+    /// Transmit an option to the server on push (repeatable), e.g. -o ci.skip
+    #[arg(long = "push-option", short = 'o', value_name = "OPTION")]
+    push_options: Vec<String>,
+
+    /// Colorize output: auto (terminal and no NO_COLOR), always, or never
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = output::ColorChoice::Auto, global = true)]
+    color: output::ColorChoice,
+
+    /// Log what scommit does to stderr: -v for git commands, timings and AI round-trips,
+    /// -vv to include prompts and responses (SCOMMIT_LOG takes a full filter instead)
+    #[arg(long, short = 'v', action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Print how long each phase took (staging, diff, AI call, commit, rebase, push)
+    #[arg(long, global = true)]
+    timings: bool,
+
+    /// Count changed lines ignoring whitespace, and label formatting-only files as such
+    /// (same as `[diff] ignore_whitespace`)
+    #[arg(long, global = true)]
+    ignore_whitespace: bool,
+
+    /// Run for automation (implied by CI=true): never prompt, skip the AI unless
+    /// --allow-ai, use deterministic messages and print the --json record
+    #[arg(long, conflicts_with_all = ["tui", "edit"])]
+    ci: bool,
+
+    /// In CI mode, still generate messages with the AI provider
+    #[arg(long)]
+    allow_ai: bool,
+
+    /// Print only warnings and errors: no progress, no git output, and no review prompts
+    #[arg(long, short = 'q', global = true)]
+    quiet: bool,
+
+    /// Print one JSON record of the run (files, message, commits, push) to stdout; progress
+    /// goes to stderr
+    #[arg(long)]
+    json: bool,
+
+    /// Print stable, line-oriented records (files, message, questions, commits, result) to
+    /// stdout for editor plugins, and read answers to questions from stdin
+    #[arg(long, value_name = "VERSION", value_enum, require_equals = true, conflicts_with_all = ["json", "print_only", "tui"])]
+    porcelain: Option<porcelain::Version>,
+
+    /// Print the generated subject and body to stdout and stop: nothing is staged,
+    /// committed or pushed
+    #[arg(long, conflicts_with_all = ["json", "tui", "edit", "dry_run", "split_by", "fixup"])]
+    print_only: bool,
+
+    /// Also save the final message to PATH in `git commit -F` format (`-` for stdout)
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["split_by", "fixup"])]
+    output: Option<PathBuf>,
+
+    /// Provide a custom commit message subject (auto body will still be added)
+    #[arg(long, short = 'm')]
+    message: Option<String>,
+
+    /// Shape the message with a named template (see `scommit template list`)
+    #[arg(long, value_name = "NAME", conflicts_with = "message")]
+    template: Option<String>,
+
+    /// Create a fixup! commit for REV, or `auto` to match each staged hunk to the commit it amends
+    #[arg(long, value_name = "REV", conflicts_with = "message")]
+    fixup: Option<String>,
+
+    /// Create and switch to a new branch before committing (name derived from the message if omitted)
+    #[arg(
+        long,
+        short = 'b',
+        value_name = "NAME",
+        num_args = 0..=1,
+        default_missing_value = "",
+        conflicts_with = "fixup"
+    )]
+    branch: Option<String>,
+
+    /// Finish an in-progress merge, rebase, cherry-pick or revert instead of refusing
+    #[arg(long = "continue")]
+    continue_op: bool,
+
+    /// Run scommit inside each dirty submodule first, then commit the updated pointers
+    #[arg(long)]
+    recurse_submodules: bool,
+
+    /// Allow committing on a detached HEAD without creating a branch
+    #[arg(long)]
+    allow_detached: bool,
+
+    /// Split staged changes into one commit per group, each with its own message
+    #[arg(long, value_enum, value_name = "GROUPING", conflicts_with_all = ["message", "fixup"])]
+    split_by: Option<split::SplitBy>,
+
+    /// Disable AI generation even if OPENAI_API_KEY is present
+    #[arg(long)]
+    no_ai: bool,
+
+    /// Override OpenAI model (default: gpt-4o-mini or env SCOMMIT_MODEL)
+    #[arg(long, value_parser = completions::ModelParser, hide_possible_values = true)]
+    model: Option<String>,
+
+    /// Print the exact AI prompt (after privacy mode and redaction) and exit without
+    /// sending it or committing
+    #[arg(long)]
+    show_prompt: bool,
+
+    /// What the AI prompt may contain: `full` diff excerpts, or only file `metadata`
+    #[arg(long, value_enum, default_value_t = Privacy::Full)]
+    privacy: Privacy,
+
+    /// Sign the commit (optionally with KEYID); commit.gpgsign is honored without this
+    #[arg(
+        long,
+        short = 'S',
+        value_name = "KEYID",
+        num_args = 0..=1,
+        default_missing_value = ""
+    )]
+    gpg_sign: Option<String>,
+
+    /// Don't sign the commit, even if commit.gpgsign is set
+    #[arg(long, conflicts_with = "gpg_sign")]
+    no_sign: bool,
+
+    /// Skip the pre-commit and commit-msg hooks and the configured hooks.commands
+    #[arg(long, short = 'n')]
+    no_verify: bool,
+
+    /// Commit even when nothing is staged (e.g. to re-trigger CI)
+    #[arg(long)]
+    allow_empty: bool,
+
+    /// Override the commit author ("Name <email>")
+    #[arg(long, value_name = "AUTHOR")]
+    author: Option<String>,
+
+    /// Override the author date (any format git commit --date accepts)
+    #[arg(long, value_name = "DATE")]
+    date: Option<String>,
+
+    /// Open the generated message in your editor (core.editor/$EDITOR) before committing
+    #[arg(long, short = 'e')]
+    edit: bool,
+
+    /// Choose files and edit the message in a full-screen terminal UI before committing
+    #[arg(long, conflicts_with_all = ["json", "quiet", "split_by", "fixup", "edit"])]
+    tui: bool,
+
+    /// Pick which changed files go into the commit from a checklist grouped by category
+    #[arg(long, conflicts_with_all = ["tui", "json", "quiet", "no_stage", "print_only"])]
+    select: bool,
+
+    /// Page through the full staged diff ($GIT_PAGER/core.pager/less) before committing
+    #[arg(long)]
+    review: bool,
+
+    /// Have the AI provider review the staged diff for likely bugs and risky changes before
+    /// the message is generated; its comments are advisory
+    #[arg(long)]
+    ai_review: bool,
+
+    /// Like --ai-review, but findings stop the commit until you acknowledge them
+    #[arg(long)]
+    review_block: bool,
+
+    /// Commit even if the staged diff appears to contain API keys or other credentials
+    #[arg(long)]
+    allow_secrets: bool,
+
+    /// Commit even if added lines look like <<<<<<< / >>>>>>> conflict markers
+    #[arg(long)]
+    allow_conflict_markers: bool,
+
+    /// Commit (and push) on a protected branch (branches.protected in the config)
+    #[arg(long)]
+    allow_protected: bool,
+
+    /// Stage and commit credential-looking files (.env, *.pem, id_rsa, ...) anyway
+    #[arg(long)]
+    force: bool,
+
+    /// Commit files over large_files.threshold_mb even when large_files.block is set
+    #[arg(long)]
+    allow_large_files: bool,
+
+    /// Create an annotated tag on the new commit (`auto` = next semver) and push it too
+    #[arg(long, value_name = "TAG")]
+    tag: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Undo the last scommit run: soft-reset its commits (or offer a revert if pushed)
+    Undo,
+    /// Print a shell completion script, e.g. `scommit completions zsh > ~/.zfunc/_scommit`
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Run as a git hook, or install scommit as one
+    Hook {
+        #[command(subcommand)]
+        action: hooks::HookAction,
+    },
+    /// Write a CHANGELOG section for a commit range (default: since the last tag), grouped
+    /// by conventional-commit type
+    Changelog {
+        /// Commits to describe, e.g. v1.2.0..v1.3.0 (default: <last tag>..HEAD)
+        range: Option<String>,
+        /// Heading for the section (default: the tag the range ends at, or "Unreleased")
+        #[arg(long, value_name = "NAME")]
+        version: Option<String>,
+        /// Have the AI provider reword the entries for readers of the changelog
+        #[arg(long)]
+        polish: bool,
+        /// Insert the section above the newest one in this file instead of printing it
+        #[arg(long, value_name = "FILE")]
+        file: Option<PathBuf>,
+    },
+    /// Cut a release: bump the root manifest's version, add the CHANGELOG section, commit,
+    /// tag and push, after showing the plan
+    Release {
+        /// Version to release, e.g. v1.4.0 (default: the semver bump the commits since
+        /// the last tag call for)
+        version: Option<String>,
+        /// Changelog to add the section to (default: CHANGELOG.md when there is one)
+        #[arg(long, value_name = "FILE")]
+        changelog: Option<PathBuf>,
+        /// Leave the changelog alone
+        #[arg(long, conflicts_with = "changelog")]
+        no_changelog: bool,
+        /// Have the AI provider reword the changelog entries
+        #[arg(long)]
+        polish: bool,
+    },
+    /// Open a GitHub pull request or GitLab merge request for the branch, titled and
+    /// described from its commits (pushes unpushed commits first)
+    #[command(visible_alias = "mr")]
+    Pr {
+        /// Branch to merge into (default: the remote's default branch)
+        #[arg(long, value_name = "BRANCH")]
+        base: Option<String>,
+        /// Open it as a draft
+        #[arg(long)]
+        draft: bool,
+    },
+    /// Summarize what a range of commits did, e.g. `scommit summarize main..HEAD`, for
+    /// standups, release notes or a PR description
+    Summarize {
+        /// Commits to summarize; a single rev means everything since it (`main` → main..HEAD)
+        range: String,
+    },
+    /// List the messages scommit generated (newest first), print one, or restore one as
+    /// HEAD's message
+    Log {
+        /// Entry to print (1 = newest); with --output it is written there instead
+        number: Option<usize>,
+        /// Make this entry HEAD's message (amends HEAD; nothing else changes)
+        #[arg(long)]
+        restore: bool,
+        /// Entries to list
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Break the history down by file category, author and period, and count the
+    /// messages scommit wrote
+    Stats {
+        /// Commits to count; a single rev means everything since it (default: all of HEAD)
+        range: Option<String>,
+        /// Only commits after this date, e.g. 2026-01-01 or "6 months ago"
+        #[arg(long, value_name = "DATE")]
+        since: Option<String>,
+        /// Period to chart churn by
+        #[arg(long, value_enum, default_value = "month")]
+        by: stats::Period,
+    },
+    /// Regenerate the message of an existing commit from its diff (older commits are
+    /// rebased non-interactively)
+    Reword {
+        /// The commit to reword
+        #[arg(default_value = "HEAD")]
+        rev: String,
+    },
+    /// Explain an existing commit in plain language: what changed, the likely intent and
+    /// the risk areas (needs the AI provider)
+    Explain {
+        /// The commit to explain
+        #[arg(default_value = "HEAD")]
+        rev: String,
+    },
+    /// Write one message for a series of commits being squashed, from their combined diff
+    /// and their messages
+    SquashMsg {
+        /// The commits to squash, e.g. `main..HEAD` (a single rev means REV..HEAD)
+        range: String,
+    },
+    /// List the models the AI provider offers this API key, with their context sizes
+    Models {
+        /// Include embedding, speech, image and moderation models
+        #[arg(long)]
+        all: bool,
+    },
+    /// List, add, edit or pick the named message templates kept in the config
+    Template {
+        #[command(subcommand)]
+        action: templates::TemplateAction,
+    },
+    /// Suggest a branch name ([branches] pattern) for the uncommitted changes, or for the
+    /// work a description names, and optionally switch to it
+    Branch {
+        /// What the branch is for, e.g. `add login form` (default: the uncommitted changes)
+        description: Vec<String>,
+        /// Create the branch and switch to it; uncommitted changes come along
+        #[arg(long, short = 'c')]
+        create: bool,
+    },
+    /// Commit the worktree to a wip branch with a generated message whenever it has been
+    /// quiet for a while (local checkpoints; the checked-out branch is left alone)
+    Watch {
+        /// Seconds without changes before a checkpoint (default: [watch] quiet_seconds)
+        #[arg(long, value_name = "SECONDS")]
+        quiet_for: Option<u64>,
+        /// Make one checkpoint of the current state now and exit
+        #[arg(long)]
+        once: bool,
+        /// Push each checkpoint to the default remote
+        #[arg(long)]
+        push: bool,
+    },
+    /// Check git, the repo's state, the upstream, the AI provider, the config files and
+    /// the hooks, and suggest a fix for anything that's off
+    Doctor,
+    /// Answer JSON-RPC requests on stdin (analyze, generate, commit, push), one per line,
+    /// for editor extensions that keep one process running
+    Serve,
+    /// Ask the AI provider to review the staged diff for likely bugs, missing error
+    /// handling and risky changes (advisory; nothing is committed)
+    Review,
+    /// Print the scommit(1) man page, or write it and the subcommand pages to --dir
+    Man {
+        /// Directory to write scommit.1, scommit-undo.1, ... into
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Privacy {
+    /// File list, numstat and a trimmed diff excerpt
+    Full,
+    /// File names, categories and numstat only; no diff content leaves the machine
+    Metadata,
+}
+
+/// Extra flags forwarded to every `git commit` scommit runs.
+#[derive(Debug, Clone, Default)]
+struct CommitOptions {
+    sign: SignMode,
+    no_verify: bool,
+    allow_empty: bool,
+    author: Option<String>,
+    date: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+enum SignMode {
+    /// Leave it to commit.gpgsign / gpg.format.
+    #[default]
+    Config,
+    /// `-S`, optionally with an explicit key.
+    Key(Option<String>),
+    Off,
+}
+
+impl CommitOptions {
+    fn from_cli(cli: &Cli) -> Self {
+        let sign = match (&cli.gpg_sign, cli.no_sign) {
+            (_, true) => SignMode::Off,
+            (Some(key), _) if key.is_empty() => SignMode::Key(None),
+            (Some(key), _) => SignMode::Key(Some(key.clone())),
+            (None, false) => SignMode::Config,
+        };
+        CommitOptions {
+            sign,
+            no_verify: cli.no_verify,
+            allow_empty: cli.allow_empty,
+            author: cli.author.clone(),
+            date: cli.date.clone(),
+        }
+    }
+
+    fn git_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        match &self.sign {
+            SignMode::Config => {}
+            SignMode::Key(None) => args.push("--gpg-sign".to_string()),
+            SignMode::Key(Some(key)) => args.push(format!("--gpg-sign={key}")),
+            SignMode::Off => args.push("--no-gpg-sign".to_string()),
+        }
+        if self.no_verify {
+            args.push("--no-verify".to_string());
+        }
+        if self.allow_empty {
+            args.push("--allow-empty".to_string());
+        }
+        if let Some(author) = &self.author {
+            args.push(format!("--author={author}"));
+        }
+        if let Some(date) = &self.date {
+            args.push(format!("--date={date}"));
+        }
+        args
+    }
+}
+
+/// How a staged file changed, as `git diff --raw` reports it.
+#[derive(Debug, Clone)]
+pub enum FileStatus {
+    Added,
+    Modified,
+    Deleted,
+    Renamed {
+        from: String,
+        to: String,
+    },
+    /// A new file detected as a copy of `from`, which is unchanged.
+    Copied {
+        from: String,
+        to: String,
+    },
+    /// Switched between regular file, symlink and submodule.
+    TypeChanged,
+}
+
+/// What kind of file a path is, from its name and location; see [`categorize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    Docs,
+    Tests,
+    Config,
+    Code,
+    Ci,
+    Build,
+    Assets,
+    Migrations,
+    Other,
+}
+
+/// One staged file: its path (the new one for renames and copies), how it changed, its
+/// added and deleted lines (0/0 for binary files) and its category.
+#[derive(Debug, Clone)]
+pub struct FileChange {
+    pub path: String,
+    pub status: FileStatus,
+    pub added: u32,
+    pub deleted: u32,
+    pub category: Category,
+}
+
+/// Totals over a set of changes; see [`compute_stats`].
+#[derive(Debug, Default, Clone)]
+pub struct Stats {
+    pub files: usize,
+    pub added: u32,
+    pub deleted: u32,
+    /// Files per category.
+    pub categories: HashMap<Category, usize>,
+    pub new_files: usize,
+    pub removed_files: usize,
+}
+
+impl Category {
+    /// The lowercase name used in message bodies and `--json`, e.g. `docs`.
+    pub fn name(self) -> &'static str {
+        CATEGORY_NAMES.get(&self).copied().unwrap_or("other")
+    }
+}
+
+/// What [`generate_message`] may use. The default asks the AI provider when
+/// `OPENAI_API_KEY` is set, with the model from `SCOMMIT_MODEL` or `gpt-4o-mini`.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// The model to ask instead of `SCOMMIT_MODEL` or the default.
+    pub model: Option<String>,
+    /// Never call the AI provider; the heuristic writes the message.
+    pub no_ai: bool,
+    /// Send the AI provider file names, categories and line counts only, no diff content.
+    pub metadata_only: bool,
+    /// Extra guidance for the AI provider, e.g. "mention the ticket".
+    pub hint: Option<String>,
+    /// A named message template (built in or from the repo's config) to shape the message.
+    pub template: Option<String>,
+}
+
+impl Options {
+    /// The command line `scommit --print-only` would be given for these options.
+    fn cli(&self) -> Cli {
+        let mut args = vec!["scommit".to_string(), "--print-only".to_string()];
+        if self.no_ai {
+            args.push("--no-ai".to_string());
+        }
+        if let Some(model) = &self.model {
+            args.extend(["--model".to_string(), model.clone()]);
+        }
+        if self.metadata_only {
+            args.extend(["--privacy".to_string(), "metadata".to_string()]);
+        }
+        if let Some(template) = &self.template {
+            args.extend(["--template".to_string(), template.clone()]);
+        }
+        Cli::parse_from(args)
+    }
+}
+
+/// A generated commit message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub subject: String,
+    pub body: String,
+    /// Written by the AI provider rather than the heuristic.
+    pub ai: bool,
+}
+
+impl Message {
+    /// The message as `git commit -F` takes it.
+    pub fn text(&self) -> String {
+        commit_message_text(&self.subject, &self.body)
+    }
+}
+
+/// The changes staged in the repository containing `repo`.
+///
+/// git runs in the repository's top level; the process's working directory is left
+/// alone, so calls for different repositories may run on several threads at once.
+pub fn staged_changes(repo: &Path) -> Result<Vec<FileChange>> {
+    in_repo(repo, collect_staged_changes)
+}
+
+/// A commit message for the changes staged in the repository containing `repo`, as
+/// `scommit --print-only` would write it: the repo's `.scommit.toml` and the user config
+/// apply, loaded afresh for each call, staged secrets keep the diff from the AI
+/// provider, and a failed AI call falls back to the heuristic. Nothing is staged or
+/// committed. See [`staged_changes`] about the working directory. With nothing staged
+/// the error downcasts to [`ScommitError::NoChanges`].
+pub fn generate_message(repo: &Path, opts: &Options) -> Result<Message> {
+    let cli = opts.cli();
+    in_repo(repo, || {
+        let changes = collect_staged_changes()?;
+        if changes.is_empty() {
+            return Err(anyhow!("nothing is staged").context(ScommitError::NoChanges));
+        }
+        let stats = compute_stats(&changes);
+        output::record(|r| r.ai = false);
+        let (subject, body) = match secrets::check(&staged_added_lines()?, false) {
+            Ok(()) => compose_message(&cli, &changes, &stats, opts.hint.as_deref())?,
+            Err(_) => build_commit_message(&changes, &stats),
+        };
+        Ok(Message {
+            subject,
+            body,
+            ai: output::inspect(|r| r.ai).unwrap_or(false),
+        })
+    })
+}
+
+/// Run `f` in a session (see `session`) for the repository containing `repo`, with its
+/// config loaded.
+fn in_repo<T>(repo: &Path, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    session::run(Some(session::open(repo)?), f)
+}
+
+static CATEGORY_NAMES: Lazy<HashMap<Category, &'static str>> = Lazy::new(|| {
+    use Category::*;
+    HashMap::from([
+        (Docs, "docs"),
+        (Tests, "tests"),
+        (Config, "config"),
+        (Code, "code"),
+        (Ci, "ci"),
+        (Build, "build"),
+        (Assets, "assets"),
+        (Migrations, "migrations"),
+        (Other, "other"),
+    ])
+});
+
+/// The `scommit` command line: parse the arguments, run, report and exit.
+#[doc(hidden)]
+pub fn cli_main() -> std::process::ExitCode {
+    let started = Instant::now();
+    let cli = Cli::parse();
+    output::init(&cli);
+    init_logging(cli.verbose);
+    timings::init(cli.timings);
+    let result = run(cli);
+    if result.is_ok() {
+        output::summary(|| current_branch().ok().flatten(), started.elapsed());
+    }
+    timings::report(started.elapsed());
+    let code = exit::code(result.as_ref().map(|_| ()));
+    output::finish(result.as_ref().err(), code);
+    notify::send(
+        &config::get().notify,
+        result.as_ref().err(),
+        code,
+        started.elapsed(),
+    );
+    if let Err(err) = &result {
+        eprintln!("Error: {err:?}");
+    }
+    std::process::ExitCode::from(code)
+}
+
+/// Send `tracing` events to stderr. SCOMMIT_LOG (an EnvFilter directive such as
+/// `scommit=debug`) wins over -v/-vv.
+fn init_logging(verbose: u8) {
+    let filter = match env::var("SCOMMIT_LOG") {
+        Ok(directives) => tracing_subscriber::EnvFilter::new(directives),
+        Err(_) => tracing_subscriber::EnvFilter::new(match verbose {
+            0 => "off",
+            1 => "scommit=debug",
+            _ => "scommit=trace",
+        }),
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::IsTerminal::is_terminal(&std::io::stderr()))
+        .with_target(false)
+        .init();
+}
+
+/// Log a finished child process: the command line, its exit code and how long it took.
+fn log_command(cmd: &Command, status: Option<ExitStatus>, started: Instant) {
+    let args: Vec<String> = cmd
+        .get_args()
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect();
+    tracing::debug!(
+        program = %cmd.get_program().to_string_lossy(),
+        args = %args.join(" "),
+        code = status.and_then(|s| s.code()),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "ran command"
+    );
+}
+
+fn run(mut cli: Cli) -> Result<()> {
+    if let Some(Commands::Completions { shell }) = cli.command {
+        return completions::print(shell);
+    }
+    if let Some(Commands::Man { dir }) = &cli.command {
+        return manpage::print(dir.as_deref());
+    }
+    output::record(|r| r.dry_run = cli.dry_run);
+    if let Some(Commands::Doctor) = cli.command {
+        return doctor::run(&cli);
+    }
+    if let Some(Commands::Models { all }) = cli.command {
+        return models::run(&cli, all);
+    }
+    if let Some(Commands::Serve) = cli.command {
+        return serve::run();
+    }
+    if output::ci() && (cli.tui || cli.edit) {
+        bail!(
+            "--tui and --edit need someone at the keyboard; they are not available in CI mode (CI is set)"
+        );
+    }
+    if cli.json && cli.output.as_deref() == Some(Path::new("-")) {
+        bail!("--json and --output - both write to stdout; save the message to a file instead");
+    }
+    if let Some(path) = &mut cli.output
+        && path != Path::new("-")
+    {
+        // Relative to where scommit was started, not the repo root it moves to.
+        *path = env::current_dir()?.join(&path);
+    }
+    if let Some(
+        Commands::Changelog {
+            file: Some(path), ..
+        }
+        | Commands::Release {
+            changelog: Some(path),
+            ..
+        },
+    ) = &mut cli.command
+    {
+        *path = env::current_dir()?.join(&path);
+    }
+    let repo_root = repo_root()?;
+    env::set_current_dir(&repo_root)
+        .with_context(|| format!("failed to enter repo at {}", repo_root.display()))?;
+    let mut loaded = Config::load(&repo_root)?;
+    loaded.diff.ignore_whitespace |= cli.ignore_whitespace;
+    let config = config::init(loaded);
+    // Kept until the run ends; dropping it deletes the scratch index.
+    let _scratch = (cli.print_only || cli.dry_run || cli.show_prompt)
+        .then(staging::ScratchIndex::enter)
+        .transpose()?;
+
+    if let Some(Commands::Undo) = cli.command {
+        return undo::run(&cli);
+    }
+    if let Some(Commands::Release {
+        version,
+        changelog,
+        no_changelog,
+        polish,
+    }) = &cli.command
+    {
+        return release::run(
+            &cli,
+            version.as_deref(),
+            changelog.as_deref(),
+            *no_changelog,
+            *polish,
+        );
+    }
+    if let Some(Commands::Changelog {
+        range,
+        version,
+        polish,
+        file,
+    }) = &cli.command
+    {
+        return changelog::run(
+            &cli,
+            range.as_deref(),
+            version.as_deref(),
+            *polish,
+            file.as_deref(),
+        );
+    }
+    if let Some(Commands::Summarize { range }) = &cli.command {
+        return summarize::run(&cli, range);
+    }
+    if let Some(Commands::Log {
+        number,
+        restore,
+        limit,
+    }) = &cli.command
+    {
+        return history::run(&cli, *number, *limit, *restore);
+    }
+    if let Some(Commands::Stats { range, since, by }) = &cli.command {
+        return stats::run(range.as_deref(), since.as_deref(), *by);
+    }
+    if let Some(Commands::Reword { rev }) = &cli.command {
+        return reword::run(&cli, rev);
+    }
+    if let Some(Commands::Explain { rev }) = &cli.command {
+        return explain::run(&cli, rev);
+    }
+    if let Some(Commands::SquashMsg { range }) = &cli.command {
+        return squash::run(&cli, range);
+    }
+    if let Some(Commands::Branch {
+        description,
+        create,
+    }) = &cli.command
+    {
+        return branch::run(&cli, description, *create);
+    }
+    if let Some(Commands::Watch {
+        quiet_for,
+        once,
+        push,
+    }) = &cli.command
+    {
+        return watch::run(&cli, *quiet_for, *once, *push);
+    }
+    if let Some(Commands::Review) = cli.command {
+        return review::run(&cli);
+    }
+    if let Some(Commands::Pr { base, draft }) = &cli.command {
+        let (base, draft) = (base.clone(), *draft);
+        return pr::run(&mut cli, base.as_deref(), draft);
+    }
+    if let Some(Commands::Template { action }) = &cli.command {
+        return templates::run(action);
+    }
+    if let Some(Commands::Hook { action }) = &cli.command {
+        return hooks::run_action(&cli, action);
+    }
+    if !cli.print_only && !cli.show_prompt {
+        hooks::enforce_pre_commit(config, cli.no_verify).context(ScommitError::Refused {
+            check: SafetyCheck::PreCommitHook,
+        })?;
+        if let Some(spec) = &cli.tag {
+            tag::validate(spec)?;
+        }
+
+        // Must run before `git add -A`, which would mark conflicts as resolved.
+        if let Some(op) = repo_state::check(&cli)? {
+            return repo_state::continue_operation(&cli, op);
+        }
+
+        submodules::commit_submodules(&cli)?;
+    }
+
+    let plan = staging::plan(&cli)?;
+    sensitive_files::check(&plan.paths, cli.force).context(ScommitError::Refused {
+        check: SafetyCheck::SensitiveFiles,
+    })?;
+    large_files::check(&config.large_files, &plan.paths, cli.allow_large_files).context(
+        ScommitError::Refused {
+            check: SafetyCheck::LargeFiles,
+        },
+    )?;
+    // Lets a declined size check put the index back exactly as it was.
+    let index_before = git_output(&["write-tree"]).ok();
+    if !cli.no_stage {
+        staging::apply(&plan).context(ScommitError::StagingFailed)?;
+    }
+    let mut aside = staging::set_aside(&plan)?;
+
+    let staged = has_staged_changes()?;
+    if !staged && !cli.allow_empty {
+        say!("No staged changes found. Nothing to commit.");
+        exit::note(exit::Code::NothingToCommit);
+        return Ok(());
+    }
+    let ask = !cli.yes
+        && !cli.quiet
+        && !cli.dry_run
+        && !cli.print_only
+        && !cli.show_prompt
+        && !output::ci();
+    if !large_files::confirm_staged_size(&config.large_files, &collect_staged_changes()?, ask)? {
+        if let Some(tree) = index_before.as_deref().map(str::trim)
+            && !cli.no_stage
+        {
+            git(&["read-tree", tree])?;
+        }
+        say!("Nothing committed; the index is back to how it was before scommit ran.");
+        exit::note(exit::Code::Cancelled);
+        return Ok(());
+    }
+    // Before anything else reads the diff, in particular before it is sent to the AI API.
+    let added_lines = staged_added_lines()?;
+    secrets::check(&added_lines, cli.allow_secrets).context(ScommitError::Refused {
+        check: SafetyCheck::Secrets,
+    })?;
+    repo_state::check_staged_markers(&added_lines, cli.allow_conflict_markers).context(
+        ScommitError::Refused {
+            check: SafetyCheck::ConflictMarkers,
+        },
+    )?;
+    leftovers::check(&config.leftovers, &added_lines);
+    // An audit: nothing past this point runs, so no hooks, commits or pushes.
+    if cli.show_prompt {
+        let changes = collect_staged_changes()?;
+        let stats = compute_stats(&changes);
+        let template = templates::active(&cli)?;
+        let hint = templates::hint(template.as_ref(), None);
+        let prompt = commit_prompt(&changes, &stats, cli.privacy, hint.as_deref())?;
+        say!("--- system ---\n{COMMIT_SYSTEM_PROMPT}\n\n--- user ---\n{prompt}");
+        return Ok(());
+    }
+    if !cli.no_verify && !cli.print_only && !config.hooks.commands.is_empty() {
+        if cli.dry_run {
+            say!("Would run: {}", config.hooks.commands.join(" && "));
+        } else {
+            hooks::run_commands(&config.hooks.commands)?;
+        }
+    }
+    if cli.review {
+        review_staged_diff()?;
+    }
+
+    if !cli.print_only {
+        guard_detached_head(&mut cli)?;
+        guard_protected_branch(&cli, config).context(ScommitError::Refused {
+            check: SafetyCheck::ProtectedBranch,
+        })?;
+    }
+
+    if staged && cli.fixup.as_deref() == Some("auto") {
+        let created = fixup::absorb(cli.dry_run, &CommitOptions::from_cli(&cli))?;
+        if cli.dry_run || created == 0 {
+            return Ok(());
+        }
+        aside.restore()?;
+        return push_to_upstream(&cli);
+    }
+
+    let mut changes = collect_staged_changes()?;
+    let mut excluded = None;
+    if cli.select {
+        let Some(deselected) = select_files(&changes)? else {
+            say!("Nothing committed; the changes are still staged.");
+            exit::note(exit::Code::Cancelled);
+            return Ok(());
+        };
+        excluded = Some(deselected);
+        changes = collect_staged_changes()?;
+    }
+    output::record_files(&changes);
+    missing_tests::check(&config.missing_tests, &changes);
+    if (cli.ai_review || cli.review_block)
+        && !cli.print_only
+        && !changes.is_empty()
+        && !review::before_commit(&cli, &changes)?
+    {
+        say!("Nothing committed; the changes are still staged.");
+        exit::note(exit::Code::Cancelled);
+        return Ok(());
+    }
+
+    if let Some(by) = cli.split_by {
+        let groups = split::group_changes(&changes, by);
+        if groups.len() > 1 {
+            let stats = compute_stats(&changes);
+            switch_to_new_branch(&cli, &build_subject(&changes, &stats, &[]))?;
+            let finished = split::commit_groups(&cli, &groups)?;
+            if cli.dry_run || !finished {
+                return Ok(());
+            }
+            aside.restore()?;
+            if let Some(excluded) = &mut excluded {
+                excluded.restore()?;
+            }
+            return push_to_upstream(&cli);
+        }
+    }
+
+    let stats = compute_stats(&changes);
+    // The push needs the upstream; finding it can overlap the AI request.
+    if !cli.print_only && !cli.no_push && cli.branch.is_none() {
+        prefetch::upstream();
+    }
+    let (subject, body) = if changes.is_empty() {
+        empty_commit_message(cli.message.as_deref())
+    } else {
+        compose_message(&cli, &changes, &stats, None)?
+    };
+    if cli.print_only {
+        match &cli.output {
+            Some(path) => save_message(path, &subject, &body)?,
+            None => print!("{}", commit_message_text(&subject, &body)),
+        }
+        if let Err(e) = history::record(&commit_message_text(&subject, &body), None) {
+            eprintln!("Warning: could not record the message for `scommit log` ({e}).");
+        }
+        return Ok(());
+    }
+
+    let (subject, body) = if cli.dry_run {
+        (subject, body)
+    } else if cli.tui {
+        match tui_message(&mut cli, &changes, subject, body)? {
+            Some((subject, body, aside)) => {
+                excluded = Some(aside);
+                (subject, body)
+            }
+            None => {
+                say!("Nothing committed; the changes are still staged.");
+                exit::note(exit::Code::Cancelled);
+                return Ok(());
+            }
+        }
+    } else {
+        match interactive::confirm_message(&cli, &changes, &stats, subject, body)? {
+            Some(message) => message,
+            None => {
+                say!("Nothing committed; the changes are still staged.");
+                exit::note(exit::Code::Cancelled);
+                return Ok(());
+            }
+        }
+    };
+
+    output::record(|r| {
+        r.subject = Some(subject.clone());
+        r.body = Some(body.clone());
+    });
+    if let Some(path) = &cli.output {
+        save_message(path, &subject, &body)?;
+    }
+    let commit_opts = CommitOptions::from_cli(&cli);
+    if cli.dry_run {
+        interactive::print_summary(&changes, &stats);
+        say!(
+            "{}\n{}",
+            output::yellow("DRY RUN"),
+            output::message(&subject, body.trim_end())
+        );
+        return dry_run::print_plan(&cli, &subject, &commit_opts);
+    }
+    switch_to_new_branch(&cli, &subject)?;
+
+    create_commit(&subject, &body, &commit_opts)?;
+    aside.restore()?;
+    if let Some(excluded) = &mut excluded {
+        excluded.restore()?;
+    }
+    push_to_upstream(&cli)
+}
+
+/// Let the user untick files for --select; None if they cancelled.
+#[cfg(feature = "tui")]
+fn select_files(changes: &[FileChange]) -> Result<Option<staging::SetAside>> {
+    select::run(changes)
+}
+
+#[cfg(not(feature = "tui"))]
+fn select_files(_changes: &[FileChange]) -> Result<Option<staging::SetAside>> {
+    bail!("--select is not available: scommit was built without the `tui` feature")
+}
+
+/// Hand the commit over to the TUI. Returns the message and the files it left out (to
+/// re-stage after committing), or None if the user quit.
+#[cfg(feature = "tui")]
+fn tui_message(
+    cli: &mut Cli,
+    changes: &[FileChange],
+    subject: String,
+    body: String,
+) -> Result<Option<(String, String, staging::SetAside)>> {
+    match tui::run(cli, changes, subject, body)? {
+        tui::Outcome::Commit {
+            subject,
+            body,
+            push,
+            excluded,
+        } => {
+            cli.no_push |= !push;
+            Ok(Some((subject, body, excluded)))
+        }
+        tui::Outcome::Quit => Ok(None),
+    }
+}
+
+#[cfg(not(feature = "tui"))]
+fn tui_message(
+    _cli: &mut Cli,
+    _changes: &[FileChange],
+    _subject: String,
+    _body: String,
+) -> Result<Option<(String, String, staging::SetAside)>> {
+    bail!("--tui is not available: scommit was built without the `tui` feature")
+}
+
+fn push_to_upstream(cli: &Cli) -> Result<()> {
+    let pushed = push_branch(cli)?;
+    output::record(|r| {
+        r.push = Some(output::PushRecord {
+            pushed: pushed.is_some(),
+            remote: pushed.as_ref().map(|(remote, _)| remote.clone()),
+            dest: pushed.as_ref().map(|(_, dest)| dest.clone()),
+        })
+    });
+    let remote = pushed.map(|(remote, _)| remote);
+    if cli.pr
+        && let Some(remote) = &remote
+        && let Err(e) = pr::open(cli, remote, None, false)
+    {
+        eprintln!("Pushed, but couldn't open a pull request: {e:#}");
+    }
+    // Tag after any pre-push rebase so the tag names the commit that was published.
+    if let Some(spec) = &cli.tag {
+        let name = tag::create(cli, spec)?;
+        output::record(|r| r.tag = Some(name.clone()));
+        match remote {
+            Some(remote) => tag::push(&remote, &name)?,
+            None => say!("Tag {name} not pushed; push it with `git push <remote> {name}`."),
+        }
+    }
+    Ok(())
+}
+
+/// Push the branch; returns the remote and `remote/branch` it now lives on, or None if
+/// it wasn't pushed.
+fn push_branch(cli: &Cli) -> Result<Option<(String, String)>> {
+    if cli.no_push {
+        say!("Skipping push (--no-push).");
+        return Ok(None);
+    }
+
+    let target = push_target(cli)?;
+    let upstream = match prefetch::take_upstream() {
+        Some(prefetched) => prefetched?.name,
+        None => upstream_branch()?,
+    };
+    let (remote, dest) = match (&target, &upstream) {
+        (Some((remote, branch)), _) => (remote.clone(), format!("{remote}/{branch}")),
+        (None, Some(upstream)) => (
+            upstream
+                .split_once('/')
+                .map_or(upstream.as_str(), |(r, _)| r)
+                .to_string(),
+            upstream.clone(),
+        ),
+        (None, None) => return push_new_branch(cli),
+    };
+
+    if cli.force_with_lease
+        && !cli.yes
+        && !confirm("force-push", &format!("Force-push (with lease) to {dest}?"))?
+    {
+        say!("Push cancelled; commit kept locally.");
+        exit::note(exit::Code::Cancelled);
+        return Ok(None);
+    }
+
+    let mut should_push = true;
+    // Rewritten history diverges on purpose; rebasing would resurrect the old commits.
+    if let Some(upstream) = upstream.as_deref()
+        && !cli.force_with_lease
+    {
+        // Counted again rather than prefetched: the commit has moved HEAD since.
+        let (ahead, behind) = ahead_behind(upstream)?;
+        if behind > 0 && !cli.skip_pull {
+            say!(
+                "Branch is behind {} by {} commit(s); rebasing before push...",
+                upstream,
+                behind
+            );
+            pull_rebase(cli)?;
+        } else if behind > 0 {
+            say!(
+                "Branch is behind {} by {} commit(s); skipping pull (--skip-pull).",
+                upstream,
+                behind
+            );
+        }
+        should_push = ahead > 0 || behind == 0 || target.is_some();
+    }
+
+    if should_push {
+        say!("Pushing to {dest}...");
+        let mut args = push_args(cli, target.as_ref());
+        // Only a branch without one gets an upstream; an existing one is left alone.
+        if cli.set_upstream && upstream.is_none() {
+            args.insert(1, "--set-upstream".to_string());
+        }
+        let _timer = timings::Timer::start(timings::Phase::Push);
+        git_push(&args)?;
+    } else {
+        say!("No local commits to push.");
+    }
+
+    Ok(Some((remote, dest)))
+}
+
+/// Pick the subject/body for the staged changes: fixup target, forced subject, AI, or
+/// the heuristic generator, in that order of precedence. `hint` is extra guidance for
+/// the AI (e.g. when regenerating from the interactive prompt).
+fn compose_message(
+    cli: &Cli,
+    changes: &[FileChange],
+    stats: &Stats,
+    hint: Option<&str>,
+) -> Result<(String, String)> {
+    let model = ai_model(cli);
+    let template = match (&cli.fixup, &cli.message) {
+        (None, None) => templates::active(cli)?,
+        _ => None,
+    };
+    let hint = templates::hint(template.as_ref(), hint);
+    let hint = hint.as_deref();
+
+    let (subject, body) = match (&cli.fixup, &cli.message, model) {
+        (Some(rev), _, _) => (fixup::fixup_subject(rev)?, String::new()),
+        (None, Some(subject), _) => (subject.clone(), build_body(changes, stats)),
+        (None, None, Some(model)) => {
+            match ai_commit_message(changes, stats, &model, cli.privacy, hint) {
+                Ok(Some((subject, body))) => {
+                    output::record(|r| r.ai = true);
+                    match deps::version_bump(changes) {
+                        Some(bump) => (deps::release_subject(&bump), body),
+                        None => (subject, body),
+                    }
+                }
+                Ok(None) => build_commit_message(changes, stats),
+                Err(e) => {
+                    eprintln!("AI generation failed ({e}); falling back to heuristic.");
+                    exit::note(exit::Code::AiFallback);
+                    build_commit_message(changes, stats)
+                }
+            }
+        }
+        _ => build_commit_message(changes, stats),
+    };
+    let (subject, mut body) = match &template {
+        Some(template) => templates::apply(template, &subject, &body),
+        None => (subject, body),
+    };
+    let subject = match (&cli.fixup, &cli.message) {
+        (None, None) => plugins::format_subject(subject, &body, changes)?,
+        _ => subject,
+    };
+    // Split commits separate tests from code by design; the nudge before splitting covers
+    // the whole change.
+    if cli.fixup.is_none()
+        && cli.split_by.is_none()
+        && let Some(note) = missing_tests::body_note(&config::get().missing_tests, changes)
+    {
+        body = format!("{}\n\n{note}\n", body.trim_end());
+    }
+    let (subject, body) = match &config::get().message.post_process {
+        Some(command) if cli.fixup.is_none() => {
+            post_process::apply(command, subject, body, changes)?
+        }
+        _ => (subject, body),
+    };
+    porcelain::message(&subject, &body);
+    Ok((subject, body))
+}
+
+/// The model to use, or None when AI generation is off (--no-ai or no API key).
+fn ai_model(cli: &Cli) -> Option<String> {
+    if cli.no_ai || (output::ci() && !cli.allow_ai) || env::var("OPENAI_API_KEY").is_err() {
+        return None;
+    }
+    Some(configured_model(cli))
+}
+
+/// --model, else SCOMMIT_MODEL, else the default, whether or not AI is on.
+fn configured_model(cli: &Cli) -> String {
+    cli.model
+        .clone()
+        .or_else(|| env::var("SCOMMIT_MODEL").ok())
+        .unwrap_or_else(|| "gpt-4o-mini".to_string())
+}
+
+/// Commits on a detached HEAD are easily lost once another ref is checked out, so
+/// require --branch or --allow-detached, or ask for a branch name.
+fn guard_detached_head(cli: &mut Cli) -> Result<()> {
+    if cli.branch.is_some() || current_branch()?.is_some() {
+        return Ok(());
+    }
+    if cli.allow_detached {
+        eprintln!("Warning: committing on a detached HEAD (--allow-detached).");
+        return Ok(());
+    }
+    if cli.dry_run {
+        say!("Warning: HEAD is detached; a real run needs --branch or --allow-detached.");
+        return Ok(());
+    }
+    if cli.fixup.is_none() {
+        let name = prompt_line(
+            "branch-name",
+            "HEAD is detached. Branch name for this commit (empty to abort): ",
+        )?;
+        if !name.is_empty() {
+            cli.branch = Some(name);
+            return Ok(());
+        }
+    }
+    bail!("HEAD is detached; pass --branch <name> to keep the commit, or --allow-detached")
+}
+
+/// The one-shot add/commit/push flow makes direct pushes to main too easy: refuse on
+/// protected branches unless --allow-protected, or --branch moves the commit elsewhere.
+fn guard_protected_branch(cli: &Cli, config: &Config) -> Result<()> {
+    if cli.branch.is_some() || cli.allow_protected {
+        return Ok(());
+    }
+    let Some(branch) = current_branch()? else {
+        return Ok(());
+    };
+    if !config
+        .branches
+        .protected
+        .iter()
+        .any(|pattern| glob_match(pattern, &branch))
+    {
+        return Ok(());
+    }
+    bail!(
+        "{branch} is a protected branch; commit on a new branch with -b [NAME], or pass --allow-protected"
+    )
+}
+
+/// The branch --branch asks for (derived from the subject when no name is given).
+fn new_branch_name(cli: &Cli, subject: &str) -> Result<Option<String>> {
+    let name = match cli.branch.as_deref() {
+        None => return Ok(None),
+        Some("") => branch_name_from_subject(subject),
+        Some(name) => name.to_string(),
+    };
+    if git_output(&["check-ref-format", "--branch", &name]).is_err() {
+        bail!("'{name}' is not a valid branch name");
+    }
+    Ok(Some(name))
+}
+
+/// Handle `--branch`: switch to a new branch (staged changes carry over), named
+/// explicitly or derived from the commit subject.
+fn switch_to_new_branch(cli: &Cli, subject: &str) -> Result<()> {
+    let Some(name) = new_branch_name(cli, subject)? else {
+        return Ok(());
+    };
+    if cli.dry_run {
+        say!("Would create and switch to branch {name}");
+        return Ok(());
+    }
+    git(&["switch", "--create", &name])
+}
+
+/// `feat: add login flow` -> `feat/add-login-flow`.
+fn branch_name_from_subject(subject: &str) -> String {
+    let (kind, rest) = match subject.split_once(':') {
+        Some((kind, rest)) if !kind.contains(' ') => {
+            // Drop any conventional scope: `fix(api)` -> `fix`.
+            let kind = kind.split('(').next().unwrap_or(kind).trim_end_matches('!');
+            (Some(kind.to_ascii_lowercase()), rest)
+        }
+        _ => (None, subject),
+    };
+
+    let mut slug = branch::slug(rest, 40);
+    if slug.is_empty() {
+        slug.push_str("changes");
+    }
+
+    match kind {
+        Some(kind) if !kind.is_empty() => format!("{kind}/{slug}"),
+        _ => slug,
+    }
+}
+
+/// Message for an --allow-empty commit, whose only purpose is usually to re-run CI.
+fn empty_commit_message(subject: Option<&str>) -> (String, String) {
+    let subject = subject.unwrap_or("chore: trigger rebuild").to_string();
+    let body = "No file changes; empty commit to re-run CI.\n\nAuto-generated by scommit. Edit with --message if you want to override.\n".to_string();
+    (subject, body)
+}
+
+fn repo_root() -> Result<PathBuf> {
+    backend::get().repo_root()
+}
+
+fn has_staged_changes() -> Result<bool> {
+    backend::get().has_staged_changes()
+}
+
+fn collect_staged_changes() -> Result<Vec<FileChange>> {
+    let _timer = timings::Timer::start(timings::Phase::Diff);
+    let mut changes = backend::get().staged_changes()?;
+    linguist::apply(&mut changes)?;
+    generated::apply(&mut changes)?;
+    Ok(changes)
+}
+
+/// Short name of the checked-out branch, or None when HEAD is detached.
+fn current_branch() -> Result<Option<String>> {
+    backend::get().current_branch()
+}
+
+fn upstream_branch() -> Result<Option<String>> {
+    backend::get().upstream_branch()
+}
+
+fn ahead_behind(upstream: &str) -> Result<(u32, u32)> {
+    backend::get().ahead_behind(upstream)
+}
+
+fn stage_everything() -> Result<()> {
+    git(&["add", "-A"])?;
+    Ok(())
+}
+
+/// A line the staged diff adds, with its line number in the new file.
+#[derive(Debug, Clone)]
+struct AddedLine {
+    path: String,
+    line: u32,
+    text: String,
+}
+
+/// Every added line is kept (the secret and conflict-marker checks need them all), but
+/// the diff itself is read a line at a time rather than held whole.
+fn staged_added_lines() -> Result<Vec<AddedLine>> {
+    let _timer = timings::Timer::start(timings::Phase::Diff);
+    let mut parser = AddedLines::default();
+    git_stream(
+        &["diff", "--cached", "-U0", "--no-color", "--no-ext-diff"],
+        b'\n',
+        |line| {
+            parser.push(line);
+            ControlFlow::Continue(())
+        },
+    )?;
+    Ok(parser.added)
+}
+
+#[cfg(test)]
+fn parse_added_lines(diff: &str) -> Vec<AddedLine> {
+    let mut parser = AddedLines::default();
+    diff.lines().for_each(|line| parser.push(line));
+    parser.added
+}
+
+/// The added lines of a `-U0` patch, a line at a time.
+#[derive(Default)]
+struct AddedLines {
+    added: Vec<AddedLine>,
+    path: String,
+    line_no: u32,
+    in_header: bool,
+}
+
+impl AddedLines {
+    fn push(&mut self, line: &str) {
+        if line.starts_with("diff --git ") {
+            self.in_header = true;
+        } else if self.in_header
+            && let Some(new_path) = line.strip_prefix("+++ ")
+        {
+            self.path = diff_parse::patch_path(new_path).unwrap_or_default();
+        } else if let Some(header) = line.strip_prefix("@@ ") {
+            self.in_header = false;
+            // `@@ -a,b +c,d @@`: added lines start at c.
+            self.line_no = header
+                .split(' ')
+                .find_map(|part| part.strip_prefix('+'))
+                .and_then(|new| new.split(',').next()?.parse().ok())
+                .unwrap_or(0);
+        } else if !self.in_header
+            && let Some(text) = line.strip_prefix('+')
+        {
+            self.added.push(AddedLine {
+                path: self.path.clone(),
+                line: self.line_no,
+                text: text.to_string(),
+            });
+            self.line_no += 1;
+        }
+    }
+}
+
+/// Paths that end up in the commit: what `git add -A` would stage, or (with --no-stage)
+/// what is staged already, limited to `pathspecs` if any. Parsed from `git status
+/// --porcelain -z`.
+fn pending_paths(no_stage: bool, pathspecs: &[String]) -> Result<Vec<String>> {
+    let mut args = vec!["status", "--porcelain", "-z", "--untracked-files=all", "--"];
+    args.extend(pathspecs.iter().map(String::as_str));
+    let out = git_output(&args)?;
+    Ok(parse_pending_paths(&out, no_stage))
+}
+
+fn parse_pending_paths(porcelain: &str, no_stage: bool) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut entries = porcelain.split('\0');
+    while let Some(entry) = entries.next() {
+        if entry.len() < 4 {
+            continue;
+        }
+        let (index, worktree) = (entry.as_bytes()[0], entry.as_bytes()[1]);
+        if matches!(index, b'R' | b'C') {
+            entries.next(); // rename/copy source
+        }
+        let staged = matches!(index, b'A' | b'M' | b'R' | b'C');
+        let to_stage = !matches!(worktree, b' ' | b'D');
+        if staged || (!no_stage && to_stage) {
+            paths.push(entry[3..].to_string());
+        }
+    }
+    paths
+}
+
+/// Shell-style match where `*` stands for any run of characters (including `/`).
+fn glob_match(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            let Some(tail) = name.strip_prefix(prefix) else {
+                return false;
+            };
+            if !rest.contains('*') {
+                return tail.len() >= rest.len() && tail.ends_with(rest);
+            }
+            (0..=tail.len())
+                .filter(|&i| tail.is_char_boundary(i))
+                .any(|i| glob_match(rest, &tail[i..]))
+        }
+    }
+}
+
+/// CI and workflow definitions, by directory or file name.
+const CI_DIRS: [&str; 6] = [
+    ".github/workflows/",
+    ".github/actions/",
+    ".circleci/",
+    ".buildkite/",
+    ".gitlab/ci/",
+    ".woodpecker/",
+];
+const CI_FILES: [&str; 9] = [
+    ".gitlab-ci.yml",
+    "jenkinsfile",
+    ".travis.yml",
+    "azure-pipelines.yml",
+    "bitbucket-pipelines.yml",
+    ".drone.yml",
+    "appveyor.yml",
+    ".appveyor.yml",
+    "cloudbuild.yaml",
+];
+
+/// Build scripts and packaging specs, by exact file name.
+const BUILD_FILES: [&str; 18] = [
+    "makefile",
+    "gnumakefile",
+    "cmakelists.txt",
+    "build.rs",
+    "build.zig",
+    "meson.build",
+    "build",
+    "build.bazel",
+    "workspace",
+    "workspace.bazel",
+    "justfile",
+    "rakefile",
+    "pkgbuild",
+    "snapcraft.yaml",
+    "flake.nix",
+    "containerfile",
+    ".dockerignore",
+    ".goreleaser.yml",
+];
+
+/// Build tooling and packaging formats, by extension.
+const BUILD_EXTENSIONS: [&str; 6] = ["mk", "cmake", "gradle", "bzl", "nsi", "wxs"];
+
+/// Schema migration directories: Django/Alembic/Prisma/Knex/Laravel (`migrations/`),
+/// Rails (`db/migrate/`), Alembic (`alembic/versions/`), Flyway (`db/migration/`).
+const MIGRATION_DIRS: [&str; 4] = [
+    "migrations/",
+    "db/migrate/",
+    "alembic/versions/",
+    "db/migration/",
+];
+
+/// Flyway's `V42__add_index.sql` (versioned), `U42__...` (undo) and `R__...` (repeatable),
+/// including Java-based migrations wherever they live.
+static FLYWAY_NAME: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^([vu]\d+(_\d+)*|r)__.+\.(sql|java|kt)$").expect("valid Flyway pattern")
+});
+
+/// Images, icons, fonts, audio/video and design files.
+const ASSET_EXTENSIONS: [&str; 27] = [
+    "png", "jpg", "jpeg", "gif", "webp", "avif", "bmp", "tif", "tiff", "ico", "icns", "svg", "ttf",
+    "otf", "woff", "woff2", "eot", "mp3", "wav", "ogg", "flac", "mp4", "webm", "mov", "psd",
+    "sketch", "blend",
+];
+
+/// Packaging directories whose contents (control files, rules, recipes) are build input.
+const BUILD_DIRS: [&str; 3] = ["debian/", "packaging/", "rpm/"];
+
+/// The category of a path: CI, build, migrations, assets, docs, tests, config, code or
+/// other, checked in that order.
+pub fn categorize(path: &str) -> Category {
+    if let Some(category) = plugins::categorize(path) {
+        return category;
+    }
+    let lower = path.to_ascii_lowercase();
+    let ext = PathBuf::from(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    // Before docs/tests/config: `.github/workflows/test.yml` is CI, not a test or config.
+    if CI_DIRS
+        .iter()
+        .any(|dir| lower.starts_with(dir) || lower.contains(&format!("/{dir}")))
+        || CI_FILES.contains(&short_name(&lower).as_str())
+    {
+        return Category::Ci;
+    }
+
+    // Also before tests: `Dockerfile.test` builds an image, it isn't a test.
+    let name = short_name(&lower);
+    if BUILD_FILES.contains(&name.as_str())
+        || name.starts_with("dockerfile")
+        || name.ends_with(".dockerfile")
+        || name.ends_with(".gradle.kts")
+        || matches!(ext.as_deref(), Some(e) if BUILD_EXTENSIONS.contains(&e))
+        || BUILD_DIRS.iter().any(|dir| lower.starts_with(dir))
+    {
+        return Category::Build;
+    }
+
+    // Before tests and code: `migrations/0003_add_test_runs.py` is a schema change.
+    if MIGRATION_DIRS
+        .iter()
+        .any(|dir| lower.starts_with(dir) || lower.contains(&format!("/{dir}")))
+        || FLYWAY_NAME.is_match(&name)
+        || ext.as_deref() == Some("sql")
+    {
+        return Category::Migrations;
+    }
+
+    if matches!(ext.as_deref(), Some(e) if ASSET_EXTENSIONS.contains(&e)) {
+        return Category::Assets;
+    }
+
+    if lower.contains("readme")
+        || lower.contains("docs/")
+        || matches!(
+            ext.as_deref(),
+            Some("md" | "markdown" | "rst" | "txt" | "adoc" | "org")
+        )
+    {
+        return Category::Docs;
+    }
+
+    if lower.contains("test")
+        || matches!(
+            ext.as_deref(),
+            Some("spec" | "snap" | "snap.new" | "snap.old")
+        )
+    {
+        return Category::Tests;
+    }
+
+    if matches!(
+        ext.as_deref(),
+        Some(
+            "yml"
+                | "yaml"
+                | "json"
+                | "toml"
+                | "ini"
+                | "cfg"
+                | "conf"
+                | "lock"
+                | "env"
+                | "properties"
+        )
+    ) || lower.contains("config")
+    {
+        return Category::Config;
+    }
+
+    if matches!(
+        ext.as_deref(),
+        Some(
+            "rs" | "ts"
+                | "tsx"
+                | "js"
+                | "jsx"
+                | "py"
+                | "go"
+                | "rb"
+                | "java"
+                | "kt"
+                | "c"
+                | "cc"
+                | "cpp"
+                | "h"
+                | "hpp"
+                | "swift"
+                | "scala"
+                | "php"
+        )
+    ) {
+        return Category::Code;
+    }
+
+    Category::Other
+}
+
+/// File, line and per-category totals for `changes`.
+pub fn compute_stats(changes: &[FileChange]) -> Stats {
+    let mut stats = Stats {
+        files: changes.len(),
+        ..Stats::default()
+    };
+    for c in changes {
+        stats.added += c.added;
+        stats.deleted += c.deleted;
+        *stats.categories.entry(c.category).or_insert(0) += 1;
+        match c.status {
+            FileStatus::Added | FileStatus::Copied { .. } => stats.new_files += 1,
+            FileStatus::Deleted => stats.removed_files += 1,
+            _ => {}
+        }
+    }
+    stats
+}
+
+fn build_commit_message(changes: &[FileChange], stats: &Stats) -> (String, String) {
+    if deps::lockfile_only(changes) {
+        return lockfile_message(changes, stats);
+    }
+    let subject = build_subject(changes, stats, &symbols::changed(changes));
+    let body = build_body(changes, stats);
+    (subject, body)
+}
+
+/// A `chore(deps)` subject for commits that only touch lockfiles; the body lists the
+/// resolved version changes.
+fn lockfile_message(changes: &[FileChange], stats: &Stats) -> (String, String) {
+    let locked: Vec<deps::DepChange> = deps::locked(changes)
+        .into_iter()
+        .flat_map(|(_, deps)| deps)
+        .collect();
+    (deps::lockfile_subject(&locked), build_body(changes, stats))
+}
+
+/// "prefix: update a.rs & b.rs", or named after the functions and types that changed
+/// when they are all in one file.
+fn build_subject(
+    changes: &[FileChange],
+    stats: &Stats,
+    symbols: &[symbols::SymbolChange],
+) -> String {
+    // A release commit is about the version, whatever else it touches.
+    if let Some(bump) = deps::version_bump(changes) {
+        return deps::release_subject(&bump);
+    }
+    let formatting = whitespace::formatting_only(changes);
+    if !changes.is_empty() && changes.iter().all(|c| formatting.contains(&c.path)) {
+        let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+        return whitespace::subject(&paths);
+    }
+    let prefix = choose_prefix(stats);
+    if let Some((focus, extracted)) = symbols::subject_focus(symbols) {
+        let subject = format!("{}: {focus}", if extracted { "refactor" } else { prefix });
+        if subject.len() <= 72 {
+            return subject;
+        }
+    }
+
+    let mut subject = format!("{prefix}: update {}", file_focus(changes));
+    if subject.len() > 72 {
+        let cut = (0..=72)
+            .rev()
+            .find(|&i| subject.is_char_boundary(i))
+            .unwrap_or(0);
+        subject.truncate(cut);
+    }
+    subject
+}
+
+/// Files that say nothing on their own; the directory they sit in names the module.
+const INDEX_FILES: &[&str] = &[
+    "mod.rs",
+    "lib.rs",
+    "__init__.py",
+    "index.js",
+    "index.jsx",
+    "index.ts",
+    "index.tsx",
+];
+
+/// What the subject names: the deepest common directory of three or more files (minus
+/// a trailing `src`/`lib`, so a crate or package is named rather than its source root),
+/// otherwise the two files with the most churn. Index files and basenames that appear
+/// twice keep their parent directory, so it's never "mod.rs & mod.rs".
+fn file_focus(changes: &[FileChange]) -> String {
+    if changes.len() >= 3
+        && let Some(dir) = common_dir(changes.iter().map(|c| c.path.as_str()))
+    {
+        return dir;
+    }
+
+    let mut ranked: Vec<&FileChange> = changes.iter().collect();
+    ranked.sort_by_key(|c| std::cmp::Reverse(c.added + c.deleted));
+    let names: Vec<String> = ranked
+        .iter()
+        .take(2)
+        .map(|c| {
+            let name = short_name(&c.path);
+            let repeated = changes
+                .iter()
+                .any(|o| o.path != c.path && short_name(&o.path) == name);
+            let parent = c.path.rsplit('/').nth(1);
+            match parent {
+                Some(parent) if repeated || INDEX_FILES.contains(&name.as_str()) => {
+                    format!("{parent}/{name}")
+                }
+                _ => name,
+            }
+        })
+        .collect();
+    if names.is_empty() {
+        "changes".to_string()
+    } else {
+        names.join(" & ")
+    }
+}
+
+/// The deepest directory containing every path, without a trailing `src` or `lib`.
+fn common_dir<'a>(mut paths: impl Iterator<Item = &'a str>) -> Option<String> {
+    let parent = |path: &'a str| path.rsplit_once('/').map_or("", |(dir, _)| dir);
+    let mut common: Vec<&str> = parent(paths.next()?).split('/').collect();
+    for path in paths {
+        let shared = common
+            .iter()
+            .zip(parent(path).split('/'))
+            .take_while(|(a, b)| *a == b)
+            .count();
+        common.truncate(shared);
+    }
+    while matches!(common.last(), Some(&"src" | &"lib" | &"")) {
+        common.pop();
+    }
+    (!common.is_empty()).then(|| common.join("/"))
+}
+
+fn choose_prefix(stats: &Stats) -> &'static str {
+    let only_category = if stats.categories.len() == 1 {
+        stats.categories.keys().next().copied()
+    } else {
+        None
+    };
+
+    match only_category {
+        Some(Category::Docs) => "docs",
+        Some(Category::Tests) => "test",
+        Some(Category::Config) => "chore",
+        Some(Category::Ci) => "ci",
+        Some(Category::Build) => "build",
+        Some(Category::Assets) => "chore",
+        _ => {
+            if stats.new_files > 0 && stats.added > stats.deleted {
+                "feat"
+            } else if stats.deleted > stats.added && stats.categories.contains_key(&Category::Code)
+            {
+                "refactor"
+            } else {
+                "chore"
+            }
+        }
+    }
+}
+
+fn build_body(changes: &[FileChange], stats: &Stats) -> String {
+    use std::fmt::Write;
+    let mut body = String::new();
+    write!(
+        &mut body,
+        "Files: {} | +{} / -{}",
+        stats.files, stats.added, stats.deleted
+    )
+    .ok();
+    // CI runs must produce the same message for the same changes.
+    if !output::ci() {
+        write!(
+            &mut body,
+            " | generated {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M")
+        )
+        .ok();
+    }
+    writeln!(&mut body).ok();
+    writeln!(&mut body, "Changes:").ok();
+
+    // Binary assets have no meaningful line counts, and nobody reads generated code line
+    // by line; each gets one summary line instead.
+    let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+    let generated = generated::detect(&paths).unwrap_or_default();
+    let formatting = whitespace::formatting_only(changes);
+    let words = prose::word_stats(changes);
+    let (regenerated, changed): (Vec<&FileChange>, Vec<&FileChange>) = changes
+        .iter()
+        .partition(|c| generated.contains(&c.path) && !matches!(c.status, FileStatus::Deleted));
+    let (assets, files): (Vec<&FileChange>, Vec<&FileChange>) = changed
+        .into_iter()
+        .partition(|c| c.category == Category::Assets);
+    let mut listed = 0usize;
+    for change in files.iter().take(12) {
+        listed += 1;
+        let category = CATEGORY_NAMES
+            .get(&change.category)
+            .copied()
+            .unwrap_or("other");
+        let counts = prose::counts(change, &words);
+        // A newline in a path would end the entry early.
+        let path = diff_parse::quote(&change.path);
+        if formatting.contains(&change.path) {
+            writeln!(
+                &mut body,
+                "- reformat {} (formatting only) [{}]",
+                path, category
+            )
+            .ok();
+            continue;
+        }
+        match &change.status {
+            FileStatus::Added => {
+                writeln!(&mut body, "- add {} ({}) [{}]", path, counts, category).ok();
+            }
+            FileStatus::Modified => {
+                writeln!(&mut body, "- update {} ({}) [{}]", path, counts, category).ok();
+            }
+            FileStatus::Deleted => {
+                writeln!(&mut body, "- remove {} ({}) [{}]", path, counts, category).ok();
+            }
+            FileStatus::Renamed { from, .. } => {
+                let from = diff_parse::quote(from);
+                writeln!(
+                    &mut body,
+                    "- rename {} -> {} ({}) [{}]",
+                    from, path, counts, category
+                )
+                .ok();
+            }
+            FileStatus::Copied { from, .. } => {
+                let from = diff_parse::quote(from);
+                writeln!(
+                    &mut body,
+                    "- copy {} -> {} ({}) [{}]",
+                    from, path, counts, category
+                )
+                .ok();
+            }
+            FileStatus::TypeChanged => {
+                writeln!(
+                    &mut body,
+                    "- change type of {} ({}) [{}]",
+                    path, counts, category
+                )
+                .ok();
+            }
+        }
+    }
+
+    if files.len() > listed {
+        writeln!(
+            &mut body,
+            "- ... {} more file(s) not listed",
+            files.len() - listed
+        )
+        .ok();
+    }
+    if let Some(summary) = asset_summary(&assets) {
+        writeln!(&mut body, "{summary}").ok();
+    }
+    if let Some(summary) = generated::summary(&regenerated) {
+        writeln!(&mut body, "{summary}").ok();
+    }
+
+    let manifests = deps::changed(changes);
+    if !manifests.is_empty() {
+        writeln!(&mut body, "\nDependencies:").ok();
+        for (path, changed) in &manifests {
+            for dep in changed {
+                if manifests.len() > 1 {
+                    writeln!(&mut body, "- {} ({path})", dep.describe()).ok();
+                } else {
+                    writeln!(&mut body, "- {}", dep.describe()).ok();
+                }
+            }
+        }
+    }
+
+    // Schema changes are what reviewers most want surfaced.
+    let migrations: Vec<String> = changes
+        .iter()
+        .filter(|c| c.category == Category::Migrations)
+        .map(migration_note)
+        .collect();
+    if !migrations.is_empty() {
+        writeln!(&mut body, "\nMigrations:\n{}", migrations.join("\n")).ok();
+    }
+
+    // Lockfile-only commits are described by what they resolve differently; alongside
+    // other changes the manifests above say what was meant.
+    if deps::lockfile_only(changes) {
+        let locked = deps::locked(changes);
+        if !locked.is_empty() {
+            writeln!(&mut body, "\nLocked versions:").ok();
+        }
+        for (path, changed) in &locked {
+            for dep in changed.iter().take(40) {
+                if locked.len() > 1 {
+                    writeln!(&mut body, "- {} ({path})", dep.describe()).ok();
+                } else {
+                    writeln!(&mut body, "- {}", dep.describe()).ok();
+                }
+            }
+            if changed.len() > 40 {
+                writeln!(&mut body, "- ... {} more in {path}", changed.len() - 40).ok();
+            }
+        }
+    }
+
+    // Pipeline changes affect every later build, so they are named even past the list.
+    let ci: Vec<&str> = changes
+        .iter()
+        .filter(|c| c.category == Category::Ci)
+        .map(|c| c.path.as_str())
+        .collect();
+    if !ci.is_empty() {
+        writeln!(&mut body, "\nCI: {}", ci.join(", ")).ok();
+    }
+
+    writeln!(
+        &mut body,
+        "\nAuto-generated by scommit. Edit with --message if you want to override."
+    )
+    .ok();
+
+    body
+}
+
+/// "- adds migration 0042_add_users_index". Tools that keep one directory per migration
+/// (Prisma's `20240101_init/migration.sql`, `up.sql`/`down.sql`) are named by directory.
+fn migration_note(change: &FileChange) -> String {
+    let path = Path::new(&change.path);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned());
+    let name = match stem.as_deref() {
+        Some("migration" | "up" | "down") | None => path
+            .parent()
+            .and_then(|p| p.file_name())
+            .map(|d| d.to_string_lossy().into_owned())
+            .unwrap_or_else(|| change.path.clone()),
+        Some(stem) => stem.to_string(),
+    };
+    let verb = match change.status {
+        FileStatus::Added | FileStatus::Copied { .. } => "adds",
+        FileStatus::Modified | FileStatus::TypeChanged => "changes",
+        FileStatus::Deleted => "removes",
+        FileStatus::Renamed { .. } => "renames",
+    };
+    format!("- {verb} migration {name}")
+}
+
+/// "- 3 asset(s): 2 added, 1 updated (2 png, 1 woff2)", or None without assets.
+fn asset_summary(assets: &[&FileChange]) -> Option<String> {
+    if assets.is_empty() {
+        return None;
+    }
+    let mut by_status: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut by_ext: BTreeMap<String, usize> = BTreeMap::new();
+    for change in assets {
+        let status = match change.status {
+            FileStatus::Added | FileStatus::Copied { .. } => "added",
+            FileStatus::Modified | FileStatus::TypeChanged => "updated",
+            FileStatus::Deleted => "removed",
+            FileStatus::Renamed { .. } => "renamed",
+        };
+        *by_status.entry(status).or_default() += 1;
+        let ext = Path::new(&change.path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        *by_ext.entry(ext).or_default() += 1;
+    }
+    let statuses = ["added", "updated", "renamed", "removed"]
+        .iter()
+        .filter_map(|s| by_status.get(s).map(|n| format!("{n} {s}")))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let kinds = by_ext
+        .iter()
+        .map(|(ext, n)| format!("{n} {ext}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(format!("- {} asset(s): {statuses} ({kinds})", assets.len()))
+}
+
+fn short_name(path: &str) -> String {
+    PathBuf::from(path)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or(path)
+        .to_string()
+}
+
+fn recent_commit_subjects(n: usize) -> Result<Vec<String>> {
+    let out = git_output(&["log", "-n", &n.to_string(), "--pretty=%s"])?;
+    Ok(out
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|s| s.to_string())
+        .collect())
+}
+
+fn sanitize_json_blob(content: &str) -> Option<String> {
+    let trimmed = content.trim();
+
+    // Fenced (``` or ```json) or not, slice from first '{' to last '}'.
+    if let (Some(start), Some(end)) = (trimmed.find('{'), trimmed.rfind('}'))
+        && start < end
+    {
+        return Some(trimmed[start..=end].to_string());
+    }
+
+    None
+}
+
+fn strip_bullet_prefix(line: &str) -> &str {
+    line.trim().trim_start_matches(&['-', '•'][..]).trim_start()
+}
+
+fn coerce_subject(value: Option<&serde_json::Value>) -> Option<String> {
+    value
+        .and_then(extract_text)
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn coerce_body(value: Option<&serde_json::Value>) -> String {
+    match value {
+        Some(serde_json::Value::String(s)) => s.trim().to_string(),
+        Some(serde_json::Value::Array(items)) => {
+            let lines: Vec<String> = items
+                .iter()
+                .filter_map(extract_text)
+                .map(|l| {
+                    let cleaned = strip_bullet_prefix(&l);
+                    format!("- {}", cleaned)
+                })
+                .collect();
+            lines.join("\n")
+        }
+        Some(serde_json::Value::Object(map)) => {
+            // Some models nest body under "bullets" or "lines".
+            if let Some(bullets) = map.get("bullets").or_else(|| map.get("lines")) {
+                return coerce_body(Some(bullets));
+            }
+            if let Some(text) = extract_text(&serde_json::Value::Object(map.clone())) {
+                return text.trim().to_string();
+            }
+            String::new()
+        }
+        _ => String::new(),
+    }
+}
+
+fn extract_text(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::Bool(b) => Some(b.to_string()),
+        serde_json::Value::Array(items) => {
+            let joined: Vec<String> = items.iter().filter_map(extract_text).collect();
+            if joined.is_empty() {
+                None
+            } else {
+                Some(joined.join("\n"))
+            }
+        }
+        serde_json::Value::Object(map) => {
+            // Look for common textual keys.
+            for key in ["text", "value", "content", "message", "summary"] {
+                if let Some(v) = map.get(key)
+                    && let Some(s) = extract_text(v)
+                {
+                    return Some(s);
+                }
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+fn diff_stat() -> Result<String> {
+    let diff_args = config::get().diff.args();
+    // The prompt lists a few dozen files at most; git sums up the rest in one line.
+    let mut args = vec![
+        "diff",
+        "--cached",
+        "--stat",
+        "--stat-count=40",
+        "--no-color",
+    ];
+    args.extend(diff_args.iter().map(String::as_str));
+    Ok(git_output(&args)?.trim().to_string())
+}
+
+fn diff_excerpt(
+    max_chars: usize,
+    redaction: &config::RedactConfig,
+    changes: &[FileChange],
+    formatting: &HashSet<String>,
+) -> Result<String> {
+    let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+    let mut omit: HashMap<String, &'static str> = linguist::lookup(&paths)?
+        .into_iter()
+        .filter_map(|(path, attrs)| Some((path, attrs.omitted()?)))
+        .collect();
+    // Generated files without the attribute are omitted the same way.
+    for path in generated::detect(&paths)? {
+        omit.entry(path).or_insert("generated");
+    }
+    for path in formatting {
+        omit.entry(path.clone()).or_insert("formatting-only");
+    }
+    // Rewrapped paragraphs are all-new lines; a word diff shows what was reworded. Files
+    // come in the same order as in the line diff, so only the first few can make it into
+    // the excerpt.
+    let mut reworded: HashMap<String, Section> = HashMap::new();
+    let mut kept = 0;
+    prose::word_diff(changes, max_chars, |section| {
+        kept += section.text.len();
+        reworded.insert(section.path.clone(), section);
+        if kept < 2 * max_chars {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    })?;
+
+    let diff_args = config::get().diff.args();
+    let mut args = vec!["diff", "--cached", "--unified=3", "--no-color"];
+    args.extend(diff_args.iter().map(String::as_str));
+    // Each file keeps at most a full excerpt's worth, and reading stops once the excerpt
+    // is full, however big the staged diff.
+    let mut excerpt = String::new();
+    let mut chars = 0;
+    diff_sections(&args, max_chars, |section| {
+        let mut section = reworded.remove(&section.path).unwrap_or(section);
+        redact::withhold(&mut section, redaction);
+        let condensed = condense_section(&section, &omit);
+        chars += condensed.chars().count();
+        excerpt.push_str(&condensed);
+        if chars < max_chars {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    })?;
+    Ok(excerpt.chars().take(max_chars).collect())
+}
+
+/// Stream the patch `git diff` prints for `args` to `each` a file at a time, keeping at
+/// most `cap` bytes of each file's text, until `each` breaks.
+fn diff_sections(
+    args: &[&str],
+    cap: usize,
+    mut each: impl FnMut(Section) -> ControlFlow<()>,
+) -> Result<()> {
+    let mut sections = diff_parse::Sections::new(cap);
+    let mut stopped = false;
+    git_stream(args, b'\n', |line| match sections.push(line) {
+        Some(section) => {
+            let flow = each(section);
+            stopped = flow.is_break();
+            flow
+        }
+        None => ControlFlow::Continue(()),
+    })?;
+    if !stopped && let Some(section) = sections.finish() {
+        let _ = each(section);
+    }
+    Ok(())
+}
+
+const LOCKFILES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "Pipfile.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "go.sum",
+];
+
+/// A file's section of the excerpt: its diff, or a one-line summary for binary, minified
+/// and lockfile diffs and those of the files in `omit` (generated, vendored,
+/// formatting-only), so they don't eat the excerpt budget that real code hunks need.
+fn condense_section(section: &Section, omit: &HashMap<String, &'static str>) -> String {
+    let header = &section.header;
+    match omit
+        .get(&section.path)
+        .copied()
+        .or_else(|| noisy_kind(section))
+    {
+        None if section.truncated => format!(
+            "{}[rest of the diff left out: +{}/-{} lines in all]\n",
+            section.text, section.added, section.deleted
+        ),
+        None => section.text.clone(),
+        Some("binary") => format!("{header}\n[binary file changed]\n"),
+        Some(kind) => format!(
+            "{header}\n[{kind} diff omitted: +{}/-{} lines]\n",
+            section.added, section.deleted
+        ),
+    }
+}
+
+fn noisy_kind(section: &Section) -> Option<&'static str> {
+    let path = section.path.as_str();
+    let name = path.rsplit('/').next().unwrap_or(path);
+    if section.binary {
+        Some("binary")
+    } else if LOCKFILES.contains(&name) {
+        Some("lockfile")
+    } else if name.contains(".min.") || name.ends_with(".map") || section.long_lines {
+        Some("minified")
+    } else {
+        None
+    }
+}
+
+#[derive(Deserialize)]
+struct ChoiceMessage {
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct Usage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+}
+
+const COMMIT_SYSTEM_PROMPT: &str = "You are a git commit assistant. Produce informative, specific commit messages that mirror the repo's tone. Respond strictly as JSON with keys \"subject\" and \"body\". Subject <=72 chars, sentence case, no trailing period. Body must be 2-5 bullets starting with '- ', focusing on concrete changes and motivations; mention new commands/flags/examples, doc sections touched, and any behavioral impacts.";
+
+/// The user prompt for commit message generation, after privacy mode and the
+/// configured redaction rules are applied: exactly what is sent to the provider.
+fn commit_prompt(
+    changes: &[FileChange],
+    stats: &Stats,
+    privacy: Privacy,
+    hint: Option<&str>,
+) -> Result<String> {
+    let formatting = whitespace::formatting_only(changes);
+    // Each of these is a git call or more and none needs another's answer, so they run
+    // side by side.
+    let (stat, patch, symbols, recent, words) = thread::scope(|scope| {
+        let stat = session::spawn(scope, || diff_stat().unwrap_or_default());
+        let patch = session::spawn(scope, || match privacy {
+            Privacy::Full => {
+                diff_excerpt(4000, &config::get().redact, changes, &formatting).unwrap_or_default()
+            }
+            Privacy::Metadata => "(withheld: only file metadata is shared)".to_string(),
+        });
+        // Symbol names are code, so metadata-only mode leaves them out like the diff.
+        let symbols = session::spawn(scope, || match privacy {
+            Privacy::Full => {
+                let redact = &config::get().redact;
+                let shared: Vec<FileChange> = changes
+                    .iter()
+                    .filter(|c| !redact.paths.iter().any(|glob| glob_match(glob, &c.path)))
+                    .cloned()
+                    .collect();
+                symbols::prompt_lines(&symbols::changed(&shared))
+            }
+            Privacy::Metadata => String::new(),
+        });
+        let recent = session::spawn(scope, || recent_commit_subjects(6).unwrap_or_default());
+        let words = prose::word_stats(changes);
+        (
+            joined(stat),
+            joined(patch),
+            joined(symbols),
+            joined(recent),
+            words,
+        )
+    });
+    let mut change_lines = String::new();
+    for c in changes.iter().take(24) {
+        let (action, detail) = match &c.status {
+            _ if formatting.contains(&c.path) => ("reformat", c.path.clone()),
+            FileStatus::Added => ("add", c.path.clone()),
+            FileStatus::Modified => ("update", c.path.clone()),
+            FileStatus::Deleted => ("remove", c.path.clone()),
+            FileStatus::Renamed { from, .. } => ("rename", format!("{from} -> {}", c.path)),
+            FileStatus::Copied { from, .. } => ("copy", format!("{from} -> {}", c.path)),
+            FileStatus::TypeChanged => ("change type of", c.path.clone()),
+        };
+        let counts = prose::counts(c, &words);
+        use std::fmt::Write;
+        writeln!(
+            &mut change_lines,
+            "{} {} ({}) [{}]",
+            action,
+            detail,
+            counts,
+            CATEGORY_NAMES.get(&c.category).copied().unwrap_or("other")
+        )
+        .ok();
+    }
+
+    let prompt = format!(
+        "Repo stats: files {}, +{}, -{}; categories {:?}; new {}, removed {}.\nRecent commit subjects:\n- {}\nChanges (staged):\n{}\n\nDiffstat:\n{}\n\nDiff excerpt (trimmed):\n{}\n\nWrite 2-5 bullets that capture the most meaningful changes (what/why), call out new commands/flags/examples or config/doc topics when present, and note any behavioral impacts or risks. Avoid generic wording; be specific to these changes.",
+        stats.files,
+        stats.added,
+        stats.deleted,
+        stats.categories,
+        stats.new_files,
+        stats.removed_files,
+        recent.join("\n- "),
+        change_lines,
+        stat,
+        patch
+    );
+    let prompt = if symbols.is_empty() {
+        prompt
+    } else {
+        format!("{prompt}\n\nFunctions, types and classes touched:\n{symbols}")
+    };
+    let prompt = if formatting.is_empty() {
+        prompt
+    } else {
+        let mut paths: Vec<&str> = formatting.iter().map(String::as_str).collect();
+        paths.sort_unstable();
+        format!(
+            "{prompt}\n\nFormatting-only changes (whitespace and line breaks, no change in behavior): {}. If that is every file, use the `style:` type; otherwise don't describe them as a refactor.",
+            paths.join(", ")
+        )
+    };
+    let prompt = match hint {
+        Some(hint) => format!("{prompt}\n\nGuidance from the author for this message: {hint}"),
+        None => prompt,
+    };
+    redact::apply_patterns(&prompt, &config::get().redact)
+}
+
+/// What a scoped thread returned; a panic in it carries on in the caller.
+fn joined<T>(handle: thread::ScopedJoinHandle<'_, T>) -> T {
+    handle
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
+
+// Extract a JSON blob even if the model wrapped it in markdown fences.
+fn ai_commit_message(
+    changes: &[FileChange],
+    stats: &Stats,
+    model: &str,
+    privacy: Privacy,
+    hint: Option<&str>,
+) -> Result<Option<(String, String)>> {
+    let key = match env::var("OPENAI_API_KEY") {
+        Ok(k) => k,
+        Err(_) => return Ok(None),
+    };
+    if deps::lockfile_only(changes) {
+        tracing::debug!("only lockfiles are staged; describing the version changes instead");
+        return Ok(None);
+    }
+
+    let prompt = commit_prompt(changes, stats, privacy, hint)?;
+    history::note_prompt(model, COMMIT_SYSTEM_PROMPT, &prompt);
+    let Some(content) = chat_completion(&key, model, COMMIT_SYSTEM_PROMPT, &prompt, true, 480)?
+    else {
+        return Ok(None);
+    };
+
+    let json_blob = sanitize_json_blob(&content).ok_or_else(|| {
+        anyhow!(
+            "AI response missing JSON object: {}",
+            content.chars().take(200).collect::<String>()
+        )
+    })?;
+
+    let ai: serde_json::Value = serde_json::from_str(&json_blob).context("decoding AI json")?;
+    let subject = coerce_subject(ai.get("subject"))
+        .ok_or_else(|| anyhow!("AI JSON missing usable subject"))?;
+    let body = coerce_body(ai.get("body"));
+
+    if subject.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some((subject, body)))
+}
+
+/// One HTTP client for every call to the AI provider, so a long-running `scommit serve`
+/// keeps its connection instead of repeating the TLS handshake.
+static AI_CLIENT: once_cell::sync::OnceCell<reqwest::blocking::Client> =
+    once_cell::sync::OnceCell::new();
+
+fn ai_client() -> Result<&'static reqwest::blocking::Client> {
+    AI_CLIENT.get_or_try_init(|| {
+        reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(20))
+            .build()
+            .context("building http client")
+    })
+}
+
+/// One chat completion round-trip; `json` asks the model for a JSON object reply.
+fn chat_completion(
+    key: &str,
+    model: &str,
+    system: &str,
+    prompt: &str,
+    json: bool,
+    max_tokens: u32,
+) -> Result<Option<String>> {
+    let client = ai_client()?;
+
+    let mut payload = serde_json::json!({
+        "model": model,
+        "messages": [
+            { "role": "system", "content": system },
+            { "role": "user", "content": prompt }
+        ],
+        "temperature": 0.25,
+        "max_tokens": max_tokens
+    });
+    if json {
+        payload["response_format"] = serde_json::json!({ "type": "json_object" });
+    }
+
+    tracing::debug!(
+        model,
+        system_chars = system.len(),
+        prompt_chars = prompt.len(),
+        max_tokens,
+        "sending chat completion"
+    );
+    tracing::trace!(%system, %prompt, "chat completion request");
+    let _timer = timings::Timer::start(timings::Phase::Ai);
+    let started = Instant::now();
+    let spinner = output::Spinner::start(format!("Waiting for {model}"));
+    let res = client
+        .post("https://api.openai.com/v1/chat/completions")
+        .bearer_auth(key)
+        .json(&payload)
+        .send()
+        .context("calling OpenAI API");
+    drop(spinner);
+    let res = res.map_err(error::ai_unavailable)?;
+    let status = res.status();
+    tracing::debug!(
+        status = status.as_u16(),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "chat completion responded"
+    );
+
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(error::ai_unavailable(models::not_found(key, model)));
+    }
+    if !status.is_success() {
+        return Err(error::ai_unavailable(anyhow!("OpenAI API error: {status}")));
+    }
+
+    let parsed: ChatResponse = res.json().context("parsing OpenAI response")?;
+    if let Some(usage) = &parsed.usage {
+        tracing::debug!(
+            prompt_tokens = usage.prompt_tokens,
+            completion_tokens = usage.completion_tokens,
+            "chat completion usage"
+        );
+    }
+    let content = parsed.choices.into_iter().next().map(|c| c.message.content);
+    tracing::trace!(response = ?content, "chat completion response");
+    Ok(content)
+}
+
+/// The message as `git commit -F` reads it: subject, blank line, body.
+fn commit_message_text(subject: &str, body: &str) -> String {
+    let mut message = format!("{subject}\n");
+    if !body.trim().is_empty() {
+        message.push('\n');
+        message.push_str(body.trim_end());
+        message.push('\n');
+    }
+    message
+}
+
+/// Save the final message for --output: to stdout for `-`, otherwise to the file.
+fn save_message(path: &Path, subject: &str, body: &str) -> Result<()> {
+    let message = commit_message_text(subject, body);
+    if path == Path::new("-") {
+        print!("{message}");
+        return Ok(());
+    }
+    std::fs::write(path, message).with_context(|| format!("writing {}", path.display()))
+}
+
+fn create_commit(subject: &str, body: &str, opts: &CommitOptions) -> Result<()> {
+    let _timer = timings::Timer::start(timings::Phase::Commit);
+    commit_with_hooks(subject, body, opts).context(ScommitError::CommitFailed)
+}
+
+fn commit_with_hooks(subject: &str, body: &str, opts: &CommitOptions) -> Result<()> {
+    let msg_path = hooks::message_file()?;
+    std::fs::write(&msg_path, commit_message_text(subject, body))
+        .with_context(|| format!("writing {}", msg_path.display()))?;
+    if !opts.no_verify {
+        plugins::validate(&commit_message_text(subject, body))?;
+    }
+
+    undo::note_commit_start();
+    let mut cmd = session::command("git");
+    // git runs the repo's hooks itself, with the environment they expect; the message
+    // hooks get the generated message as a `-F` file (source `message`).
+    cmd.arg("commit")
+        .args(opts.git_args())
+        .arg("-F")
+        .arg(&msg_path)
+        .stdout(output::child_stdout());
+    let started = Instant::now();
+    let (status, stderr) = run_teeing_stderr(&mut cmd).context("running git commit")?;
+    log_command(&cmd, Some(status), started);
+    if !status.success() {
+        let detail = stderr.trim();
+        if detail.is_empty() {
+            bail!("git commit failed");
+        }
+        bail!("git commit failed:\n{}", detail);
+    }
+    std::fs::remove_file(&msg_path).ok();
+    let sha = git_output(&["rev-parse", "HEAD"])?.trim().to_string();
+    output::record(|r| {
+        r.commits.push(output::CommitRecord {
+            sha: sha.clone(),
+            subject: subject.to_string(),
+        })
+    });
+    if let Err(e) = undo::record_commit() {
+        eprintln!("Warning: could not record this run for `scommit undo` ({e}).");
+    }
+    // What git committed, after the message hooks and cleanup had their say.
+    let committed = git_output(&["log", "-1", "--format=%B", &sha]).unwrap_or_default();
+    if let Err(e) = history::record(&format!("{}\n", committed.trim_end()), Some(&sha)) {
+        eprintln!("Warning: could not record the message for `scommit log` ({e}).");
+    }
+    Ok(())
+}
+
+fn run_teeing_stderr(cmd: &mut Command) -> Result<(std::process::ExitStatus, String)> {
+    use std::io::{BufRead, BufReader, Write};
+    let mut child = cmd.stderr(Stdio::piped()).spawn()?;
+    let mut captured = String::new();
+    if let Some(stderr) = child.stderr.take() {
+        let mut out = std::io::stderr();
+        for line in BufReader::new(stderr).lines() {
+            let line = line?;
+            if !output::quiet() {
+                writeln!(out, "{line}").ok();
+            }
+            captured.push_str(&line);
+            captured.push('\n');
+        }
+    }
+    Ok((child.wait()?, captured))
+}
+
+/// Describe how the commit will be signed, resolving commit.gpgsign, gpg.format and
+/// user.signingkey the same way `git commit` does.
+fn describe_signing(mode: &SignMode) -> Option<String> {
+    let config = |key: &str| {
+        git_output(&["config", "--get", key])
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let explicit_key = match mode {
+        SignMode::Off => return None,
+        SignMode::Config => {
+            let enabled = config("commit.gpgsign")
+                .map(|v| matches!(v.to_ascii_lowercase().as_str(), "true" | "yes" | "on" | "1"))
+                .unwrap_or(false);
+            if !enabled {
+                return None;
+            }
+            None
+        }
+        SignMode::Key(key) => key.clone(),
+    };
+
+    let format = config("gpg.format").unwrap_or_else(|| "openpgp".to_string());
+    let source = if *mode == SignMode::Config {
+        "commit.gpgsign"
+    } else {
+        "--gpg-sign"
+    };
+    match explicit_key.or_else(|| config("user.signingkey")) {
+        Some(key) => Some(format!("{format} with key {key} ({source})")),
+        None => Some(format!("{format} with default key ({source})")),
+    }
+}
+
+/// First push of a branch with no upstream: `git push -u <remote> <branch>`, either
+/// automatically (--set-upstream) or after asking.
+fn push_new_branch(cli: &Cli) -> Result<Option<(String, String)>> {
+    let (Some(branch), Some(remote)) = (current_branch()?, default_remote()?) else {
+        say!("No upstream configured; commit created but not pushed.");
+        return Ok(None);
+    };
+
+    let question = format!("No upstream configured. Push and track {remote}/{branch}?");
+    if !cli.set_upstream && !cli.yes && !confirm("set-upstream", &question)? {
+        say!("Commit created but not pushed (use --set-upstream to push new branches).");
+        return Ok(None);
+    }
+
+    let dest = format!("{remote}/{branch}");
+    say!("Pushing to {dest}...");
+    let mut args = push_args(cli, Some(&(remote.clone(), branch)));
+    args.insert(1, "--set-upstream".to_string());
+    let _timer = timings::Timer::start(timings::Phase::Push);
+    git_push(&args)?;
+    Ok(Some((remote, dest)))
+}
+
+/// remote.pushDefault, else `origin`, else the only configured remote.
+fn default_remote() -> Result<Option<String>> {
+    if let Ok(out) = git_output(&["config", "--get", "remote.pushDefault"]) {
+        let remote = out.trim();
+        if !remote.is_empty() {
+            return Ok(Some(remote.to_string()));
+        }
+    }
+    let remotes = git_output(&["remote"])?;
+    let remotes: Vec<&str> = remotes
+        .lines()
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .collect();
+    if remotes.contains(&"origin") {
+        return Ok(Some("origin".to_string()));
+    }
+    Ok(match remotes.as_slice() {
+        [only] => Some(only.to_string()),
+        _ => None,
+    })
+}
+
+/// Explicit (remote, branch) destination when --remote or --push-to is given.
+fn push_target(cli: &Cli) -> Result<Option<(String, String)>> {
+    if cli.remote.is_none() && cli.push_to.is_none() {
+        return Ok(None);
+    }
+    let branch = current_branch()?;
+    let branch_config = |key: &str| {
+        branch.as_ref().and_then(|b| {
+            git_output(&["config", "--get", &format!("branch.{b}.{key}")])
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        })
+    };
+
+    let remote = match &cli.remote {
+        Some(remote) => remote.clone(),
+        None => branch_config("pushRemote")
+            .or_else(|| {
+                git_output(&["config", "--get", "remote.pushDefault"])
+                    .ok()
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+            })
+            .or_else(|| branch_config("remote"))
+            .unwrap_or_else(|| "origin".to_string()),
+    };
+    let dest = match (&cli.push_to, branch) {
+        (Some(dest), _) => dest.clone(),
+        (None, Some(branch)) => branch,
+        (None, None) => bail!("HEAD is detached; pass --push-to to name the remote branch"),
+    };
+    Ok(Some((remote, dest)))
+}
+
+fn push_args(cli: &Cli, target: Option<&(String, String)>) -> Vec<String> {
+    let mut args = vec!["push".to_string()];
+    if cli.quiet {
+        args.push("--quiet".to_string());
+    }
+    if cli.force_with_lease {
+        args.push("--force-with-lease".to_string());
+    }
+    for option in &cli.push_options {
+        args.push(format!("--push-option={option}"));
+    }
+    if let Some((remote, branch)) = target {
+        args.push(remote.clone());
+        args.push(format!("HEAD:{branch}"));
+    }
+    args
+}
+
+/// Ask a yes/no question on the terminal; anything but y/yes (including EOF) is "no".
+fn confirm(key: &str, question: &str) -> Result<bool> {
+    let answer = prompt_line(key, &format!("{question} [y/N] "))?;
+    Ok(matches!(answer.to_ascii_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Print `prompt` and read one trimmed line from stdin (empty on EOF, an error with
+/// --porcelain). `key` names the question in --porcelain `prompt` records.
+fn prompt_line(key: &str, prompt: &str) -> Result<String> {
+    use std::io::Write;
+    if output::serving() {
+        bail!(
+            "scommit would ask \"{}\" but never prompts in serve mode; pass the parameter that answers it",
+            prompt.trim()
+        );
+    }
+    if output::ci() {
+        bail!(
+            "scommit would ask \"{}\" but never prompts in CI mode; pass the flag that answers it (e.g. --yes, --branch, --allow-detached)",
+            prompt.trim()
+        );
+    }
+    if output::porcelain() {
+        porcelain::prompt(key, prompt);
+    } else if output::stdout_reserved() {
+        eprint!("{prompt}");
+    } else {
+        print!("{prompt}");
+        std::io::stdout().flush().ok();
+    }
+    let mut answer = String::new();
+    let read = std::io::stdin()
+        .read_line(&mut answer)
+        .context("reading answer")?;
+    if read == 0 && output::porcelain() {
+        bail!("stdin closed before the answer to the `{key}` prompt");
+    }
+    Ok(answer.trim().to_string())
+}
+
+/// `git pull --rebase --autostash`. If the rebase stops on conflicts, let the user
+/// abort it (restoring the branch and autostashed edits), open the files, or keep it.
+fn pull_args(cli: &Cli) -> Vec<&'static str> {
+    let mut args = vec!["pull", "--rebase", "--autostash"];
+    if cli.quiet {
+        args.push("--quiet");
+    }
+    args
+}
+
+fn pull_rebase(cli: &Cli) -> Result<()> {
+    let _timer = timings::Timer::start(timings::Phase::Rebase);
+    let mut cmd = session::command("git");
+    cmd.args(pull_args(cli))
+        .stdin(Stdio::inherit())
+        .stdout(output::child_stdout());
+    let started = Instant::now();
+    let (status, stderr) = run_teeing_stderr(&mut cmd).context("running git pull")?;
+    log_command(&cmd, Some(status), started);
+    if status.success() {
+        return Ok(());
+    }
+    if repo_state::rebase_in_progress()? {
+        let files = repo_state::unmerged_paths().unwrap_or_default();
+        repo_state::handle_rebase_conflicts(cli)?;
+        let conflict = ScommitError::RebaseConflict { files };
+        if repo_state::rebase_in_progress()? {
+            return Err(anyhow!("rebase left in progress; nothing was pushed")).context(conflict);
+        }
+        return Err(anyhow!(
+            "the rebase was aborted; the commit was created locally but not pushed"
+        ))
+        .context(conflict);
+    }
+    let err = match stderr.trim() {
+        "" => anyhow!("git pull --rebase failed; the commit was created locally but not pushed"),
+        detail => anyhow!(
+            "git pull --rebase failed; the commit was created locally but not pushed:\n{detail}"
+        ),
+    };
+    Err(err.context(ScommitError::PullFailed { stderr }))
+}
+
+/// Show the staged diff (with a diffstat) through git's pager, exactly as
+/// `git diff --cached` would page it.
+fn review_staged_diff() -> Result<()> {
+    git(&["--paginate", "diff", "--cached", "--patch-with-stat"])
+}
+
+/// Open `paths` in the user's editor (GIT_EDITOR/core.editor/VISUAL/EDITOR, as git
+/// resolves it) and wait for it to exit.
+fn launch_editor(paths: &[&str]) -> Result<()> {
+    let editor = git_output(&["var", "GIT_EDITOR"])?;
+    let editor = editor.trim();
+    // The editor setting is a shell snippet (it may carry arguments), as in git.
+    let status = session::command("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$@\""))
+        .arg(editor)
+        .args(paths)
+        .status()
+        .with_context(|| format!("running editor {editor}"))?;
+    if !status.success() {
+        bail!("editor {editor} exited with {status}");
+    }
+    Ok(())
+}
+
+fn git(args: &[&str]) -> Result<()> {
+    let mut cmd = session::command("git");
+    cmd.args(args)
+        .stdin(Stdio::inherit())
+        .stdout(output::child_stdout())
+        .stderr(Stdio::inherit());
+    let started = Instant::now();
+    let status = cmd
+        .status()
+        .with_context(|| format!("running git {:?}", args))?;
+    log_command(&cmd, Some(status), started);
+    if !status.success() {
+        bail!("git {:?} failed", args);
+    }
+    Ok(())
+}
+
+/// `git push ARGS`, with git's stderr shown as it comes and kept for the error.
+fn git_push(args: &[String]) -> Result<()> {
+    let mut cmd = session::command("git");
+    cmd.args(args)
+        .stdin(Stdio::inherit())
+        .stdout(output::child_stdout());
+    let started = Instant::now();
+    let (status, stderr) = run_teeing_stderr(&mut cmd).context("running git push")?;
+    log_command(&cmd, Some(status), started);
+    if !status.success() {
+        let detail = stderr.trim();
+        let err = match detail {
+            "" => anyhow!("git push failed"),
+            detail => anyhow!("git push failed:\n{detail}"),
+        };
+        return Err(err.context(ScommitError::PushRejected { stderr }));
+    }
+    Ok(())
+}
+
+fn git_with_input(args: &[&str], input: &str) -> Result<()> {
+    use std::io::Write;
+    let mut cmd = session::command("git");
+    cmd.args(args)
+        .stdin(Stdio::piped())
+        .stdout(output::child_stdout());
+    let started = Instant::now();
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("running git {:?}", args))?;
+    tracing::trace!(input = %input.replace('\0', " "), "git stdin");
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.as_bytes())
+            .with_context(|| format!("writing to git {:?}", args))?;
+    }
+    let status = child
+        .wait()
+        .with_context(|| format!("running git {:?}", args))?;
+    log_command(&cmd, Some(status), started);
+    if !status.success() {
+        bail!("git {:?} failed", args);
+    }
+    Ok(())
+}
+
+/// The content of each object (`HEAD:path`, `:path`), in order, from one
+/// `git cat-file --batch`; `None` for objects that don't exist.
+fn git_blobs(specs: &[String]) -> Result<Vec<Option<Vec<u8>>>> {
+    use std::io::{Read, Write};
+    if specs.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut cmd = session::command("git");
+    cmd.args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    let started = Instant::now();
+    let mut child = cmd.spawn().context("running git cat-file")?;
+    let input: String = specs.iter().map(|spec| format!("{spec}\n")).collect();
+    let mut stdin = child.stdin.take().context("git cat-file stdin")?;
+    // Written from another thread: git answers as it reads, and would block on a full
+    // stdout pipe while we block on a full stdin one.
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let mut out = Vec::new();
+    child
+        .stdout
+        .take()
+        .context("git cat-file stdout")?
+        .read_to_end(&mut out)?;
+    writer
+        .join()
+        .map_err(|_| anyhow!("writing to git cat-file panicked"))??;
+    let status = child.wait()?;
+    log_command(&cmd, Some(status), started);
+    Ok(parse_cat_file_batch(&out, specs.len()))
+}
+
+/// `<oid> blob <size>\n<content>\n` per object, or `<name> missing\n`.
+fn parse_cat_file_batch(mut out: &[u8], count: usize) -> Vec<Option<Vec<u8>>> {
+    let mut blobs = Vec::with_capacity(count);
+    while blobs.len() < count {
+        let Some(end) = out.iter().position(|&b| b == b'\n') else {
+            break;
+        };
+        let header = String::from_utf8_lossy(&out[..end]).into_owned();
+        out = &out[end + 1..];
+        let size = header
+            .rsplit_once(' ')
+            .filter(|_| !header.ends_with(" missing") && !header.ends_with(" ambiguous"))
+            .and_then(|(_, size)| size.parse::<usize>().ok());
+        match size {
+            Some(size) if size <= out.len() => {
+                blobs.push(Some(out[..size].to_vec()));
+                out = out.get(size + 1..).unwrap_or_default();
+            }
+            _ => blobs.push(None),
+        }
+    }
+    blobs.resize(count, None);
+    blobs
+}
+
+/// `git rev-parse --git-path NAME` as an absolute path, wherever git ran.
+fn git_path(name: &str) -> Result<PathBuf> {
+    let out = git_output(&["rev-parse", "--git-path", name])?;
+    let base = match session::root() {
+        Some(root) => root,
+        None => env::current_dir()?,
+    };
+    Ok(base.join(out.trim()))
+}
+
+fn git_output(args: &[&str]) -> Result<String> {
+    let mut cmd = session::command("git");
+    // Paths in patch headers and name lists keep their non-ASCII characters instead of
+    // octal escapes; what still gets quoted goes through `diff_parse::unquote`.
+    cmd.args(["-c", "core.quotepath=off"]).args(args);
+    let started = Instant::now();
+    let output = cmd
+        .output()
+        .with_context(|| format!("running git {:?}", args))?;
+    log_command(&cmd, Some(output.status), started);
+    if !output.status.success() {
+        bail!("git {:?} failed", args);
+    }
+    // `-z` output is paths; see `diff_parse::non_utf8_path`.
+    if args.contains(&"-z") {
+        return String::from_utf8(output.stdout).map_err(|e| {
+            let at = e.utf8_error().valid_up_to();
+            diff_parse::non_utf8_path(e.as_bytes(), at)
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Like `git_output`, but each `delimiter`-terminated record of stdout goes to `each` as it
+/// arrives, so memory stays flat however much git prints. `ControlFlow::Break` stops git
+/// early (and isn't a failure).
+fn git_stream(
+    args: &[&str],
+    delimiter: u8,
+    mut each: impl FnMut(&str) -> ControlFlow<()>,
+) -> Result<()> {
+    use std::io::{BufRead, BufReader};
+    let mut cmd = session::command("git");
+    cmd.args(["-c", "core.quotepath=off"])
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    let started = Instant::now();
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("running git {:?}", args))?;
+    let mut reader = BufReader::new(child.stdout.take().context("git stdout")?);
+    let mut record = Vec::new();
+    let mut stopped = false;
+    loop {
+        record.clear();
+        if reader.read_until(delimiter, &mut record)? == 0 {
+            break;
+        }
+        if record.last() == Some(&delimiter) {
+            record.pop();
+        }
+        // NUL-separated records are paths; see `diff_parse::non_utf8_path`.
+        let text = match std::str::from_utf8(&record) {
+            Ok(text) => Cow::Borrowed(text),
+            Err(e) if delimiter == b'\0' => {
+                child.kill().ok();
+                child.wait().ok();
+                return Err(diff_parse::non_utf8_path(&record, e.valid_up_to()));
+            }
+            Err(_) => String::from_utf8_lossy(&record),
+        };
+        if each(&text).is_break() {
+            stopped = true;
+            child.kill().ok();
+            break;
+        }
+    }
+    drop(reader);
+    let status = child.wait()?;
+    log_command(&cmd, Some(status), started);
+    if !stopped && !status.success() {
+        bail!("git {:?} failed", args);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn categorize_detects_common_path_types() {
+        assert_eq!(categorize("README.md"), Category::Docs);
+        assert_eq!(categorize("tests/scommit_spec.snap"), Category::Tests);
+        assert_eq!(categorize("config/settings.toml"), Category::Config);
+        assert_eq!(categorize("src/main.rs"), Category::Code);
+        assert_eq!(categorize("assets/logo.svg"), Category::Assets);
+        assert_eq!(categorize("static/fonts/Inter.WOFF2"), Category::Assets);
+        assert_eq!(
+            categorize("app/migrations/0003_add_test_runs.py"),
+            Category::Migrations
+        );
+        assert_eq!(
+            categorize("db/migrate/20240101120000_create_users.rb"),
+            Category::Migrations
+        );
+        assert_eq!(
+            categorize("src/main/resources/db/V42__add_index.sql"),
+            Category::Migrations
+        );
+        assert_eq!(
+            categorize("alembic/versions/ab12_init.py"),
+            Category::Migrations
+        );
+        assert_eq!(
+            categorize("src/main/java/db/V7__Backfill.java"),
+            Category::Migrations
+        );
+        assert_eq!(categorize(".github/workflows/test.yml"), Category::Ci);
+        assert_eq!(categorize("Jenkinsfile"), Category::Ci);
+        assert_eq!(categorize("services/api/.gitlab-ci.yml"), Category::Ci);
+        assert_eq!(categorize(".circleci/config.yml"), Category::Ci);
+        assert_eq!(categorize("Dockerfile"), Category::Build);
+        assert_eq!(categorize("docker/Dockerfile.test"), Category::Build);
+        assert_eq!(categorize("Makefile"), Category::Build);
+        assert_eq!(categorize("build.rs"), Category::Build);
+        assert_eq!(categorize("app/build.gradle.kts"), Category::Build);
+        assert_eq!(categorize("CMakeLists.txt"), Category::Build);
+        assert_eq!(categorize("debian/control"), Category::Build);
+    }
+
+    #[test]
+    fn choose_prefix_uses_category_and_diff_shape() {
+        let mut docs_only = Stats::default();
+        docs_only.categories.insert(Category::Docs, 2);
+        assert_eq!(choose_prefix(&docs_only), "docs");
+
+        let mut ci_only = Stats::default();
+        ci_only.categories.insert(Category::Ci, 1);
+        assert_eq!(choose_prefix(&ci_only), "ci");
+
+        let mut feat_stats = Stats::default();
+        feat_stats.categories.insert(Category::Code, 1);
+        feat_stats.new_files = 1;
+        feat_stats.added = 24;
+        feat_stats.deleted = 3;
+        assert_eq!(choose_prefix(&feat_stats), "feat");
+
+        let mut refactor_stats = Stats::default();
+        refactor_stats.categories.insert(Category::Code, 2);
+        refactor_stats.added = 4;
+        refactor_stats.deleted = 19;
+        assert_eq!(choose_prefix(&refactor_stats), "refactor");
+    }
+
+    #[test]
+    fn build_subject_picks_top_files_and_truncates() {
+        let changes = vec![
+            FileChange {
+                path: "src/very_long_module_name_with_details.rs".to_string(),
+                status: FileStatus::Modified,
+                added: 60,
+                deleted: 20,
+                category: Category::Code,
+            },
+            FileChange {
+                path: "docs/README_with_many_words_and_explanations.md".to_string(),
+                status: FileStatus::Modified,
+                added: 10,
+                deleted: 2,
+                category: Category::Docs,
+            },
+        ];
+        let stats = compute_stats(&changes);
+        let subject = build_subject(&changes, &stats, &[]);
+        assert!(subject.starts_with("chore: update "));
+        assert!(subject.len() <= 72);
+
+        // Paths are raw UTF-8, so the cut has to land between characters.
+        let changes: Vec<FileChange> = [format!("x{}", "ä".repeat(40)), "é".repeat(40)]
+            .iter()
+            .map(|name| FileChange {
+                path: format!("src/{name}.rs"),
+                status: FileStatus::Modified,
+                added: 3,
+                deleted: 1,
+                category: Category::Code,
+            })
+            .collect();
+        let subject = build_subject(&changes, &compute_stats(&changes), &[]);
+        assert!(subject.starts_with("chore: update "));
+        assert!(subject.len() <= 72);
+    }
+
+    #[test]
+    fn non_ascii_paths_reach_the_subject_and_body_intact() {
+        // With core.quotepath off, `-z` output carries the UTF-8 bytes as they are.
+        let long = format!("docs/xy{}.md", "ü".repeat(40));
+        let out = format!(
+            ":000000 100644 0000000 ba077a4 A\0{long}\0\
+             :100644 100644 587be6b b77b4eb M\0src/café.rs\0\
+             3\t0\t{long}\0\
+             1\t1\tsrc/café.rs\0"
+        );
+        let mut parser = diff_parse::RawNumstat::default();
+        out.split_terminator('\0')
+            .for_each(|field| parser.push(field));
+        let changes: Vec<FileChange> = parser
+            .finish()
+            .into_iter()
+            .map(|entry| FileChange {
+                category: categorize(&entry.path),
+                path: entry.path,
+                status: entry.status,
+                added: entry.added,
+                deleted: entry.deleted,
+            })
+            .collect();
+        let (subject, body) = build_commit_message(&changes, &compute_stats(&changes));
+        assert!(subject.len() <= 72 && subject.contains("xyüü"), "{subject}");
+        assert!(body.contains(&format!("- add {long} (+3/-0")), "{body}");
+        assert!(body.contains("- update src/café.rs (+1/-1)"), "{body}");
+    }
+
+    #[test]
+    fn file_focus_prefers_directories_and_disambiguates_basenames() {
+        let change = |path: &str, added: u32| FileChange {
+            path: path.to_string(),
+            status: FileStatus::Modified,
+            added,
+            deleted: 0,
+            category: Category::Code,
+        };
+        assert_eq!(
+            file_focus(&[
+                change("src/parser/mod.rs", 9),
+                change("src/lexer/mod.rs", 3)
+            ]),
+            "parser/mod.rs & lexer/mod.rs"
+        );
+        assert_eq!(
+            file_focus(&[change("src/cli.rs", 9), change("README.md", 3)]),
+            "cli.rs & README.md"
+        );
+        assert_eq!(
+            file_focus(&[change("pkg/api/__init__.py", 1)]),
+            "api/__init__.py"
+        );
+        assert_eq!(
+            file_focus(&[
+                change("crates/core/src/a.rs", 1),
+                change("crates/core/src/b.rs", 1),
+                change("crates/core/src/c/d.rs", 1),
+            ]),
+            "crates/core"
+        );
+        assert_eq!(
+            file_focus(&[
+                change("src/a.rs", 5),
+                change("src/b.rs", 1),
+                change("tests/c.rs", 1),
+            ]),
+            "a.rs & b.rs"
+        );
+    }
+
+    #[test]
+    fn migration_note_names_the_migration() {
+        let migration = |path: &str, status: FileStatus| FileChange {
+            path: path.to_string(),
+            status,
+            added: 12,
+            deleted: 0,
+            category: Category::Migrations,
+        };
+        assert_eq!(
+            migration_note(&migration(
+                "app/migrations/0042_add_users_index.py",
+                FileStatus::Added
+            )),
+            "- adds migration 0042_add_users_index"
+        );
+        assert_eq!(
+            migration_note(&migration(
+                "prisma/migrations/20240101_init/migration.sql",
+                FileStatus::Modified
+            )),
+            "- changes migration 20240101_init"
+        );
+    }
+
+    #[test]
+    fn asset_summary_counts_by_status_and_type() {
+        let asset = |path: &str, status: FileStatus| FileChange {
+            path: path.to_string(),
+            status,
+            added: 0,
+            deleted: 0,
+            category: Category::Assets,
+        };
+        let assets = [
+            asset("img/logo.png", FileStatus::Added),
+            asset("img/hero.PNG", FileStatus::Modified),
+            asset("fonts/inter.woff2", FileStatus::Added),
+        ];
+        let refs: Vec<&FileChange> = assets.iter().collect();
+        assert_eq!(
+            asset_summary(&refs).as_deref(),
+            Some("- 3 asset(s): 2 added, 1 updated (2 png, 1 woff2)")
+        );
+        assert_eq!(asset_summary(&[]), None);
+    }
+
+    #[test]
+    fn sanitize_json_blob_extracts_from_markdown_fence() {
+        let raw = "```json\n{\"subject\":\"feat: update cli\",\"body\":[\"one\",\"two\"]}\n```";
+        let blob = sanitize_json_blob(raw).expect("expected JSON blob");
+        assert_eq!(
+            blob,
+            "{\"subject\":\"feat: update cli\",\"body\":[\"one\",\"two\"]}"
+        );
+    }
+
+    #[test]
+    fn coerce_body_normalizes_arrays_and_nested_bullets() {
+        let arr = json!(["added --dry-run example", "- tightened docs wording"]);
+        assert_eq!(
+            coerce_body(Some(&arr)),
+            "- added --dry-run example\n- tightened docs wording"
+        );
+
+        let nested = json!({"bullets": ["- one", "two"]});
+        assert_eq!(coerce_body(Some(&nested)), "- one\n- two");
+    }
+
+    #[test]
+    fn commit_message_text_omits_blank_body() {
+        assert_eq!(commit_message_text("fix: x", "  \n"), "fix: x\n");
+        assert_eq!(
+            commit_message_text("fix: x", "Details.\n\n"),
+            "fix: x\n\nDetails.\n"
+        );
+    }
+
+    #[test]
+    fn build_body_formats_rename_entries() {
+        let changes = vec![FileChange {
+            path: "src/new_name.rs".to_string(),
+            status: FileStatus::Renamed {
+                from: "src/old_name.rs".to_string(),
+                to: "src/new_name.rs".to_string(),
+            },
+            added: 7,
+            deleted: 2,
+            category: Category::Code,
+        }];
+        let stats = compute_stats(&changes);
+        let body = build_body(&changes, &stats);
+        assert!(body.contains("- rename src/old_name.rs -> src/new_name.rs (+7/-2) [code]"));
+    }
+
+    #[test]
+    fn build_body_reports_unlisted_files_when_changes_exceed_limit() {
+        let mut changes = Vec::new();
+        for idx in 0..13u32 {
+            changes.push(FileChange {
+                path: format!("src/file_{idx}.rs"),
+                status: FileStatus::Modified,
+                added: idx + 1,
+                deleted: 0,
+                category: Category::Code,
+            });
+        }
+        let stats = compute_stats(&changes);
+        let body = build_body(&changes, &stats);
+
+        assert!(body.contains("- ... 1 more file(s) not listed"));
+        assert!(body.contains("src/file_11.rs"));
+        assert!(!body.contains("src/file_12.rs (+13/-0) [code]"));
+    }
+
+    #[test]
+    fn glob_match_supports_star_wildcards() {
+        assert!(glob_match("release/*", "release/1.2"));
+        assert!(glob_match("main", "main"));
+        assert!(glob_match("*.min.*", "app.min.js"));
+        assert!(!glob_match("main", "maintenance"));
+        assert!(!glob_match("release/*", "releases/1.2"));
+    }
+
+    #[test]
+    fn parse_pending_paths_follows_what_gets_committed() {
+        let porcelain =
+            "M  staged.rs\0 M edited.bin\0?? new/dump.sql\0 D gone.txt\0R  to.iso\0from.iso\0";
+        assert_eq!(
+            parse_pending_paths(porcelain, false),
+            vec!["staged.rs", "edited.bin", "new/dump.sql", "to.iso"]
+        );
+        assert_eq!(
+            parse_pending_paths(porcelain, true),
+            vec!["staged.rs", "to.iso"]
+        );
+    }
+
+    #[test]
+    fn condense_section_keeps_code_hunks() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1 +1 @@
+-old
++new
+diff --git a/Cargo.lock b/Cargo.lock
+--- a/Cargo.lock
++++ b/Cargo.lock
+@@ -1,2 +1,3 @@
+-a
++b
++c
+diff --git a/logo.png b/logo.png
+Binary files a/logo.png and b/logo.png differ
+diff --git a/dist/app.min.js b/dist/app.min.js
+--- a/dist/app.min.js
++++ b/dist/app.min.js
+@@ -1 +1 @@
++x
+";
+        let condense = |omit: &HashMap<String, &'static str>| -> String {
+            diff_parse::sections(diff, usize::MAX)
+                .iter()
+                .map(|section| condense_section(section, omit))
+                .collect()
+        };
+        let condensed = condense(&HashMap::new());
+        assert!(condensed.starts_with("diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs"));
+        assert!(condensed.contains("+new\n"));
+        assert!(condensed.contains("b/Cargo.lock\n[lockfile diff omitted: +2/-1 lines]\n"));
+        assert!(condensed.contains("b/logo.png\n[binary file changed]\n"));
+        assert!(condensed.contains("b/dist/app.min.js\n[minified diff omitted: +1/-0 lines]\n"));
+        assert!(!condensed.contains("+c\n"));
+
+        let omit = HashMap::from([("src/lib.rs".to_string(), "vendored")]);
+        assert!(condense(&omit).starts_with(
+            "diff --git a/src/lib.rs b/src/lib.rs\n[vendored diff omitted: +1/-1 lines]\n"
+        ));
+    }
+
+    #[test]
+    fn branch_name_from_subject_slugifies_conventional_subjects() {
+        assert_eq!(
+            branch_name_from_subject("feat: add login flow"),
+            "feat/add-login-flow"
+        );
+        assert_eq!(
+            branch_name_from_subject("fix(api)!: handle empty input & errors"),
+            "fix/handle-empty-input-errors"
+        );
+        assert_eq!(branch_name_from_subject("Update main.rs"), "update-main-rs");
+    }
+
+    #[test]
+    fn push_args_forward_push_options() {
+        let cli = Cli::parse_from(["scommit", "-o", "merge_request.create", "-o", "ci.skip"]);
+        assert_eq!(
+            push_args(&cli, None),
+            vec![
+                "push",
+                "--push-option=merge_request.create",
+                "--push-option=ci.skip"
+            ]
+        );
+
+        let forced = Cli::parse_from(["scommit", "--force-with-lease"]);
+        assert_eq!(push_args(&forced, None), vec!["push", "--force-with-lease"]);
+        // -u is added by the callers, and only for a branch without an upstream.
+        let tracking = Cli::parse_from(["scommit", "--set-upstream"]);
+        assert_eq!(push_args(&tracking, None), vec!["push"]);
+
+        let target = ("fork".to_string(), "feature/foo".to_string());
+        assert_eq!(
+            push_args(&Cli::parse_from(["scommit"]), Some(&target)),
+            vec!["push", "fork", "HEAD:feature/foo"]
+        );
+    }
+
+    #[test]
+    fn commit_options_map_signing_flags() {
+        let args = |argv: &[&str]| CommitOptions::from_cli(&Cli::parse_from(argv)).git_args();
+        assert!(args(&["scommit"]).is_empty());
+        assert_eq!(args(&["scommit", "-S"]), vec!["--gpg-sign"]);
+        assert_eq!(
+            args(&["scommit", "--gpg-sign=ABCD1234"]),
+            vec!["--gpg-sign=ABCD1234"]
+        );
+        assert_eq!(args(&["scommit", "--no-sign"]), vec!["--no-gpg-sign"]);
+        assert_eq!(
+            args(&["scommit", "-S", "--no-verify"]),
+            vec!["--gpg-sign", "--no-verify"]
+        );
+        assert_eq!(
+            args(&[
+                "scommit",
+                "--author",
+                "Ada <ada@example.com>",
+                "--date",
+                "2024-01-02"
+            ]),
+            vec!["--author=Ada <ada@example.com>", "--date=2024-01-02"]
+        );
+    }
+
+    #[test]
+    fn cat_file_batch_output_is_split_per_object() {
+        let out = b"abc blob 3\nfoo\n:gone missing\ndef blob 0\n\n";
+        assert_eq!(
+            parse_cat_file_batch(out, 3),
+            vec![Some(b"foo".to_vec()), None, Some(Vec::new())]
+        );
+    }
+
+    #[test]
+    fn library_options_map_to_the_command_line() {
+        let cli = Options {
+            model: Some("gpt-4.1".to_string()),
+            no_ai: true,
+            metadata_only: true,
+            hint: None,
+            template: Some("ticket".to_string()),
+        }
+        .cli();
+        assert!(cli.print_only && cli.no_ai);
+        assert_eq!(cli.model.as_deref(), Some("gpt-4.1"));
+        assert_eq!(cli.privacy, Privacy::Metadata);
+        assert_eq!(cli.template.as_deref(), Some("ticket"));
+        assert_eq!(Options::default().cli().privacy, Privacy::Full);
+        assert_eq!(Category::Migrations.name(), "migrations");
+    }
+}
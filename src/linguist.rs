@@ -0,0 +1,123 @@
+//! GitHub Linguist overrides from `.gitattributes` (`linguist-generated`,
+//! `linguist-documentation`, `linguist-vendored`). Repos that already annotate their
+//! generated and vendored code get it categorized, and kept out of the AI prompt, for free.
+
+use anyhow::Result;
+use std::collections::HashMap;
+
+use crate::{Category, FileChange, git_output};
+
+const ATTRIBUTES: [&str; 3] = [
+    "linguist-generated",
+    "linguist-documentation",
+    "linguist-vendored",
+];
+
+/// Paths per `git check-attr` call, to stay under the argument length limit.
+const CHUNK: usize = 500;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Attrs {
+    pub generated: bool,
+    pub documentation: bool,
+    pub vendored: bool,
+}
+
+impl Attrs {
+    /// Why the file's diff is left out of the AI prompt, if it is.
+    pub fn omitted(&self) -> Option<&'static str> {
+        if self.generated {
+            Some("generated")
+        } else if self.vendored {
+            Some("vendored")
+        } else {
+            None
+        }
+    }
+}
+
+/// The attributes set for each of `paths`, read from the index's `.gitattributes` (what
+/// is being committed). Paths with none of them set are left out.
+pub fn lookup(paths: &[&str]) -> Result<HashMap<String, Attrs>> {
+    let mut found = HashMap::new();
+    for chunk in paths.chunks(CHUNK) {
+        let mut args = vec!["check-attr", "--cached", "-z"];
+        args.extend(ATTRIBUTES);
+        args.push("--");
+        args.extend(chunk);
+        found.extend(parse(&git_output(&args)?));
+    }
+    Ok(found)
+}
+
+/// `git check-attr -z` prints `path NUL attribute NUL value NUL` triples. Only a set
+/// attribute (`linguist-generated` or `linguist-generated=true`) counts.
+fn parse(out: &str) -> HashMap<String, Attrs> {
+    let fields: Vec<&str> = out.split('\0').collect();
+    let mut found: HashMap<String, Attrs> = HashMap::new();
+    for triple in fields.chunks_exact(3) {
+        let [path, attribute, value] = [triple[0], triple[1], triple[2]];
+        if !matches!(value, "set" | "true") {
+            continue;
+        }
+        let attrs = found.entry(path.to_string()).or_default();
+        match attribute {
+            "linguist-generated" => attrs.generated = true,
+            "linguist-documentation" => attrs.documentation = true,
+            "linguist-vendored" => attrs.vendored = true,
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Recategorize by the attributes: documentation is docs, whatever its extension, and
+/// generated or vendored files are never code, tests or config of this repo.
+pub fn apply(changes: &mut [FileChange]) -> Result<()> {
+    let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let found = lookup(&paths)?;
+    for change in changes {
+        let Some(attrs) = found.get(&change.path) else {
+            continue;
+        };
+        if attrs.omitted().is_some() {
+            change.category = Category::Other;
+        } else if attrs.documentation {
+            change.category = Category::Docs;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_keeps_set_attributes_only() {
+        let out = "api/gen.pb.go\0linguist-generated\0set\0\
+                   api/gen.pb.go\0linguist-documentation\0unspecified\0\
+                   api/gen.pb.go\0linguist-vendored\0unspecified\0\
+                   third_party/lib.c\0linguist-generated\0false\0\
+                   third_party/lib.c\0linguist-documentation\0unspecified\0\
+                   third_party/lib.c\0linguist-vendored\0true\0\
+                   guide/intro.html\0linguist-generated\0unspecified\0\
+                   guide/intro.html\0linguist-documentation\0set\0\
+                   guide/intro.html\0linguist-vendored\0unset\0\
+                   src/lib.rs\0linguist-generated\0unspecified\0";
+        let found = parse(out);
+        assert_eq!(found.len(), 3);
+        assert_eq!(found["api/gen.pb.go"].omitted(), Some("generated"));
+        assert_eq!(found["third_party/lib.c"].omitted(), Some("vendored"));
+        assert_eq!(
+            found["guide/intro.html"],
+            Attrs {
+                documentation: true,
+                ..Attrs::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use anyhow::{Context, Result};
+use clap::CommandFactory;
+use std::fs;
+use std::path::Path;
+
+use crate::Cli;
+use crate::output::say;
+
+/// Render scommit(1) from the CLI definition to stdout, or with `dir`, write it and one
+/// page per subcommand (scommit-undo.1, ...) there for packaging.
+pub fn print(dir: Option<&Path>) -> Result<()> {
+    let cmd = Cli::command();
+    let Some(dir) = dir else {
+        clap_mangen::Man::new(cmd)
+            .render(&mut std::io::stdout())
+            .context("failed to write the man page")?;
+        return Ok(());
+    };
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    clap_mangen::generate_to(cmd, dir)
+        .with_context(|| format!("failed to write man pages to {}", dir.display()))?;
+    say!("Wrote man pages to {}", dir.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn man_page_documents_flags_and_subcommands() {
+        let mut page = Vec::new();
+        clap_mangen::Man::new(Cli::command())
+            .render(&mut page)
+            .unwrap();
+        let page = String::from_utf8(page).unwrap();
+        assert!(page.starts_with(".ie \\n(.g .ds Aq"));
+        assert!(page.contains(".TH scommit 1"));
+        assert!(page.contains("\\-\\-dry\\-run"));
+        assert!(page.contains("scommit\\-undo(1)"));
+    }
+}
@@ -0,0 +1,95 @@
+//! A nudge when a commit adds a good amount of code without touching any tests; some
+//! teams want it in front of every author, others in the history as well.
+
+use crate::config::{MissingTestsAction, MissingTestsConfig};
+use crate::{Category, FileChange};
+
+/// Code files and their added lines, when they reach `min_lines` and no test file is
+/// staged alongside them.
+fn untested(config: &MissingTestsConfig, changes: &[FileChange]) -> Option<(u32, Vec<String>)> {
+    if config.action == MissingTestsAction::Off || config.min_lines == 0 {
+        return None;
+    }
+    if changes.iter().any(|c| c.category == Category::Tests) {
+        return None;
+    }
+    let code: Vec<&FileChange> = changes
+        .iter()
+        .filter(|c| c.category == Category::Code && c.added > 0)
+        .collect();
+    let added: u32 = code.iter().map(|c| c.added).sum();
+    (added >= config.min_lines).then(|| (added, code.iter().map(|c| c.path.clone()).collect()))
+}
+
+fn describe(config: &MissingTestsConfig, added: u32, paths: &[String]) -> String {
+    if let Some(message) = &config.message {
+        return message.clone();
+    }
+    let shown: Vec<&str> = paths.iter().take(3).map(String::as_str).collect();
+    let more = match paths.len().saturating_sub(shown.len()) {
+        0 => String::new(),
+        n => format!(" and {n} more"),
+    };
+    format!(
+        "+{added} lines of code in {}{more}, but no tests changed.",
+        shown.join(", ")
+    )
+}
+
+/// Print the nudge, for either action.
+pub fn check(config: &MissingTestsConfig, changes: &[FileChange]) {
+    if let Some((added, paths)) = untested(config, changes) {
+        eprintln!("Note: {}", describe(config, added, &paths));
+    }
+}
+
+/// The line to add to the commit body when the action is `note`.
+pub fn body_note(config: &MissingTestsConfig, changes: &[FileChange]) -> Option<String> {
+    if config.action != MissingTestsAction::Note {
+        return None;
+    }
+    let (added, paths) = untested(config, changes)?;
+    Some(format!("Untested: {}", describe(config, added, &paths)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileStatus;
+
+    fn change(path: &str, category: Category, added: u32) -> FileChange {
+        FileChange {
+            path: path.to_string(),
+            status: FileStatus::Modified,
+            added,
+            deleted: 0,
+            category,
+        }
+    }
+
+    #[test]
+    fn nudges_only_for_untested_code_over_the_threshold() {
+        let config = MissingTestsConfig {
+            action: MissingTestsAction::Note,
+            ..MissingTestsConfig::default()
+        };
+        let code = vec![
+            change("src/a.rs", Category::Code, 40),
+            change("src/b.rs", Category::Code, 20),
+            change("README.md", Category::Docs, 100),
+        ];
+        assert_eq!(
+            body_note(&config, &code).unwrap(),
+            "Untested: +60 lines of code in src/a.rs, src/b.rs, but no tests changed."
+        );
+        assert!(body_note(&config, &code[..1]).is_none());
+
+        let mut tested = code.clone();
+        tested.push(change("tests/a.rs", Category::Tests, 1));
+        assert!(body_note(&config, &tested).is_none());
+
+        let warn = MissingTestsConfig::default();
+        assert!(untested(&warn, &code).is_some());
+        assert!(body_note(&warn, &code).is_none());
+    }
+}
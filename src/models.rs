@@ -0,0 +1,191 @@
+//! `scommit models`: the models the provider offers this API key, with their context
+//! sizes, and the "did you mean" error for a `--model` the provider doesn't know.
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use std::env;
+
+use crate::output::{self, say};
+use crate::{Cli, configured_model};
+
+const MODELS_URL: &str = "https://api.openai.com/v1/models";
+
+/// Context windows (tokens) of the common model families, longest prefix first wins.
+/// The OpenAI listing doesn't include them; compatible providers that do are believed.
+const CONTEXT_SIZES: &[(&str, u64)] = &[
+    ("gpt-5", 400_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 16_385),
+    ("o4-mini", 200_000),
+    ("o3", 200_000),
+    ("o1", 200_000),
+    ("chatgpt-4o", 128_000),
+];
+
+/// Families that can write a commit message; the listing also has embeddings, speech,
+/// image and moderation models.
+const CHAT_PREFIXES: &[&str] = &["gpt-", "o1", "o3", "o4", "chatgpt-"];
+
+#[derive(Debug, Deserialize)]
+struct Listing {
+    data: Vec<Model>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Model {
+    id: String,
+    #[serde(default, alias = "context_length")]
+    context_window: Option<u64>,
+}
+
+impl Model {
+    fn context(&self) -> Option<u64> {
+        self.context_window.or_else(|| context_size(&self.id))
+    }
+}
+
+pub fn run(cli: &Cli, all: bool) -> Result<()> {
+    let key =
+        env::var("OPENAI_API_KEY").map_err(|_| anyhow!("listing models needs OPENAI_API_KEY"))?;
+    let mut models = list(&key)?;
+    if !all {
+        models.retain(|m| is_chat(&m.id));
+    }
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    if models.is_empty() {
+        say!("The provider lists no models for this key.");
+        return Ok(());
+    }
+    let current = configured_model(cli);
+    for model in &models {
+        let marker = if model.id == current { "*" } else { " " };
+        let context = model
+            .context()
+            .map_or_else(|| "?".to_string(), human_tokens);
+        println!("{marker} {:<40} {context:>6}", model.id);
+    }
+    if !models.iter().any(|m| m.id == current) {
+        eprintln!(
+            "{}",
+            output::yellow(&format!(
+                "{current} (the model scommit uses) isn't listed for this key."
+            ))
+        );
+    }
+    Ok(())
+}
+
+pub fn list(key: &str) -> Result<Vec<Model>> {
+    let client = crate::ai_client()?;
+    let res = client
+        .get(MODELS_URL)
+        .bearer_auth(key)
+        .send()
+        .context("calling OpenAI API")?;
+    let status = res.status();
+    if !status.is_success() {
+        bail!("OpenAI API error listing models: {status}");
+    }
+    let listing: Listing = res.json().context("parsing the model list")?;
+    Ok(listing.data)
+}
+
+/// The error for a model the provider answered 404 for: the closest names it does
+/// offer, or the plain status when the list can't be had either.
+pub fn not_found(key: &str, model: &str) -> anyhow::Error {
+    let Ok(models) = list(key) else {
+        return anyhow!("OpenAI API error: 404 Not Found (is the model name {model} right?)");
+    };
+    let names: Vec<&str> = models
+        .iter()
+        .map(|m| m.id.as_str())
+        .filter(|id| is_chat(id))
+        .collect();
+    match closest(model, &names).as_slice() {
+        [] => anyhow!(
+            "the provider has no model named {model}; `scommit models` lists the ones it has"
+        ),
+        close => anyhow!(
+            "the provider has no model named {model}; did you mean {}? (`scommit models` lists them all)",
+            close.join(", ")
+        ),
+    }
+}
+
+fn is_chat(id: &str) -> bool {
+    CHAT_PREFIXES.iter().any(|prefix| id.starts_with(prefix))
+}
+
+fn context_size(id: &str) -> Option<u64> {
+    CONTEXT_SIZES
+        .iter()
+        .filter(|(prefix, _)| id.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, size)| *size)
+}
+
+/// 128000 → "128k", 1047576 → "1M".
+fn human_tokens(tokens: u64) -> String {
+    if tokens >= 1_000_000 {
+        format!("{}M", tokens / 1_000_000)
+    } else if tokens >= 1_000 {
+        format!("{}k", tokens / 1_000)
+    } else {
+        tokens.to_string()
+    }
+}
+
+/// Up to three names within a few edits of `wanted`, nearest first.
+fn closest<'a>(wanted: &str, names: &[&'a str]) -> Vec<&'a str> {
+    let limit = (wanted.len() / 3).max(2);
+    let mut scored: Vec<(usize, &str)> = names
+        .iter()
+        .map(|name| (distance(wanted, name), *name))
+        .filter(|(d, _)| *d <= limit)
+        .collect();
+    scored.sort();
+    scored.into_iter().take(3).map(|(_, name)| name).collect()
+}
+
+/// Levenshtein distance over chars.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_sizes_and_suggestions() {
+        assert_eq!(context_size("gpt-4o-mini-2024-07-18"), Some(128_000));
+        assert_eq!(context_size("gpt-4-0613"), Some(8_192));
+        assert_eq!(context_size("gpt-4.1-nano"), Some(1_047_576));
+        assert_eq!(context_size("whisper-1"), None);
+        assert_eq!(human_tokens(128_000), "128k");
+        assert_eq!(human_tokens(1_047_576), "1M");
+
+        let names = ["gpt-4o", "gpt-4o-mini", "gpt-4.1-mini", "o3-mini"];
+        assert_eq!(
+            closest("gpt4o-mini", &names),
+            vec!["gpt-4o-mini", "gpt-4.1-mini"]
+        );
+        assert_eq!(closest("gpt-4.1-mimi", &names)[0], "gpt-4.1-mini");
+        assert!(closest("claude", &names).is_empty());
+        assert!(is_chat("o3-mini") && !is_chat("text-embedding-3-small"));
+    }
+}
@@ -0,0 +1,108 @@
+//! Desktop notification when a run ends (`[notify] enabled = true`), for runs started
+//! from an editor task or left pushing over a slow link in a terminal that isn't visible.
+
+use std::time::Duration;
+
+use crate::config::NotifyConfig;
+use crate::exit;
+use crate::output::{self, Report};
+
+/// Notify about the finished run, if configured. Never fails the run: a missing
+/// notification daemon is only logged.
+pub fn send(config: &NotifyConfig, error: Option<&anyhow::Error>, code: u8, elapsed: Duration) {
+    if !config.enabled || output::ci() || elapsed < Duration::from_secs(config.min_seconds) {
+        return;
+    }
+    let Some((summary, body)) = output::inspect(|report| describe(report, error, code)) else {
+        return;
+    };
+    if let Err(err) = show(&summary, &body) {
+        tracing::debug!(%err, "desktop notification failed");
+    }
+}
+
+/// Title and text: the commit and where it went, or why the run stopped.
+fn describe(report: &Report, error: Option<&anyhow::Error>, code: u8) -> (String, String) {
+    if let Some(error) = error {
+        return ("scommit failed".to_string(), format!("{error:#}"));
+    }
+    let Some(first) = report.commits.first() else {
+        let outcome = match code {
+            c if c == exit::Code::NothingToCommit as u8 => "Nothing to commit.",
+            c if c == exit::Code::Cancelled as u8 => "Cancelled; nothing was committed.",
+            _ => "Done.",
+        };
+        return ("scommit".to_string(), outcome.to_string());
+    };
+    let mut lines = vec![first.subject.clone()];
+    if report.commits.len() > 1 {
+        lines.push(format!("and {} more commit(s)", report.commits.len() - 1));
+    }
+    lines.push(match &report.push {
+        Some(output::PushRecord {
+            pushed: true,
+            dest: Some(dest),
+            ..
+        }) => format!("Pushed to {dest}"),
+        _ => "Not pushed".to_string(),
+    });
+    let sha: String = first.sha.chars().take(7).collect();
+    (format!("scommit: committed {sha}"), lines.join("\n"))
+}
+
+#[cfg(feature = "notify")]
+fn show(summary: &str, body: &str) -> anyhow::Result<()> {
+    notify_rust::Notification::new()
+        .appname("scommit")
+        .summary(summary)
+        .body(body)
+        .show()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "notify"))]
+fn show(_summary: &str, _body: &str) -> anyhow::Result<()> {
+    eprintln!("Warning: [notify] is enabled, but scommit was built without the `notify` feature.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{CommitRecord, PushRecord};
+
+    #[test]
+    fn describe_reports_commit_push_and_failure() {
+        let mut report = Report::default();
+        assert_eq!(
+            describe(&report, None, exit::Code::NothingToCommit as u8).1,
+            "Nothing to commit."
+        );
+
+        report.commits.push(CommitRecord {
+            sha: "3f2a1bc9d0".to_string(),
+            subject: "feat: add login".to_string(),
+        });
+        report.push = Some(PushRecord {
+            pushed: true,
+            remote: Some("origin".to_string()),
+            dest: Some("origin/main".to_string()),
+        });
+        assert_eq!(
+            describe(&report, None, 0),
+            (
+                "scommit: committed 3f2a1bc".to_string(),
+                "feat: add login\nPushed to origin/main".to_string()
+            )
+        );
+
+        let error = anyhow::anyhow!("rejected").context("pushing");
+        assert_eq!(
+            describe(&report, Some(&error), 6),
+            (
+                "scommit failed".to_string(),
+                "pushing: rejected".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,510 @@
+use clap::ValueEnum;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Serialize;
+use std::io::{IsTerminal, Write};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::error::ScommitError;
+use crate::{CATEGORY_NAMES, Category, Cli, Commands, FileChange, FileStatus};
+use crate::{porcelain, session};
+
+static CI: OnceCell<bool> = OnceCell::new();
+static JSON: OnceCell<bool> = OnceCell::new();
+static MESSAGE_ON_STDOUT: OnceCell<bool> = OnceCell::new();
+static QUIET: OnceCell<bool> = OnceCell::new();
+static COLOR: OnceCell<bool> = OnceCell::new();
+static SERVE: OnceCell<bool> = OnceCell::new();
+static PORCELAIN: OnceCell<bool> = OnceCell::new();
+static REPORT: Lazy<Mutex<Report>> = Lazy::new(|| Mutex::new(Report::default()));
+
+/// With --json, stdout carries only the final record, and with --print-only or
+/// `--output -` only the message; progress moves to stderr. With --quiet, progress is dropped altogether and
+/// only warnings and errors remain. CI mode (--ci or CI=true) implies --json, unless
+/// --porcelain already has stdout.
+pub fn init(cli: &Cli) {
+    CI.get_or_init(|| cli.ci || ci_env(std::env::var("CI").ok().as_deref()));
+    let message_on_stdout =
+        cli.print_only || cli.output.as_deref() == Some(std::path::Path::new("-"));
+    MESSAGE_ON_STDOUT.get_or_init(|| message_on_stdout);
+    PORCELAIN.get_or_init(|| cli.porcelain.is_some());
+    JSON.get_or_init(|| cli.json || (ci() && !message_on_stdout && !porcelain()));
+    QUIET.get_or_init(|| cli.quiet);
+    SERVE.get_or_init(|| matches!(cli.command, Some(Commands::Serve)));
+    COLOR.get_or_init(|| {
+        use_color(
+            cli.color,
+            std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+            !stdout_reserved() && std::io::stdout().is_terminal(),
+        )
+    });
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal and NO_COLOR is unset
+    Auto,
+    Always,
+    Never,
+}
+
+/// An explicit --color wins; `auto` defers to NO_COLOR and whether stdout is a terminal.
+fn use_color(choice: ColorChoice, no_color: bool, terminal: bool) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => !no_color && terminal,
+    }
+}
+
+fn paint(code: &str, text: &str) -> String {
+    if COLOR.get().copied().unwrap_or(false) {
+        format!("\x1b[{code}m{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn bold(text: &str) -> String {
+    paint("1", text)
+}
+
+pub fn dim(text: &str) -> String {
+    paint("2", text)
+}
+
+pub fn green(text: &str) -> String {
+    paint("32", text)
+}
+
+pub fn red(text: &str) -> String {
+    paint("31", text)
+}
+
+pub fn yellow(text: &str) -> String {
+    paint("33", text)
+}
+
+/// `+A / -D`, additions in green and deletions in red.
+pub fn line_counts(added: u32, deleted: u32) -> String {
+    format!(
+        "{} / {}",
+        green(&format!("+{added}")),
+        red(&format!("-{deleted}"))
+    )
+}
+
+/// The category as a bracketed badge, one color per category.
+pub fn badge(category: Category) -> String {
+    let name = CATEGORY_NAMES.get(&category).copied().unwrap_or("other");
+    let code = match category {
+        Category::Code => "34",
+        Category::Tests => "32",
+        Category::Docs => "36",
+        Category::Config => "33",
+        Category::Ci => "35",
+        Category::Build => "95",
+        Category::Assets => "96",
+        Category::Migrations => "93",
+        Category::Other => "2",
+    };
+    paint(code, &format!("[{name}]"))
+}
+
+/// "Subject: ..." with the subject highlighted, followed by the body.
+pub fn message(subject: &str, body: &str) -> String {
+    format!("{} {}\n\n{body}", dim("Subject:"), bold(subject))
+}
+
+/// CI providers set CI=true (some CI=1); an empty value, `0` or `false` means not CI.
+fn ci_env(value: Option<&str>) -> bool {
+    value.is_some_and(|v| !v.is_empty() && v != "0" && !v.eq_ignore_ascii_case("false"))
+}
+
+/// Non-interactive automation: never prompt, no AI unless --allow-ai, deterministic
+/// messages, and the --json record on stdout.
+pub fn ci() -> bool {
+    CI.get().copied().unwrap_or(false)
+}
+
+pub fn json() -> bool {
+    JSON.get().copied().unwrap_or(false)
+}
+
+/// Whether stdout carries a result (the --json record, --porcelain records, the message
+/// or `scommit serve`'s responses), so progress and prompts have to go to stderr.
+pub fn stdout_reserved() -> bool {
+    json() || porcelain() || serving() || MESSAGE_ON_STDOUT.get().copied().unwrap_or(false)
+}
+
+pub fn porcelain() -> bool {
+    PORCELAIN.get().copied().unwrap_or(false)
+}
+
+/// Whether a question can be answered: by someone at the terminal, or by the program
+/// reading --porcelain `prompt` records.
+pub fn can_prompt() -> bool {
+    porcelain() || std::io::stdin().is_terminal()
+}
+
+/// `scommit serve`: requests come in on stdin, so nothing may prompt.
+pub fn serving() -> bool {
+    SERVE.get().copied().unwrap_or(false)
+}
+
+pub fn quiet() -> bool {
+    QUIET.get().copied().unwrap_or(false)
+}
+
+/// Print a line of progress to stdout, to stderr when stdout is reserved for --json or
+/// --print-only, or nowhere with --quiet.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if !$crate::output::quiet() {
+            if $crate::output::stdout_reserved() {
+                eprintln!($($arg)*);
+            } else {
+                println!($($arg)*);
+            }
+        }
+    };
+}
+pub(crate) use say;
+
+/// Stdout for child processes (git, hooks, nested scommit runs) that would otherwise
+/// write straight into the --json record or the message on stdout, or past --quiet.
+pub fn child_stdout() -> Stdio {
+    if quiet() {
+        Stdio::null()
+    } else if stdout_reserved() {
+        Stdio::from(std::io::stderr())
+    } else {
+        Stdio::inherit()
+    }
+}
+
+/// A spinner on stderr for slow steps that print nothing themselves (staging a big tree,
+/// waiting on the AI provider). It only appears once the step has taken a moment, and
+/// never without a terminal or with --quiet. Stops and clears its line when dropped.
+pub struct Spinner {
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// Set while a spinner owns the current stderr line.
+static SPINNING: AtomicBool = AtomicBool::new(false);
+
+impl Spinner {
+    const DELAY: Duration = Duration::from_millis(300);
+    const FRAME: Duration = Duration::from_millis(80);
+    const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+    pub fn start(label: impl Into<String>) -> Spinner {
+        if quiet() || !std::io::stderr().is_terminal() || SPINNING.load(Ordering::SeqCst) {
+            return Spinner {
+                stop: None,
+                handle: None,
+            };
+        }
+        let label = label.into();
+        let (stop, stopped) = mpsc::channel::<()>();
+        let handle = thread::spawn(move || {
+            if stopped.recv_timeout(Self::DELAY) != Err(RecvTimeoutError::Timeout) {
+                return;
+            }
+            SPINNING.store(true, Ordering::SeqCst);
+            let mut stderr = std::io::stderr();
+            for frame in Self::FRAMES.iter().cycle() {
+                write!(stderr, "\r{} {label}...", paint("36", &frame.to_string())).ok();
+                stderr.flush().ok();
+                if stopped.recv_timeout(Self::FRAME) != Err(RecvTimeoutError::Timeout) {
+                    break;
+                }
+            }
+            write!(stderr, "\r\x1b[2K").ok();
+            stderr.flush().ok();
+            SPINNING.store(false, Ordering::SeqCst);
+        });
+        Spinner {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the thread; joining makes sure the line is clear
+        // before anything else is printed.
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+    }
+}
+
+/// What a run did, emitted as one JSON object with --json.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub dry_run: bool,
+    pub files: Vec<FileRecord>,
+    pub subject: Option<String>,
+    pub body: Option<String>,
+    pub ai: bool,
+    pub commits: Vec<CommitRecord>,
+    pub push: Option<PushRecord>,
+    pub tag: Option<String>,
+    /// URL of the pull or merge request opened by --pr or `scommit pr`.
+    pub pull_request: Option<String>,
+    pub error: Option<String>,
+    /// What kind of failure `error` is, for programs; see `ScommitError`.
+    pub failure: Option<ScommitError>,
+    /// The process exit code; see `exit::Code`.
+    pub exit_code: u8,
+    /// HEAD before the run's first commit ("" for an unborn branch), for `scommit undo`.
+    #[serde(skip)]
+    pub head_before: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileRecord {
+    pub path: String,
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    pub added: u32,
+    pub deleted: u32,
+    pub category: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommitRecord {
+    pub sha: String,
+    pub subject: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PushRecord {
+    pub pushed: bool,
+    pub remote: Option<String>,
+    /// `remote/branch` pushed to.
+    pub dest: Option<String>,
+}
+
+/// Update the report, the session's inside one (see `session`); cheap enough to call
+/// whether or not --json is on.
+pub fn record(update: impl FnOnce(&mut Report)) {
+    if session::current().is_some() {
+        session::with_report(update);
+    } else if let Ok(mut report) = REPORT.lock() {
+        update(&mut report);
+    }
+}
+
+pub fn record_files(changes: &[FileChange]) {
+    let files: Vec<FileRecord> = changes.iter().map(file_record).collect();
+    porcelain::files(&files);
+    record(|r| r.files = files);
+}
+
+pub fn file_record(change: &FileChange) -> FileRecord {
+    let (status, from) = match &change.status {
+        FileStatus::Added => ("added", None),
+        FileStatus::Modified => ("modified", None),
+        FileStatus::Deleted => ("deleted", None),
+        FileStatus::Renamed { from, .. } => ("renamed", Some(from.clone())),
+        FileStatus::Copied { from, .. } => ("copied", Some(from.clone())),
+        FileStatus::TypeChanged => ("typechange", None),
+    };
+    FileRecord {
+        path: change.path.clone(),
+        status,
+        from,
+        added: change.added,
+        deleted: change.deleted,
+        category: CATEGORY_NAMES
+            .get(&change.category)
+            .copied()
+            .unwrap_or("other"),
+    }
+}
+
+/// After a run that committed something, recap it in a few lines: what was committed,
+/// how big it was, where it went and how long it took. --json has the record instead.
+pub fn summary(branch: impl FnOnce() -> Option<String>, elapsed: Duration) {
+    if json() || porcelain() || quiet() {
+        return;
+    }
+    let Ok(report) = REPORT.lock() else {
+        return;
+    };
+    if report.commits.is_empty() {
+        return;
+    }
+    if let Some(text) = render_summary(&report, branch().as_deref(), elapsed) {
+        println!("\n{text}");
+    }
+}
+
+fn render_summary(report: &Report, branch: Option<&str>, elapsed: Duration) -> Option<String> {
+    let (first, rest) = report.commits.split_first()?;
+    let short = |sha: &str| sha.chars().take(7).collect::<String>();
+    let mut lines = vec![format!(
+        "{} {} {}",
+        green("Committed"),
+        yellow(&short(&first.sha)),
+        first.subject
+    )];
+    for commit in rest {
+        lines.push(format!(
+            "          {} {}",
+            yellow(&short(&commit.sha)),
+            commit.subject
+        ));
+    }
+    let (added, deleted) = report
+        .files
+        .iter()
+        .fold((0, 0), |(a, d), f| (a + f.added, d + f.deleted));
+    lines.push(format!(
+        "  {} file(s), {}",
+        report.files.len(),
+        line_counts(added, deleted)
+    ));
+    let branch = branch.unwrap_or("HEAD");
+    lines.push(match &report.push {
+        Some(PushRecord {
+            pushed: true,
+            dest: Some(dest),
+            ..
+        }) => format!("  {branch} → {dest} {}", green("(pushed)")),
+        _ => format!("  {branch} {}", dim("(not pushed)")),
+    });
+    if let Some(tag) = &report.tag {
+        lines.push(format!("  tag {tag}"));
+    }
+    if let Some(url) = &report.pull_request {
+        lines.push(format!("  PR {url}"));
+    }
+    lines.push(dim(&format!("  Done in {:.1}s", elapsed.as_secs_f64())));
+    Some(lines.join("\n"))
+}
+
+/// Read the report so far, e.g. to describe the run in a notification.
+pub fn inspect<T>(read: impl FnOnce(&Report) -> T) -> Option<T> {
+    if session::current().is_some() {
+        return session::with_report(|report| read(report));
+    }
+    REPORT.lock().ok().map(|report| read(&report))
+}
+
+/// Print the --json record (with the error, if the run failed) or the last --porcelain
+/// records to stdout.
+pub fn finish(error: Option<&anyhow::Error>, exit_code: u8) {
+    if !json() && !porcelain() {
+        return;
+    }
+    let Ok(mut report) = REPORT.lock() else {
+        return;
+    };
+    if porcelain() {
+        porcelain::finish(&report, error, exit_code);
+        return;
+    }
+    report.error = error.map(|e| format!("{e:#}"));
+    report.failure = error.and_then(|e| e.downcast_ref::<ScommitError>().cloned());
+    report.exit_code = exit_code;
+    match serde_json::to_string(&*report) {
+        Ok(line) => println!("{line}"),
+        Err(e) => eprintln!("Warning: could not serialize the --json record ({e})."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn use_color_respects_flag_then_no_color_then_terminal() {
+        assert!(use_color(ColorChoice::Always, true, false));
+        assert!(!use_color(ColorChoice::Never, false, true));
+        assert!(use_color(ColorChoice::Auto, false, true));
+        assert!(!use_color(ColorChoice::Auto, true, true));
+        assert!(!use_color(ColorChoice::Auto, false, false));
+    }
+
+    #[test]
+    fn ci_env_accepts_provider_values_only() {
+        assert!(ci_env(Some("true")));
+        assert!(ci_env(Some("1")));
+        assert!(!ci_env(Some("false")));
+        assert!(!ci_env(Some("FALSE")));
+        assert!(!ci_env(Some("0")));
+        assert!(!ci_env(Some("")));
+        assert!(!ci_env(None));
+    }
+
+    #[test]
+    fn render_summary_lists_commits_size_and_push() {
+        let mut report = Report::default();
+        assert_eq!(render_summary(&report, Some("main"), Duration::ZERO), None);
+
+        report.commits = vec![
+            CommitRecord {
+                sha: "3f2a1bc9d0".to_string(),
+                subject: "feat: add login".to_string(),
+            },
+            CommitRecord {
+                sha: "9e1d2c0aa1".to_string(),
+                subject: "test: cover login".to_string(),
+            },
+        ];
+        report.files = vec![FileRecord {
+            path: "src/login.rs".to_string(),
+            status: "added",
+            from: None,
+            added: 40,
+            deleted: 2,
+            category: "code",
+        }];
+        report.push = Some(PushRecord {
+            pushed: true,
+            remote: Some("origin".to_string()),
+            dest: Some("origin/main".to_string()),
+        });
+        assert_eq!(
+            render_summary(&report, Some("main"), Duration::from_millis(2350)).unwrap(),
+            "Committed 3f2a1bc feat: add login\n          9e1d2c0 test: cover login\n  1 file(s), +40 / -2\n  main → origin/main (pushed)\n  Done in 2.4s"
+        );
+    }
+
+    #[test]
+    fn file_record_names_status_and_category() {
+        let change = FileChange {
+            path: "src/new.rs".to_string(),
+            status: FileStatus::Renamed {
+                from: "src/old.rs".to_string(),
+                to: "src/new.rs".to_string(),
+            },
+            added: 3,
+            deleted: 1,
+            category: Category::Code,
+        };
+        let json = serde_json::to_value(file_record(&change)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "path": "src/new.rs",
+                "status": "renamed",
+                "from": "src/old.rs",
+                "added": 3,
+                "deleted": 1,
+                "category": "code"
+            })
+        );
+    }
+}
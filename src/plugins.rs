@@ -0,0 +1,494 @@
+//! WASM plugins: sandboxed extensions loaded from `.scommit/plugins/*.wasm` in the repo
+//! and `plugins/*.wasm` next to the user config, repo plugins first, each directory in
+//! file-name order. They run in wasmtime (the `plugins` cargo feature) with no imports at
+//! all, so no files, network or clock, and with capped memory and fuel per call.
+//!
+//! A plugin exports `memory` and `scommit_alloc(len: i32) -> i32`, which hands out `len`
+//! bytes for the host to write the input into, plus any of these hooks, each called with
+//! a UTF-8 input as `(ptr: i32, len: i32)`:
+//!
+//! - `scommit_categorize(path) -> i32`: the category for a path, as an index into
+//!   [`CATEGORIES`], or -1 for no opinion. The first plugin with an opinion wins.
+//! - `scommit_format_subject(json) -> i64`: a new subject for the generated message.
+//!   The input is `{"subject", "body", "branch", "files"}`; every plugin gets a turn.
+//! - `scommit_validate(message) -> i64`: what is wrong with a message about to be
+//!   committed; nothing means it is fine. Every plugin gets a say.
+//!
+//! The `i64` results are a string in the plugin's memory, `ptr << 32 | len`; 0 is none.
+
+use anyhow::{Result, bail};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::output::{self, FileRecord};
+use crate::{Category, FileChange, config, current_branch, git_output};
+
+/// Repo plugins, relative to the repo root.
+pub const REPO_DIR: &str = ".scommit/plugins";
+
+/// What `scommit_categorize` returns an index into.
+pub const CATEGORIES: [Category; 9] = [
+    Category::Docs,
+    Category::Tests,
+    Category::Config,
+    Category::Code,
+    Category::Ci,
+    Category::Build,
+    Category::Assets,
+    Category::Migrations,
+    Category::Other,
+];
+
+/// Git's autosquash subjects name the commit they amend; validators don't see them.
+const AUTOSQUASH: [&str; 3] = ["fixup! ", "squash! ", "amend! "];
+
+/// The plugins loaded for each repo root (None outside a repo), so a `scommit serve`
+/// working on several repositories runs each one's own.
+static LOADED: Lazy<Mutex<HashMap<Option<PathBuf>, Vec<Loaded>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+struct Loaded {
+    name: String,
+    plugin: runtime::Plugin,
+    /// Set after a categorizer call fails, so the warning is shown once.
+    broken: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct SubjectInput<'a> {
+    subject: &'a str,
+    body: &'a str,
+    branch: Option<String>,
+    files: Vec<FileRecord>,
+}
+
+/// The plugin files, repo first; `root` is the repo's top level when there is one.
+pub fn files(root: Option<&Path>) -> Vec<PathBuf> {
+    let user = config::user_config_path().and_then(|p| Some(p.parent()?.join("plugins")));
+    root.map(|root| root.join(REPO_DIR))
+        .into_iter()
+        .chain(user)
+        .flat_map(|dir| {
+            let mut found: Vec<PathBuf> = fs::read_dir(&dir)
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+                .collect();
+            found.sort();
+            found
+        })
+        .collect()
+}
+
+/// Each plugin file with the hooks it exports, or why it doesn't load; for `scommit doctor`.
+pub fn describe(root: Option<&Path>) -> Vec<(PathBuf, Result<Vec<&'static str>>)> {
+    files(root)
+        .into_iter()
+        .map(|path| {
+            let hooks = runtime::load(&path).map(|plugin| plugin.hooks());
+            (path, hooks)
+        })
+        .collect()
+}
+
+/// A plugin's category for `path`, if any plugin has an opinion.
+pub fn categorize(path: &str) -> Option<Category> {
+    let mut all = LOADED.lock().ok()?;
+    let loaded = loaded(&mut all);
+    for plugin in loaded.iter_mut().filter(|plugin| !plugin.broken) {
+        match plugin.plugin.categorize(path) {
+            Ok(Some(index)) => {
+                if let Some(category) = usize::try_from(index).ok().and_then(|i| CATEGORIES.get(i))
+                {
+                    return Some(*category);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!(
+                    "Plugin {} failed to categorize {path} ({e:#}); ignoring its categories.",
+                    plugin.name
+                );
+                plugin.broken = true;
+            }
+        }
+    }
+    None
+}
+
+/// The subject after every plugin with a formatter has had its turn.
+pub fn format_subject(subject: String, body: &str, changes: &[FileChange]) -> Result<String> {
+    let Ok(mut all) = LOADED.lock() else {
+        return Ok(subject);
+    };
+    let loaded = loaded(&mut all);
+    if loaded.is_empty() {
+        return Ok(subject);
+    }
+    let branch = current_branch().ok().flatten();
+    let mut subject = subject;
+    for plugin in loaded.iter_mut() {
+        let input = serde_json::to_string(&SubjectInput {
+            subject: &subject,
+            body,
+            branch: branch.clone(),
+            files: changes.iter().map(output::file_record).collect(),
+        })?;
+        match plugin.plugin.format_subject(&input) {
+            Ok(Some(formatted)) if !formatted.trim().is_empty() => {
+                subject = formatted.trim().to_string();
+            }
+            Ok(_) => {}
+            Err(e) => bail!("plugin {} failed to format the subject: {e:#}", plugin.name),
+        }
+    }
+    Ok(subject)
+}
+
+/// Refuse `message` if any plugin's validator finds something wrong with it.
+pub fn validate(message: &str) -> Result<()> {
+    if AUTOSQUASH.iter().any(|prefix| message.starts_with(prefix)) {
+        return Ok(());
+    }
+    let Ok(mut all) = LOADED.lock() else {
+        return Ok(());
+    };
+    let loaded = loaded(&mut all);
+    let mut problems = Vec::new();
+    for plugin in loaded.iter_mut() {
+        match plugin.plugin.validate(message) {
+            Ok(Some(problem)) if !problem.trim().is_empty() => {
+                problems.push(format!("{}: {}", plugin.name, problem.trim()));
+            }
+            Ok(_) => {}
+            Err(e) => bail!(
+                "plugin {} failed to validate the message: {e:#}",
+                plugin.name
+            ),
+        }
+    }
+    if !problems.is_empty() {
+        bail!(
+            "the message was refused by a plugin:\n{}",
+            problems.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// The current repo's plugins, loading them the first time it comes up.
+fn loaded(all: &mut HashMap<Option<PathBuf>, Vec<Loaded>>) -> &mut Vec<Loaded> {
+    let root = git_output(&["rev-parse", "--show-toplevel"])
+        .ok()
+        .map(|out| PathBuf::from(out.trim()));
+    all.entry(root.clone())
+        .or_insert_with(|| load_all(root.as_deref()))
+}
+
+/// A plugin that doesn't load is left out with a warning; `scommit doctor` says more.
+fn load_all(root: Option<&Path>) -> Vec<Loaded> {
+    files(root)
+        .into_iter()
+        .filter_map(|path| {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            match runtime::load(&path) {
+                Ok(plugin) => Some(Loaded {
+                    name,
+                    plugin,
+                    broken: false,
+                }),
+                Err(e) => {
+                    eprintln!("Skipping plugin {}: {e:#}", path.display());
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "plugins")]
+mod runtime {
+    use anyhow::{Context, Result, bail};
+    use once_cell::sync::Lazy;
+    use std::path::Path;
+    use wasmtime::{
+        Config, Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+    };
+
+    /// Instructions' worth of fuel per call: plenty for string work, and a loop that
+    /// never ends runs out instead of hanging the commit.
+    const FUEL: u64 = 50_000_000;
+
+    const MAX_MEMORY: usize = 64 << 20;
+
+    static ENGINE: Lazy<Result<Engine, String>> = Lazy::new(|| {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        Engine::new(&config).map_err(|e| format!("{e:#}"))
+    });
+
+    pub(super) struct Plugin {
+        store: Store<StoreLimits>,
+        memory: Memory,
+        alloc: TypedFunc<i32, i32>,
+        categorize: Option<TypedFunc<(i32, i32), i32>>,
+        format_subject: Option<TypedFunc<(i32, i32), i64>>,
+        validate: Option<TypedFunc<(i32, i32), i64>>,
+    }
+
+    pub(super) fn load(path: &Path) -> Result<Plugin> {
+        let engine = ENGINE.as_ref().map_err(|e| anyhow::anyhow!("{e}"))?;
+        let module = Module::from_file(engine, path)?;
+        let imports: Vec<String> = module
+            .imports()
+            .map(|import| format!("{}::{}", import.module(), import.name()))
+            .collect();
+        if !imports.is_empty() {
+            bail!(
+                "it imports {}; plugins get no host functions (no WASI)",
+                imports.join(", ")
+            );
+        }
+        let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build();
+        let mut store = Store::new(engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(FUEL)?;
+        let instance = Instance::new(&mut store, &module, &[])?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .context("it exports no `memory`")?;
+        let alloc = instance
+            .get_typed_func(&mut store, "scommit_alloc")
+            .ok()
+            .context("it exports no `scommit_alloc(len: i32) -> i32`")?;
+        let categorize = instance
+            .get_typed_func(&mut store, "scommit_categorize")
+            .ok();
+        let format_subject = instance
+            .get_typed_func(&mut store, "scommit_format_subject")
+            .ok();
+        let validate = instance.get_typed_func(&mut store, "scommit_validate").ok();
+        if categorize.is_none() && format_subject.is_none() && validate.is_none() {
+            bail!(
+                "it exports none of scommit_categorize, scommit_format_subject and scommit_validate (or with other signatures)"
+            );
+        }
+        Ok(Plugin {
+            store,
+            memory,
+            alloc,
+            categorize,
+            format_subject,
+            validate,
+        })
+    }
+
+    impl Plugin {
+        pub(super) fn hooks(&self) -> Vec<&'static str> {
+            [
+                (self.categorize.is_some(), "categorize"),
+                (self.format_subject.is_some(), "format_subject"),
+                (self.validate.is_some(), "validate"),
+            ]
+            .into_iter()
+            .filter_map(|(exported, name)| exported.then_some(name))
+            .collect()
+        }
+
+        pub(super) fn categorize(&mut self, path: &str) -> Result<Option<i32>> {
+            let Some(hook) = self.categorize.clone() else {
+                return Ok(None);
+            };
+            let (ptr, len) = self.write(path)?;
+            let index = hook.call(&mut self.store, (ptr, len))?;
+            Ok((index >= 0).then_some(index))
+        }
+
+        pub(super) fn format_subject(&mut self, input: &str) -> Result<Option<String>> {
+            match self.format_subject.clone() {
+                Some(hook) => self.call_for_text(hook, input),
+                None => Ok(None),
+            }
+        }
+
+        pub(super) fn validate(&mut self, message: &str) -> Result<Option<String>> {
+            match self.validate.clone() {
+                Some(hook) => self.call_for_text(hook, message),
+                None => Ok(None),
+            }
+        }
+
+        fn call_for_text(
+            &mut self,
+            hook: TypedFunc<(i32, i32), i64>,
+            input: &str,
+        ) -> Result<Option<String>> {
+            let (ptr, len) = self.write(input)?;
+            let packed = hook.call(&mut self.store, (ptr, len))?;
+            let Some((ptr, len)) = unpack(packed) else {
+                return Ok(None);
+            };
+            let mut bytes = vec![0; len];
+            self.memory
+                .read(&self.store, ptr, &mut bytes)
+                .context("its result points outside its memory")?;
+            Ok(Some(
+                String::from_utf8(bytes).context("its result isn't UTF-8")?,
+            ))
+        }
+
+        /// Copy `input` into the plugin's memory, refilling its fuel for the call ahead.
+        fn write(&mut self, input: &str) -> Result<(i32, i32)> {
+            self.store.set_fuel(FUEL)?;
+            let len = i32::try_from(input.len()).context("the input is too large")?;
+            let ptr = self.alloc.call(&mut self.store, len)?;
+            self.memory
+                .write(&mut self.store, ptr as u32 as usize, input.as_bytes())
+                .context("scommit_alloc returned memory it doesn't have")?;
+            Ok((ptr, len))
+        }
+    }
+
+    /// `ptr << 32 | len` from a hook's result; None for 0.
+    pub(super) fn unpack(packed: i64) -> Option<(usize, usize)> {
+        let packed = packed as u64;
+        let len = (packed & 0xffff_ffff) as usize;
+        (len > 0).then_some(((packed >> 32) as usize, len))
+    }
+}
+
+#[cfg(not(feature = "plugins"))]
+mod runtime {
+    use anyhow::{Result, bail};
+    use std::path::Path;
+
+    /// Built without the `plugins` feature: no plugin ever loads.
+    pub(super) enum Plugin {}
+
+    pub(super) fn load(_path: &Path) -> Result<Plugin> {
+        bail!("scommit was built without the `plugins` feature; rebuild with --features plugins")
+    }
+
+    impl Plugin {
+        pub(super) fn hooks(&self) -> Vec<&'static str> {
+            match *self {}
+        }
+
+        pub(super) fn categorize(&mut self, _path: &str) -> Result<Option<i32>> {
+            match *self {}
+        }
+
+        pub(super) fn format_subject(&mut self, _input: &str) -> Result<Option<String>> {
+            match *self {}
+        }
+
+        pub(super) fn validate(&mut self, _message: &str) -> Result<Option<String>> {
+            match *self {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repo_plugins_come_first_in_name_order() {
+        let root =
+            std::env::temp_dir().join(format!("scommit-plugin-files-{}", std::process::id()));
+        let dir = root.join(REPO_DIR);
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["b.wasm", "a.wasm", "notes.txt"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let found = files(Some(&root));
+        assert_eq!(found[..2], [dir.join("a.wasm"), dir.join("b.wasm")]);
+        assert!(!found.contains(&dir.join("notes.txt")));
+        assert_eq!(CATEGORIES[3], Category::Code);
+        assert_eq!(CATEGORIES.map(Category::name).last(), Some(&"other"));
+        fs::remove_dir_all(&root).ok();
+    }
+
+    /// Categorizes `*.proto` as config (2), prefixes subjects with `[bot] ` and refuses
+    /// messages that contain `WIP`.
+    #[cfg(feature = "plugins")]
+    const PLUGIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (data (i32.const 0) "[bot] ")
+          (data (i32.const 16) "no WIP commits")
+          (global $next (mut i32) (i32.const 1024))
+          (func (export "scommit_alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func $ends_with_proto (param $ptr i32) (param $len i32) (result i32)
+            (if (result i32) (i32.lt_u (local.get $len) (i32.const 6))
+              (then (i32.const 0))
+              (else (i64.eq
+                (i64.shr_u
+                  (i64.load (i32.sub (i32.add (local.get $ptr) (local.get $len)) (i32.const 8)))
+                  (i64.const 16))
+                (i64.const 0x6f746f72702e)))))
+          (func (export "scommit_categorize") (param $ptr i32) (param $len i32) (result i32)
+            (if (result i32) (call $ends_with_proto (local.get $ptr) (local.get $len))
+              (then (i32.const 2))
+              (else (i32.const -1))))
+          (func (export "scommit_validate") (param $ptr i32) (param $len i32) (result i64)
+            (local $i i32)
+            (block $done
+              (loop $scan
+                (br_if $done (i32.gt_u (i32.add (local.get $i) (i32.const 3)) (local.get $len)))
+                (if (i32.eq
+                      (i32.and (i32.load (i32.add (local.get $ptr) (local.get $i))) (i32.const 0xffffff))
+                      (i32.const 0x504957))
+                  (then (return (i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const 14)))))
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br $scan)))
+            (i64.const 0))
+          (func (export "scommit_format_subject") (param i32 i32) (result i64)
+            (i64.const 6)))
+    "#;
+
+    #[cfg(feature = "plugins")]
+    #[test]
+    fn wasm_hooks_categorize_format_and_validate() {
+        let dir = std::env::temp_dir().join(format!("scommit-plugins-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("team.wasm");
+        fs::write(&path, PLUGIN).unwrap();
+        assert_eq!(runtime::unpack(0), None);
+        assert_eq!(runtime::unpack((1024 << 32) | 5), Some((1024, 5)));
+        let mut plugin = runtime::load(&path).unwrap();
+        assert_eq!(plugin.hooks(), ["categorize", "format_subject", "validate"]);
+        assert_eq!(plugin.categorize("api/user.proto").unwrap(), Some(2));
+        assert_eq!(plugin.categorize("src/lib.rs").unwrap(), None);
+        assert_eq!(
+            plugin.validate("feat: WIP parser\n").unwrap().as_deref(),
+            Some("no WIP commits")
+        );
+        assert_eq!(plugin.validate("feat: add parser\n").unwrap(), None);
+        assert_eq!(
+            plugin.format_subject("{}").unwrap().as_deref(),
+            Some("[bot] ")
+        );
+
+        fs::write(
+            &path,
+            r#"(module (import "wasi" "fd_write" (func)) (memory (export "memory") 1))"#,
+        )
+        .unwrap();
+        let refused = runtime::load(&path).err().unwrap();
+        assert!(format!("{refused:#}").contains("wasi::fd_write"));
+        fs::remove_dir_all(&dir).ok();
+    }
+}
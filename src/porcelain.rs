@@ -0,0 +1,220 @@
+//! `--porcelain=v1`: the run as stable, line-oriented records on stdout for editor
+//! plugins, like git's porcelain formats. Each record is a keyword and its fields separated
+//! by single spaces. Free text comes last and runs to the end of the line; paths are
+//! C-quoted like git's when they hold whitespace, quotes, backslashes or control
+//! characters. Progress and git's own output go to stderr.
+//!
+//! The staged files and the message are written as soon as they are known. Each question
+//! is a `prompt` record, answered with one line on stdin. The rest comes when the run
+//! ends, and `exit <code>` is always the last record. New record types may be added to v1;
+//! readers skip keywords they don't know.
+
+use clap::ValueEnum;
+use std::io::Write;
+
+use crate::diff_parse;
+use crate::error::ScommitError;
+use crate::output::{self, FileRecord, Report};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Version {
+    V1,
+}
+
+/// `file` and `stats` records for the staged files.
+pub fn files(files: &[FileRecord]) {
+    emit(&file_records(files));
+}
+
+/// `subject`, `body` and `ai` records for a message, once per generated message
+/// (again after a regenerate).
+pub fn message(subject: &str, body: &str) {
+    if !output::porcelain() {
+        return;
+    }
+    let ai = output::inspect(|r| r.ai).unwrap_or(false);
+    emit(&message_records(subject, body, ai));
+}
+
+/// The `prompt` record for a question; `key` names it for good, whatever the wording.
+pub fn prompt(key: &str, question: &str) {
+    emit(&[format!("prompt {key} {}", one_line(question.trim()))]);
+}
+
+/// The records of what the run did, ending with `exit`.
+pub fn finish(report: &Report, error: Option<&anyhow::Error>, exit_code: u8) {
+    emit(&end_records(report, error, exit_code));
+}
+
+fn emit(records: &[String]) {
+    if !output::porcelain() {
+        return;
+    }
+    let mut stdout = std::io::stdout().lock();
+    for record in records {
+        writeln!(stdout, "{record}").ok();
+    }
+    stdout.flush().ok();
+}
+
+fn file_records(files: &[FileRecord]) -> Vec<String> {
+    let mut records: Vec<String> = files
+        .iter()
+        .map(|f| {
+            let mut record = format!(
+                "file {} {} {} {} {}",
+                f.status,
+                f.category,
+                f.added,
+                f.deleted,
+                quote(&f.path)
+            );
+            if let Some(from) = &f.from {
+                record.push(' ');
+                record.push_str(&quote(from));
+            }
+            record
+        })
+        .collect();
+    let (added, deleted) = files
+        .iter()
+        .fold((0, 0), |(a, d), f| (a + f.added, d + f.deleted));
+    records.push(format!("stats {} {added} {deleted}", files.len()));
+    records
+}
+
+fn message_records(subject: &str, body: &str, ai: bool) -> Vec<String> {
+    let mut records = vec![format!("subject {}", one_line(subject))];
+    records.extend(body.trim_end().lines().map(|line| format!("body {line}")));
+    records.push(format!("ai {ai}"));
+    records
+}
+
+fn end_records(report: &Report, error: Option<&anyhow::Error>, exit_code: u8) -> Vec<String> {
+    let mut records: Vec<String> = report
+        .commits
+        .iter()
+        .map(|c| format!("commit {} {}", c.sha, one_line(&c.subject)))
+        .collect();
+    if let Some(push) = &report.push
+        && let (true, Some(remote), Some(dest)) = (push.pushed, &push.remote, &push.dest)
+    {
+        records.push(format!("push {remote} {dest}"));
+    }
+    if let Some(tag) = &report.tag {
+        records.push(format!("tag {tag}"));
+    }
+    if let Some(url) = &report.pull_request {
+        records.push(format!("pr {url}"));
+    }
+    if let Some(error) = error {
+        let failure = error.downcast_ref::<ScommitError>();
+        match failure {
+            Some(ScommitError::RebaseConflict { files }) => {
+                records.extend(files.iter().map(|path| format!("conflict {}", quote(path))));
+            }
+            Some(ScommitError::Refused { check }) => {
+                if let Ok(serde_json::Value::String(check)) = serde_json::to_value(check) {
+                    records.push(format!("refused {check}"));
+                }
+            }
+            _ => {}
+        }
+        let kind = failure
+            .and_then(|f| serde_json::to_value(f).ok())
+            .and_then(|v| v["kind"].as_str().map(str::to_string))
+            .unwrap_or_else(|| "other".to_string());
+        records.push(format!("error {kind} {}", one_line(&format!("{error:#}"))));
+    }
+    records.push(format!("exit {exit_code}"));
+    records
+}
+
+/// The first line of `text`, so free text can't start a record of its own.
+fn one_line(text: &str) -> &str {
+    text.lines().next().unwrap_or_default()
+}
+
+/// `path` as is, or in double quotes with C escapes when it would be ambiguous.
+fn quote(path: &str) -> String {
+    if path.contains(char::is_whitespace) {
+        diff_parse::c_quote(path)
+    } else {
+        diff_parse::quote(path).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::SafetyCheck;
+    use crate::output::CommitRecord;
+
+    #[test]
+    fn records_are_stable_lines() {
+        let files = [
+            FileRecord {
+                path: "src/new name.rs".to_string(),
+                status: "renamed",
+                from: Some("src/old.rs".to_string()),
+                added: 3,
+                deleted: 1,
+                category: "code",
+            },
+            FileRecord {
+                path: "docs/\"q\"\t.md".to_string(),
+                status: "added",
+                from: None,
+                added: 10,
+                deleted: 0,
+                category: "docs",
+            },
+        ];
+        assert_eq!(
+            file_records(&files),
+            [
+                "file renamed code 3 1 \"src/new name.rs\" src/old.rs",
+                "file added docs 10 0 \"docs/\\\"q\\\"\\t.md\"",
+                "stats 2 13 1",
+            ]
+        );
+        assert_eq!(quote("a\u{1}b"), "\"a\\001b\"");
+        assert_eq!(quote("ünï.rs"), "ünï.rs");
+
+        assert_eq!(
+            message_records(
+                "feat: add parser",
+                "- add src/parse.rs\n\nCloses #4\n",
+                true
+            ),
+            [
+                "subject feat: add parser",
+                "body - add src/parse.rs",
+                "body ",
+                "body Closes #4",
+                "ai true",
+            ]
+        );
+
+        let report = Report {
+            commits: vec![CommitRecord {
+                sha: "9e6618c0".to_string(),
+                subject: "fix: x".to_string(),
+            }],
+            ..Report::default()
+        };
+        let error = anyhow::anyhow!("branch main is protected").context(ScommitError::Refused {
+            check: SafetyCheck::ProtectedBranch,
+        });
+        assert_eq!(
+            end_records(&report, Some(&error), 8),
+            [
+                "commit 9e6618c0 fix: x",
+                "refused protected_branch",
+                "error refused refused by a safety check: branch main is protected",
+                "exit 8",
+            ]
+        );
+        assert_eq!(end_records(&Report::default(), None, 0), ["exit 0"]);
+    }
+}
@@ -0,0 +1,127 @@
+//! `[message] post_process`: a team's own command gets the last word on every generated
+//! message. It runs through `sh` at the repo root, reads a JSON object on stdin (the
+//! subject, the body, the whole message, the branch and the staged files) and prints the
+//! message to use on stdout, subject first as `git commit -F` takes it. Printing nothing
+//! keeps the message; exiting non-zero stops the run with the command's stderr.
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::io::Write;
+use std::process::Stdio;
+use std::thread;
+
+use crate::output::{self, FileRecord};
+use crate::session;
+use crate::{FileChange, commit_message_text, current_branch};
+
+#[derive(Debug, Serialize)]
+struct Input<'a> {
+    subject: &'a str,
+    body: &'a str,
+    /// Subject and body as one `git commit -F` message.
+    message: String,
+    /// Written by the AI provider rather than the heuristic.
+    ai: bool,
+    branch: Option<String>,
+    files: Vec<FileRecord>,
+}
+
+/// The message after `command` has had its say.
+pub fn apply(
+    command: &str,
+    subject: String,
+    body: String,
+    changes: &[FileChange],
+) -> Result<(String, String)> {
+    let input = serde_json::to_string(&Input {
+        subject: &subject,
+        body: &body,
+        message: commit_message_text(&subject, &body),
+        ai: output::inspect(|r| r.ai).unwrap_or(false),
+        branch: current_branch().ok().flatten(),
+        files: changes.iter().map(output::file_record).collect(),
+    })?;
+    let label = format!("the post-processor `{command}`");
+    let mut child = session::command("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("running {label}"))?;
+    // Written from a thread so a command that prints before it has read everything can't
+    // deadlock on full pipes; one that never reads stdin at all is fine too.
+    let mut stdin = child.stdin.take().context("opening stdin")?;
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()).ok());
+    let out = child
+        .wait_with_output()
+        .with_context(|| format!("running {label}"))?;
+    writer.join().ok();
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        bail!(
+            "{label} failed ({}){}",
+            out.status,
+            if stderr.trim().is_empty() {
+                String::new()
+            } else {
+                format!(":\n{}", stderr.trim_end())
+            }
+        );
+    }
+    let printed = String::from_utf8(out.stdout)
+        .with_context(|| format!("{label} printed a message that isn't UTF-8"))?;
+    Ok(parse(&printed).unwrap_or((subject, body)))
+}
+
+/// Subject and body from a printed message; None when nothing was printed. Lines
+/// starting with `#` are kept: unlike git's editor, the command means what it prints.
+fn parse(printed: &str) -> Option<(String, String)> {
+    let text = printed.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let (subject, body) = text.split_once('\n').unwrap_or((text, ""));
+    let body = body.trim();
+    let body = if body.is_empty() {
+        String::new()
+    } else {
+        format!("{body}\n")
+    };
+    Some((subject.trim().to_string(), body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn printed_messages_replace_the_generated_one() {
+        assert_eq!(
+            parse("feat: add parser\n\n- add src/parse.rs\n#123 too\n"),
+            Some((
+                "feat: add parser".to_string(),
+                "- add src/parse.rs\n#123 too\n".to_string()
+            ))
+        );
+        assert_eq!(
+            parse("fix: x\n"),
+            Some(("fix: x".to_string(), String::new()))
+        );
+        assert_eq!(parse(" \n"), None);
+
+        let (subject, body) = apply(
+            "tr a-z A-Z | grep -o '\"SUBJECT\":\"[^\"]*\"' | cut -d'\"' -f4",
+            "fix: shout".to_string(),
+            "- body\n".to_string(),
+            &[],
+        )
+        .unwrap();
+        assert_eq!((subject.as_str(), body.as_str()), ("FIX: SHOUT", ""));
+        let kept = apply("cat >/dev/null", "a".to_string(), "b\n".to_string(), &[]).unwrap();
+        assert_eq!(kept, ("a".to_string(), "b\n".to_string()));
+        let err = apply("echo nope >&2; exit 3", "a".to_string(), String::new(), &[]);
+        assert!(format!("{:#}", err.unwrap_err()).ends_with(":\nnope"));
+    }
+}
@@ -0,0 +1,576 @@
+//! `scommit pr` / `scommit mr` (and `--pr`): open a pull request (GitHub) or merge
+//! request (GitLab) for the pushed branch, titled and described from every commit on it
+//! that the base doesn't have, with a checklist of the areas it touches. GitHub goes
+//! through `gh` when it's installed, otherwise the REST API; GitLab through its API, or
+//! push options when there is no token.
+
+use anyhow::{Context, Result, anyhow, bail};
+use std::collections::BTreeMap;
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::output::say;
+use crate::{
+    CATEGORY_NAMES, Category, Cli, ai_model, categorize, chat_completion, common_dir,
+    current_branch, default_remote, diff_parse, git_output, output, push_branch, push_target,
+    sanitize_json_blob, upstream_branch,
+};
+
+/// The order areas are listed in the checklist.
+const AREA_ORDER: &[Category] = &[
+    Category::Code,
+    Category::Tests,
+    Category::Docs,
+    Category::Config,
+    Category::Migrations,
+    Category::Build,
+    Category::Ci,
+    Category::Assets,
+    Category::Other,
+];
+
+const SYSTEM_PROMPT: &str = "You write pull request titles and descriptions from the commits on a branch. Reply with a JSON object: {\"title\": string, \"description\": string}. The title is one line under 72 characters saying what the branch does as a whole, in the imperative mood, without a trailing period; use a conventional-commit prefix only if the commits do. The description is markdown: a short paragraph on what changes and why, then a few bullets for the notable changes. Don't list every commit, don't invent motivation the commits don't give, and don't add a checklist or testing section.";
+
+/// Where the remote is hosted, from its URL.
+#[derive(Debug, PartialEq, Eq)]
+enum Forge {
+    GitHub {
+        owner: String,
+        repo: String,
+    },
+    /// `project` is the full namespace path, e.g. `group/subgroup/app`.
+    GitLab {
+        host: String,
+        project: String,
+    },
+    Unknown,
+}
+
+impl Forge {
+    fn detect(url: &str) -> Forge {
+        let Some((host, path)) = host_and_path(url) else {
+            return Forge::Unknown;
+        };
+        if host == "github.com" || host == "www.github.com" {
+            return match path.split_once('/') {
+                Some((owner, repo)) if !repo.contains('/') => Forge::GitHub {
+                    owner: owner.to_string(),
+                    repo: repo.to_string(),
+                },
+                _ => Forge::Unknown,
+            };
+        }
+        let self_hosted = ["CI_SERVER_HOST", "GITLAB_HOST"].iter().any(|var| {
+            env::var(var).is_ok_and(|value| {
+                let value = value.split_once("://").map_or(value.as_str(), |(_, h)| h);
+                value.trim_end_matches('/') == host
+            })
+        });
+        if host == "gitlab.com" || host.starts_with("gitlab.") || self_hosted {
+            return Forge::GitLab {
+                host,
+                project: path,
+            };
+        }
+        Forge::Unknown
+    }
+
+    fn of_remote(remote: &str) -> Result<Forge> {
+        let url = git_output(&["remote", "get-url", remote])?;
+        Ok(Forge::detect(url.trim()))
+    }
+
+    fn noun(&self) -> &'static str {
+        match self {
+            Forge::GitLab { .. } => "merge request",
+            _ => "pull request",
+        }
+    }
+}
+
+/// What to open: the branch, where it merges, and the generated title and description.
+struct Proposal {
+    head: String,
+    base: String,
+    title: String,
+    body: String,
+}
+
+#[derive(Debug)]
+struct BranchCommit {
+    hash: String,
+    subject: String,
+    body: String,
+}
+
+/// `scommit pr`: push the branch if it has unpushed commits, then open the request.
+pub fn run(cli: &mut Cli, base: Option<&str>, draft: bool) -> Result<()> {
+    let remote = match push_target(cli)? {
+        Some((remote, _)) => Some(remote),
+        None => upstream_branch()?
+            .and_then(|up| up.split_once('/').map(|(remote, _)| remote.to_string()))
+            .or(default_remote()?),
+    };
+    let Some(remote) = remote else {
+        bail!("no remote to open it on; add one with `git remote add`");
+    };
+    if !cli.dry_run
+        && matches!(Forge::of_remote(&remote)?, Forge::GitLab { .. })
+        && gitlab_token().is_none()
+    {
+        return open_with_push_options(cli, &remote, base, draft);
+    }
+    if !cli.dry_run && push_branch(cli)?.is_none() {
+        bail!("the branch wasn't pushed, so there is nothing to open a request for");
+    }
+    open(cli, &remote, base, draft)
+}
+
+/// Open a pull or merge request for the current branch, which is already on `remote`.
+pub fn open(cli: &Cli, remote: &str, base: Option<&str>, draft: bool) -> Result<()> {
+    let forge = Forge::of_remote(remote)?;
+    let proposal = propose(cli, remote, base)?;
+    let Proposal {
+        head,
+        base,
+        title,
+        body,
+    } = &proposal;
+    if cli.dry_run {
+        say!("Would open a {} {head} → {base}:", forge.noun());
+        println!(
+            "{} {}\n\n{body}",
+            output::dim("Title:"),
+            output::bold(title)
+        );
+        return Ok(());
+    }
+    say!("Opening a {} {head} → {base}...", forge.noun());
+    let url = match &forge {
+        Forge::GitLab { host, project } => {
+            let token = gitlab_token().ok_or_else(|| {
+                anyhow!("opening a merge request needs GITLAB_TOKEN (or `scommit mr` before the branch is pushed, which uses push options)")
+            })?;
+            create_on_gitlab(host, project, &token, &proposal, draft)?
+        }
+        _ if gh_available() => create_with_gh(&proposal, draft)?,
+        Forge::GitHub { owner, repo } => create_with_api(owner, repo, &proposal, draft)?,
+        Forge::Unknown => bail!(
+            "{remote} isn't on GitHub or GitLab as far as scommit can tell; install gh for GitHub Enterprise, or set GITLAB_HOST"
+        ),
+    };
+    output::record(|r| r.pull_request = Some(url.clone()));
+    say!("Opened {url}");
+    Ok(())
+}
+
+/// The title and description for the current branch against `base` on `remote`.
+fn propose(cli: &Cli, remote: &str, base: Option<&str>) -> Result<Proposal> {
+    let head = current_branch()?.ok_or_else(|| anyhow!("HEAD is detached; check out a branch"))?;
+    let base = match base {
+        Some(base) => base.to_string(),
+        None => default_base(remote)?,
+    };
+    if head == base {
+        bail!("{head} is the base branch; open the request from a feature branch");
+    }
+    let range = format!("{remote}/{base}..HEAD");
+    let commits = branch_commits(&range)?;
+    if commits.is_empty() {
+        bail!("no commits on {head} that {remote}/{base} doesn't have");
+    }
+    let files = numstat(&format!("{remote}/{base}...HEAD"))?;
+
+    let (title, description) = match describe_with_ai(cli, &commits, &files) {
+        Ok(Some(described)) => described,
+        Ok(None) => describe(&head, &commits),
+        Err(e) => {
+            eprintln!("AI description failed ({e}); using the commit list.");
+            describe(&head, &commits)
+        }
+    };
+    let body = format!(
+        "{}\n\n## Touched areas\n\n{}",
+        description.trim_end(),
+        checklist(&files)
+    );
+    Ok(Proposal {
+        head,
+        base,
+        title,
+        body,
+    })
+}
+
+/// Without a token, GitLab still opens a merge request from options on the push that
+/// updates the branch. Push options can't span lines, so the description stays empty.
+fn open_with_push_options(
+    cli: &mut Cli,
+    remote: &str,
+    base: Option<&str>,
+    draft: bool,
+) -> Result<()> {
+    let proposal = propose(cli, remote, base)?;
+    let pushed = git_output(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("refs/remotes/{remote}/{}", proposal.head),
+    ]);
+    if pushed.is_ok_and(|tip| git_output(&["rev-parse", "HEAD"]).is_ok_and(|head| head == tip)) {
+        bail!(
+            "{} is already pushed, and GitLab only reads merge request options on a push that updates it; set GITLAB_TOKEN to open one through the API",
+            proposal.head
+        );
+    }
+    let title = if draft {
+        format!("Draft: {}", proposal.title)
+    } else {
+        proposal.title.clone()
+    };
+    cli.push_options.extend([
+        "merge_request.create".to_string(),
+        format!("merge_request.target={}", proposal.base),
+        format!("merge_request.title={title}"),
+    ]);
+    if push_branch(cli)?.is_none() {
+        bail!("the branch wasn't pushed, so no merge request was opened");
+    }
+    say!(
+        "GitLab opens the merge request from the push options; its link is in the push output above."
+    );
+    Ok(())
+}
+
+fn gitlab_token() -> Option<String> {
+    env::var("GITLAB_TOKEN").ok().filter(|t| !t.is_empty())
+}
+
+/// The remote's default branch (its HEAD), else `main` or `master`, whichever it has.
+fn default_base(remote: &str) -> Result<String> {
+    let symbolic = format!("refs/remotes/{remote}/HEAD");
+    if let Ok(out) = git_output(&["symbolic-ref", "--short", &symbolic])
+        && let Some((_, branch)) = out.trim().split_once('/')
+    {
+        return Ok(branch.to_string());
+    }
+    for candidate in ["main", "master"] {
+        let name = format!("refs/remotes/{remote}/{candidate}");
+        if git_output(&["rev-parse", "--verify", "--quiet", &name]).is_ok() {
+            return Ok(candidate.to_string());
+        }
+    }
+    bail!("can't tell the base branch of {remote}; pass --base")
+}
+
+fn branch_commits(range: &str) -> Result<Vec<BranchCommit>> {
+    let out = git_output(&[
+        "log",
+        "--no-merges",
+        "--reverse",
+        "--format=%h%x00%s%x00%b%x1e",
+        range,
+    ])
+    .with_context(|| format!("reading the log for {range}"))?;
+    Ok(out
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(3, '\0');
+            Some(BranchCommit {
+                hash: fields.next()?.to_string(),
+                subject: fields.next()?.trim().to_string(),
+                body: fields.next()?.trim().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// (path, added, deleted) per file `git diff` reports for `range`; binary files count 0.
+pub fn numstat(range: &str) -> Result<Vec<(String, u32, u32)>> {
+    let out = git_output(&["diff", "--numstat", "-z", "--no-renames", range])?;
+    Ok(diff_parse::parse_numstat(&out))
+}
+
+/// "- [ ] Code: 3 files in src/ (+120/-40)", one line per category.
+fn checklist(files: &[(String, u32, u32)]) -> String {
+    let mut areas: BTreeMap<usize, Vec<&(String, u32, u32)>> = BTreeMap::new();
+    for file in files {
+        let category = categorize(&file.0);
+        let rank = AREA_ORDER.iter().position(|c| *c == category).unwrap_or(0);
+        areas.entry(rank).or_default().push(file);
+    }
+    let mut lines = Vec::new();
+    for (rank, files) in areas {
+        let name = CATEGORY_NAMES
+            .get(&AREA_ORDER[rank])
+            .copied()
+            .unwrap_or("other");
+        let mut name = name.to_string();
+        name[..1].make_ascii_uppercase();
+        let (added, deleted) = files.iter().fold((0, 0), |(a, d), (_, added, deleted)| {
+            (a + added, d + deleted)
+        });
+        let what = match files.as_slice() {
+            [one] => one.0.clone(),
+            _ => match common_dir(files.iter().map(|f| f.0.as_str())) {
+                Some(dir) => format!("{} files in {dir}/", files.len()),
+                None => format!("{} files", files.len()),
+            },
+        };
+        lines.push(format!("- [ ] {name}: {what} (+{added}/-{deleted})"));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Without the AI: the only commit's subject, or the branch name; the commits as bullets.
+fn describe(head: &str, commits: &[BranchCommit]) -> (String, String) {
+    let title = match commits {
+        [only] => only.subject.clone(),
+        _ => {
+            let name = head.rsplit('/').next().unwrap_or(head);
+            let mut title = name.replace(['-', '_'], " ");
+            if let Some(first) = title.get_mut(..1) {
+                first.make_ascii_uppercase();
+            }
+            title
+        }
+    };
+    let description = match commits {
+        [only] if !only.body.is_empty() => only.body.clone(),
+        _ => {
+            let lines: Vec<String> = commits
+                .iter()
+                .map(|c| format!("- {} ({})", c.subject, c.hash))
+                .collect();
+            format!("## Commits\n\n{}", lines.join("\n"))
+        }
+    };
+    (title, description)
+}
+
+fn describe_with_ai(
+    cli: &Cli,
+    commits: &[BranchCommit],
+    files: &[(String, u32, u32)],
+) -> Result<Option<(String, String)>> {
+    let Some(model) = ai_model(cli) else {
+        return Ok(None);
+    };
+    let key = env::var("OPENAI_API_KEY")?;
+    let mut prompt = String::from("Commits, oldest first:\n");
+    for commit in commits {
+        prompt.push_str(&format!("\n- {}\n", commit.subject));
+        let body: String = commit.body.chars().take(600).collect();
+        for line in body.lines().filter(|l| !l.trim().is_empty()) {
+            prompt.push_str(&format!("  {line}\n"));
+        }
+    }
+    prompt.push_str("\nFiles changed (+added/-deleted):\n");
+    for (path, added, deleted) in files.iter().take(80) {
+        prompt.push_str(&format!("- {path} (+{added}/-{deleted})\n"));
+    }
+    if files.len() > 80 {
+        prompt.push_str(&format!("- ... and {} more\n", files.len() - 80));
+    }
+
+    let Some(reply) = chat_completion(&key, &model, SYSTEM_PROMPT, &prompt, true, 700)? else {
+        return Ok(None);
+    };
+    let blob = sanitize_json_blob(&reply).ok_or_else(|| anyhow!("AI reply wasn't JSON"))?;
+    let value: serde_json::Value = serde_json::from_str(&blob).context("decoding AI json")?;
+    let field = |name: &str| {
+        value
+            .get(name)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+    let title = field("title").map(|t| t.lines().next().unwrap_or_default().to_string());
+    Ok(title.zip(field("description")))
+}
+
+fn gh_available() -> bool {
+    Command::new("gh")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// `gh pr create`, which prints the new PR's URL.
+fn create_with_gh(proposal: &Proposal, draft: bool) -> Result<String> {
+    let mut args = vec![
+        "pr",
+        "create",
+        "--title",
+        &proposal.title,
+        "--body-file",
+        "-",
+        "--base",
+        &proposal.base,
+        "--head",
+        &proposal.head,
+    ];
+    if draft {
+        args.push("--draft");
+    }
+    let mut child = Command::new("gh")
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .context("running gh pr create")?;
+    child
+        .stdin
+        .take()
+        .context("gh stdin")?
+        .write_all(proposal.body.as_bytes())?;
+    let out = child.wait_with_output()?;
+    if !out.status.success() {
+        bail!("gh pr create failed");
+    }
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    Ok(stdout
+        .lines()
+        .rfind(|l| l.starts_with("http"))
+        .unwrap_or(stdout.trim())
+        .to_string())
+}
+
+/// POST /repos/{owner}/{repo}/pulls with GITHUB_TOKEN (or GH_TOKEN).
+fn create_with_api(owner: &str, repo: &str, proposal: &Proposal, draft: bool) -> Result<String> {
+    let token = env::var("GITHUB_TOKEN")
+        .or_else(|_| env::var("GH_TOKEN"))
+        .map_err(|_| anyhow!("opening a PR needs the gh CLI or GITHUB_TOKEN"))?;
+    let res = http_client()?
+        .post(format!("https://api.github.com/repos/{owner}/{repo}/pulls"))
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "scommit")
+        .json(&serde_json::json!({
+            "title": proposal.title,
+            "body": proposal.body,
+            "base": proposal.base,
+            "head": proposal.head,
+            "draft": draft,
+        }))
+        .send()
+        .context("calling the GitHub API")?;
+    let status = res.status();
+    let reply: serde_json::Value = res.json().unwrap_or_default();
+    if !status.is_success() {
+        let detail = reply["errors"][0]["message"]
+            .as_str()
+            .or(reply["message"].as_str())
+            .unwrap_or("");
+        bail!("GitHub API error: {status} {detail}");
+    }
+    reply["html_url"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("GitHub API reply had no html_url"))
+}
+
+/// POST /projects/:id/merge_requests with GITLAB_TOKEN; drafts are marked by title.
+fn create_on_gitlab(
+    host: &str,
+    project: &str,
+    token: &str,
+    proposal: &Proposal,
+    draft: bool,
+) -> Result<String> {
+    let title = if draft {
+        format!("Draft: {}", proposal.title)
+    } else {
+        proposal.title.clone()
+    };
+    let project = project.replace('/', "%2F");
+    let res = http_client()?
+        .post(format!(
+            "https://{host}/api/v4/projects/{project}/merge_requests"
+        ))
+        .header("PRIVATE-TOKEN", token)
+        .json(&serde_json::json!({
+            "source_branch": proposal.head,
+            "target_branch": proposal.base,
+            "title": title,
+            "description": proposal.body,
+        }))
+        .send()
+        .context("calling the GitLab API")?;
+    let status = res.status();
+    let reply: serde_json::Value = res.json().unwrap_or_default();
+    if !status.is_success() {
+        let detail = match &reply["message"] {
+            serde_json::Value::String(message) => message.clone(),
+            serde_json::Value::Null => String::new(),
+            other => other.to_string(),
+        };
+        bail!("GitLab API error: {status} {detail}");
+    }
+    reply["web_url"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("GitLab API reply had no web_url"))
+}
+
+fn http_client() -> Result<reqwest::blocking::Client> {
+    reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(20))
+        .build()
+        .context("building http client")
+}
+
+/// (host, path) from an https, ssh or scp-style remote URL, without port or `.git`.
+fn host_and_path(url: &str) -> Option<(String, String)> {
+    let (host, path) = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?,
+        None => url.split_once(':')?,
+    };
+    let host = host.rsplit('@').next()?.split(':').next()?;
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    (!host.is_empty() && path.contains('/')).then(|| (host.to_string(), path.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forges_from_remote_urls_and_touched_areas() {
+        let github = Forge::GitHub {
+            owner: "winslowb".to_string(),
+            repo: "scommit".to_string(),
+        };
+        assert_eq!(Forge::detect("git@github.com:winslowb/scommit.git"), github);
+        assert_eq!(Forge::detect("https://github.com/winslowb/scommit"), github);
+        assert_eq!(
+            Forge::detect("ssh://git@github.com/winslowb/scommit.git"),
+            github
+        );
+        assert_eq!(
+            Forge::detect("ssh://git@gitlab.example.com:2222/tools/cli/scommit.git"),
+            Forge::GitLab {
+                host: "gitlab.example.com".to_string(),
+                project: "tools/cli/scommit".to_string(),
+            }
+        );
+        assert_eq!(Forge::detect("/srv/git/scommit.git"), Forge::Unknown);
+
+        let files = vec![
+            ("crates/cli/src/pr.rs".to_string(), 200, 0),
+            ("crates/cli/src/main.rs".to_string(), 30, 4),
+            ("README.md".to_string(), 12, 1),
+        ];
+        assert_eq!(
+            checklist(&files),
+            "- [ ] Code: 2 files in crates/cli/ (+230/-4)\n- [ ] Docs: README.md (+12/-1)\n"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+//! Git queries a run needs after the message is settled, started on a background thread
+//! so they overlap AI generation instead of following it. On a network filesystem each
+//! git call can take a noticeable fraction of a second.
+
+use anyhow::{Result, anyhow};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+use crate::{ahead_behind, session, upstream_branch};
+
+/// The checked-out branch's upstream and how far HEAD is ahead of and behind it, as they
+/// were before the commit.
+pub struct Upstream {
+    pub name: Option<String>,
+    pub ahead_behind: Option<(u32, u32)>,
+}
+
+static UPSTREAM: Mutex<Option<JoinHandle<Result<Upstream>>>> = Mutex::new(None);
+
+/// Look up the upstream in the background. Only for runs that push from the branch that
+/// is checked out now: `--branch` switches to one without an upstream.
+pub fn upstream() {
+    let session = session::current();
+    let handle = thread::spawn(move || {
+        session::run(session, || {
+            let name = upstream_branch()?;
+            let ahead_behind = name.as_deref().map(ahead_behind).transpose()?;
+            Ok(Upstream { name, ahead_behind })
+        })
+    });
+    if let Ok(mut slot) = UPSTREAM.lock() {
+        *slot = Some(handle);
+    }
+}
+
+/// The prefetched upstream, once; None if it wasn't started.
+pub fn take_upstream() -> Option<Result<Upstream>> {
+    let handle = UPSTREAM.lock().ok()?.take()?;
+    Some(
+        handle
+            .join()
+            .unwrap_or_else(|_| Err(anyhow!("the upstream lookup panicked"))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_upstream_is_taken_once() {
+        assert!(take_upstream().is_none());
+        upstream();
+        let first = take_upstream().expect("started");
+        if let Ok(upstream) = first {
+            assert_eq!(upstream.name.is_some(), upstream.ahead_behind.is_some());
+        }
+        assert!(take_upstream().is_none());
+    }
+}
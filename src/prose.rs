@@ -0,0 +1,153 @@
+//! Word-level views of prose (docs-category) changes: rewrapping a paragraph rewrites
+//! every line of it, so line counts and line diffs overstate what was said differently.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+
+use crate::diff_parse::{self, Section};
+use crate::{Category, FileChange, FileStatus, config, diff_sections, git_stream};
+
+/// Docs files that get word counts and word diffs. The body and the prompt only list the
+/// first few dozen files, so a huge change doesn't need them all.
+const MAX_FILES: usize = 64;
+
+/// Pathspecs for the staged docs files, old names included so renames are still paired.
+fn pathspecs(changes: &[FileChange]) -> Vec<&str> {
+    let mut paths = Vec::new();
+    let docs = changes.iter().filter(|c| c.category == Category::Docs);
+    for change in docs.take(MAX_FILES) {
+        if let FileStatus::Renamed { from, .. } | FileStatus::Copied { from, .. } = &change.status {
+            paths.push(from.as_str());
+        }
+        paths.push(change.path.as_str());
+    }
+    paths
+}
+
+fn word_diff_args<'a>(
+    paths: &[&'a str],
+    mode: &'a str,
+    unified: &'a str,
+    diff_args: &'a [String],
+) -> Vec<&'a str> {
+    let mut args = vec!["diff", "--cached", "--no-color", mode, unified];
+    args.extend(diff_args.iter().map(String::as_str));
+    args.push("--");
+    args.extend(paths);
+    args
+}
+
+/// Words added and removed per staged docs file.
+pub fn word_stats(changes: &[FileChange]) -> HashMap<String, (u32, u32)> {
+    let paths = pathspecs(changes);
+    if paths.is_empty() {
+        return HashMap::new();
+    }
+    let diff_args = config::get().diff.args();
+    let args = word_diff_args(&paths, "--word-diff=porcelain", "--unified=0", &diff_args);
+    let mut counts = WordCounts::default();
+    let streamed = git_stream(&args, b'\n', |line| {
+        counts.push(line);
+        ControlFlow::Continue(())
+    });
+    match streamed {
+        Ok(()) => counts.stats,
+        Err(_) => HashMap::new(),
+    }
+}
+
+/// "+3/-1", or for prose "+40/-38 lines, +6/-2 words".
+pub fn counts(change: &FileChange, words: &HashMap<String, (u32, u32)>) -> String {
+    match words.get(&change.path) {
+        Some((added, deleted)) => format!(
+            "+{}/-{} lines, +{added}/-{deleted} words",
+            change.added, change.deleted
+        ),
+        None => format!("+{}/-{}", change.added, change.deleted),
+    }
+}
+
+/// `git diff --word-diff=plain` for the staged docs files (`[-old-]{+new+}` inline), to
+/// stand in for their line diffs in the AI prompt; one section per file, as `each` asks
+/// for them.
+pub fn word_diff(
+    changes: &[FileChange],
+    cap: usize,
+    each: impl FnMut(Section) -> ControlFlow<()>,
+) -> Result<()> {
+    let paths = pathspecs(changes);
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let diff_args = config::get().diff.args();
+    let args = word_diff_args(&paths, "--word-diff=plain", "--unified=1", &diff_args);
+    diff_sections(&args, cap, each)
+}
+
+/// Porcelain word diffs put each changed run of words on its own `+` or `-` line, after
+/// the usual file headers; counted a line at a time.
+#[derive(Default)]
+struct WordCounts {
+    stats: HashMap<String, (u32, u32)>,
+    path: Option<String>,
+    in_hunk: bool,
+}
+
+impl WordCounts {
+    fn push(&mut self, line: &str) {
+        if line.starts_with("diff --git ") {
+            self.in_hunk = false;
+            self.path = None;
+        } else if line.starts_with("@@") {
+            self.in_hunk = true;
+        } else if !self.in_hunk {
+            if let Some(new) = line.strip_prefix("+++ ").and_then(diff_parse::patch_path) {
+                self.path = Some(new);
+            } else if let Some(old) = line.strip_prefix("--- ").and_then(diff_parse::patch_path) {
+                self.path.get_or_insert(old);
+            }
+        } else if let Some(path) = &self.path {
+            let words = line.get(1..).unwrap_or("").split_whitespace().count() as u32;
+            let entry = self.stats.entry(path.clone()).or_default();
+            match line.as_bytes().first() {
+                Some(b'+') => entry.0 += words,
+                Some(b'-') => entry.1 += words,
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn porcelain_word_diffs_count_words_per_file() {
+        let out = "diff --git a/README.md b/README.md\n\
+                   index 1..2 100644\n\
+                   --- a/README.md\n\
+                   +++ b/README.md\n\
+                   @@ -1,2 +1,2 @@\n \
+                   The quick\n\
+                   -brown\n\
+                   +red\n \
+                   fox jumps\n\
+                   ~\n\
+                   +extra words\n\
+                   ~\n\
+                   diff --git a/old.md b/old.md\n\
+                   deleted file mode 100644\n\
+                   --- a/old.md\n\
+                   +++ /dev/null\n\
+                   @@ -1 +0,0 @@\n\
+                   -gone for good\n\
+                   ~\n";
+        let mut counts = WordCounts::default();
+        out.lines().for_each(|line| counts.push(line));
+        let stats = counts.stats;
+        assert_eq!(stats["README.md"], (3, 1));
+        assert_eq!(stats["old.md"], (0, 3));
+    }
+}
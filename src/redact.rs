@@ -0,0 +1,71 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use crate::config::RedactConfig;
+use crate::diff_parse::Section;
+use crate::glob_match;
+
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Drop the diff of a file matching `redact.paths`, keeping its header so the model
+/// still knows the file changed.
+pub fn withhold(section: &mut Section, config: &RedactConfig) {
+    if config
+        .paths
+        .iter()
+        .any(|glob| glob_match(glob, &section.path))
+    {
+        section.text = format!("{}\n[hunks withheld by redaction rule]\n", section.header);
+        section.truncated = false;
+    }
+}
+
+/// Replace every match of `redact.patterns` in `text`.
+pub fn apply_patterns(text: &str, config: &RedactConfig) -> Result<String> {
+    let mut text = text.to_string();
+    for pattern in &config.patterns {
+        let re =
+            Regex::new(pattern).with_context(|| format!("invalid redact pattern {pattern:?}"))?;
+        text = re.replace_all(&text, REDACTED).into_owned();
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff_parse;
+
+    #[test]
+    fn redaction_withholds_paths_and_masks_patterns() {
+        let config = RedactConfig {
+            patterns: vec![r"password\s*=\s*\S+".to_string()],
+            paths: vec!["secrets/**".to_string()],
+        };
+        let diff = "\
+diff --git a/secrets/prod.yml b/secrets/prod.yml
++token: abc
+diff --git a/app.cfg b/app.cfg
++password = hunter2
++user = ada
+";
+        let withheld: String = diff_parse::sections(diff, usize::MAX)
+            .into_iter()
+            .map(|mut section| {
+                withhold(&mut section, &config);
+                section.text
+            })
+            .collect();
+        let redacted = apply_patterns(&withheld, &config).unwrap();
+        assert_eq!(
+            redacted,
+            "\
+diff --git a/secrets/prod.yml b/secrets/prod.yml
+[hunks withheld by redaction rule]
+diff --git a/app.cfg b/app.cfg
++[REDACTED]
++user = ada
+"
+        );
+    }
+}
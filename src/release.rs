@@ -0,0 +1,295 @@
+//! `scommit release [<version>]`: the release steps in one go. It suggests the semver
+//! version, bumps it in the root manifest, adds the CHANGELOG section, commits both,
+//! tags the commit and pushes the branch and the tag. The plan is shown first; with
+//! --dry-run that is all that happens.
+
+use anyhow::{Context, Result, bail};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::DocumentMut;
+
+use crate::output::{self, say};
+use crate::{
+    Cli, CommitOptions, changelog, collect_staged_changes, confirm, create_commit, current_branch,
+    deps, exit, git, git_output, push_branch, repo_state, tag,
+};
+
+/// Manifests at the repo root whose version is the release version.
+const MANIFESTS: [&str; 3] = ["Cargo.toml", "package.json", "pyproject.toml"];
+
+const DEFAULT_CHANGELOG: &str = "CHANGELOG.md";
+
+/// A manifest's top-level `"version": "..."`, the first one in the file.
+static JSON_VERSION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#""version"(\s*):(\s*)"[^"]*""#).expect("valid regex"));
+
+/// One file the release edits, with its new content.
+struct Edit {
+    path: PathBuf,
+    what: String,
+    content: String,
+}
+
+pub fn run(
+    cli: &Cli,
+    version: Option<&str>,
+    changelog_file: Option<&Path>,
+    no_changelog: bool,
+    polish: bool,
+) -> Result<()> {
+    if current_branch()?.is_none() {
+        bail!("HEAD is detached; check out the branch to release from");
+    }
+    if let Some(op) = repo_state::in_progress()? {
+        bail!("a {} is in progress; finish or abort it first", op.name());
+    }
+    if !git_output(&["status", "--porcelain", "--untracked-files=no"])?
+        .trim()
+        .is_empty()
+    {
+        bail!("the working tree has uncommitted changes; commit or stash them before releasing");
+    }
+
+    let last = tag::last_tag();
+    if let Some(last) = &last
+        && git_output(&["rev-list", "--count", &format!("{last}..HEAD")])?.trim() == "0"
+    {
+        bail!("nothing to release: HEAD is {last}");
+    }
+    let name = match version {
+        Some(version) => version.to_string(),
+        None => tag::suggest_version(None)?,
+    };
+    tag::validate(&name)?;
+    let number = name.trim_start_matches(|c: char| !c.is_ascii_digit());
+    if number.is_empty() {
+        bail!("{name} has no version number in it; pass one like v1.4.0");
+    }
+    let range = changelog::resolve_range(None);
+
+    let mut edits = Vec::new();
+    for manifest in MANIFESTS {
+        let Ok(text) = fs::read_to_string(manifest) else {
+            continue;
+        };
+        let Some(old) = deps::declared_version(manifest, &text) else {
+            continue;
+        };
+        if old == number {
+            continue;
+        }
+        let content = set_version(manifest, &text, number)
+            .with_context(|| format!("updating the version in {manifest}"))?;
+        edits.push(Edit {
+            path: PathBuf::from(manifest),
+            what: format!("bump {manifest} {old} → {number}"),
+            content,
+        });
+        if manifest == "Cargo.toml"
+            && let Some(lock) = cargo_lock(&text, &old, number)
+        {
+            edits.push(lock);
+        }
+    }
+    let changelog_path = match (no_changelog, changelog_file) {
+        (true, _) => None,
+        (false, Some(path)) => Some(path.to_path_buf()),
+        (false, None) => Some(PathBuf::from(DEFAULT_CHANGELOG)).filter(|path| path.exists()),
+    };
+    let section = match &changelog_path {
+        Some(_) => changelog::section(cli, &range, Some(&name), polish)?,
+        None => None,
+    };
+
+    let subject = format!("chore(release): {name}");
+    say!(
+        "{} {} → {name}",
+        output::bold("Release"),
+        last.as_deref().unwrap_or("(no earlier tag)")
+    );
+    for edit in &edits {
+        say!("  {}", edit.what);
+    }
+    if let (Some(path), Some((_, count))) = (&changelog_path, &section) {
+        say!(
+            "  add the {name} section ({count} commit(s)) to {}",
+            path.display()
+        );
+    }
+    if edits.is_empty() && section.is_none() {
+        say!("  nothing to edit; the tag goes on HEAD");
+    } else {
+        say!("  commit \"{subject}\"");
+    }
+    say!("  tag {name}");
+    if cli.no_push {
+        say!("  no push (--no-push)");
+    } else {
+        say!("  push the branch and the tag");
+    }
+    if cli.dry_run {
+        if let Some((text, _)) = &section {
+            say!("\n{}\n{}", output::yellow("DRY RUN"), text.trim_end());
+        }
+        return Ok(());
+    }
+    if !cli.yes && !confirm("release", "Go ahead?")? {
+        exit::note(exit::Code::Cancelled);
+        return Ok(());
+    }
+
+    let mut paths = Vec::new();
+    for edit in &edits {
+        fs::write(&edit.path, &edit.content)
+            .with_context(|| format!("writing {}", edit.path.display()))?;
+        paths.push(edit.path.to_string_lossy().into_owned());
+    }
+    if let (Some(path), Some((text, _))) = (&changelog_path, &section) {
+        changelog::prepend(path, text)?;
+        paths.push(path.to_string_lossy().into_owned());
+    }
+    if !paths.is_empty() {
+        let mut args = vec!["add", "--"];
+        args.extend(paths.iter().map(String::as_str));
+        git(&args)?;
+        output::record_files(&collect_staged_changes()?);
+        let body: Vec<String> = edits
+            .iter()
+            .map(|edit| format!("- {}", edit.what))
+            .chain(
+                changelog_path
+                    .iter()
+                    .filter(|_| section.is_some())
+                    .map(|path| format!("- add the {name} section to {}", path.display())),
+            )
+            .collect();
+        create_commit(
+            &subject,
+            &format!("{}\n", body.join("\n")),
+            &CommitOptions::from_cli(cli),
+        )?;
+    }
+    let name = tag::create(cli, &name)?;
+    output::record(|r| r.tag = Some(name.clone()));
+    let pushed = push_branch(cli)?;
+    output::record(|r| {
+        r.push = Some(output::PushRecord {
+            pushed: pushed.is_some(),
+            remote: pushed.as_ref().map(|(remote, _)| remote.clone()),
+            dest: pushed.as_ref().map(|(_, dest)| dest.clone()),
+        })
+    });
+    match pushed {
+        Some((remote, _)) => tag::push(&remote, &name)?,
+        None => say!("Tag {name} not pushed; push it with `git push <remote> {name}`."),
+    }
+    Ok(())
+}
+
+/// `text` with its declared version set to `version`, formatting and comments kept.
+fn set_version(path: &str, text: &str, version: &str) -> Result<String> {
+    if path.ends_with(".json") {
+        if !JSON_VERSION.is_match(text) {
+            bail!("no \"version\" field");
+        }
+        return Ok(JSON_VERSION
+            .replace(text, |caps: &regex::Captures| {
+                format!("\"version\"{}:{}\"{version}\"", &caps[1], &caps[2])
+            })
+            .into_owned());
+    }
+    let mut doc: DocumentMut = text.parse()?;
+    let tables: &[&[&str]] = if path == "Cargo.toml" {
+        &[&["package"], &["workspace", "package"]]
+    } else {
+        &[&["project"], &["tool", "poetry"]]
+    };
+    for keys in tables {
+        // Looked up read-only first: a mutable lookup adds the tables it walks through.
+        let found = keys
+            .iter()
+            .try_fold(doc.as_item(), |item, key| item.get(key))
+            .and_then(|table| table.get("version"))
+            .is_some_and(|version| version.is_str());
+        if !found {
+            continue;
+        }
+        let item = keys
+            .iter()
+            .fold(doc.as_item_mut(), |item, key| &mut item[key]);
+        let item = &mut item["version"];
+        let decor = item.as_value().map(|v| v.decor().clone());
+        *item = toml_edit::value(version);
+        if let (Some(decor), Some(value)) = (decor, item.as_value_mut()) {
+            *value.decor_mut() = decor;
+        }
+        return Ok(doc.to_string());
+    }
+    bail!("no version to update")
+}
+
+/// A tracked Cargo.lock's entry for the root package, which would otherwise change on
+/// the next build and leave the tree dirty.
+fn cargo_lock(manifest: &str, old: &str, new: &str) -> Option<Edit> {
+    let doc: toml::Table = manifest.parse().ok()?;
+    let name = doc.get("package")?.get("name")?.as_str()?;
+    git_output(&["ls-files", "--error-unmatch", "Cargo.lock"]).ok()?;
+    let lock = fs::read_to_string("Cargo.lock").ok()?;
+    let content = bump_lock_entry(&lock, name, old, new)?;
+    Some(Edit {
+        path: PathBuf::from("Cargo.lock"),
+        what: format!("bump {name} in Cargo.lock {old} → {new}"),
+        content,
+    })
+}
+
+fn bump_lock_entry(lock: &str, name: &str, old: &str, new: &str) -> Option<String> {
+    let entry = format!("name = \"{name}\"\nversion = \"{old}\"\n");
+    lock.contains(&entry).then(|| {
+        lock.replacen(
+            &entry,
+            &format!("name = \"{name}\"\nversion = \"{new}\"\n"),
+            1,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_set_in_place() {
+        let cargo = "[package]\nname = \"demo\"\nversion = \"1.2.0\" # bumped by release\nedition = \"2024\"\n\n[dependencies]\nserde = { version = \"1.0\" }\n";
+        assert_eq!(
+            set_version("Cargo.toml", cargo, "1.3.0").unwrap(),
+            cargo.replace("\"1.2.0\"", "\"1.3.0\"")
+        );
+        let workspace = "[workspace.package]\nversion = \"0.4.1\"\n";
+        assert_eq!(
+            set_version("Cargo.toml", workspace, "0.5.0").unwrap(),
+            "[workspace.package]\nversion = \"0.5.0\"\n"
+        );
+        let poetry = "[tool.poetry]\nname = \"demo\"\nversion = \"2.0.0\"\n";
+        assert!(
+            set_version("pyproject.toml", poetry, "2.1.0")
+                .unwrap()
+                .contains("version = \"2.1.0\"")
+        );
+        let npm = "{\n  \"name\": \"demo\",\n  \"version\": \"1.0.0\",\n  \"dependencies\": { \"x\": \"1.0.0\" }\n}\n";
+        assert_eq!(
+            set_version("package.json", npm, "1.1.0").unwrap(),
+            npm.replacen("1.0.0", "1.1.0", 1)
+        );
+        assert!(set_version("Cargo.toml", "[dependencies]\n", "1.0.0").is_err());
+
+        let lock = "[[package]]\nname = \"serde\"\nversion = \"1.2.0\"\n\n[[package]]\nname = \"demo\"\nversion = \"1.2.0\"\n";
+        assert_eq!(
+            bump_lock_entry(lock, "demo", "1.2.0", "1.3.0").unwrap(),
+            "[[package]]\nname = \"serde\"\nversion = \"1.2.0\"\n\n[[package]]\nname = \"demo\"\nversion = \"1.3.0\"\n"
+        );
+        assert_eq!(bump_lock_entry(lock, "other", "1.2.0", "1.3.0"), None);
+    }
+}
@@ -0,0 +1,272 @@
+use anyhow::{Context, Result, bail};
+use std::fs;
+
+use std::time::Instant;
+
+use crate::output::{self, say};
+use crate::session;
+use crate::{
+    AddedLine, Cli, CommitOptions, create_commit, git, git_output, git_path, launch_editor,
+    log_command, prompt_line, push_to_upstream, stage_everything,
+};
+
+/// A multi-step git operation that a plain `add -A` + commit would silently finish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Merge,
+    Rebase,
+    CherryPick,
+    Revert,
+}
+
+impl Operation {
+    pub fn name(self) -> &'static str {
+        match self {
+            Operation::Merge => "merge",
+            Operation::Rebase => "rebase",
+            Operation::CherryPick => "cherry-pick",
+            Operation::Revert => "revert",
+        }
+    }
+}
+
+pub fn in_progress() -> Result<Option<Operation>> {
+    let checks = [
+        ("rebase-merge", Operation::Rebase),
+        ("rebase-apply", Operation::Rebase),
+        ("MERGE_HEAD", Operation::Merge),
+        ("CHERRY_PICK_HEAD", Operation::CherryPick),
+        ("REVERT_HEAD", Operation::Revert),
+    ];
+    for (marker, op) in checks {
+        if git_path(marker)?.exists() {
+            return Ok(Some(op));
+        }
+    }
+    Ok(None)
+}
+
+pub fn rebase_in_progress() -> Result<bool> {
+    Ok(in_progress()? == Some(Operation::Rebase))
+}
+
+/// Paths git still considers conflicted.
+pub fn unmerged_paths() -> Result<Vec<String>> {
+    let out = git_output(&["diff", "--name-only", "-z", "--diff-filter=U"])?;
+    Ok(out
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Refuse to touch an in-progress operation unless `--continue` asked us to finish it.
+pub fn check(cli: &Cli) -> Result<Option<Operation>> {
+    let Some(op) = in_progress()? else {
+        return Ok(None);
+    };
+    if !cli.continue_op {
+        bail!(
+            "a {0} is in progress; finish it with `git {0} --continue` (or `scommit --continue`), or abort it with `git {0} --abort`",
+            op.name()
+        );
+    }
+    Ok(Some(op))
+}
+
+/// Finish `op`: conflicts must be resolved (no markers left in conflicted files). Merges
+/// are committed with git's prepared MERGE_MSG; the others run `git <op> --continue`.
+pub fn continue_operation(cli: &Cli, op: Operation) -> Result<()> {
+    let unresolved: Vec<String> = unmerged_paths()?
+        .into_iter()
+        .filter(|path| has_conflict_markers(path))
+        .collect();
+    if !unresolved.is_empty() {
+        bail!(
+            "conflict markers remain in:\n  {}\nresolve them before continuing the {}",
+            unresolved.join("\n  "),
+            op.name()
+        );
+    }
+
+    if !cli.no_stage {
+        stage_everything()?;
+    }
+
+    if cli.dry_run {
+        say!("DRY RUN\nWould continue the in-progress {}.", op.name());
+        return Ok(());
+    }
+
+    match op {
+        Operation::Merge => {
+            let (subject, body) = merge_message()?;
+            create_commit(&subject, &body, &CommitOptions::from_cli(cli))?;
+            push_to_upstream(cli)
+        }
+        _ => {
+            let mut cmd = session::command("git");
+            cmd.args([op.name(), "--continue"])
+                .env("GIT_EDITOR", "true")
+                .stdout(output::child_stdout());
+            let started = Instant::now();
+            let status = cmd
+                .status()
+                .with_context(|| format!("running git {} --continue", op.name()))?;
+            log_command(&cmd, Some(status), started);
+            if !status.success() {
+                bail!("git {} --continue failed", op.name());
+            }
+            match in_progress()? {
+                Some(op) => say!(
+                    "The {} stopped again; resolve the conflicts and rerun scommit --continue.",
+                    op.name()
+                ),
+                None => say!(
+                    "Finished the {}; not pushing rewritten history automatically.",
+                    op.name()
+                ),
+            }
+            Ok(())
+        }
+    }
+}
+
+/// A pull --rebase stopped on conflicts: list them and let the user abort, open the
+/// files in their editor, or leave the rebase in place. Without a terminal (or with
+/// --yes) the rebase is aborted, so unattended runs never leave a half-done rebase.
+pub fn handle_rebase_conflicts(cli: &Cli) -> Result<()> {
+    loop {
+        let conflicted = unmerged_paths()?;
+        eprintln!("Rebase stopped with conflicts in:");
+        for path in &conflicted {
+            eprintln!("  {path}");
+        }
+        if cli.yes || !output::can_prompt() {
+            eprintln!("Aborting the rebase to restore your branch and stashed changes.");
+            return git(&["rebase", "--abort"]);
+        }
+
+        let answer = prompt_line(
+            "rebase-conflict",
+            "[a]bort rebase, [o]pen files in editor, [l]eave it for me? ",
+        )?;
+        match answer.to_ascii_lowercase().as_str() {
+            "o" | "open" => {
+                let paths: Vec<&str> = conflicted.iter().map(String::as_str).collect();
+                if let Err(e) = launch_editor(&paths) {
+                    eprintln!("{e}");
+                }
+                if unmerged_paths()?
+                    .iter()
+                    .all(|path| !has_conflict_markers(path))
+                {
+                    print_rebase_next_steps(true);
+                    return Ok(());
+                }
+            }
+            "l" | "leave" => {
+                print_rebase_next_steps(false);
+                return Ok(());
+            }
+            _ => {
+                eprintln!("Aborting the rebase to restore your branch and stashed changes.");
+                return git(&["rebase", "--abort"]);
+            }
+        }
+    }
+}
+
+fn print_rebase_next_steps(resolved: bool) {
+    if resolved {
+        eprintln!("No conflict markers left. To finish:");
+    } else {
+        eprintln!("Leaving the rebase in place. Resolve the conflicts, then:");
+    }
+    eprintln!("  scommit --continue     # stage and run git rebase --continue");
+    eprintln!("  git push               # once the rebase is done");
+    eprintln!("Or give up with `git rebase --abort` (restores your branch and stashed edits).");
+}
+
+fn is_conflict_marker(line: &str) -> bool {
+    ["<<<<<<<", ">>>>>>>", "|||||||"]
+        .iter()
+        .any(|marker| line == *marker || line.starts_with(&format!("{marker} ")))
+}
+
+/// Refuse to commit added lines that are leftover conflict markers. A bare `=======`
+/// is ignored on its own since it doubles as a Markdown/RST heading underline.
+pub fn check_staged_markers(added: &[AddedLine], allow: bool) -> Result<()> {
+    let hits: Vec<String> = added
+        .iter()
+        .filter(|line| is_conflict_marker(&line.text))
+        .map(|line| format!("{}:{}", line.path, line.line))
+        .collect();
+    if hits.is_empty() {
+        return Ok(());
+    }
+    if allow {
+        eprintln!(
+            "Warning: committing conflict markers (--allow-conflict-markers):\n  {}",
+            hits.join("\n  ")
+        );
+        return Ok(());
+    }
+    bail!(
+        "conflict markers in the staged changes:\n  {}\nresolve them, or pass --allow-conflict-markers if they are intentional",
+        hits.join("\n  ")
+    )
+}
+
+fn has_conflict_markers(path: &str) -> bool {
+    fs::read(path)
+        .map(|bytes| {
+            String::from_utf8_lossy(&bytes)
+                .lines()
+                .any(|l| is_conflict_marker(l) || l == "=======")
+        })
+        .unwrap_or(false)
+}
+
+/// git's prepared merge message (MERGE_MSG) minus comment lines.
+fn merge_message() -> Result<(String, String)> {
+    let raw = fs::read_to_string(git_path("MERGE_MSG")?).unwrap_or_default();
+    Ok(split_message(&raw).unwrap_or_else(|| ("Merge changes".to_string(), String::new())))
+}
+
+/// Subject and body of a commit message file, ignoring `#` comment lines.
+pub fn split_message(raw: &str) -> Option<(String, String)> {
+    let lines: Vec<&str> = raw.lines().filter(|l| !l.starts_with('#')).collect();
+    let text = lines.join("\n");
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let (subject, body) = text.split_once('\n').unwrap_or((text, ""));
+    Some((subject.trim().to_string(), body.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conflict_markers_are_recognized_without_heading_underlines() {
+        assert!(is_conflict_marker("<<<<<<< HEAD"));
+        assert!(is_conflict_marker(">>>>>>> feature/login"));
+        assert!(is_conflict_marker("||||||| merged common ancestors"));
+        assert!(!is_conflict_marker("======="));
+        assert!(!is_conflict_marker("<<<<<<<<<< not a marker"));
+        assert!(!is_conflict_marker("a <<<<<<< b"));
+    }
+
+    #[test]
+    fn split_message_drops_comments_and_splits_subject() {
+        let raw = "Merge branch 'feature'\n\n# Conflicts:\n#\tsrc/main.rs\n";
+        assert_eq!(
+            split_message(raw),
+            Some(("Merge branch 'feature'".to_string(), String::new()))
+        );
+        assert_eq!(split_message("# only comments\n"), None);
+    }
+}
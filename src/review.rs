@@ -0,0 +1,171 @@
+//! `scommit review` and `--ai-review`: a second AI pass over the staged diff that flags
+//! likely bugs, missing error handling and risky changes. The comments are advisory;
+//! `--review-block` makes the commit wait until someone has read them.
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use std::env;
+
+use crate::error::{self, SafetyCheck, ScommitError};
+use crate::output::{self, say};
+use crate::{
+    Cli, FileChange, Privacy, ai_model, chat_completion, collect_staged_changes, config,
+    diff_excerpt, prompt_line, redact, sanitize_json_blob, secrets, staged_added_lines, whitespace,
+};
+
+/// The reviewer gets more of the diff than the message writer does; it needs the code,
+/// not just its shape.
+const MAX_DIFF_CHARS: usize = 12_000;
+
+const SYSTEM_PROMPT: &str = "You review a staged git diff before it is committed. Point out only concrete problems a careful reviewer would raise: likely bugs, unhandled errors or edge cases, security risks, and changes that look accidental (debug leftovers, commented-out code, unrelated edits). Skip style, naming and praise. Reply with a JSON object {\"findings\": [{\"path\": string, \"line\": number or null, \"severity\": \"bug\" | \"risk\" | \"nit\", \"comment\": string}]}, at most 8 findings, most important first; line is the line number in the new file. If nothing stands out, reply {\"findings\": []}.";
+
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+struct Finding {
+    path: String,
+    #[serde(default)]
+    line: Option<u32>,
+    #[serde(default = "default_severity")]
+    severity: String,
+    comment: String,
+}
+
+fn default_severity() -> String {
+    "risk".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct Reply {
+    #[serde(default)]
+    findings: Vec<Finding>,
+}
+
+/// `scommit review`: review what is staged now, without committing.
+pub fn run(cli: &Cli) -> Result<()> {
+    let changes = collect_staged_changes()?;
+    if changes.is_empty() {
+        say!("Nothing staged to review.");
+        return Ok(());
+    }
+    if ai_model(cli).is_none() {
+        return Err(error::ai_unavailable(anyhow!(
+            "the review needs the AI provider: set OPENAI_API_KEY (CI mode keeps the AI off unless --allow-ai)"
+        )));
+    }
+    let findings = review(cli, &changes)?;
+    print_findings(&findings);
+    Ok(())
+}
+
+/// `--ai-review` in a normal run: print the findings; with `--review-block`, stop unless
+/// they are acknowledged. Returns false when the commit should not go ahead.
+pub fn before_commit(cli: &Cli, changes: &[FileChange]) -> Result<bool> {
+    if ai_model(cli).is_none() {
+        eprintln!("AI review skipped: no AI provider (set OPENAI_API_KEY).");
+        return Ok(true);
+    }
+    let findings = match review(cli, changes) {
+        Ok(findings) => findings,
+        Err(e) if cli.review_block => return Err(e.context("AI review failed")),
+        Err(e) => {
+            eprintln!("AI review failed ({e}); carrying on.");
+            return Ok(true);
+        }
+    };
+    print_findings(&findings);
+    if findings.is_empty() || !cli.review_block || cli.dry_run {
+        return Ok(true);
+    }
+    if output::ci() {
+        return Err(anyhow!(
+            "the AI review raised {} finding(s) and --review-block is set",
+            findings.len()
+        ))
+        .context(ScommitError::Refused {
+            check: SafetyCheck::Review,
+        });
+    }
+    // Not answered by --yes: the point is that someone has read them.
+    let answer = prompt_line(
+        "review-ack",
+        "Commit anyway? Type \"yes\" to acknowledge the review: ",
+    )?;
+    Ok(answer.eq_ignore_ascii_case("yes"))
+}
+
+fn review(cli: &Cli, changes: &[FileChange]) -> Result<Vec<Finding>> {
+    if cli.privacy == Privacy::Metadata {
+        bail!(
+            "--privacy metadata keeps the diff from the AI provider, so there is nothing to review"
+        );
+    }
+    let model = ai_model(cli).context("no AI provider")?;
+    let key = env::var("OPENAI_API_KEY")?;
+    // Before anything of the diff is sent, as in a normal run.
+    secrets::check(&staged_added_lines()?, cli.allow_secrets).context(ScommitError::Refused {
+        check: SafetyCheck::Secrets,
+    })?;
+    let formatting = whitespace::formatting_only(changes);
+    let diff = diff_excerpt(MAX_DIFF_CHARS, &config::get().redact, changes, &formatting)?;
+    if diff.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let prompt = redact::apply_patterns(&format!("Staged diff:\n{diff}"), &config::get().redact)?;
+    let Some(reply) = chat_completion(&key, &model, SYSTEM_PROMPT, &prompt, true, 900)? else {
+        return Ok(Vec::new());
+    };
+    parse_reply(&reply)
+}
+
+fn parse_reply(reply: &str) -> Result<Vec<Finding>> {
+    let blob = sanitize_json_blob(reply).ok_or_else(|| anyhow!("AI reply wasn't JSON"))?;
+    let reply: Reply = serde_json::from_str(&blob).context("decoding AI review")?;
+    Ok(reply
+        .findings
+        .into_iter()
+        .filter(|f| !f.comment.trim().is_empty())
+        .collect())
+}
+
+fn print_findings(findings: &[Finding]) {
+    if findings.is_empty() {
+        say!("AI review: nothing stood out.");
+        return;
+    }
+    eprintln!("AI review ({} finding(s), advisory):", findings.len());
+    for finding in findings {
+        eprintln!("  {}", render(finding));
+    }
+}
+
+/// "src/a.rs:12 [bug] comment".
+fn render(finding: &Finding) -> String {
+    let place = match finding.line {
+        Some(line) => format!("{}:{line}", finding.path),
+        None => finding.path.clone(),
+    };
+    let severity = match finding.severity.as_str() {
+        "bug" => output::red("[bug]"),
+        "nit" => output::dim("[nit]"),
+        other => output::yellow(&format!("[{other}]")),
+    };
+    format!("{place} {severity} {}", finding.comment.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn review_replies_become_findings() {
+        let reply = r#"{"findings": [
+            {"path": "src/a.rs", "line": 12, "severity": "bug", "comment": "unwrap on user input"},
+            {"path": "src/b.rs", "comment": "debug print left in"},
+            {"path": "src/c.rs", "line": null, "severity": "nit", "comment": "  "}
+        ]}"#;
+        let findings = parse_reply(reply).unwrap();
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[1].severity, "risk");
+        assert_eq!(findings[1].line, None);
+        assert!(parse_reply(r#"{"findings": []}"#).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+//! `scommit reword <rev>`: regenerate the message of a commit already made, from its own
+//! diff, so "wip" and "fix" commits can be upgraded before the branch is shared. HEAD is
+//! amended in place; an older commit gets an `amend!` commit folded in by a
+//! non-interactive `git rebase --autosquash`.
+
+use anyhow::{Context, Result, bail};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Stdio;
+
+use crate::error::{SafetyCheck, ScommitError};
+use crate::output::say;
+use crate::session;
+use crate::{
+    Cli, collect_staged_changes, commit_message_text, compose_message, compute_stats, confirm,
+    exit, git, git_output, git_path, interactive, output, secrets, staged_added_lines,
+};
+
+/// A throwaway worktree at `base` (no files checked out) whose index holds `tree`. There
+/// `git diff --cached` is the change from one to the other, so the usual staged-change
+/// analysis describes a commit (or a checkpoint) unchanged.
+pub struct CommitView {
+    path: PathBuf,
+    home: PathBuf,
+    index_file: Option<OsString>,
+}
+
+impl CommitView {
+    pub fn enter(base: &str, tree: &str) -> Result<CommitView> {
+        let path = git_path("scommit/view-worktree")?;
+        if path.exists() {
+            git_output(&["worktree", "remove", "--force", &path.to_string_lossy()]).ok();
+        }
+        git_output(&[
+            "worktree",
+            "add",
+            "--detach",
+            "--no-checkout",
+            &path.to_string_lossy(),
+            base,
+        ])
+        .context("creating a worktree to read the commit in")?;
+        let view = CommitView {
+            path,
+            home: env::current_dir()?,
+            index_file: env::var_os("GIT_INDEX_FILE"),
+        };
+        // SAFETY: single-threaded here; a --dry-run scratch index belongs to the main
+        // worktree, and the view needs its own.
+        unsafe { env::remove_var("GIT_INDEX_FILE") };
+        env::set_current_dir(&view.path)?;
+        git_output(&["read-tree", tree])?;
+        Ok(view)
+    }
+}
+
+impl Drop for CommitView {
+    fn drop(&mut self) {
+        env::set_current_dir(&self.home).ok();
+        if let Some(index) = &self.index_file {
+            // SAFETY: as in `enter`.
+            unsafe { env::set_var("GIT_INDEX_FILE", index) };
+        }
+        git_output(&[
+            "worktree",
+            "remove",
+            "--force",
+            &self.path.to_string_lossy(),
+        ])
+        .ok();
+    }
+}
+
+pub fn run(cli: &Cli, rev: &str) -> Result<()> {
+    let sha = git_output(&["rev-parse", "--verify", &format!("{rev}^{{commit}}")])
+        .with_context(|| format!("{rev} isn't a commit"))?
+        .trim()
+        .to_string();
+    let head = git_output(&["rev-parse", "HEAD"])?.trim().to_string();
+    if sha != head && git_output(&["merge-base", "--is-ancestor", &sha, "HEAD"]).is_err() {
+        bail!("{rev} isn't on the current branch; check out a branch that contains it");
+    }
+    let parents = git_output(&["rev-list", "--parents", "-n", "1", &sha])?;
+    match parents.split_whitespace().count() {
+        1 => bail!("{rev} is the root commit; scommit rewords commits against their parent"),
+        2 => {}
+        _ => bail!("{rev} is a merge commit; its message describes the merge, not a diff"),
+    }
+
+    let old = git_output(&["log", "-1", "--format=%B", &sha])?;
+    let old_subject = old.lines().next().unwrap_or_default().to_string();
+    let published = git_output(&["branch", "--remotes", "--contains", &sha])?;
+    if !published.trim().is_empty()
+        && !cli.dry_run
+        && !cli.yes
+        && !confirm(
+            "reword-pushed",
+            &format!(
+                "{} is already pushed; rewording rewrites published history. Continue?",
+                &sha[..7]
+            ),
+        )?
+    {
+        exit::note(exit::Code::Cancelled);
+        return Ok(());
+    }
+
+    let (subject, body) = {
+        let _view = CommitView::enter(&format!("{sha}^"), &sha)?;
+        let changes = collect_staged_changes()?;
+        if changes.is_empty() {
+            bail!("{rev} changes nothing; there is no diff to describe");
+        }
+        // Committed secrets are still secrets; don't send them on.
+        secrets::check(&staged_added_lines()?, cli.allow_secrets).context(
+            ScommitError::Refused {
+                check: SafetyCheck::Secrets,
+            },
+        )?;
+        let stats = compute_stats(&changes);
+        let (subject, body) = compose_message(cli, &changes, &stats, None)?;
+        let body = with_trailers(&body, &trailers_of(&old).unwrap_or_default());
+        say!("Old subject: {old_subject}");
+        if cli.dry_run {
+            say!(
+                "{}\n{}",
+                output::yellow("DRY RUN"),
+                output::message(&subject, body.trim_end())
+            );
+            return Ok(());
+        }
+        match interactive::confirm_message(cli, &changes, &stats, subject, body)? {
+            Some(message) => message,
+            None => {
+                say!("Kept the old message.");
+                exit::note(exit::Code::Cancelled);
+                return Ok(());
+            }
+        }
+    };
+
+    let message = commit_message_text(&subject, &body);
+    let msg_path = git_path("SCOMMIT_REWORD")?;
+    if sha == head {
+        fs::write(&msg_path, &message)?;
+        let result = git(&[
+            "commit",
+            "--amend",
+            "--only",
+            "--quiet",
+            "-F",
+            &msg_path.to_string_lossy(),
+        ]);
+        fs::remove_file(&msg_path).ok();
+        result.context(ScommitError::CommitFailed)?;
+        say!("Reworded HEAD: {subject}");
+        return Ok(());
+    }
+
+    // An `amend!` commit naming the target replaces its message when autosquashed.
+    fs::write(&msg_path, format!("amend! {sha}\n\n{message}"))?;
+    let tree = git_output(&["rev-parse", "HEAD^{tree}"])?;
+    let amend = git_output(&[
+        "commit-tree",
+        tree.trim(),
+        "-p",
+        "HEAD",
+        "-F",
+        &msg_path.to_string_lossy(),
+    ]);
+    fs::remove_file(&msg_path).ok();
+    let amend = amend?;
+    git_output(&[
+        "update-ref",
+        "-m",
+        "scommit reword",
+        "HEAD",
+        amend.trim(),
+        &head,
+    ])?;
+    let status = session::command("git")
+        .args([
+            "rebase",
+            "--interactive",
+            "--autosquash",
+            "--autostash",
+            "--quiet",
+            &format!("{sha}^"),
+        ])
+        .env("GIT_SEQUENCE_EDITOR", ":")
+        .status()
+        .context("running git rebase")?;
+    if !status.success() {
+        git_output(&["rebase", "--abort"]).ok();
+        git_output(&["update-ref", "HEAD", &head]).ok();
+        bail!("git rebase failed; the branch is back where it was");
+    }
+    say!(
+        "Reworded {}: {subject} (the commits after it were rebased onto it)",
+        &sha[..7]
+    );
+    Ok(())
+}
+
+/// Trailers (Signed-off-by, Co-authored-by, ...) from the old message.
+pub fn trailers_of(message: &str) -> Option<String> {
+    let mut child = session::command("git")
+        .args(["interpret-trailers", "--parse"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .ok()?;
+    child.stdin.take()?.write_all(message.as_bytes()).ok()?;
+    let out = child.wait_with_output().ok()?;
+    Some(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// The new body with the old trailers it doesn't already carry appended, so sign-offs
+/// and co-authors survive the rewrite.
+pub fn with_trailers(body: &str, trailers: &str) -> String {
+    let missing: Vec<&str> = trailers
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !body.lines().any(|l| l.trim() == *line))
+        .collect();
+    if missing.is_empty() {
+        return body.to_string();
+    }
+    let body = body.trim_end();
+    let separator = if body.is_empty() { "" } else { "\n\n" };
+    format!("{body}{separator}{}\n", missing.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_trailers_are_carried_over_once() {
+        let trailers =
+            "Signed-off-by: Ada <ada@example.com>\nCo-authored-by: Grace <g@example.com>\n";
+        assert_eq!(
+            with_trailers("- add parser\n", trailers),
+            "- add parser\n\nSigned-off-by: Ada <ada@example.com>\nCo-authored-by: Grace <g@example.com>\n"
+        );
+        assert_eq!(
+            with_trailers(
+                "- add parser\n\nSigned-off-by: Ada <ada@example.com>\n",
+                trailers
+            ),
+            "- add parser\n\nSigned-off-by: Ada <ada@example.com>\n\nCo-authored-by: Grace <g@example.com>\n"
+        );
+        assert_eq!(with_trailers("body\n", ""), "body\n");
+    }
+}
@@ -0,0 +1,239 @@
+//! `scommit --select`: a checklist of the staged files, grouped by category, to leave
+//! whole files out of the commit without going hunk by hunk.
+
+use anyhow::{Context, Result, bail};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame, TerminalOptions, Viewport};
+use std::io::IsTerminal;
+
+use crate::output;
+use crate::split::{CATEGORY_ORDER, group_paths, stage_from_tree};
+use crate::staging::{self, SetAside};
+use crate::{CATEGORY_NAMES, Category, FileChange, FileStatus, git_output};
+
+const HELP: &str = "↑↓ move · Space toggle · a all · Enter confirm · Esc cancel";
+
+/// Tallest the checklist gets; longer lists scroll.
+const MAX_HEIGHT: u16 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    Heading(Category),
+    File(usize),
+}
+
+/// Ask which files to commit. Deselected files are taken out of the index until the
+/// returned guard restores them; None means the user cancelled.
+pub fn run(changes: &[FileChange]) -> Result<Option<SetAside>> {
+    if output::ci() || !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        bail!("--select needs a terminal");
+    }
+    let rows = rows(changes);
+    let mut checklist = Checklist {
+        changes,
+        included: vec![true; changes.len()],
+        list: ListState::default().with_selected(Some(1)),
+        rows,
+    };
+    let height = (checklist.rows.len() as u16 + 1).min(MAX_HEIGHT);
+    let mut terminal = ratatui::try_init_with_options(TerminalOptions {
+        viewport: Viewport::Inline(height),
+    })
+    .context("starting the file checklist")?;
+    let result = checklist.event_loop(&mut terminal);
+    terminal.clear().ok();
+    ratatui::restore();
+    if !result? {
+        return Ok(None);
+    }
+
+    let excluded: Vec<FileChange> = changes
+        .iter()
+        .zip(&checklist.included)
+        .filter(|(_, included)| !**included)
+        .map(|(change, _)| change.clone())
+        .collect();
+    let paths = group_paths(&excluded);
+    if paths.is_empty() {
+        return Ok(Some(SetAside::new(String::new(), paths)));
+    }
+    let tree = git_output(&["write-tree"])?.trim().to_string();
+    stage_from_tree(staging::unstaged_base(), &paths)?;
+    Ok(Some(SetAside::new(tree, paths)))
+}
+
+/// A heading per non-empty category, in commit order, each followed by its files.
+fn rows(changes: &[FileChange]) -> Vec<Row> {
+    let mut rows = Vec::new();
+    for category in CATEGORY_ORDER {
+        let files: Vec<usize> = (0..changes.len())
+            .filter(|&i| changes[i].category == category)
+            .collect();
+        if !files.is_empty() {
+            rows.push(Row::Heading(category));
+            rows.extend(files.into_iter().map(Row::File));
+        }
+    }
+    rows
+}
+
+struct Checklist<'a> {
+    changes: &'a [FileChange],
+    included: Vec<bool>,
+    list: ListState,
+    rows: Vec<Row>,
+}
+
+impl Checklist<'_> {
+    /// true to go ahead with the selection, false to cancel.
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<bool> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let selected = self.list.selected().unwrap_or(0);
+            match key.code {
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(false);
+                }
+                KeyCode::Esc | KeyCode::Char('q') => return Ok(false),
+                KeyCode::Enter if self.included.contains(&true) => return Ok(true),
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.list.select(Some(selected.saturating_sub(1)));
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    self.list
+                        .select(Some((selected + 1).min(self.rows.len() - 1)));
+                }
+                KeyCode::Char(' ') => self.toggle(selected),
+                KeyCode::Char('a') => {
+                    let all = self.included.iter().all(|i| *i);
+                    self.included.iter_mut().for_each(|i| *i = !all);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn toggle(&mut self, row: usize) {
+        match self.rows.get(row) {
+            Some(Row::File(index)) => self.included[*index] = !self.included[*index],
+            Some(Row::Heading(category)) => {
+                let members: Vec<usize> = (0..self.changes.len())
+                    .filter(|&i| self.changes[i].category == *category)
+                    .collect();
+                let all = members.iter().all(|&i| self.included[i]);
+                for i in members {
+                    self.included[i] = !all;
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [list, help] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let items: Vec<ListItem> = self.rows.iter().map(|row| self.item(*row)).collect();
+        let list_widget =
+            List::new(items).highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list_widget, list, &mut self.list);
+
+        let included = self.included.iter().filter(|i| **i).count();
+        let footer = if included == 0 {
+            Span::styled(
+                "Nothing selected; Space to include a file, Esc to cancel",
+                Style::new().fg(Color::Yellow),
+            )
+        } else {
+            Span::styled(
+                format!("{included}/{} files · {HELP}", self.changes.len()),
+                Style::new().add_modifier(Modifier::DIM),
+            )
+        };
+        frame.render_widget(Paragraph::new(Line::from(footer)), help);
+    }
+
+    fn item(&self, row: Row) -> ListItem<'static> {
+        match row {
+            Row::Heading(category) => {
+                let name = CATEGORY_NAMES.get(&category).copied().unwrap_or("other");
+                ListItem::new(Line::styled(
+                    name.to_string(),
+                    Style::new().add_modifier(Modifier::BOLD),
+                ))
+            }
+            Row::File(index) => {
+                let change = &self.changes[index];
+                let included = self.included[index];
+                let (status, color) = match change.status {
+                    FileStatus::Added => ("A", Color::Green),
+                    FileStatus::Modified => ("M", Color::Yellow),
+                    FileStatus::Deleted => ("D", Color::Red),
+                    FileStatus::Renamed { .. } => ("R", Color::Yellow),
+                    FileStatus::Copied { .. } => ("C", Color::Green),
+                    FileStatus::TypeChanged => ("T", Color::Yellow),
+                };
+                let line = Line::from(vec![
+                    Span::raw(if included { "  [x] " } else { "  [ ] " }),
+                    Span::styled(status, Style::new().fg(color)),
+                    Span::raw(format!(" {} ", change.path)),
+                    Span::styled(format!("+{}", change.added), Style::new().fg(Color::Green)),
+                    Span::raw(" "),
+                    Span::styled(format!("-{}", change.deleted), Style::new().fg(Color::Red)),
+                ]);
+                let style = if included {
+                    Style::new()
+                } else {
+                    Style::new().add_modifier(Modifier::DIM)
+                };
+                ListItem::new(line).style(style)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(path: &str, category: Category) -> FileChange {
+        FileChange {
+            path: path.to_string(),
+            status: FileStatus::Modified,
+            added: 1,
+            deleted: 0,
+            category,
+        }
+    }
+
+    #[test]
+    fn rows_group_files_under_category_headings_in_commit_order() {
+        let changes = vec![
+            change("README.md", Category::Docs),
+            change("src/lib.rs", Category::Code),
+            change("Cargo.toml", Category::Config),
+            change("src/main.rs", Category::Code),
+        ];
+        assert_eq!(
+            rows(&changes),
+            vec![
+                Row::Heading(Category::Config),
+                Row::File(2),
+                Row::Heading(Category::Code),
+                Row::File(1),
+                Row::File(3),
+                Row::Heading(Category::Docs),
+                Row::File(0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::{Result, bail};
+
+use crate::glob_match;
+
+/// File names that almost always hold credentials. `*` matches any run of characters.
+const PATTERNS: &[&str] = &[
+    ".env",
+    ".env.*",
+    "*.pem",
+    "*.key",
+    "*.p12",
+    "*.pfx",
+    "*.keystore",
+    "*.jks",
+    "id_rsa",
+    "id_dsa",
+    "id_ecdsa",
+    "id_ed25519",
+    ".netrc",
+    ".pgpass",
+    ".htpasswd",
+    "credentials",
+    "credentials.json",
+    "client_secret*.json",
+    "service-account*.json",
+];
+
+/// Templates meant to be committed, e.g. `.env.example`.
+const TEMPLATE_SUFFIXES: &[&str] = &[".example", ".sample", ".template", ".dist"];
+
+fn is_sensitive(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    if TEMPLATE_SUFFIXES.iter().any(|s| name.ends_with(s)) {
+        return false;
+    }
+    PATTERNS.iter().any(|p| glob_match(p, name))
+}
+
+/// Refuse to stage or commit credential files unless --force was given. The automatic
+/// `git add -A` would otherwise sweep up a stray `.env` without anyone looking.
+pub fn check(paths: &[String], force: bool) -> Result<()> {
+    let hits: Vec<String> = paths
+        .iter()
+        .filter(|path| is_sensitive(path))
+        .cloned()
+        .collect();
+    if hits.is_empty() {
+        return Ok(());
+    }
+    if force {
+        eprintln!(
+            "Warning: committing credential-like files (--force):\n  {}",
+            hits.join("\n  ")
+        );
+        return Ok(());
+    }
+    bail!(
+        "refusing to commit files that usually hold credentials:\n  {}\nAdd them to .gitignore (and unstage with `git rm --cached <file>` if needed), or pass --force.",
+        hits.join("\n  ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_sensitive_matches_credential_files_but_not_templates() {
+        for path in [
+            ".env",
+            "api/.env.production",
+            "certs/server.pem",
+            "home/.ssh/id_ed25519",
+            "gcp/service-account-prod.json",
+        ] {
+            assert!(is_sensitive(path), "{path}");
+        }
+        for path in [
+            ".env.example",
+            "src/env.rs",
+            "id_rsa.pub",
+            "docs/keys.md",
+            "config.json",
+        ] {
+            assert!(!is_sensitive(path), "{path}");
+        }
+    }
+}
@@ -0,0 +1,363 @@
+//! `scommit serve`: JSON-RPC 2.0 over stdio for editor extensions, one request or
+//! response per line. A warm process keeps the AI provider's HTTP connection between
+//! commits instead of paying for process startup and a TLS handshake each time.
+//!
+//! Methods: `analyze`, `generate`, `commit`, `push` and `shutdown`. Every method but
+//! `shutdown` takes an optional `repo` (default: the directory serve was started in),
+//! whose config is loaded afresh for each request.
+//! Progress and git's own output go to stderr; stdout carries only responses.
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::Parser;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{SafetyCheck, ScommitError};
+use crate::output::{self, say};
+use crate::{
+    Cli, CommitOptions, Options, collect_staged_changes, compute_stats, config, create_commit,
+    current_branch, exit, generate_message, git_output, guard_protected_branch, has_staged_changes,
+    in_repo, push_branch, secrets, staged_added_lines,
+};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// A method that ran and failed; `data.exit_code` is the code the CLI would exit with and
+/// `data.failure` the `ScommitError`, when there is one.
+const FAILED: i64 = -32000;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    /// Absent for notifications, which get no response.
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AnalyzeParams {
+    repo: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GenerateParams {
+    repo: Option<PathBuf>,
+    model: Option<String>,
+    no_ai: bool,
+    metadata_only: bool,
+    hint: Option<String>,
+    template: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CommitParams {
+    repo: Option<PathBuf>,
+    subject: String,
+    body: String,
+    no_verify: bool,
+    allow_secrets: bool,
+    allow_protected: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PushParams {
+    repo: Option<PathBuf>,
+    remote: Option<String>,
+    set_upstream: bool,
+    force_with_lease: bool,
+    skip_pull: bool,
+}
+
+/// A failed request: the JSON-RPC error code and the error itself.
+struct Failure {
+    code: i64,
+    error: anyhow::Error,
+}
+
+impl From<anyhow::Error> for Failure {
+    fn from(error: anyhow::Error) -> Self {
+        Failure {
+            code: FAILED,
+            error,
+        }
+    }
+}
+
+pub fn run() -> Result<()> {
+    let home = env::current_dir()?;
+    say!("scommit serve: reading JSON-RPC requests from stdin, one per line");
+    let stdin = std::io::stdin();
+    for line in stdin.lock().lines() {
+        let line = line.context("reading a request")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (response, stop) = handle(&home, &line);
+        if let Some(response) = response {
+            let mut stdout = std::io::stdout().lock();
+            writeln!(stdout, "{response}").context("writing a response")?;
+            stdout.flush().ok();
+        }
+        if stop {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The response line for one request (None for a notification), and whether to stop.
+fn handle(home: &Path, line: &str) -> (Option<Value>, bool) {
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            let code = if serde_json::from_str::<Value>(line).is_ok() {
+                INVALID_REQUEST
+            } else {
+                PARSE_ERROR
+            };
+            return (Some(error(Value::Null, code, &anyhow!(e))), false);
+        }
+    };
+    if request.jsonrpc != "2.0" {
+        let e = anyhow!("only JSON-RPC 2.0 is spoken here");
+        return (
+            Some(error(request.id.unwrap_or_default(), INVALID_REQUEST, &e)),
+            false,
+        );
+    }
+    // Each request reports on its own, like a run of the CLI.
+    output::record(|r| *r = output::Report::default());
+    let stop = request.method == "shutdown";
+    let result = match request.method.as_str() {
+        "analyze" => params(request.params).and_then(|p| analyze(home, p)),
+        "generate" => params(request.params).and_then(|p| generate(home, p)),
+        "commit" => params(request.params).and_then(|p| commit(home, p)),
+        "push" => params(request.params).and_then(|p| push(home, p)),
+        "shutdown" => Ok(Value::Null),
+        method => Err(Failure {
+            code: METHOD_NOT_FOUND,
+            error: anyhow!("no method {method}; try analyze, generate, commit, push or shutdown"),
+        }),
+    };
+    let id = match request.id {
+        Some(id) => id,
+        None => return (None, stop),
+    };
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(failure) => error(id, failure.code, &failure.error),
+    };
+    (Some(response), stop)
+}
+
+fn params<T: for<'de> Deserialize<'de> + Default>(params: Value) -> Result<T, Failure> {
+    if params.is_null() {
+        return Ok(T::default());
+    }
+    serde_json::from_value(params).map_err(|e| Failure {
+        code: INVALID_PARAMS,
+        error: anyhow!(e),
+    })
+}
+
+fn error(id: Value, code: i64, e: &anyhow::Error) -> Value {
+    let mut error = json!({ "code": code, "message": format!("{e:#}") });
+    if code == FAILED {
+        error["data"] = json!({
+            "exit_code": exit::code(Err(e)),
+            "failure": e.downcast_ref::<ScommitError>(),
+        });
+    }
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+fn repo(home: &Path, repo: Option<PathBuf>) -> PathBuf {
+    repo.map_or_else(|| home.to_path_buf(), |repo| home.join(repo))
+}
+
+/// The staged files and their totals, as in the `--json` record.
+fn analyze(home: &Path, p: AnalyzeParams) -> Result<Value, Failure> {
+    let changes = in_repo(&repo(home, p.repo), collect_staged_changes)?;
+    let stats = compute_stats(&changes);
+    let files: Vec<_> = changes.iter().map(output::file_record).collect();
+    let categories: BTreeMap<&str, usize> = stats
+        .categories
+        .iter()
+        .map(|(category, n)| (category.name(), *n))
+        .collect();
+    Ok(json!({
+        "files": files,
+        "stats": {
+            "files": stats.files,
+            "added": stats.added,
+            "deleted": stats.deleted,
+            "new_files": stats.new_files,
+            "removed_files": stats.removed_files,
+            "categories": categories,
+        },
+    }))
+}
+
+fn generate(home: &Path, p: GenerateParams) -> Result<Value, Failure> {
+    let options = Options {
+        model: p.model,
+        no_ai: p.no_ai,
+        metadata_only: p.metadata_only,
+        hint: p.hint,
+        template: p.template,
+    };
+    let message = generate_message(&repo(home, p.repo), &options)?;
+    Ok(json!({
+        "subject": message.subject,
+        "body": message.body,
+        "text": message.text(),
+        "ai": message.ai,
+    }))
+}
+
+/// Commit what is staged with the given message, behind the same checks as the CLI.
+fn commit(home: &Path, p: CommitParams) -> Result<Value, Failure> {
+    if p.subject.trim().is_empty() {
+        return Err(Failure {
+            code: INVALID_PARAMS,
+            error: anyhow!("commit needs a subject"),
+        });
+    }
+    let mut args = vec!["scommit"];
+    if p.no_verify {
+        args.push("--no-verify");
+    }
+    if p.allow_protected {
+        args.push("--allow-protected");
+    }
+    let cli = Cli::parse_from(args);
+    let sha = in_repo(&repo(home, p.repo), || {
+        if !has_staged_changes()? {
+            bail!(anyhow!("nothing is staged").context(ScommitError::NoChanges));
+        }
+        if current_branch()?.is_none() {
+            bail!("HEAD is detached; check out a branch to commit on");
+        }
+        guard_protected_branch(&cli, &config::get()).context(ScommitError::Refused {
+            check: SafetyCheck::ProtectedBranch,
+        })?;
+        secrets::check(&staged_added_lines()?, p.allow_secrets).context(ScommitError::Refused {
+            check: SafetyCheck::Secrets,
+        })?;
+        create_commit(&p.subject, &p.body, &CommitOptions::from_cli(&cli))?;
+        Ok(git_output(&["rev-parse", "HEAD"])?.trim().to_string())
+    })?;
+    Ok(json!({ "sha": sha }))
+}
+
+fn push(home: &Path, p: PushParams) -> Result<Value, Failure> {
+    let mut args = vec!["scommit".to_string()];
+    if let Some(remote) = p.remote {
+        args.extend(["--remote".to_string(), remote]);
+    }
+    for (on, flag) in [
+        (p.set_upstream, "--set-upstream"),
+        (p.force_with_lease, "--force-with-lease"),
+        (p.skip_pull, "--skip-pull"),
+    ] {
+        if on {
+            args.push(flag.to_string());
+        }
+    }
+    // Asking was done by whoever sent the request.
+    args.push("--yes".to_string());
+    let cli = Cli::parse_from(args);
+    let pushed = in_repo(&repo(home, p.repo), || push_branch(&cli))?;
+    Ok(json!({
+        "pushed": pushed.is_some(),
+        "remote": pushed.as_ref().map(|(remote, _)| remote),
+        "dest": pushed.as_ref().map(|(_, dest)| dest),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protocol_errors_and_notifications() {
+        let home = PathBuf::from(".");
+        let (response, stop) = handle(&home, "{not json");
+        assert_eq!(response.unwrap()["error"]["code"], PARSE_ERROR);
+        assert!(!stop);
+
+        let (response, _) = handle(&home, r#"{"jsonrpc":"2.0","id":7,"method":"rebase"}"#);
+        let response = response.unwrap();
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        let (response, _) = handle(
+            &home,
+            r#"{"jsonrpc":"2.0","id":"a","method":"generate","params":{"modle":"x"}}"#,
+        );
+        assert_eq!(response.unwrap()["error"]["code"], INVALID_PARAMS);
+
+        let (response, _) = handle(&home, r#"{"jsonrpc":"1.0","id":1,"method":"analyze"}"#);
+        assert_eq!(response.unwrap()["error"]["code"], INVALID_REQUEST);
+
+        let (response, stop) = handle(&home, r#"{"jsonrpc":"2.0","method":"shutdown"}"#);
+        assert!(response.is_none() && stop);
+    }
+
+    #[test]
+    fn each_request_runs_in_its_own_repo() {
+        let base = env::temp_dir().join(format!("scommit-serve-{}", std::process::id()));
+        let git = |repo: &Path, args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(args)
+                .current_dir(repo)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {args:?}");
+        };
+        let mut subjects = Vec::new();
+        for name in ["one", "two"] {
+            let repo = base.join(name);
+            std::fs::create_dir_all(&repo).unwrap();
+            git(&repo, &["init", "--quiet"]);
+            git(&repo, &["config", "user.name", "t"]);
+            git(&repo, &["config", "user.email", "t@t"]);
+            std::fs::write(
+                repo.join(".scommit.toml"),
+                format!("[message]\ntemplate = \"t\"\n[templates.t]\nsubject = \"{name}: {{subject}}\"\n"),
+            )
+            .unwrap();
+            git(&repo, &["add", ".scommit.toml"]);
+
+            let generate = json!({ "jsonrpc": "2.0", "id": 1, "method": "generate",
+                "params": { "repo": repo, "no_ai": true } });
+            let (response, _) = handle(&base, &generate.to_string());
+            subjects.push(response.unwrap()["result"]["subject"].clone());
+
+            let commit = json!({ "jsonrpc": "2.0", "id": 2, "method": "commit",
+                "params": { "repo": repo, "subject": "init", "allow_protected": true } });
+            let (response, _) = handle(&base, &commit.to_string());
+            assert!(response.unwrap()["result"]["sha"].is_string());
+            // Both repos started unborn: the second request's undo record doesn't carry
+            // the first one's HEAD.
+            let record = std::fs::read_to_string(repo.join(".git/scommit/last-run")).unwrap();
+            assert!(record.starts_with("before \n"), "{record}");
+        }
+        std::fs::remove_dir_all(&base).ok();
+        assert!(subjects[0].as_str().unwrap().starts_with("one: "));
+        assert!(subjects[1].as_str().unwrap().starts_with("two: "));
+    }
+}
@@ -0,0 +1,127 @@
+//! The repository a call works on, with its config and run record. The CLI works in the
+//! process's directory (the repo root, entered at startup) with the config loaded then.
+//! Each library call and each `scommit serve` request opens a session of its own on the
+//! calling thread instead: git runs in the session's root, `config::get` answers with
+//! its config and `output::record` writes to its report, so neither the process's
+//! working directory nor an earlier call's repository leaks into it.
+
+use anyhow::{Context, Result, bail};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread::{Scope, ScopedJoinHandle};
+use std::time::Instant;
+
+use crate::config::Config;
+use crate::log_command;
+use crate::output::Report;
+
+pub struct Session {
+    root: PathBuf,
+    config: Arc<Config>,
+    report: Mutex<Report>,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<Session>>> = const { RefCell::new(None) };
+}
+
+/// A session for the repository containing `repo`, with that repository's config.
+pub fn open(repo: &Path) -> Result<Arc<Session>> {
+    let mut cmd = Command::new("git");
+    cmd.args(["rev-parse", "--show-toplevel"]).current_dir(repo);
+    let started = Instant::now();
+    let output = cmd
+        .output()
+        .with_context(|| format!("running git in {}", repo.display()))?;
+    log_command(&cmd, Some(output.status), started);
+    if !output.status.success() {
+        bail!("{} is not inside a git repository", repo.display());
+    }
+    let root = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    let config = Config::load(&root)?;
+    Ok(Arc::new(Session {
+        root,
+        config: Arc::new(config),
+        report: Mutex::new(Report::default()),
+    }))
+}
+
+/// Run `f` in `session` on this thread, then go back to whatever was current before.
+pub fn run<T>(session: Option<Arc<Session>>, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<Arc<Session>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            CURRENT.with(|current| *current.borrow_mut() = previous);
+        }
+    }
+    let previous = CURRENT.with(|current| current.replace(session));
+    let _restore = Restore(previous);
+    f()
+}
+
+/// The session this thread runs in, if any.
+pub fn current() -> Option<Arc<Session>> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// `scope.spawn(f)`, with `f` in this thread's session.
+pub fn spawn<'scope, T: Send + 'scope>(
+    scope: &'scope Scope<'scope, '_>,
+    f: impl FnOnce() -> T + Send + 'scope,
+) -> ScopedJoinHandle<'scope, T> {
+    let session = current();
+    scope.spawn(move || run(session, f))
+}
+
+/// The session's repository root; None outside a session, where it is the working
+/// directory.
+pub fn root() -> Option<PathBuf> {
+    current().map(|session| session.root.clone())
+}
+
+pub fn config() -> Option<Arc<Config>> {
+    current().map(|session| Arc::clone(&session.config))
+}
+
+/// Apply `f` to the session's report; None outside a session.
+pub fn with_report<T>(f: impl FnOnce(&mut Report) -> T) -> Option<T> {
+    let session = current()?;
+    let mut report = session.report.lock().ok()?;
+    Some(f(&mut report))
+}
+
+/// `Command::new(program)`, run in the session's root when there is one.
+pub fn command(program: &str) -> Command {
+    let mut cmd = Command::new(program);
+    if let Some(root) = root() {
+        cmd.current_dir(root);
+    }
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_are_per_thread_and_restored() {
+        let session = open(Path::new(".")).unwrap();
+        let root = session.root.clone();
+        assert!(current().is_none());
+        run(Some(session), || {
+            assert_eq!(self::root(), Some(root.clone()));
+            with_report(|r| r.ai = true);
+            assert_eq!(with_report(|r| r.ai), Some(true));
+            std::thread::scope(|scope| {
+                assert_eq!(spawn(scope, self::root).join().unwrap(), Some(root.clone()));
+                assert_eq!(scope.spawn(self::root).join().unwrap(), None);
+            });
+            assert_eq!(command("git").get_current_dir(), Some(root.as_path()));
+        });
+        assert!(current().is_none());
+        assert!(with_report(|r| r.ai).is_none());
+    }
+}
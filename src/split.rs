@@ -0,0 +1,237 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::output::{self, say};
+use crate::{
+    CATEGORY_NAMES, Category, Cli, CommitOptions, FileChange, FileStatus, compose_message,
+    compute_stats, create_commit, git, git_output, git_with_input, interactive,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SplitBy {
+    /// One commit each for config, build, assets, migrations, code, tests, docs, CI and other
+    /// files
+    Category,
+    /// One commit per workspace member / package directory (falls back to top-level dir)
+    Package,
+}
+
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub label: String,
+    pub changes: Vec<FileChange>,
+}
+
+/// Files that mark a directory as its own package or workspace member.
+const MANIFESTS: [&str; 8] = [
+    "Cargo.toml",
+    "package.json",
+    "go.mod",
+    "pyproject.toml",
+    "setup.py",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+];
+
+const ROOT_LABEL: &str = "(root)";
+
+/// Config, build files, assets and migrations land first so code that depends on them
+/// never precedes them; docs come last.
+pub const CATEGORY_ORDER: [Category; 9] = [
+    Category::Config,
+    Category::Build,
+    Category::Assets,
+    Category::Migrations,
+    Category::Code,
+    Category::Tests,
+    Category::Docs,
+    Category::Ci,
+    Category::Other,
+];
+
+pub fn group_changes(changes: &[FileChange], by: SplitBy) -> Vec<Group> {
+    match by {
+        SplitBy::Category => CATEGORY_ORDER
+            .iter()
+            .filter_map(|category| {
+                let members: Vec<FileChange> = changes
+                    .iter()
+                    .filter(|c| c.category == *category)
+                    .cloned()
+                    .collect();
+                (!members.is_empty()).then(|| Group {
+                    label: CATEGORY_NAMES
+                        .get(category)
+                        .copied()
+                        .unwrap_or("other")
+                        .to_string(),
+                    changes: members,
+                })
+            })
+            .collect(),
+        SplitBy::Package => {
+            let mut by_package: BTreeMap<String, Vec<FileChange>> = BTreeMap::new();
+            for change in changes {
+                let package = package_of(&change.path, |dir| {
+                    MANIFESTS.iter().any(|m| Path::new(dir).join(m).is_file())
+                });
+                by_package.entry(package).or_default().push(change.clone());
+            }
+            // Root-level files usually glue packages together, so they go last.
+            let root = by_package.remove(ROOT_LABEL);
+            by_package
+                .into_iter()
+                .chain(root.map(|changes| (ROOT_LABEL.to_string(), changes)))
+                .map(|(label, changes)| Group { label, changes })
+                .collect()
+        }
+    }
+}
+
+/// The nearest ancestor directory of `path` that holds a manifest, or its top-level
+/// directory when only the repo root does.
+fn package_of(path: &str, has_manifest: impl Fn(&str) -> bool) -> String {
+    let mut dir = Path::new(path).parent();
+    while let Some(d) = dir {
+        let d_str = d.to_string_lossy();
+        if d_str.is_empty() {
+            break;
+        }
+        if has_manifest(&d_str) {
+            return d_str.into_owned();
+        }
+        dir = d.parent();
+    }
+
+    match path.split_once('/') {
+        Some((top, _)) => top.to_string(),
+        None => ROOT_LABEL.to_string(),
+    }
+}
+
+/// Commit each group on its own, staging only that group's paths so the message
+/// generator (heuristic or AI) sees just those changes. The index is restored to the
+/// full staged state afterwards, so a failure part-way never loses staged work. Returns
+/// false if the user quit at one of the confirmation prompts.
+pub fn commit_groups(cli: &Cli, groups: &[Group]) -> Result<bool> {
+    let full_tree = git_output(&["write-tree"])?.trim().to_string();
+    let result = commit_each(cli, groups, &full_tree);
+    git(&["read-tree", &full_tree])?;
+    result
+}
+
+fn commit_each(cli: &Cli, groups: &[Group], full_tree: &str) -> Result<bool> {
+    let opts = CommitOptions::from_cli(cli);
+    if cli.dry_run {
+        say!("DRY RUN ({} commits)", groups.len());
+    }
+
+    for group in groups {
+        git(&["read-tree", "HEAD"])?;
+        stage_from_tree(full_tree, &group_paths(&group.changes))
+            .with_context(|| format!("staging {} changes", group.label))?;
+
+        let stats = compute_stats(&group.changes);
+        let (subject, body) = compose_message(cli, &group.changes, &stats, None)?;
+        if cli.dry_run {
+            say!("\n[{}]\n{}", group.label, output::message(&subject, &body));
+            continue;
+        }
+        say!("\n[{}]", group.label);
+        let Some((subject, body)) =
+            interactive::confirm_message(cli, &group.changes, &stats, subject, body)?
+        else {
+            say!(
+                "Stopped before the {} commit; its changes and the rest stay staged.",
+                group.label
+            );
+            return Ok(false);
+        };
+        create_commit(&subject, &body, &opts)?;
+    }
+    Ok(true)
+}
+
+pub fn stage_from_tree(tree: &str, paths: &[String]) -> Result<()> {
+    let mut spec = String::new();
+    for path in paths {
+        spec.push_str(path);
+        spec.push('\0');
+    }
+    git_with_input(
+        &[
+            "--literal-pathspecs",
+            "reset",
+            "-q",
+            tree,
+            "--pathspec-from-file=-",
+            "--pathspec-file-nul",
+        ],
+        &spec,
+    )
+}
+
+pub fn group_paths(changes: &[FileChange]) -> Vec<String> {
+    let mut paths = Vec::new();
+    for change in changes {
+        if let FileStatus::Renamed { from, .. } = &change.status {
+            paths.push(from.clone());
+        }
+        paths.push(change.path.clone());
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(path: &str, category: Category) -> FileChange {
+        FileChange {
+            path: path.to_string(),
+            status: FileStatus::Modified,
+            added: 1,
+            deleted: 0,
+            category,
+        }
+    }
+
+    #[test]
+    fn category_groups_follow_commit_order() {
+        let changes = vec![
+            change("README.md", Category::Docs),
+            change("src/main.rs", Category::Code),
+            change("Cargo.toml", Category::Config),
+            change("src/lib.rs", Category::Code),
+        ];
+        let groups = group_changes(&changes, SplitBy::Category);
+        let labels: Vec<&str> = groups.iter().map(|g| g.label.as_str()).collect();
+        assert_eq!(labels, vec!["config", "code", "docs"]);
+        assert_eq!(groups[1].changes.len(), 2);
+    }
+
+    #[test]
+    fn package_of_prefers_nearest_manifest_dir() {
+        let manifests = |dir: &str| dir == "crates/core" || dir == "web";
+        assert_eq!(
+            package_of("crates/core/src/lib.rs", manifests),
+            "crates/core"
+        );
+        assert_eq!(package_of("web/src/app.ts", manifests), "web");
+        assert_eq!(package_of("docs/guide/intro.md", manifests), "docs");
+        assert_eq!(package_of("Cargo.lock", manifests), ROOT_LABEL);
+    }
+
+    #[test]
+    fn group_paths_include_rename_sources() {
+        let mut renamed = change("src/new.rs", Category::Code);
+        renamed.status = FileStatus::Renamed {
+            from: "src/old.rs".to_string(),
+            to: "src/new.rs".to_string(),
+        };
+        assert_eq!(group_paths(&[renamed]), vec!["src/old.rs", "src/new.rs"]);
+    }
+}
@@ -0,0 +1,165 @@
+//! `scommit squash-msg <base>..<head>`: one message for a series of commits about to be
+//! squashed, written from their combined diff with their messages as guidance, so a
+//! feature branch lands as a single commit that reads as one change.
+
+use anyhow::{Context, Result, bail};
+use std::collections::BTreeSet;
+
+use crate::reword::{self, CommitView};
+use crate::{
+    Cli, build_commit_message, collect_staged_changes, commit_message_text, compose_message,
+    compute_stats, git_output, output, save_message, secrets, staged_added_lines, summarize,
+};
+
+/// Each commit's body is cut here in the guidance; the diff carries the detail.
+const MAX_BODY_CHARS: usize = 400;
+
+struct Commit {
+    subject: String,
+    body: String,
+    author: String,
+}
+
+pub fn run(cli: &Cli, range: &str) -> Result<()> {
+    let range = summarize::normalize(range);
+    let Some((base, head)) = range
+        .split_once("..")
+        .filter(|(_, head)| !head.starts_with('.'))
+    else {
+        bail!("{range} isn't a <base>..<head> range");
+    };
+    let head = if head.is_empty() { "HEAD" } else { head };
+    let commits = log(&range)?;
+    if commits.is_empty() {
+        bail!("no commits in {range} to squash");
+    }
+    let fork = git_output(&["merge-base", base, head])
+        .with_context(|| format!("{base} and {head} have no common history"))?;
+    let tree = git_output(&["rev-parse", &format!("{head}^{{tree}}")])?;
+
+    let (subject, body) = {
+        let _view = CommitView::enter(fork.trim(), tree.trim())?;
+        let changes = collect_staged_changes()?;
+        if changes.is_empty() {
+            bail!("{range} changes nothing overall; there is no diff to describe");
+        }
+        let stats = compute_stats(&changes);
+        // As for a commit, the diff only goes to the AI provider past the secrets check.
+        match secrets::check(&staged_added_lines()?, cli.allow_secrets) {
+            Ok(()) => compose_message(cli, &changes, &stats, Some(&guidance(&commits)))?,
+            Err(e) => {
+                eprintln!(
+                    "{e:#}\nKeeping the diff from the AI provider; using the heuristic message."
+                );
+                build_commit_message(&changes, &stats)
+            }
+        }
+    };
+    let mut body = body;
+    if !output::inspect(|r| r.ai).unwrap_or(false) {
+        // The heuristic describes the diff only; keep the story the commits told.
+        body = format!("{}\n\n{}", body.trim_end(), squashed_list(&commits));
+    }
+    let body = reword::with_trailers(&body, &trailers(&commits));
+
+    match &cli.output {
+        Some(path) => save_message(path, &subject, &body),
+        None => {
+            print!("{}", commit_message_text(&subject, &body));
+            Ok(())
+        }
+    }
+}
+
+/// Oldest first, merges left out: they carry no story of their own.
+fn log(range: &str) -> Result<Vec<Commit>> {
+    let out = git_output(&[
+        "log",
+        "--no-merges",
+        "--reverse",
+        "--format=%s%x00%b%x00%an <%ae>%x1e",
+        range,
+    ])
+    .with_context(|| format!("reading the log for {range}"))?;
+    Ok(out
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(3, '\0');
+            Some(Commit {
+                subject: fields.next()?.trim().to_string(),
+                body: fields.next()?.trim().to_string(),
+                author: fields.next()?.trim().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// What the AI is told about the commits, so the message keeps their intent and not
+/// just what the diff shows.
+fn guidance(commits: &[Commit]) -> String {
+    let mut text = format!(
+        "These {} commits are being squashed into one; write a single message for the combined change, not a list of the commits. Their messages, oldest first:\n",
+        commits.len()
+    );
+    for commit in commits {
+        text.push_str(&format!("\n- {}\n", commit.subject));
+        let body: String = commit.body.chars().take(MAX_BODY_CHARS).collect();
+        for line in body.lines().filter(|l| !l.trim().is_empty()) {
+            text.push_str(&format!("  {line}\n"));
+        }
+    }
+    text
+}
+
+fn squashed_list(commits: &[Commit]) -> String {
+    let lines: Vec<String> = commits.iter().map(|c| format!("- {}", c.subject)).collect();
+    format!("Squashed commits:\n{}\n", lines.join("\n"))
+}
+
+/// Every trailer the commits carried, once, plus a `Co-authored-by:` for each author
+/// other than whoever makes the squashed commit.
+fn trailers(commits: &[Commit]) -> String {
+    let me = git_output(&["var", "GIT_AUTHOR_IDENT"]).unwrap_or_default();
+    let me = me.find('>').map_or("", |end| &me[..=end]);
+    let mut seen = BTreeSet::new();
+    let mut lines = Vec::new();
+    for commit in commits {
+        let message = format!("{}\n\n{}\n", commit.subject, commit.body);
+        let own = reword::trailers_of(&message).unwrap_or_default();
+        let co_author = (commit.author != me).then(|| format!("Co-authored-by: {}", commit.author));
+        for line in own.lines().map(str::to_string).chain(co_author) {
+            if !line.trim().is_empty() && seen.insert(line.clone()) {
+                lines.push(line);
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guidance_and_list_keep_the_commits_in_order() {
+        let commits = vec![
+            Commit {
+                subject: "feat: add parser".to_string(),
+                body: "Handles nested lists.\n\nSigned-off-by: Ada <ada@example.com>".to_string(),
+                author: "Ada <ada@example.com>".to_string(),
+            },
+            Commit {
+                subject: "fix typo".to_string(),
+                body: String::new(),
+                author: "Ada <ada@example.com>".to_string(),
+            },
+        ];
+        let text = guidance(&commits);
+        assert!(text.starts_with("These 2 commits are being squashed into one;"));
+        assert!(text.ends_with("\n- feat: add parser\n  Handles nested lists.\n  Signed-off-by: Ada <ada@example.com>\n\n- fix typo\n"));
+        assert_eq!(
+            squashed_list(&commits),
+            "Squashed commits:\n- feat: add parser\n- fix typo\n"
+        );
+    }
+}
@@ -0,0 +1,434 @@
+use anyhow::{Context, Result, bail};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::output::{self, say};
+use crate::split::stage_from_tree;
+use crate::timings;
+use crate::{Cli, git_output, git_path, git_with_input, pending_paths, prompt_line};
+
+/// git's empty tree, for resetting index entries on a branch with no commits yet.
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Directory names that hold installed dependencies or build output. Almost never meant
+/// to be committed, and usually thousands of files when they are.
+const DEPENDENCY_DIRS: &[&str] = &[
+    "node_modules",
+    "bower_components",
+    "target",
+    "dist",
+    "build",
+    ".venv",
+    "venv",
+    "__pycache__",
+    ".tox",
+    ".gradle",
+    ".next",
+    ".terraform",
+];
+
+/// What a run will stage: the paths that end up in the commit, and the untracked files
+/// that `git add -A` would have swept up but were left out.
+#[derive(Debug, Default)]
+pub struct StagePlan {
+    pub paths: Vec<String>,
+    excluded: Vec<String>,
+    /// --only/--exclude as pathspecs; empty when neither was given.
+    filter: Vec<String>,
+    /// `.scommitignore` entries as exclude pathspecs.
+    ignored: Vec<String>,
+}
+
+impl StagePlan {
+    /// Pathspecs for what `git add -A` may touch: the filter minus `.scommitignore`.
+    fn stage_pathspecs(&self) -> Vec<String> {
+        let mut specs = if self.filter.is_empty() {
+            vec![":/".to_string()]
+        } else {
+            self.filter.clone()
+        };
+        specs.extend(self.ignored.iter().cloned());
+        specs
+    }
+}
+
+/// Work out what to stage. New untracked files are listed for review first; without a
+/// terminal to ask on (or with --yes or --quiet) they are only added with
+/// --include-untracked.
+pub fn plan(cli: &Cli) -> Result<StagePlan> {
+    let filter = filter_pathspecs(&cli.only, &cli.exclude);
+    let staged = pending_paths(true, &filter)?;
+    if cli.no_stage {
+        return Ok(StagePlan {
+            paths: staged,
+            excluded: Vec::new(),
+            filter,
+            ignored: Vec::new(),
+        });
+    }
+    let mut plan = StagePlan {
+        paths: Vec::new(),
+        excluded: Vec::new(),
+        filter,
+        ignored: ignore_pathspecs(&read_ignore_file()?),
+    };
+    let specs = plan.stage_pathspecs();
+    let untracked = untracked_files(&specs)?;
+    check_dependency_dirs(&untracked)?;
+    plan.excluded = review_untracked(cli, &untracked)?;
+    // Ignored files that are staged already still go in; scommit just won't add them.
+    plan.paths = pending_paths(false, &specs)?;
+    plan.paths.retain(|path| !plan.excluded.contains(path));
+    for path in staged {
+        if !plan.paths.contains(&path) {
+            plan.paths.push(path);
+        }
+    }
+    Ok(plan)
+}
+
+/// Repo-level list of globs scommit never stages by itself, even for tracked files.
+const IGNORE_FILE: &str = ".scommitignore";
+
+fn read_ignore_file() -> Result<String> {
+    match fs::read_to_string(IGNORE_FILE) {
+        Ok(contents) => Ok(contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e).with_context(|| format!("reading {IGNORE_FILE}")),
+    }
+}
+
+/// `.scommitignore` lines as exclude pathspecs: one glob per line, matched from the repo
+/// root like --exclude; blank lines and `#` comments are skipped.
+fn ignore_pathspecs(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.trim_start_matches('/').trim_end_matches('/'))
+        .filter(|glob| !glob.is_empty())
+        .map(|glob| format!(":(top,exclude){glob}"))
+        .collect()
+}
+
+/// `--only`/`--exclude` globs as top-level pathspecs, so they mean the same thing from any
+/// subdirectory and match the way git matches them (`*` also crosses `/`).
+fn filter_pathspecs(only: &[String], exclude: &[String]) -> Vec<String> {
+    if only.is_empty() && exclude.is_empty() {
+        return Vec::new();
+    }
+    let mut specs: Vec<String> = if only.is_empty() {
+        vec![":/".to_string()]
+    } else {
+        only.iter().map(|glob| format!(":(top){glob}")).collect()
+    };
+    specs.extend(exclude.iter().map(|glob| format!(":(top,exclude){glob}")));
+    specs
+}
+
+/// `git add -A` within the --only/--exclude filter, minus `.scommitignore` and the
+/// untracked files the plan leaves out.
+pub fn apply(plan: &StagePlan) -> Result<()> {
+    let mut spec = String::new();
+    for filter in plan.stage_pathspecs() {
+        spec.push_str(&filter);
+        spec.push('\0');
+    }
+    for path in &plan.excluded {
+        spec.push_str(":(exclude,literal)");
+        spec.push_str(path);
+        spec.push('\0');
+    }
+    let _timer = timings::Timer::start(timings::Phase::Staging);
+    let _spinner = output::Spinner::start("Staging changes");
+    git_with_input(
+        &["add", "-A", "--pathspec-from-file=-", "--pathspec-file-nul"],
+        &spec,
+    )
+}
+
+/// What an index entry goes back to when a path is unstaged: HEAD, or on an unborn
+/// branch the empty tree (dropping the entry entirely).
+pub fn unstaged_base() -> &'static str {
+    match git_output(&["rev-parse", "--verify", "--quiet", "HEAD"]) {
+        Ok(_) => "HEAD",
+        Err(_) => EMPTY_TREE,
+    }
+}
+
+/// Staged paths outside --only/--exclude (or toggled off in the TUI), taken out of the
+/// index for the commit and put back afterwards so nothing the user staged is lost.
+pub struct SetAside {
+    tree: String,
+    paths: Vec<String>,
+}
+
+/// Unstage whatever is staged outside the plan's filter (e.g. from an earlier `git add`).
+pub fn set_aside(plan: &StagePlan) -> Result<SetAside> {
+    if plan.filter.is_empty() {
+        return Ok(SetAside::new(String::new(), Vec::new()));
+    }
+    let mut args = vec![
+        "diff",
+        "--cached",
+        "--name-only",
+        "-z",
+        "--no-renames",
+        "--",
+    ];
+    let staged = git_output(&args)?;
+    args.extend(plan.filter.iter().map(String::as_str));
+    let inside = git_output(&args)?;
+    let inside: Vec<&str> = inside.split('\0').collect();
+    let paths: Vec<String> = staged
+        .split('\0')
+        .filter(|p| !p.is_empty() && !inside.contains(p))
+        .map(str::to_string)
+        .collect();
+    if paths.is_empty() {
+        return Ok(SetAside::new(String::new(), paths));
+    }
+    let tree = git_output(&["write-tree"])?.trim().to_string();
+    stage_from_tree(unstaged_base(), &paths)?;
+    say!(
+        "Leaving {} staged file(s) outside --only/--exclude out of this commit.",
+        paths.len()
+    );
+    Ok(SetAside::new(tree, paths))
+}
+
+impl SetAside {
+    /// Paths already unstaged by the caller, to be restored from `tree`.
+    pub fn new(tree: String, paths: Vec<String>) -> SetAside {
+        SetAside { tree, paths }
+    }
+
+    /// Re-stage the set-aside paths as they were; a no-op once done.
+    pub fn restore(&mut self) -> Result<()> {
+        if self.paths.is_empty() {
+            return Ok(());
+        }
+        let paths = std::mem::take(&mut self.paths);
+        stage_from_tree(&self.tree, &paths)
+    }
+}
+
+/// Early returns (a declined prompt, a failed check) still give the staged work back.
+impl Drop for SetAside {
+    fn drop(&mut self) {
+        if let Err(err) = self.restore() {
+            eprintln!("Warning: could not re-stage files set aside for --only/--exclude: {err:#}");
+        }
+    }
+}
+
+/// A throwaway copy of the index for --print-only. Staging and every diff scommit reads
+/// go through it (via GIT_INDEX_FILE), so the message describes exactly what would be
+/// committed while the real index stays untouched.
+pub struct ScratchIndex {
+    path: PathBuf,
+}
+
+impl ScratchIndex {
+    /// Must run before any other thread starts: it changes the process environment.
+    pub fn enter() -> Result<ScratchIndex> {
+        // `--git-path index` already honors a GIT_INDEX_FILE set by a hook.
+        let index = git_path("index")?;
+        let path = git_path("scommit/scratch-index")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        if index.exists() {
+            fs::copy(&index, &path).with_context(|| format!("copying {}", index.display()))?;
+        } else if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        // SAFETY: called from the main thread before any spinner or TUI thread exists.
+        unsafe { env::set_var("GIT_INDEX_FILE", &path) };
+        Ok(ScratchIndex { path })
+    }
+}
+
+impl Drop for ScratchIndex {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path).ok();
+    }
+}
+
+fn untracked_files(filter: &[String]) -> Result<Vec<String>> {
+    let mut args = vec!["ls-files", "--others", "--exclude-standard", "-z", "--"];
+    args.extend(filter.iter().map(String::as_str));
+    let out = git_output(&args)?;
+    Ok(out
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Stop before sweeping an unignored `node_modules/` or `target/` into the commit.
+fn check_dependency_dirs(untracked: &[String]) -> Result<()> {
+    let dirs = dependency_dirs(untracked);
+    if dirs.is_empty() {
+        return Ok(());
+    }
+    let listing: Vec<String> = dirs
+        .iter()
+        .map(|(dir, count)| format!("{dir}/ ({count} files)"))
+        .collect();
+    let ignore: Vec<String> = dirs.keys().map(|dir| format!("/{dir}/")).collect();
+    bail!(
+        "refusing to stage untracked dependency or build directories:\n  {}\nAdd them to .gitignore:\n  {}\nor stage what you want yourself and run with --no-stage.",
+        listing.join("\n  "),
+        ignore.join("\n  ")
+    )
+}
+
+/// Untracked files grouped by the dependency/build directory they sit in, e.g.
+/// `web/node_modules` -> 1200.
+fn dependency_dirs(untracked: &[String]) -> BTreeMap<String, usize> {
+    let mut dirs = BTreeMap::new();
+    for path in untracked {
+        let Some((parent, _)) = path.rsplit_once('/') else {
+            continue;
+        };
+        let mut end = 0;
+        for part in parent.split('/') {
+            end += part.len();
+            if DEPENDENCY_DIRS.contains(&part) {
+                *dirs.entry(path[..end].to_string()).or_default() += 1;
+                break;
+            }
+            end += 1;
+        }
+    }
+    dirs
+}
+
+/// The untracked files to leave out of staging.
+fn review_untracked(cli: &Cli, untracked: &[String]) -> Result<Vec<String>> {
+    if untracked.is_empty() || cli.include_untracked {
+        return Ok(Vec::new());
+    }
+    if cli.yes || cli.quiet || output::ci() || !output::can_prompt() {
+        eprintln!(
+            "Leaving {} untracked file(s) unstaged (pass --include-untracked to add them):\n  {}",
+            untracked.len(),
+            untracked.join("\n  ")
+        );
+        return Ok(untracked.to_vec());
+    }
+
+    say!("Untracked files that would be added:");
+    for (i, path) in untracked.iter().enumerate() {
+        say!("  {:>3}  {path}", i + 1);
+    }
+    loop {
+        let answer = prompt_line(
+            "untracked",
+            "Stage them? [Enter] all, [n]one, or numbers to leave out (e.g. 2 4-6): ",
+        )?;
+        match deselected(&answer, untracked.len()) {
+            Some(skip) => {
+                return Ok(skip.into_iter().map(|i| untracked[i].clone()).collect());
+            }
+            None => say!(
+                "Please answer n or numbers between 1 and {}.",
+                untracked.len()
+            ),
+        }
+    }
+}
+
+/// Zero-based indices named by an answer like `2 4-6` (commas work too); `n` names all of
+/// them and an empty answer none. None if the answer doesn't parse or is out of range.
+fn deselected(answer: &str, count: usize) -> Option<Vec<usize>> {
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "" | "a" | "all" => return Some(Vec::new()),
+        "n" | "none" => return Some((0..count).collect()),
+        _ => {}
+    }
+    let mut skip = Vec::new();
+    for part in answer.split([' ', ',']).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((a, b)) => (a.parse::<usize>().ok()?, b.parse::<usize>().ok()?),
+            None => {
+                let n = part.parse::<usize>().ok()?;
+                (n, n)
+            }
+        };
+        if start == 0 || start > end || end > count {
+            return None;
+        }
+        skip.extend(start - 1..end);
+    }
+    skip.sort_unstable();
+    skip.dedup();
+    Some(skip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deselected_parses_numbers_and_ranges() {
+        assert_eq!(deselected("", 3), Some(vec![]));
+        assert_eq!(deselected("n", 3), Some(vec![0, 1, 2]));
+        assert_eq!(deselected("3 1", 3), Some(vec![0, 2]));
+        assert_eq!(deselected("2-4, 3", 5), Some(vec![1, 2, 3]));
+        assert_eq!(deselected("4", 3), None);
+        assert_eq!(deselected("0", 3), None);
+        assert_eq!(deselected("3-2", 3), None);
+        assert_eq!(deselected("x", 3), None);
+    }
+
+    #[test]
+    fn filter_pathspecs_anchor_globs_at_the_top() {
+        let strings = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(filter_pathspecs(&[], &[]).is_empty());
+        assert_eq!(
+            filter_pathspecs(&[], &strings(&["*.snap"])),
+            vec![":/", ":(top,exclude)*.snap"]
+        );
+        assert_eq!(
+            filter_pathspecs(&strings(&["src/**", "Cargo.toml"]), &strings(&["*.snap"])),
+            vec![":(top)src/**", ":(top)Cargo.toml", ":(top,exclude)*.snap"]
+        );
+    }
+
+    #[test]
+    fn ignore_pathspecs_skip_comments_and_anchor_at_the_root() {
+        let contents = "# local only\nscratch/\n\n/notes.md\n  *.fixture.json  \n";
+        assert_eq!(
+            ignore_pathspecs(contents),
+            vec![
+                ":(top,exclude)scratch",
+                ":(top,exclude)notes.md",
+                ":(top,exclude)*.fixture.json"
+            ]
+        );
+    }
+
+    #[test]
+    fn dependency_dirs_groups_files_under_the_first_matching_directory() {
+        let untracked: Vec<String> = [
+            "node_modules/a/index.js",
+            "node_modules/b/node_modules/c.js",
+            "web/dist/app.js",
+            "src/build.rs",
+            "docs/target.md",
+            "notes.txt",
+        ]
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+        let dirs: Vec<(String, usize)> = dependency_dirs(&untracked).into_iter().collect();
+        assert_eq!(
+            dirs,
+            vec![("node_modules".to_string(), 2), ("web/dist".to_string(), 1)]
+        );
+    }
+}
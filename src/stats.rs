@@ -0,0 +1,339 @@
+//! `scommit stats [<range>]`: what the history is made of. Commits and line churn are
+//! broken down by the same file categories a new commit is described with, by author
+//! and by week, month or year, along with how many of the messages scommit wrote.
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, Local, TimeZone};
+use clap::ValueEnum;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::output::{self, say};
+use crate::{CATEGORY_NAMES, categorize, diff_parse, git_output, history, summarize};
+
+/// Authors past this are folded into one "others" line.
+const MAX_AUTHORS: usize = 10;
+
+/// Only the most recent periods are charted.
+const MAX_PERIODS: usize = 24;
+
+const BAR_WIDTH: usize = 30;
+
+/// The heuristic body's footer, or one of its file lines (`- update src/a.rs (+3/-1)
+/// [code]`) when the footer was edited out. AI-written messages carry no such mark; they
+/// are only known from the local history (`scommit log`).
+static GENERATED_LINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?m)^Auto-generated by scommit\.|^- (add|update|remove|rename|copy|reformat|change type of) .+ \[(docs|tests|config|code|ci|build|assets|migrations|other)\]$",
+    )
+    .expect("valid regex")
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Period {
+    Week,
+    Month,
+    Year,
+}
+
+impl Period {
+    fn label(self, time: i64) -> String {
+        let Some(date) = Local.timestamp_opt(time, 0).single() else {
+            return "?".to_string();
+        };
+        match self {
+            Period::Week => {
+                let week = date.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            Period::Month => date.format("%Y-%m").to_string(),
+            Period::Year => date.format("%Y").to_string(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Period::Week => "week",
+            Period::Month => "month",
+            Period::Year => "year",
+        }
+    }
+}
+
+struct Commit {
+    sha: String,
+    author: String,
+    time: i64,
+    generated: bool,
+    files: Vec<(String, u64, u64)>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Tally {
+    commits: usize,
+    added: u64,
+    deleted: u64,
+}
+
+impl Tally {
+    fn add(&mut self, added: u64, deleted: u64) {
+        self.added += added;
+        self.deleted += deleted;
+    }
+
+    fn churn(&self) -> u64 {
+        self.added + self.deleted
+    }
+}
+
+pub fn run(range: Option<&str>, since: Option<&str>, by: Period) -> Result<()> {
+    let range = range.map_or_else(|| "HEAD".to_string(), summarize::normalize);
+    let commits = log(&range, since)?;
+    if commits.is_empty() {
+        say!("No commits in {range}.");
+        return Ok(());
+    }
+
+    let authors: BTreeSet<&str> = commits.iter().map(|c| c.author.as_str()).collect();
+    let first = commits.iter().map(|c| c.time).min().unwrap_or_default();
+    let last = commits.iter().map(|c| c.time).max().unwrap_or_default();
+    println!(
+        "{} commit(s) by {} author(s), {} to {} (merges left out)\n",
+        commits.len(),
+        authors.len(),
+        day(first),
+        day(last)
+    );
+
+    println!("{}", output::bold("By category"));
+    print_table(&by_category(&commits));
+    println!("\n{}", output::bold("By author"));
+    let mut by_author = by_author(&commits);
+    if by_author.len() > MAX_AUTHORS {
+        let rest = by_author.split_off(MAX_AUTHORS);
+        let mut others = Tally::default();
+        for (_, tally) in &rest {
+            others.commits += tally.commits;
+            others.add(tally.added, tally.deleted);
+        }
+        by_author.push((format!("{} others", rest.len()), others));
+    }
+    print_table(&by_author);
+
+    println!("\n{}", output::bold(&format!("Churn by {}", by.name())));
+    let periods = by_period(&commits, by);
+    if periods.len() > MAX_PERIODS {
+        println!(
+            "  {}",
+            output::dim(&format!(
+                "({} earlier {}s left out)",
+                periods.len() - MAX_PERIODS,
+                by.name()
+            ))
+        );
+    }
+    let periods = &periods[periods.len().saturating_sub(MAX_PERIODS)..];
+    let widest = periods.iter().map(|(_, t)| t.churn()).max().unwrap_or(0);
+    for (label, tally) in periods {
+        println!(
+            "  {label:<10} {:>5} commit(s) {:>8} {:>8}  {}",
+            tally.commits,
+            format!("+{}", tally.added),
+            format!("-{}", tally.deleted),
+            bar(tally.churn(), widest)
+        );
+    }
+
+    let recorded = history::committed();
+    let generated = commits
+        .iter()
+        .filter(|c| c.generated || recorded.contains(&c.sha))
+        .count();
+    println!(
+        "\n{} {generated} of {} commit(s) ({}%)",
+        output::bold("Written by scommit:"),
+        commits.len(),
+        generated * 100 / commits.len()
+    );
+    println!(
+        "  {}",
+        output::dim(
+            "the heuristic's messages, and AI-written ones made in this clone (`scommit log`)"
+        )
+    );
+    Ok(())
+}
+
+/// Merges are left out: their diffs repeat what the merged commits did.
+fn log(range: &str, since: Option<&str>) -> Result<Vec<Commit>> {
+    let since = since.map(|date| format!("--since={date}"));
+    let mut args = vec![
+        "log",
+        "--no-merges",
+        "--no-renames",
+        "--numstat",
+        "--format=%x1e%H%x00%an%x00%at%x00%B%x00",
+    ];
+    args.extend(since.as_deref());
+    args.push(range);
+    let out = git_output(&args).with_context(|| format!("reading the log for {range}"))?;
+    Ok(parse_log(&out))
+}
+
+fn parse_log(out: &str) -> Vec<Commit> {
+    out.split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.splitn(5, '\0');
+            let sha = fields.next()?.trim().to_string();
+            let author = fields.next()?.trim().to_string();
+            let time = fields.next()?.trim().parse().ok()?;
+            let message = fields.next()?;
+            let files = fields
+                .next()?
+                .lines()
+                .filter_map(|line| {
+                    let mut parts = line.splitn(3, '\t');
+                    // Binary files show `-` for both counts; they still count as touched.
+                    let added = parts.next()?.parse().unwrap_or(0);
+                    let deleted = parts.next()?.parse().unwrap_or(0);
+                    Some((
+                        diff_parse::unquote(parts.next()?).into_owned(),
+                        added,
+                        deleted,
+                    ))
+                })
+                .collect();
+            Some(Commit {
+                sha,
+                author,
+                time,
+                generated: GENERATED_LINE.is_match(message),
+                files,
+            })
+        })
+        .collect()
+}
+
+/// A commit counts once for every category it touches; lines count where they changed.
+fn by_category(commits: &[Commit]) -> Vec<(String, Tally)> {
+    let mut tallies: HashMap<&str, Tally> = HashMap::new();
+    for commit in commits {
+        let mut touched = BTreeSet::new();
+        for (path, added, deleted) in &commit.files {
+            let name = CATEGORY_NAMES
+                .get(&categorize(path))
+                .copied()
+                .unwrap_or("other");
+            tallies.entry(name).or_default().add(*added, *deleted);
+            touched.insert(name);
+        }
+        for name in touched {
+            tallies.entry(name).or_default().commits += 1;
+        }
+    }
+    sorted(
+        tallies
+            .into_iter()
+            .map(|(name, tally)| (name.to_string(), tally)),
+    )
+}
+
+fn by_author(commits: &[Commit]) -> Vec<(String, Tally)> {
+    let mut tallies: HashMap<&str, Tally> = HashMap::new();
+    for commit in commits {
+        let tally = tallies.entry(&commit.author).or_default();
+        tally.commits += 1;
+        for (_, added, deleted) in &commit.files {
+            tally.add(*added, *deleted);
+        }
+    }
+    sorted(
+        tallies
+            .into_iter()
+            .map(|(name, tally)| (name.to_string(), tally)),
+    )
+}
+
+/// Oldest period first; periods without commits are left out.
+fn by_period(commits: &[Commit], by: Period) -> Vec<(String, Tally)> {
+    let mut tallies: BTreeMap<String, Tally> = BTreeMap::new();
+    for commit in commits {
+        let tally = tallies.entry(by.label(commit.time)).or_default();
+        tally.commits += 1;
+        for (_, added, deleted) in &commit.files {
+            tally.add(*added, *deleted);
+        }
+    }
+    tallies.into_iter().collect()
+}
+
+/// Most commits first, then by name.
+fn sorted(rows: impl Iterator<Item = (String, Tally)>) -> Vec<(String, Tally)> {
+    let mut rows: Vec<_> = rows.collect();
+    rows.sort_by(|a, b| b.1.commits.cmp(&a.1.commits).then(a.0.cmp(&b.0)));
+    rows
+}
+
+fn print_table(rows: &[(String, Tally)]) {
+    for (name, tally) in rows {
+        println!(
+            "  {name:<28} {:>5} commit(s) {:>8} {:>8}",
+            tally.commits,
+            format!("+{}", tally.added),
+            format!("-{}", tally.deleted)
+        );
+    }
+}
+
+fn bar(value: u64, widest: u64) -> String {
+    if widest == 0 || value == 0 {
+        return String::new();
+    }
+    let width = (value * BAR_WIDTH as u64).div_ceil(widest);
+    "#".repeat(width as usize)
+}
+
+fn day(time: i64) -> String {
+    Local
+        .timestamp_opt(time, 0)
+        .single()
+        .map_or_else(|| "?".to_string(), |d| d.format("%Y-%m-%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_breaks_down_by_category_author_and_scommit() {
+        let out = "\x1eaaa\x00Ada\x001760000000\x00feat: add parser\n\n- add src/parse.rs (+40) [code]\n- update README.md (+2/-1) [docs]\n\x00\n\n40\t0\tsrc/parse.rs\n2\t1\tREADME.md\n\
+                   \x1ebbb\x00Grace\x001760100000\x00Fix typo in docs\n\x00\n\n1\t1\tREADME.md\n-\t-\tassets/logo.png\n";
+        let commits = parse_log(out);
+        assert_eq!(commits.len(), 2);
+        assert!(commits[0].generated && !commits[1].generated);
+        assert!(GENERATED_LINE.is_match("ci: re-run\n\nAuto-generated by scommit. Edit with --message if you want to override.\n"));
+        assert_eq!(commits[1].files[1], ("assets/logo.png".to_string(), 0, 0));
+
+        let categories = by_category(&commits);
+        assert_eq!(categories[0].0, "docs");
+        assert_eq!(
+            categories[0].1,
+            Tally {
+                commits: 2,
+                added: 3,
+                deleted: 2
+            }
+        );
+        assert_eq!(
+            categories
+                .iter()
+                .map(|(n, _)| n.as_str())
+                .collect::<Vec<_>>(),
+            ["docs", "assets", "code"]
+        );
+        assert_eq!(by_author(&commits)[0].1.churn(), 40 + 3);
+        assert_eq!(bar(5, 10), "#".repeat(15));
+        assert_eq!(bar(0, 10), "");
+    }
+}
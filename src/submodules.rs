@@ -0,0 +1,114 @@
+use anyhow::{Context, Result, bail};
+use std::env;
+use std::ffi::OsString;
+use std::process::Command;
+
+use crate::output::{self, say};
+use crate::{Cli, diff_parse, git_output};
+
+/// Submodules whose worktree has modified or untracked content, i.e. whose recorded
+/// gitlink would be stale if the superproject were committed now.
+pub fn dirty_submodules() -> Result<Vec<String>> {
+    let out = git_output(&["status", "--porcelain=v2", "--ignore-submodules=none"])?;
+    Ok(parse_dirty(&out))
+}
+
+fn parse_dirty(porcelain: &str) -> Vec<String> {
+    porcelain
+        .lines()
+        .filter_map(|line| {
+            // "1 XY <sub> <mH> <mI> <mW> <hH> <hI> <path>", sub = "S<c><m><u>" for submodules.
+            let fields: Vec<&str> = line.splitn(9, ' ').collect();
+            if fields.len() != 9 || fields[0] != "1" {
+                return None;
+            }
+            let sub = fields[2].as_bytes();
+            let dirty = sub.len() == 4 && sub[0] == b'S' && (sub[2] == b'M' || sub[3] == b'U');
+            dirty.then(|| diff_parse::unquote(fields[8]).into_owned())
+        })
+        .collect()
+}
+
+/// Commit dirty submodules first by running scommit (same arguments, less the message
+/// ones) inside each one, so the superproject records their new commits instead of stale
+/// pointers.
+pub fn commit_submodules(cli: &Cli) -> Result<()> {
+    let dirty = dirty_submodules()?;
+    if !cli.recurse_submodules {
+        for path in &dirty {
+            eprintln!(
+                "Warning: submodule {path} has uncommitted changes; its pointer will be stale (use --recurse-submodules)."
+            );
+        }
+        return Ok(());
+    }
+
+    let exe = env::current_exe().context("locating the scommit executable")?;
+    let args = submodule_args(env::args_os().skip(1));
+    for path in dirty {
+        say!("Entering submodule {path}");
+        let status = Command::new(&exe)
+            .args(&args)
+            .current_dir(&path)
+            .stdout(output::child_stdout())
+            .status()
+            .with_context(|| format!("running scommit in submodule {path}"))?;
+        if !status.success() {
+            bail!("scommit failed in submodule {path}; superproject left uncommitted");
+        }
+    }
+    Ok(())
+}
+
+/// The superproject's arguments without `-m/--message` and `--output`: each submodule
+/// gets a message of its own, and the saved message is the superproject's.
+fn submodule_args(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let mut kept = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("-m" | "--message" | "--output") => {
+                args.next();
+            }
+            Some(arg)
+                if arg.starts_with("--message=")
+                    || arg.starts_with("--output=")
+                    || (arg.starts_with("-m") && !arg.starts_with("--")) => {}
+            _ => kept.push(arg),
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dirty_picks_submodules_with_content_changes() {
+        let porcelain = "\
+1 .M S.M. 160000 160000 160000 aaa aaa libs/core
+1 .M SC.. 160000 160000 160000 aaa bbb libs/moved
+1 .M S..U 160000 160000 160000 aaa aaa vendor/ui kit
+1 .M N... 100644 100644 100644 ccc ccc src/main.rs
+? notes.txt
+";
+        assert_eq!(parse_dirty(porcelain), vec!["libs/core", "vendor/ui kit"]);
+
+        let args = [
+            "-m",
+            "fix: x",
+            "--no-push",
+            "--output=msg.txt",
+            "--message=y",
+            "-mz",
+            "--output",
+            "out/msg",
+            "-q",
+        ];
+        assert_eq!(
+            submodule_args(args.map(OsString::from)),
+            ["--no-push", "-q"].map(OsString::from)
+        );
+    }
+}
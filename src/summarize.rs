@@ -0,0 +1,180 @@
+//! `scommit summarize <range>`: a readable account of what a range of commits did, for
+//! standups, release-note drafts and PR descriptions. The AI provider writes the
+//! narrative when it's available; otherwise the commits are grouped by type.
+
+use anyhow::{Context, Result, bail};
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+
+use crate::output::say;
+use crate::{
+    CATEGORY_NAMES, Cli, ai_model, categorize, changelog, chat_completion, git_output, pr,
+};
+
+const SYSTEM_PROMPT: &str = "You summarize a range of git commits for teammates: a standup update, a release-notes draft or a PR description. Write two to four short paragraphs of plain prose (markdown allowed, no headings) saying what was done and why it matters, grouping related commits into themes instead of retelling them one by one. Mention breaking changes and anything risky first. Don't invent details the commits don't give. Reply with the summary only.";
+
+/// Bodies past this are cut; the subject and opening lines carry most of the meaning.
+const MAX_BODY_CHARS: usize = 500;
+
+/// `main..HEAD` as given; a single rev means everything since it (`main` → `main..HEAD`).
+pub fn normalize(range: &str) -> String {
+    if range.contains("..") {
+        range.to_string()
+    } else {
+        format!("{range}..HEAD")
+    }
+}
+
+/// What the range changed, measured from the merge base as `git diff a...b` does, so a
+/// branch range doesn't count what happened on the other side meanwhile.
+fn diff_range(range: &str) -> String {
+    match range.split_once("..") {
+        Some((from, to)) if !to.starts_with('.') => format!("{from}...{to}"),
+        _ => range.to_string(),
+    }
+}
+
+/// "12 commit(s) by 3 authors (2026-10-01 to 2026-10-14); 24 file(s) changed
+/// (+800/-210): code 15, tests 6, docs 3."
+fn headline(
+    commits: usize,
+    authors: &BTreeSet<String>,
+    dates: (&str, &str),
+    files: &[(String, u32, u32)],
+) -> String {
+    let by = match authors.len() {
+        1 => format!("by {}", authors.iter().next().map_or("", |a| a.as_str())),
+        n => format!("by {n} authors"),
+    };
+    let when = if dates.0 == dates.1 {
+        format!("on {}", dates.0)
+    } else {
+        format!("{} to {}", dates.0, dates.1)
+    };
+    let (added, deleted) = files.iter().fold((0, 0), |(a, d), (_, added, deleted)| {
+        (a + added, d + deleted)
+    });
+    let mut areas: HashMap<&str, usize> = HashMap::new();
+    for (path, _, _) in files {
+        let name = CATEGORY_NAMES
+            .get(&categorize(path))
+            .copied()
+            .unwrap_or("other");
+        *areas.entry(name).or_default() += 1;
+    }
+    let mut areas: Vec<(&str, usize)> = areas.into_iter().collect();
+    areas.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let areas: Vec<String> = areas
+        .iter()
+        .map(|(name, n)| format!("{name} {n}"))
+        .collect();
+    let mut line = format!(
+        "{commits} commit(s) {by} ({when}); {} file(s) changed (+{added}/-{deleted})",
+        files.len()
+    );
+    if !areas.is_empty() {
+        line.push_str(&format!(": {}", areas.join(", ")));
+    }
+    line + "."
+}
+
+/// (subject, body, author, date) per commit, oldest first.
+fn log(range: &str) -> Result<Vec<(String, String, String, String)>> {
+    let out = git_output(&[
+        "log",
+        "--no-merges",
+        "--reverse",
+        "--format=%s%x00%b%x00%an%x00%cs%x1e",
+        range,
+    ])
+    .with_context(|| format!("reading the log for {range}"))?;
+    Ok(out
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(4, '\0');
+            Some((
+                fields.next()?.trim().to_string(),
+                fields.next()?.trim().to_string(),
+                fields.next()?.to_string(),
+                fields.next()?.trim().to_string(),
+            ))
+        })
+        .collect())
+}
+
+fn narrate(
+    cli: &Cli,
+    headline: &str,
+    commits: &[(String, String, String, String)],
+) -> Result<Option<String>> {
+    let Some(model) = ai_model(cli) else {
+        return Ok(None);
+    };
+    let key = env::var("OPENAI_API_KEY")?;
+    let mut prompt = format!("{headline}\n\nCommits, oldest first:\n");
+    for (subject, body, _, _) in commits {
+        prompt.push_str(&format!("\n- {subject}\n"));
+        let body: String = body.chars().take(MAX_BODY_CHARS).collect();
+        for line in body.lines().filter(|l| !l.trim().is_empty()) {
+            prompt.push_str(&format!("  {line}\n"));
+        }
+    }
+    let reply = chat_completion(&key, &model, SYSTEM_PROMPT, &prompt, false, 700)?;
+    Ok(reply
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty()))
+}
+
+pub fn run(cli: &Cli, range: &str) -> Result<()> {
+    let range = normalize(range);
+    let commits = log(&range)?;
+    let (Some(first), Some(last)) = (commits.first(), commits.last()) else {
+        say!("No commits in {range}.");
+        return Ok(());
+    };
+    let authors: BTreeSet<String> = commits.iter().map(|c| c.2.clone()).collect();
+    let files = pr::numstat(&diff_range(&range))?;
+    let headline = headline(commits.len(), &authors, (&first.3, &last.3), &files);
+
+    let narrative = match narrate(cli, &headline, &commits) {
+        Ok(Some(text)) => text,
+        Ok(None) => changelog::groups(&changelog::entries(&range)?)
+            .trim()
+            .to_string(),
+        Err(e) => {
+            eprintln!("AI summary failed ({e}); listing the commits by type.");
+            changelog::groups(&changelog::entries(&range)?)
+                .trim()
+                .to_string()
+        }
+    };
+    if narrative.is_empty() {
+        bail!("nothing to summarize in {range}");
+    }
+    println!("{headline}\n\n{narrative}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_and_headline() {
+        assert_eq!(normalize("main"), "main..HEAD");
+        assert_eq!(normalize("v1.0..v1.1"), "v1.0..v1.1");
+        assert_eq!(diff_range("main..HEAD"), "main...HEAD");
+        assert_eq!(diff_range("main...feature"), "main...feature");
+
+        let authors = BTreeSet::from(["Ada".to_string(), "Grace".to_string()]);
+        let files = vec![
+            ("src/a.rs".to_string(), 40, 10),
+            ("src/b.rs".to_string(), 5, 0),
+            ("README.md".to_string(), 3, 1),
+        ];
+        assert_eq!(
+            headline(4, &authors, ("2026-10-01", "2026-10-14"), &files),
+            "4 commit(s) by 2 authors (2026-10-01 to 2026-10-14); 3 file(s) changed (+48/-11): code 2, docs 1."
+        );
+    }
+}
@@ -0,0 +1,473 @@
+//! The functions, types and classes a change adds, removes or modifies, found by parsing
+//! the file before and after with tree-sitter. They give the heuristic subject something
+//! better than file names ("refactor: extract `parse_header` in parser.rs") and the AI a
+//! list of what actually changed.
+
+use std::collections::HashMap;
+
+use crate::{Category, FileChange, FileStatus, git_output, short_name, whitespace};
+
+/// Files above this size are skipped; parsing them costs more than the names are worth.
+const MAX_FILE_BYTES: usize = 512 * 1024;
+
+/// At most this many files are parsed per run.
+const MAX_FILES: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    /// The definition's own text changed; `shrank` when it got shorter.
+    Modified {
+        shrank: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolChange {
+    pub path: String,
+    /// `fn`, `struct`, `class`, `method`, ...
+    pub kind: &'static str,
+    /// Qualified by its impl/class where there is one (`Parser::parse`).
+    pub name: String,
+    pub change: Change,
+}
+
+impl SymbolChange {
+    fn verb(&self) -> &'static str {
+        match self.change {
+            Change::Added => "add",
+            Change::Removed => "remove",
+            Change::Modified { .. } => "update",
+        }
+    }
+}
+
+/// A definition in one version of a file: what it is, and the text that belongs to it
+/// alone (nested definitions cut out, so a changed method doesn't also change its class).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Definition {
+    kind: &'static str,
+    name: String,
+    own_text: String,
+}
+
+/// Symbol-level changes for the staged files in a supported language, in file order.
+/// Anything that can't be read or parsed is skipped.
+pub fn changed(changes: &[FileChange]) -> Vec<SymbolChange> {
+    let mut found = Vec::new();
+    let formatting = whitespace::formatting_only(changes);
+    for change in changes.iter().take(MAX_FILES) {
+        // Generated and vendored files are recategorized as other, and reformatted ones
+        // only differ in layout; their definitions aren't what the commit is about.
+        if change.category == Category::Other || formatting.contains(&change.path) {
+            continue;
+        }
+        let (old_path, new_path) = match &change.status {
+            FileStatus::Added => (None, Some(&change.path)),
+            FileStatus::Deleted => (Some(&change.path), None),
+            FileStatus::Modified => (Some(&change.path), Some(&change.path)),
+            FileStatus::Renamed { from, .. } => (Some(from), Some(&change.path)),
+            // A copy's definitions all exist already; a type change isn't about code.
+            FileStatus::Copied { .. } | FileStatus::TypeChanged => continue,
+        };
+        let read = |spec: String| {
+            git_output(&["show", &spec])
+                .ok()
+                .filter(|text| text.len() <= MAX_FILE_BYTES)
+        };
+        let old = match old_path {
+            Some(path) => read(format!("HEAD:{path}")),
+            None => Some(String::new()),
+        };
+        let new = match new_path {
+            Some(path) => read(format!(":{path}")),
+            None => Some(String::new()),
+        };
+        let (Some(old), Some(new)) = (old, new) else {
+            continue;
+        };
+        let (Some(before), Some(after)) = (
+            parse::definitions(&change.path, &old),
+            parse::definitions(&change.path, &new),
+        ) else {
+            continue;
+        };
+        found.extend(diff(&change.path, &before, &after));
+    }
+    found
+}
+
+/// Compare two versions' definitions by kind and name.
+fn diff(path: &str, before: &[Definition], after: &[Definition]) -> Vec<SymbolChange> {
+    let key = |d: &Definition| (d.kind, d.name.clone());
+    let old: HashMap<_, &Definition> = before.iter().map(|d| (key(d), d)).collect();
+    let new: HashMap<_, &Definition> = after.iter().map(|d| (key(d), d)).collect();
+    let symbol = |d: &Definition, change| SymbolChange {
+        path: path.to_string(),
+        kind: d.kind,
+        name: d.name.clone(),
+        change,
+    };
+    let mut found = Vec::new();
+    for definition in after {
+        match old.get(&key(definition)) {
+            None => found.push(symbol(definition, Change::Added)),
+            Some(previous) if previous.own_text != definition.own_text => {
+                let shrank = definition.own_text.len() < previous.own_text.len();
+                found.push(symbol(definition, Change::Modified { shrank }));
+            }
+            Some(_) => {}
+        }
+    }
+    for definition in before {
+        if !new.contains_key(&key(definition)) {
+            found.push(symbol(definition, Change::Removed));
+        }
+    }
+    found.dedup_by(|a, b| a.kind == b.kind && a.name == b.name && a.change == b.change);
+    found
+}
+
+/// The subject's focus when the symbol changes are all in one file: "add `parse` in
+/// lib.rs", or "extract `parse_header` in parser.rs" for a new function carved out of
+/// ones that shrank. The bool says whether it is an extraction (a refactor).
+pub fn subject_focus(symbols: &[SymbolChange]) -> Option<(String, bool)> {
+    let first = symbols.first()?;
+    if symbols.iter().any(|s| s.path != first.path) {
+        return None;
+    }
+    let file = short_name(&first.path);
+    let added: Vec<&SymbolChange> = symbols
+        .iter()
+        .filter(|s| s.change == Change::Added)
+        .collect();
+    let extracted = added.len() == 1
+        && symbols.len() > 1
+        && symbols
+            .iter()
+            .filter(|s| s.change != Change::Added)
+            .all(|s| s.change == Change::Modified { shrank: true });
+    if extracted {
+        return Some((format!("extract `{}` in {file}", added[0].name), true));
+    }
+    let verb = first.verb();
+    let names: Vec<String> = symbols
+        .iter()
+        .filter(|s| s.verb() == verb)
+        .map(|s| format!("`{}`", s.name))
+        .collect();
+    let names = match names.len() {
+        1 | 2 => names.join(" & "),
+        n => format!("{} & {} more", names[0], n - 1),
+    };
+    Some((format!("{verb} {names} in {file}"), false))
+}
+
+/// One line per symbol for the AI prompt: "- add fn parse_header (src/parser.rs)".
+pub fn prompt_lines(symbols: &[SymbolChange]) -> String {
+    symbols
+        .iter()
+        .map(|s| format!("- {} {} {} ({})", s.verb(), s.kind, s.name, s.path))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(feature = "symbols")]
+mod parse {
+    use std::path::Path;
+    use tree_sitter::{Language, Node, Parser, Query, QueryCursor, StreamingIterator};
+
+    use super::Definition;
+
+    const RUST: &str = r#"
+        (function_item name: (identifier) @name) @fn
+        (function_signature_item name: (identifier) @name) @fn
+        (struct_item name: (type_identifier) @name) @struct
+        (enum_item name: (type_identifier) @name) @enum
+        (union_item name: (type_identifier) @name) @union
+        (trait_item name: (type_identifier) @name) @trait
+        (type_item name: (type_identifier) @name) @type
+        (const_item name: (identifier) @name) @const
+        (static_item name: (identifier) @name) @static
+        (macro_definition name: (identifier) @name) @macro
+    "#;
+
+    const PYTHON: &str = r#"
+        (function_definition name: (identifier) @name) @def
+        (class_definition name: (identifier) @name) @class
+    "#;
+
+    const JAVASCRIPT: &str = r#"
+        (function_declaration name: (identifier) @name) @function
+        (generator_function_declaration name: (identifier) @name) @function
+        (class_declaration name: (identifier) @name) @class
+        (method_definition name: (property_identifier) @name) @method
+        (variable_declarator
+            name: (identifier) @name
+            value: [(arrow_function) (function_expression)]) @function
+    "#;
+
+    const TYPESCRIPT: &str = r#"
+        (function_declaration name: (identifier) @name) @function
+        (generator_function_declaration name: (identifier) @name) @function
+        (class_declaration name: (type_identifier) @name) @class
+        (abstract_class_declaration name: (type_identifier) @name) @class
+        (method_definition name: (property_identifier) @name) @method
+        (interface_declaration name: (type_identifier) @name) @interface
+        (type_alias_declaration name: (type_identifier) @name) @type
+        (enum_declaration name: (identifier) @name) @enum
+        (variable_declarator
+            name: (identifier) @name
+            value: [(arrow_function) (function_expression)]) @function
+    "#;
+
+    const GO: &str = r#"
+        (function_declaration name: (identifier) @name) @func
+        (method_declaration name: (field_identifier) @name) @method
+        (type_spec name: (type_identifier) @name) @type
+    "#;
+
+    fn language_of(path: &str) -> Option<&'static str> {
+        let ext = Path::new(path).extension()?.to_str()?;
+        Some(match ext {
+            "rs" => "rust",
+            "py" | "pyi" => "python",
+            "js" | "jsx" | "mjs" | "cjs" => "javascript",
+            "ts" | "mts" | "cts" => "typescript",
+            "tsx" => "tsx",
+            "go" => "go",
+            _ => return None,
+        })
+    }
+
+    fn grammar(language: &str) -> Option<(Language, &'static str)> {
+        Some(match language {
+            "rust" => (tree_sitter_rust::LANGUAGE.into(), RUST),
+            "python" => (tree_sitter_python::LANGUAGE.into(), PYTHON),
+            "javascript" => (tree_sitter_javascript::LANGUAGE.into(), JAVASCRIPT),
+            "typescript" => (
+                tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+                TYPESCRIPT,
+            ),
+            "tsx" => (tree_sitter_typescript::LANGUAGE_TSX.into(), TYPESCRIPT),
+            "go" => (tree_sitter_go::LANGUAGE.into(), GO),
+            _ => return None,
+        })
+    }
+
+    /// The definitions in `text`, or None for an unsupported language or a failed parse.
+    pub(super) fn definitions(path: &str, text: &str) -> Option<Vec<Definition>> {
+        let (language, source) = grammar(language_of(path)?)?;
+        let query = Query::new(&language, source).ok()?;
+        let mut parser = Parser::new();
+        parser.set_language(&language).ok()?;
+        let tree = parser.parse(text, None)?;
+
+        let name_index = query.capture_index_for_name("name")?;
+        let mut found: Vec<(Node, &'static str, String)> = Vec::new();
+        let mut cursor = QueryCursor::new();
+        let mut matches = cursor.matches(&query, tree.root_node(), text.as_bytes());
+        while let Some(m) = matches.next() {
+            let mut name = None;
+            let mut definition = None;
+            for capture in m.captures {
+                if capture.index == name_index {
+                    name = capture.node.utf8_text(text.as_bytes()).ok();
+                } else {
+                    definition = Some((capture.node, capture.index));
+                }
+            }
+            let (Some(name), Some((node, index))) = (name, definition) else {
+                continue;
+            };
+            let kind = kind_name(query.capture_names()[index as usize]);
+            let name = match owner(node, text) {
+                Some(owner) => format!("{owner}{name}"),
+                None => name.to_string(),
+            };
+            found.push((node, kind, name));
+        }
+
+        let ranges: Vec<(usize, usize)> = found
+            .iter()
+            .map(|(node, _, _)| (node.start_byte(), node.end_byte()))
+            .collect();
+        Some(
+            found
+                .into_iter()
+                .map(|(node, kind, name)| Definition {
+                    kind: if kind == "def" && owner(node, text).is_some() {
+                        "method"
+                    } else {
+                        kind
+                    },
+                    name,
+                    own_text: own_text(text, (node.start_byte(), node.end_byte()), &ranges),
+                })
+                .collect(),
+        )
+    }
+
+    fn kind_name(capture: &str) -> &'static str {
+        match capture {
+            "fn" => "fn",
+            "struct" => "struct",
+            "enum" => "enum",
+            "union" => "union",
+            "trait" => "trait",
+            "type" => "type",
+            "const" => "const",
+            "static" => "static",
+            "macro" => "macro",
+            "def" => "def",
+            "class" => "class",
+            "function" => "function",
+            "method" => "method",
+            "interface" => "interface",
+            "func" => "func",
+            _ => "symbol",
+        }
+    }
+
+    /// `Type::` for Rust impl and trait items, `Class.` for methods in Python, JS and TS.
+    fn owner(node: Node, text: &str) -> Option<String> {
+        let mut parent = node.parent();
+        while let Some(p) = parent {
+            let field = match p.kind() {
+                "impl_item" => p.child_by_field_name("type"),
+                "trait_item"
+                | "class_definition"
+                | "class_declaration"
+                | "abstract_class_declaration" => p.child_by_field_name("name"),
+                _ => None,
+            };
+            if let Some(field) = field {
+                let owner = field.utf8_text(text.as_bytes()).ok()?;
+                let separator = if p.kind().ends_with("_item") {
+                    "::"
+                } else {
+                    "."
+                };
+                return Some(format!("{owner}{separator}"));
+            }
+            parent = p.parent();
+        }
+        None
+    }
+
+    /// The text of `range` with every other definition nested inside it removed.
+    fn own_text(text: &str, range: (usize, usize), all: &[(usize, usize)]) -> String {
+        let mut nested: Vec<(usize, usize)> = all
+            .iter()
+            .copied()
+            .filter(|&(start, end)| start >= range.0 && end <= range.1 && (start, end) != range)
+            .collect();
+        nested.sort();
+        let mut own = String::new();
+        let mut at = range.0;
+        for (start, end) in nested {
+            if start >= at {
+                own.push_str(&text[at..start]);
+                at = end;
+            }
+        }
+        own.push_str(&text[at..range.1]);
+        own
+    }
+}
+
+#[cfg(not(feature = "symbols"))]
+mod parse {
+    use super::Definition;
+
+    /// Built without the `symbols` feature: no definitions, so subjects use file names.
+    pub(super) fn definitions(_path: &str, _text: &str) -> Option<Vec<Definition>> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str, change: Change) -> SymbolChange {
+        SymbolChange {
+            path: "src/parser.rs".to_string(),
+            kind: "fn",
+            name: name.to_string(),
+            change,
+        }
+    }
+
+    #[test]
+    fn subject_focus_names_symbols_and_spots_extractions() {
+        assert_eq!(
+            subject_focus(&[symbol("parse", Change::Added)]),
+            Some(("add `parse` in parser.rs".to_string(), false))
+        );
+        assert_eq!(
+            subject_focus(&[
+                symbol("parse_header", Change::Added),
+                symbol("parse", Change::Modified { shrank: true }),
+            ]),
+            Some(("extract `parse_header` in parser.rs".to_string(), true))
+        );
+        let mut elsewhere = symbol("run", Change::Removed);
+        elsewhere.path = "src/main.rs".to_string();
+        assert_eq!(
+            subject_focus(&[symbol("parse", Change::Added), elsewhere]),
+            None
+        );
+    }
+
+    #[cfg(feature = "symbols")]
+    #[test]
+    fn changed_definitions_are_found_per_symbol() {
+        let before = "struct Parser;\n\
+                      impl Parser {\n    fn parse(&self) -> u32 { let a = 1; a + 1 }\n}\n\
+                      fn gone() {}\n";
+        let after = "struct Parser;\n\
+                     impl Parser {\n    fn parse(&self) -> u32 { header() + 1 }\n}\n\
+                     fn header() -> u32 { 1 }\n";
+        let before = parse::definitions("src/parser.rs", before).unwrap();
+        let after = parse::definitions("src/parser.rs", after).unwrap();
+        let found: Vec<(String, Change)> = diff("src/parser.rs", &before, &after)
+            .into_iter()
+            .map(|s| (s.name, s.change))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "Parser::parse".to_string(),
+                    Change::Modified { shrank: true }
+                ),
+                ("header".to_string(), Change::Added),
+                ("gone".to_string(), Change::Removed),
+            ]
+        );
+
+        let python = "class Cart:\n    def total(self):\n        return 1\n";
+        let names: Vec<(&str, String)> = parse::definitions("cart.py", python)
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.kind, d.name))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("class", "Cart".to_string()),
+                ("method", "Cart.total".to_string())
+            ]
+        );
+    }
+
+    #[cfg(feature = "symbols")]
+    #[test]
+    fn every_grammar_query_compiles() {
+        for path in ["a.rs", "a.py", "a.js", "a.ts", "a.tsx", "a.go"] {
+            assert!(parse::definitions(path, "").is_some(), "{path}");
+        }
+    }
+}
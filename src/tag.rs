@@ -0,0 +1,252 @@
+use anyhow::{Context, Result, bail};
+
+use std::time::Instant;
+
+use crate::output::{self, say};
+use crate::session;
+use crate::timings;
+use crate::{Cli, ai_model, chat_completion, git_output, git_push, git_with_input, log_command};
+
+/// Commits considered when the repository has no earlier tag.
+const UNTAGGED_DEPTH: &str = "50";
+
+/// Most recent tag reachable from HEAD.
+pub fn last_tag() -> Option<String> {
+    git_output(&["describe", "--tags", "--abbrev=0"])
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// `(subject, body)` of every commit since `since` (or the recent history without one).
+fn commits_since(since: Option<&str>) -> Result<Vec<(String, String)>> {
+    let range = since.map(|tag| format!("{tag}..HEAD"));
+    let mut args = vec!["log", "--no-merges", "--format=%s%x00%b%x1e"];
+    match &range {
+        Some(range) => args.push(range),
+        None => args.extend(["-n", UNTAGGED_DEPTH]),
+    }
+    // An unborn branch has no history yet; git log fails there.
+    let out = git_output(&args).unwrap_or_default();
+    Ok(out
+        .split('\x1e')
+        .filter_map(|record| {
+            let (subject, body) = record.trim_start_matches('\n').split_once('\0')?;
+            Some((subject.trim().to_string(), body.trim().to_string()))
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bump {
+    Patch,
+    Minor,
+    Major,
+}
+
+/// Conventional-commit bump: `!`/BREAKING CHANGE is major, `feat` minor, else patch.
+fn bump_for(commits: &[(String, String)]) -> Bump {
+    let mut bump = Bump::Patch;
+    for (subject, body) in commits {
+        let Some((kind, _)) = subject.split_once(':') else {
+            continue;
+        };
+        if kind.ends_with('!') || body.contains("BREAKING CHANGE") {
+            return Bump::Major;
+        }
+        let kind = kind.split('(').next().unwrap_or(kind);
+        if kind == "feat" {
+            bump = Bump::Minor;
+        }
+    }
+    bump
+}
+
+/// Next version after `last` (keeping its prefix, e.g. `v`). While the major version is
+/// 0, breaking changes bump the minor version, as semver allows for initial development.
+fn next_version(last: Option<&str>, bump: Bump) -> Option<String> {
+    let Some(last) = last else {
+        return Some("v0.1.0".to_string());
+    };
+    let start = last.find(|c: char| c.is_ascii_digit())?;
+    let (prefix, version) = last.split_at(start);
+    let core = version.split(['-', '+']).next().unwrap_or(version);
+    let parts: Vec<u64> = core
+        .split('.')
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .ok()?;
+    let [major, minor, patch] = parts[..] else {
+        return None;
+    };
+    let (major, minor, patch) = match bump {
+        Bump::Major if major > 0 => (major + 1, 0, 0),
+        Bump::Major | Bump::Minor => (major, minor + 1, 0),
+        Bump::Patch => (major, minor, patch + 1),
+    };
+    Some(format!("{prefix}{major}.{minor}.{patch}"))
+}
+
+/// Semver suggestion for the next tag, counting `pending` as if it were already committed.
+pub fn suggest_version(pending: Option<&str>) -> Result<String> {
+    let last = last_tag();
+    let mut commits = commits_since(last.as_deref())?;
+    if let Some(subject) = pending {
+        commits.push((subject.to_string(), String::new()));
+    }
+    let Some(next) = next_version(last.as_deref(), bump_for(&commits)) else {
+        bail!(
+            "can't derive a semver version from tag {}; pass --tag <name> instead of auto",
+            last.unwrap_or_default()
+        );
+    };
+    Ok(next)
+}
+
+/// Reject a bad or already-existing explicit tag name before anything is committed.
+pub fn validate(spec: &str) -> Result<()> {
+    if spec == "auto" {
+        return Ok(());
+    }
+    let mut cmd = session::command("git");
+    cmd.args(["check-ref-format", &format!("refs/tags/{spec}")]);
+    let started = Instant::now();
+    let status = cmd.status().context("running git check-ref-format")?;
+    log_command(&cmd, Some(status), started);
+    let valid = status.success();
+    if !valid {
+        bail!("{spec:?} is not a valid tag name");
+    }
+    if tag_exists(spec) {
+        bail!("tag {spec} already exists");
+    }
+    Ok(())
+}
+
+pub fn tag_exists(name: &str) -> bool {
+    git_output(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("refs/tags/{name}"),
+    ])
+    .map(|out| !out.trim().is_empty())
+    .unwrap_or(false)
+}
+
+/// Create the annotated tag on HEAD (resolving `auto`) and return its name.
+pub fn create(cli: &Cli, spec: &str) -> Result<String> {
+    let name = match spec {
+        "auto" => suggest_version(None)?,
+        name => name.to_string(),
+    };
+    if tag_exists(&name) {
+        bail!("tag {name} already exists");
+    }
+    let last = last_tag();
+    let commits = commits_since(last.as_deref())?;
+    let message = tag_message(cli, &name, last.as_deref(), &commits);
+    git_with_input(&["tag", "-a", &name, "-F", "-"], &message)?;
+    say!("Tagged {name}");
+    Ok(name)
+}
+
+/// Push the tag on its own after the branch, so it lands next to the commit it names.
+pub fn push(remote: &str, name: &str) -> Result<()> {
+    let refspec = format!("refs/tags/{name}");
+    let _timer = timings::Timer::start(timings::Phase::Push);
+    let mut args = vec!["push".to_string(), remote.to_string(), refspec];
+    if output::quiet() {
+        args.push("--quiet".to_string());
+    }
+    git_push(&args)
+}
+
+fn tag_message(cli: &Cli, name: &str, last: Option<&str>, commits: &[(String, String)]) -> String {
+    if let Some(model) = ai_model(cli) {
+        match ai_tag_message(name, last, commits, &model) {
+            Ok(Some(message)) => return message,
+            Ok(None) => {}
+            Err(e) => eprintln!("AI tag message failed ({e}); falling back to commit list."),
+        }
+    }
+    heuristic_message(name, commits)
+}
+
+fn heuristic_message(name: &str, commits: &[(String, String)]) -> String {
+    let mut message = format!("Release {name}\n");
+    if !commits.is_empty() {
+        message.push('\n');
+        for (subject, _) in commits {
+            message.push_str(&format!("- {subject}\n"));
+        }
+    }
+    message
+}
+
+fn ai_tag_message(
+    name: &str,
+    last: Option<&str>,
+    commits: &[(String, String)],
+    model: &str,
+) -> Result<Option<String>> {
+    let Ok(key) = std::env::var("OPENAI_API_KEY") else {
+        return Ok(None);
+    };
+    let log: Vec<String> = commits
+        .iter()
+        .take(80)
+        .map(|(subject, body)| {
+            if body.is_empty() {
+                format!("- {subject}")
+            } else {
+                format!("- {subject}\n  {}", body.replace('\n', "\n  "))
+            }
+        })
+        .collect();
+    let prompt = format!(
+        "Tag: {name}\nPrevious tag: {}\nCommits since then:\n{}",
+        last.unwrap_or("(none)"),
+        log.join("\n")
+    );
+    let system = "You write annotated git tag messages. Reply with plain text only: a first line summarizing the release (<=72 chars), a blank line, then 3-8 bullets starting with '- ' covering user-visible changes, most important first. Mention breaking changes explicitly. No markdown headings or code fences.";
+    let message = chat_completion(&key, model, system, &prompt, false, 400)?;
+    Ok(message
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .map(|m| m + "\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commits(subjects: &[&str]) -> Vec<(String, String)> {
+        subjects
+            .iter()
+            .map(|s| (s.to_string(), String::new()))
+            .collect()
+    }
+
+    #[test]
+    fn next_version_follows_conventional_commits() {
+        let fix = bump_for(&commits(&["fix: typo", "docs: readme"]));
+        let feat = bump_for(&commits(&["fix: typo", "feat(cli): add --tag"]));
+        let breaking = bump_for(&commits(&["feat(api)!: drop v1"]));
+        assert_eq!(next_version(Some("v1.2.3"), fix).as_deref(), Some("v1.2.4"));
+        assert_eq!(
+            next_version(Some("v1.2.3"), feat).as_deref(),
+            Some("v1.3.0")
+        );
+        assert_eq!(
+            next_version(Some("1.2.3-rc.1"), breaking).as_deref(),
+            Some("2.0.0")
+        );
+        assert_eq!(
+            next_version(Some("v0.4.1"), breaking).as_deref(),
+            Some("v0.5.0")
+        );
+        assert_eq!(next_version(None, fix).as_deref(), Some("v0.1.0"));
+        assert_eq!(next_version(Some("nightly"), fix), None);
+    }
+}
@@ -0,0 +1,411 @@
+//! Message templates: named subject/body formats and AI instructions, so a repo can
+//! switch between conventional commits, a ticket style or plain prose with one flag
+//! (`--template NAME`) or one setting (`[message] template`). Built-in templates can be
+//! overridden by `[templates.NAME]` in either config file.
+
+use anyhow::{Context, Result, bail};
+use clap::{Args, Subcommand};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::{self, Config, Template};
+use crate::output::say;
+use crate::{Cli, current_branch, launch_editor, repo_root};
+
+/// `scommit template ...`: manage the named templates in the config.
+#[derive(Subcommand, Debug)]
+pub enum TemplateAction {
+    /// List the built-in and configured templates; `*` marks the one in use
+    List,
+    /// Add a named template to the user config (or the repo's with --repo)
+    Add {
+        name: String,
+        #[command(flatten)]
+        fields: Fields,
+        /// Write to the repo's .scommit.toml instead of the user config
+        #[arg(long)]
+        repo: bool,
+    },
+    /// Change a template's formats; with none given, open its config file in the editor
+    Edit {
+        name: String,
+        #[command(flatten)]
+        fields: Fields,
+        /// Edit the repo's .scommit.toml instead of the user config
+        #[arg(long)]
+        repo: bool,
+    },
+    /// Make a template the default for messages (`[message] template`)
+    Use {
+        name: String,
+        /// Set it in the repo's .scommit.toml instead of the user config
+        #[arg(long)]
+        repo: bool,
+    },
+}
+
+#[derive(Args, Debug, Default)]
+pub struct Fields {
+    /// Subject format, e.g. "{ticket}: {Description}"
+    #[arg(long, value_name = "FORMAT")]
+    subject: Option<String>,
+    /// Body format, e.g. "{body}\n\nRefs: {ticket}"
+    #[arg(long, value_name = "FORMAT")]
+    body: Option<String>,
+    /// Extra instructions for the AI provider
+    #[arg(long, value_name = "TEXT")]
+    prompt: Option<String>,
+}
+
+impl Fields {
+    fn is_empty(&self) -> bool {
+        self.subject.is_none() && self.body.is_none() && self.prompt.is_none()
+    }
+}
+
+/// Tickets as most trackers spell them (`PROJ-123`), looked for in the branch name.
+static TICKET: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b[A-Z][A-Z0-9]+-[0-9]+\b").unwrap());
+
+fn builtin() -> BTreeMap<String, Template> {
+    let mut templates = BTreeMap::new();
+    // What scommit writes without a template.
+    templates.insert("conventional".to_string(), Template::default());
+    templates.insert(
+        "plain".to_string(),
+        Template {
+            subject: Some("{Description}".to_string()),
+            body: None,
+            prompt: Some("Write the subject as a plain imperative sentence without a type prefix, e.g. \"Add a parser for nested lists\".".to_string()),
+        },
+    );
+    templates.insert(
+        "ticket".to_string(),
+        Template {
+            subject: Some("{ticket}: {Description}".to_string()),
+            body: Some("{body}\n\nRefs: {ticket}".to_string()),
+            prompt: None,
+        },
+    );
+    templates
+}
+
+/// Built-in templates, with the configured ones added or replacing them by name.
+fn all(config: &Config) -> BTreeMap<String, Template> {
+    let mut templates = builtin();
+    templates.extend(config.templates.clone());
+    templates
+}
+
+/// The template this run uses: --template, else `[message] template`, else none.
+pub fn active(cli: &Cli) -> Result<Option<Template>> {
+    let config = config::get();
+    let Some(name) = cli.template.as_ref().or(config.message.template.as_ref()) else {
+        return Ok(None);
+    };
+    let templates = all(&config);
+    match templates.get(name) {
+        Some(template) => Ok(Some(template.clone())),
+        None => bail!(
+            "there is no message template named {name}; available: {}",
+            templates.keys().cloned().collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+/// The author's hint with the template's instructions in front, for the AI prompt.
+pub fn hint(template: Option<&Template>, hint: Option<&str>) -> Option<String> {
+    let style = template
+        .and_then(|t| t.prompt.as_deref())
+        .map(|prompt| format!("Follow this house style: {}", prompt.trim()));
+    match (style, hint) {
+        (Some(style), Some(hint)) => Some(format!("{style}\n{hint}")),
+        (Some(style), None) => Some(style),
+        (None, hint) => hint.map(str::to_string),
+    }
+}
+
+/// Shape a generated message with the template's formats.
+pub fn apply(template: &Template, subject: &str, body: &str) -> (String, String) {
+    let branch = current_branch().ok().flatten().unwrap_or_default();
+    let ticket = TICKET
+        .find(&branch)
+        .map(|m| m.as_str().to_string())
+        .unwrap_or_default();
+    render(template, subject, body, &branch, &ticket)
+}
+
+fn render(
+    template: &Template,
+    subject: &str,
+    body: &str,
+    branch: &str,
+    ticket: &str,
+) -> (String, String) {
+    let (kind, scope, description) = split_subject(subject);
+    let mut capitalized = description.to_string();
+    if let Some(first) = capitalized.get(..1) {
+        capitalized.replace_range(..1, &first.to_uppercase());
+    }
+    let values = [
+        ("{subject}", subject),
+        ("{type}", kind),
+        ("{scope}", scope),
+        ("{description}", description),
+        ("{Description}", capitalized.as_str()),
+        ("{ticket}", ticket),
+        ("{branch}", branch),
+    ];
+    let fill = |format: &str| {
+        values
+            .iter()
+            .fold(format.to_string(), |text, (key, value)| {
+                text.replace(key, value)
+            })
+    };
+
+    let subject = match &template.subject {
+        Some(format) => tidy(&fill(format)),
+        None => subject.to_string(),
+    };
+    let body = match &template.body {
+        Some(format) => {
+            // A line whose placeholders came out empty ("Refs: " without a ticket) goes.
+            let lines: Vec<String> = format
+                .lines()
+                .filter(|line| {
+                    !values
+                        .iter()
+                        .any(|(key, value)| value.is_empty() && line.contains(key))
+                })
+                .map(|line| line.replace("{body}", body.trim_end()))
+                .map(|line| fill(&line))
+                .collect();
+            format!("{}\n", lines.join("\n").trim())
+        }
+        None => body.to_string(),
+    };
+    (subject, body)
+}
+
+/// `type(scope)!: description` into its parts; other subjects are all description.
+fn split_subject(subject: &str) -> (&str, &str, &str) {
+    if let Some((prefix, description)) = subject.split_once(": ") {
+        let prefix = prefix.trim_end_matches('!');
+        let (kind, scope) = match prefix.split_once('(') {
+            Some((kind, scope)) => (kind, scope.trim_end_matches(')')),
+            None => (prefix, ""),
+        };
+        if !kind.is_empty() && kind.chars().all(|c| c.is_ascii_lowercase()) {
+            return (kind, scope, description.trim());
+        }
+    }
+    ("", "", subject.trim())
+}
+
+/// Drop what an empty placeholder leaves behind: `()`, `[]`, a leading `: `.
+fn tidy(subject: &str) -> String {
+    let subject = subject.replace("()", "").replace("[]", "");
+    let subject = subject.trim_start_matches([':', '-', ' ']);
+    subject.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn run(action: &TemplateAction) -> Result<()> {
+    match action {
+        TemplateAction::List => list(),
+        TemplateAction::Add { name, fields, repo } => {
+            if all(&config::get()).contains_key(name) {
+                bail!(
+                    "a template named {name} exists; change it with `scommit template edit {name}`"
+                );
+            }
+            if fields.is_empty() {
+                bail!("give the template at least one of --subject, --body or --prompt");
+            }
+            let path = config_path(*repo)?;
+            update(&path, |doc| set_fields(doc, name, fields))?;
+            say!("Added template {name} to {}", path.display());
+            Ok(())
+        }
+        TemplateAction::Edit { name, fields, repo } => {
+            let Some(current) = all(&config::get()).get(name).cloned() else {
+                bail!(
+                    "there is no template named {name}; add it with `scommit template add {name}`"
+                );
+            };
+            let path = config_path(*repo)?;
+            if fields.is_empty() {
+                // Built-ins get copied out first, so there is something to edit.
+                let copied = Fields {
+                    subject: current.subject,
+                    body: current.body,
+                    prompt: current.prompt,
+                };
+                update(&path, |doc| {
+                    if doc.get("templates").and_then(|t| t.get(name)).is_none() {
+                        set_fields(doc, name, &copied);
+                    }
+                })?;
+                launch_editor(&[&path.to_string_lossy()])?;
+                Config::check_file(&path)?;
+            } else {
+                update(&path, |doc| set_fields(doc, name, fields))?;
+            }
+            say!("Updated template {name} in {}", path.display());
+            Ok(())
+        }
+        TemplateAction::Use { name, repo } => {
+            if !all(&config::get()).contains_key(name) {
+                bail!("there is no template named {name}; see `scommit template list`");
+            }
+            let path = config_path(*repo)?;
+            update(&path, |doc| {
+                let message = doc
+                    .entry("message")
+                    .or_insert_with(toml_edit::table)
+                    .as_table_like_mut();
+                if let Some(message) = message {
+                    message.insert("template", toml_edit::value(name.as_str()));
+                }
+            })?;
+            say!(
+                "Messages now follow the {name} template ({})",
+                path.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+fn list() -> Result<()> {
+    let config = config::get();
+    let builtin = builtin();
+    let active = config.message.template.as_deref();
+    for (name, template) in all(&config) {
+        let marker = if Some(name.as_str()) == active {
+            "*"
+        } else {
+            " "
+        };
+        let origin = match builtin.get(&name) {
+            Some(original) if *original == template => "built-in",
+            Some(_) => "built-in, changed in config",
+            None => "config",
+        };
+        println!(
+            "{marker} {name:<14} subject {:<26} ({origin})",
+            template.subject.as_deref().unwrap_or("as generated")
+        );
+    }
+    Ok(())
+}
+
+fn config_path(repo: bool) -> Result<PathBuf> {
+    if repo {
+        return Ok(repo_root()?.join(config::REPO_CONFIG));
+    }
+    config::user_config_path().context("can't find the user config directory (HOME isn't set)")
+}
+
+/// Edit the config file in place, keeping its comments and layout, and refuse to save
+/// anything scommit couldn't load.
+fn update(path: &PathBuf, change: impl FnOnce(&mut toml_edit::DocumentMut)) -> Result<()> {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+    };
+    let mut doc: toml_edit::DocumentMut = raw
+        .parse()
+        .with_context(|| format!("parsing {}", path.display()))?;
+    change(&mut doc);
+    let updated = doc.to_string();
+    toml::from_str::<Config>(&updated).context("the template would make the config invalid")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+    fs::write(path, updated).with_context(|| format!("writing {}", path.display()))
+}
+
+fn set_fields(doc: &mut toml_edit::DocumentMut, name: &str, fields: &Fields) {
+    let templates = doc.entry("templates").or_insert_with(|| {
+        let mut table = toml_edit::Table::new();
+        table.set_implicit(true);
+        toml_edit::Item::Table(table)
+    });
+    let Some(template) = templates
+        .as_table_like_mut()
+        .map(|t| t.entry(name).or_insert_with(toml_edit::table))
+        .and_then(|t| t.as_table_like_mut())
+    else {
+        return;
+    };
+    for (key, value) in [
+        ("subject", &fields.subject),
+        ("body", &fields.body),
+        ("prompt", &fields.prompt),
+    ] {
+        if let Some(value) = value {
+            template.insert(key, toml_edit::value(value.replace("\\n", "\n")));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_reshape_generated_messages() {
+        let templates = builtin();
+        let body = "- add the parser\n";
+        assert_eq!(
+            render(
+                &templates["ticket"],
+                "feat(cli): add parser",
+                body,
+                "feature/PROJ-7-parser",
+                "PROJ-7"
+            ),
+            (
+                "PROJ-7: Add parser".to_string(),
+                "- add the parser\n\nRefs: PROJ-7\n".to_string()
+            )
+        );
+        // Without a ticket, its placeholders leave nothing behind.
+        assert_eq!(
+            render(
+                &templates["ticket"],
+                "feat(cli): add parser",
+                body,
+                "main",
+                ""
+            ),
+            ("Add parser".to_string(), "- add the parser\n".to_string())
+        );
+        assert_eq!(
+            render(&templates["plain"], "Update README.md", body, "main", "").0,
+            "Update README.md"
+        );
+        let scoped = Template {
+            subject: Some("[{scope}] {description}".to_string()),
+            ..Template::default()
+        };
+        assert_eq!(
+            render(&scoped, "fix: handle empty input", body, "main", "").0,
+            "handle empty input"
+        );
+        assert_eq!(
+            render(
+                &templates["conventional"],
+                "fix!: drop v1 API",
+                body,
+                "main",
+                ""
+            ),
+            ("fix!: drop v1 API".to_string(), body.to_string())
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! `--timings`: how long each phase of the run took, to find out whether the time goes
+//! on staging a big tree, waiting for the AI provider or pushing over a slow link.
+
+use once_cell::sync::{Lazy, OnceCell};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+static ENABLED: OnceCell<bool> = OnceCell::new();
+static SPENT: Lazy<Mutex<[Duration; PHASES.len()]>> =
+    Lazy::new(|| Mutex::new([Duration::ZERO; PHASES.len()]));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Staging,
+    Diff,
+    Ai,
+    Commit,
+    Rebase,
+    Push,
+}
+
+/// In the order a run goes through them.
+const PHASES: [Phase; 6] = [
+    Phase::Staging,
+    Phase::Diff,
+    Phase::Ai,
+    Phase::Commit,
+    Phase::Rebase,
+    Phase::Push,
+];
+
+impl Phase {
+    fn label(self) -> &'static str {
+        match self {
+            Phase::Staging => "staging",
+            Phase::Diff => "diff collection",
+            Phase::Ai => "AI call",
+            Phase::Commit => "commit",
+            Phase::Rebase => "rebase",
+            Phase::Push => "push",
+        }
+    }
+}
+
+pub fn init(enabled: bool) {
+    ENABLED.get_or_init(|| enabled);
+}
+
+fn enabled() -> bool {
+    ENABLED.get().copied().unwrap_or(false)
+}
+
+/// Adds the time until it is dropped to its phase; a phase that runs more than once
+/// (the diff is read several times) adds up.
+pub struct Timer {
+    phase: Phase,
+    started: Instant,
+}
+
+impl Timer {
+    pub fn start(phase: Phase) -> Timer {
+        Timer {
+            phase,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Ok(mut spent) = SPENT.lock() {
+            spent[self.phase as usize] += self.started.elapsed();
+        }
+    }
+}
+
+/// Print the breakdown to stderr, whether or not the run succeeded.
+pub fn report(total: Duration) {
+    if !enabled() {
+        return;
+    }
+    let Ok(spent) = SPENT.lock() else {
+        return;
+    };
+    eprintln!("\n{}", render(&spent, total));
+}
+
+/// One line per phase that ran, then whatever is left over (checks, prompts, hooks
+/// outside the commit) and the total.
+fn render(spent: &[Duration; PHASES.len()], total: Duration) -> String {
+    let mut rows: Vec<(&str, Duration)> = PHASES
+        .iter()
+        .zip(spent)
+        .filter(|(_, d)| !d.is_zero())
+        .map(|(phase, d)| (phase.label(), *d))
+        .collect();
+    rows.push(("other", total.saturating_sub(spent.iter().sum())));
+    rows.push(("total", total));
+    let mut lines = vec!["Timings:".to_string()];
+    for (label, d) in rows {
+        lines.push(format!("  {label:<16}{:>8.2}s", d.as_secs_f64()));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_lists_phases_that_ran_and_the_remainder() {
+        let mut spent = [Duration::ZERO; PHASES.len()];
+        spent[Phase::Staging as usize] = Duration::from_millis(400);
+        spent[Phase::Ai as usize] = Duration::from_millis(2250);
+        spent[Phase::Push as usize] = Duration::from_secs(1);
+        assert_eq!(
+            render(&spent, Duration::from_millis(3750)),
+            "Timings:\n  \
+             staging             0.40s\n  \
+             AI call             2.25s\n  \
+             push                1.00s\n  \
+             other               0.10s\n  \
+             total               3.75s"
+        );
+    }
+}
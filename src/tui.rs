@@ -0,0 +1,569 @@
+//! `scommit --tui`: a full-screen view of the commit about to be made, with the staged
+//! files (toggle what goes in), a diff preview and the editable message.
+
+use anyhow::{Context, Result, bail};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashMap;
+use std::io::IsTerminal;
+
+use crate::completions::KNOWN_MODELS;
+use crate::repo_state::split_message;
+use crate::split::{group_paths, stage_from_tree};
+use crate::staging::{self, SetAside};
+use crate::{
+    CATEGORY_NAMES, Cli, FileChange, FileStatus, ai_commit_message, ai_model, build_body,
+    build_commit_message, compute_stats, git_output,
+};
+
+const HELP: &str = "Tab focus · ↑↓ move · Space include · ^R regenerate · ^T model · ^S commit · ^P commit+push · Esc quit";
+
+pub enum Outcome {
+    /// Commit with this message; `excluded` re-stages the files toggled off once the
+    /// commit is made.
+    Commit {
+        subject: String,
+        body: String,
+        push: bool,
+        excluded: SetAside,
+    },
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Files,
+    Diff,
+    Message,
+}
+
+/// Take over the terminal until the user commits or quits. Files toggled off are taken
+/// out of the index while the TUI runs, so regenerating only describes what's included.
+pub fn run(cli: &Cli, changes: &[FileChange], subject: String, body: String) -> Result<Outcome> {
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        bail!("--tui needs a terminal");
+    }
+    let mut app = App {
+        cli,
+        changes,
+        included: vec![true; changes.len()],
+        list: ListState::default().with_selected(Some(0)),
+        focus: Focus::Files,
+        diffs: HashMap::new(),
+        diff_scroll: 0,
+        message: Editor::new(&format!("{subject}\n\n{body}")),
+        model: ai_model(cli),
+        status: String::new(),
+        full_tree: git_output(&["write-tree"])?.trim().to_string(),
+        base: staging::unstaged_base(),
+    };
+    let mut terminal = ratatui::try_init().context("starting the terminal UI")?;
+    let result = app.event_loop(&mut terminal);
+    ratatui::restore();
+
+    let excluded = SetAside::new(app.full_tree.clone(), app.excluded_paths());
+    match result? {
+        Some(push) => {
+            let Some((subject, body)) = split_message(&app.message.text()) else {
+                bail!("empty commit message");
+            };
+            Ok(Outcome::Commit {
+                subject,
+                body,
+                push,
+                excluded,
+            })
+        }
+        // Dropping `excluded` puts the toggled-off files back in the index.
+        None => Ok(Outcome::Quit),
+    }
+}
+
+struct App<'a> {
+    cli: &'a Cli,
+    changes: &'a [FileChange],
+    included: Vec<bool>,
+    list: ListState,
+    focus: Focus,
+    diffs: HashMap<String, String>,
+    diff_scroll: u16,
+    message: Editor,
+    model: Option<String>,
+    status: String,
+    /// The index as it was when the TUI opened; toggling a file back on restores it.
+    full_tree: String,
+    base: &'static str,
+}
+
+impl App<'_> {
+    /// Returns Some(push) to commit, None to quit.
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<Option<bool>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            self.status.clear();
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+            match (ctrl, key.code) {
+                (true, KeyCode::Char('c')) => return Ok(None),
+                (true, KeyCode::Char('s')) if self.ready_to_commit() => return Ok(Some(false)),
+                (true, KeyCode::Char('p')) if self.ready_to_commit() => return Ok(Some(true)),
+                (true, KeyCode::Char('r')) => self.regenerate(terminal)?,
+                (true, KeyCode::Char('t')) => self.next_model(),
+                (_, KeyCode::Tab) => self.focus = self.focus.next(),
+                (_, KeyCode::BackTab) => self.focus = self.focus.next().next(),
+                (_, KeyCode::Esc) if self.focus == Focus::Message => self.focus = Focus::Files,
+                (_, KeyCode::Esc) => return Ok(None),
+                _ => match self.focus {
+                    Focus::Files => {
+                        if let Some(push) = self.files_key(key, terminal)? {
+                            return Ok(push);
+                        }
+                    }
+                    Focus::Diff => self.diff_key(key),
+                    Focus::Message => self.message.key(key),
+                },
+            }
+        }
+    }
+
+    /// Plain-letter shortcuts work in the file list, where nothing is typed.
+    fn files_key(
+        &mut self,
+        key: KeyEvent,
+        terminal: &mut DefaultTerminal,
+    ) -> Result<Option<Option<bool>>> {
+        let selected = self.list.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.select(selected.saturating_sub(1)),
+            KeyCode::Down | KeyCode::Char('j') => self.select(selected + 1),
+            KeyCode::Char(' ') => self.toggle(selected)?,
+            KeyCode::Enter | KeyCode::Char('e') => self.focus = Focus::Message,
+            KeyCode::Char('r') => self.regenerate(terminal)?,
+            KeyCode::Char('m') => self.next_model(),
+            KeyCode::Char('c') if self.ready_to_commit() => return Ok(Some(Some(false))),
+            KeyCode::Char('p') if self.ready_to_commit() => return Ok(Some(Some(true))),
+            KeyCode::Char('q') => return Ok(Some(None)),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn diff_key(&mut self, key: KeyEvent) {
+        self.diff_scroll = match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.diff_scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.diff_scroll.saturating_add(1),
+            KeyCode::PageUp => self.diff_scroll.saturating_sub(20),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.diff_scroll.saturating_add(20),
+            KeyCode::Home => 0,
+            _ => self.diff_scroll,
+        };
+    }
+
+    fn select(&mut self, index: usize) {
+        if index < self.changes.len() {
+            self.list.select(Some(index));
+            self.diff_scroll = 0;
+        }
+    }
+
+    fn toggle(&mut self, index: usize) -> Result<()> {
+        let Some(change) = self.changes.get(index) else {
+            return Ok(());
+        };
+        let include = !self.included[index];
+        let tree = if include {
+            self.full_tree.as_str()
+        } else {
+            self.base
+        };
+        stage_from_tree(tree, &group_paths(std::slice::from_ref(change)))?;
+        self.included[index] = include;
+        self.status = "The message may be out of date; ^R regenerates it.".to_string();
+        Ok(())
+    }
+
+    fn included_changes(&self) -> Vec<FileChange> {
+        self.changes
+            .iter()
+            .zip(&self.included)
+            .filter(|(_, included)| **included)
+            .map(|(change, _)| change.clone())
+            .collect()
+    }
+
+    fn excluded_paths(&self) -> Vec<String> {
+        let excluded: Vec<FileChange> = self
+            .changes
+            .iter()
+            .zip(&self.included)
+            .filter(|(_, included)| !**included)
+            .map(|(change, _)| change.clone())
+            .collect();
+        group_paths(&excluded)
+    }
+
+    fn ready_to_commit(&mut self) -> bool {
+        if !self.included.contains(&true) {
+            self.status = "Nothing included; toggle a file on with Space.".to_string();
+            return false;
+        }
+        if split_message(&self.message.text()).is_none() {
+            self.status = "The message is empty.".to_string();
+            return false;
+        }
+        true
+    }
+
+    /// Write a new message for the included files, with the current model if AI is on.
+    fn regenerate(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let changes = self.included_changes();
+        if changes.is_empty() {
+            self.status = "Nothing included to describe.".to_string();
+            return Ok(());
+        }
+        let stats = compute_stats(&changes);
+        let (cli, model) = (self.cli, self.model.clone());
+        let (subject, body) = match (&cli.message, &model) {
+            (Some(subject), _) => (subject.clone(), build_body(&changes, &stats)),
+            (None, Some(model)) => {
+                self.status = format!("Generating with {model}...");
+                terminal.draw(|frame| self.draw(frame))?;
+                match ai_commit_message(&changes, &stats, model, cli.privacy, None) {
+                    Ok(Some(message)) => {
+                        self.status = format!("Generated with {model}.");
+                        message
+                    }
+                    Ok(None) => build_commit_message(&changes, &stats),
+                    Err(e) => {
+                        self.status = format!("AI generation failed ({e}); used the heuristic.");
+                        build_commit_message(&changes, &stats)
+                    }
+                }
+            }
+            (None, None) => build_commit_message(&changes, &stats),
+        };
+        self.message = Editor::new(&format!("{subject}\n\n{body}"));
+        Ok(())
+    }
+
+    fn next_model(&mut self) {
+        let Some(current) = &self.model else {
+            self.status = "AI generation is off (--no-ai or no OPENAI_API_KEY).".to_string();
+            return;
+        };
+        let next = KNOWN_MODELS
+            .iter()
+            .position(|m| m == current)
+            .map_or(0, |i| (i + 1) % KNOWN_MODELS.len());
+        self.model = Some(KNOWN_MODELS[next].to_string());
+        self.status = format!("Model: {}. Regenerate (^R) to use it.", KNOWN_MODELS[next]);
+    }
+
+    fn diff_for(&mut self, index: usize) -> &str {
+        let change = &self.changes[index];
+        let diff = self.diffs.entry(change.path.clone()).or_insert_with(|| {
+            let paths = group_paths(std::slice::from_ref(change));
+            let mut args = vec![
+                "--literal-pathspecs",
+                "diff",
+                "--no-color",
+                "-M",
+                self.base,
+                &self.full_tree,
+                "--",
+            ];
+            args.extend(paths.iter().map(String::as_str));
+            git_output(&args).unwrap_or_else(|e| format!("(no diff: {e})"))
+        });
+        diff.as_str()
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [top, message, help] = Layout::vertical([
+            Constraint::Min(8),
+            Constraint::Length(10),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [files, diff] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)]).areas(top);
+
+        self.draw_files(frame, files);
+        self.draw_diff(frame, diff);
+        self.draw_message(frame, message);
+        let footer = if self.status.is_empty() {
+            Span::styled(HELP, Style::new().add_modifier(Modifier::DIM))
+        } else {
+            Span::styled(self.status.as_str(), Style::new().fg(Color::Yellow))
+        };
+        frame.render_widget(Paragraph::new(Line::from(footer)), help);
+    }
+
+    fn block(&self, title: String, pane: Focus) -> Block<'static> {
+        let style = if self.focus == pane {
+            Style::new().fg(Color::Cyan)
+        } else {
+            Style::new().add_modifier(Modifier::DIM)
+        };
+        Block::bordered().title(title).border_style(style)
+    }
+
+    fn draw_files(&mut self, frame: &mut Frame, area: Rect) {
+        let included = self.included.iter().filter(|i| **i).count();
+        let items: Vec<ListItem> = self
+            .changes
+            .iter()
+            .zip(&self.included)
+            .map(|(change, included)| {
+                let (status, color) = match change.status {
+                    FileStatus::Added => ("A", Color::Green),
+                    FileStatus::Modified => ("M", Color::Yellow),
+                    FileStatus::Deleted => ("D", Color::Red),
+                    FileStatus::Renamed { .. } => ("R", Color::Yellow),
+                    FileStatus::Copied { .. } => ("C", Color::Green),
+                    FileStatus::TypeChanged => ("T", Color::Yellow),
+                };
+                let category = CATEGORY_NAMES
+                    .get(&change.category)
+                    .copied()
+                    .unwrap_or("other");
+                let line = Line::from(vec![
+                    Span::raw(if *included { "[x] " } else { "[ ] " }),
+                    Span::styled(status, Style::new().fg(color)),
+                    Span::raw(format!(" {} ", change.path)),
+                    Span::styled(format!("+{}", change.added), Style::new().fg(Color::Green)),
+                    Span::raw(" "),
+                    Span::styled(format!("-{}", change.deleted), Style::new().fg(Color::Red)),
+                    Span::styled(
+                        format!(" [{category}]"),
+                        Style::new().add_modifier(Modifier::DIM),
+                    ),
+                ]);
+                let style = if *included {
+                    Style::new()
+                } else {
+                    Style::new().add_modifier(Modifier::DIM)
+                };
+                ListItem::new(line).style(style)
+            })
+            .collect();
+        let title = format!(" Files {included}/{} ", self.changes.len());
+        let list = List::new(items)
+            .block(self.block(title, Focus::Files))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.list);
+    }
+
+    fn draw_diff(&mut self, frame: &mut Frame, area: Rect) {
+        let index = self.list.selected().unwrap_or(0);
+        let (title, lines) = match self.changes.get(index) {
+            Some(change) => {
+                let title = format!(" {} ", change.path);
+                let lines: Vec<Line> = self
+                    .diff_for(index)
+                    .lines()
+                    .map(|line| {
+                        let style = if line.starts_with("@@") {
+                            Style::new().fg(Color::Cyan)
+                        } else if line.starts_with('+') && !line.starts_with("+++") {
+                            Style::new().fg(Color::Green)
+                        } else if line.starts_with('-') && !line.starts_with("---") {
+                            Style::new().fg(Color::Red)
+                        } else {
+                            Style::new()
+                        };
+                        Line::styled(line.to_string(), style)
+                    })
+                    .collect();
+                (title, lines)
+            }
+            None => (" Diff ".to_string(), Vec::new()),
+        };
+        let diff = Paragraph::new(lines)
+            .block(self.block(title, Focus::Diff))
+            .scroll((self.diff_scroll, 0));
+        frame.render_widget(diff, area);
+    }
+
+    fn draw_message(&mut self, frame: &mut Frame, area: Rect) {
+        let source = match (&self.cli.message, &self.model) {
+            (Some(_), _) => "--message".to_string(),
+            (None, Some(model)) => format!("model {model}"),
+            (None, None) => "heuristic".to_string(),
+        };
+        let title = format!(" Message ({source}) ");
+        let height = area.height.saturating_sub(2) as usize;
+        let scroll = self.message.row.saturating_sub(height.saturating_sub(1));
+        let lines: Vec<Line> = self
+            .message
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                if i == 0 {
+                    Line::styled(line.clone(), Style::new().add_modifier(Modifier::BOLD))
+                } else {
+                    Line::raw(line.clone())
+                }
+            })
+            .collect();
+        let paragraph = Paragraph::new(lines)
+            .block(self.block(title, Focus::Message))
+            .scroll((scroll as u16, 0));
+        frame.render_widget(paragraph, area);
+        if self.focus == Focus::Message {
+            frame.set_cursor_position(Position::new(
+                area.x + 1 + self.message.col as u16,
+                area.y + 1 + (self.message.row - scroll) as u16,
+            ));
+        }
+    }
+}
+
+impl Focus {
+    fn next(self) -> Focus {
+        match self {
+            Focus::Files => Focus::Diff,
+            Focus::Diff => Focus::Message,
+            Focus::Message => Focus::Files,
+        }
+    }
+}
+
+/// A small multi-line text buffer for the message pane; `col` counts characters.
+#[derive(Debug, Default)]
+struct Editor {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+}
+
+impl Editor {
+    fn new(text: &str) -> Editor {
+        let mut lines: Vec<String> = text.trim_end().lines().map(str::to_string).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        Editor {
+            lines,
+            row: 0,
+            col: 0,
+        }
+    }
+
+    fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    fn byte_index(&self) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices()
+            .nth(self.col)
+            .map_or(line.len(), |(i, _)| i)
+    }
+
+    fn line_chars(&self, row: usize) -> usize {
+        self.lines[row].chars().count()
+    }
+
+    fn key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char(c) => {
+                let at = self.byte_index();
+                self.lines[self.row].insert(at, c);
+                self.col += 1;
+            }
+            KeyCode::Enter => {
+                let at = self.byte_index();
+                let rest = self.lines[self.row].split_off(at);
+                self.row += 1;
+                self.lines.insert(self.row, rest);
+                self.col = 0;
+            }
+            KeyCode::Backspace if self.col > 0 => {
+                self.col -= 1;
+                let at = self.byte_index();
+                self.lines[self.row].remove(at);
+            }
+            KeyCode::Backspace if self.row > 0 => {
+                let line = self.lines.remove(self.row);
+                self.row -= 1;
+                self.col = self.line_chars(self.row);
+                self.lines[self.row].push_str(&line);
+            }
+            KeyCode::Delete if self.col < self.line_chars(self.row) => {
+                let at = self.byte_index();
+                self.lines[self.row].remove(at);
+            }
+            KeyCode::Delete if self.row + 1 < self.lines.len() => {
+                let next = self.lines.remove(self.row + 1);
+                self.lines[self.row].push_str(&next);
+            }
+            KeyCode::Left if self.col > 0 => self.col -= 1,
+            KeyCode::Left if self.row > 0 => {
+                self.row -= 1;
+                self.col = self.line_chars(self.row);
+            }
+            KeyCode::Right if self.col < self.line_chars(self.row) => self.col += 1,
+            KeyCode::Right if self.row + 1 < self.lines.len() => {
+                self.row += 1;
+                self.col = 0;
+            }
+            KeyCode::Up if self.row > 0 => {
+                self.row -= 1;
+                self.col = self.col.min(self.line_chars(self.row));
+            }
+            KeyCode::Down if self.row + 1 < self.lines.len() => {
+                self.row += 1;
+                self.col = self.col.min(self.line_chars(self.row));
+            }
+            KeyCode::Home => self.col = 0,
+            KeyCode::End => self.col = self.line_chars(self.row),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(editor: &mut Editor, codes: &[KeyCode]) {
+        for code in codes {
+            editor.key(KeyEvent::new(*code, KeyModifiers::NONE));
+        }
+    }
+
+    #[test]
+    fn editor_inserts_splits_and_joins_lines() {
+        let mut editor = Editor::new("fix: typo\n\n- body\n");
+        press(&mut editor, &[KeyCode::End, KeyCode::Char('s')]);
+        assert_eq!(editor.text(), "fix: typos\n\n- body");
+
+        press(
+            &mut editor,
+            &[
+                KeyCode::Down,
+                KeyCode::Down,
+                KeyCode::Home,
+                KeyCode::Backspace,
+            ],
+        );
+        assert_eq!(editor.text(), "fix: typos\n- body");
+        assert_eq!((editor.row, editor.col), (1, 0));
+
+        press(&mut editor, &[KeyCode::Right, KeyCode::Enter]);
+        assert_eq!(editor.text(), "fix: typos\n-\n body");
+        press(&mut editor, &[KeyCode::Backspace, KeyCode::Delete]);
+        assert_eq!(editor.text(), "fix: typos\n-body");
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crate::output::{self, say};
+use crate::session;
+use crate::{Cli, confirm, git, git_output, git_path, log_command, upstream_branch};
+
+fn record_path() -> Result<PathBuf> {
+    git_path("scommit/last-run")
+}
+
+fn head() -> Option<String> {
+    git_output(&["rev-parse", "--verify", "--quiet", "HEAD"])
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Called before each commit; only the first call in a run captures HEAD, into the
+/// run's report so each `scommit serve` request starts afresh.
+pub fn note_commit_start() {
+    if output::inspect(|r| r.head_before.is_none()).unwrap_or(false) {
+        let before = head().unwrap_or_default();
+        output::record(|r| r.head_before = Some(before));
+    }
+}
+
+/// Called after each commit so `scommit undo` knows the span this run created.
+pub fn record_commit() -> Result<()> {
+    let before = output::inspect(|r| r.head_before.clone()).flatten();
+    let (Some(before), Some(after)) = (before, head()) else {
+        return Ok(());
+    };
+    let path = record_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+    fs::write(&path, format!("before {before}\nafter {after}\n"))
+        .with_context(|| format!("writing {}", path.display()))
+}
+
+fn parse_record(raw: &str) -> Option<(String, String)> {
+    let mut before = None;
+    let mut after = None;
+    for line in raw.lines() {
+        match line.split_once(' ') {
+            Some(("before", sha)) => before = Some(sha.trim().to_string()),
+            Some(("after", sha)) => after = Some(sha.trim().to_string()),
+            _ => {}
+        }
+    }
+    Some((before?, after?))
+}
+
+/// `scommit undo`: put the last run's commits back into the index. Published commits
+/// are never rewritten; the revert path is offered instead.
+pub fn run(cli: &Cli) -> Result<()> {
+    let path = record_path()?;
+    let raw = fs::read_to_string(&path).unwrap_or_default();
+    let Some((before, after)) = parse_record(&raw) else {
+        bail!("no scommit run recorded in this repository; nothing to undo");
+    };
+    if head().as_deref() != Some(after.as_str()) {
+        bail!(
+            "HEAD has moved since the last scommit run (expected {}); undo it by hand with git reset --soft",
+            short(&after)
+        );
+    }
+
+    let count = if before.is_empty() {
+        git_output(&["rev-list", "--count", "HEAD"])?
+    } else {
+        git_output(&["rev-list", "--count", &format!("{before}..HEAD")])?
+    };
+    let count = count.trim().parse::<u32>().unwrap_or(1);
+
+    if let Some(upstream) = upstream_branch()?
+        && is_published(&after, &upstream)
+    {
+        return offer_revert(cli, &before, &upstream, count);
+    }
+
+    if cli.dry_run {
+        say!("DRY RUN\nWould soft-reset {count} commit(s); their changes would stay staged.");
+        return Ok(());
+    }
+    if before.is_empty() {
+        // The run created the branch's first commit: back to an unborn branch.
+        git(&["update-ref", "-d", "HEAD"])?;
+    } else {
+        git(&["reset", "--soft", &before])?;
+    }
+    fs::remove_file(&path).ok();
+    say!("Undid {count} commit(s); the changes are staged again.");
+    Ok(())
+}
+
+fn is_published(sha: &str, upstream: &str) -> bool {
+    let mut cmd = session::command("git");
+    cmd.args(["merge-base", "--is-ancestor", sha, upstream]);
+    let started = Instant::now();
+    let status = cmd.status().ok();
+    log_command(&cmd, status, started);
+    status.is_some_and(|s| s.success())
+}
+
+fn offer_revert(cli: &Cli, before: &str, upstream: &str, count: u32) -> Result<()> {
+    say!(
+        "The last scommit run ({count} commit(s)) is already on {upstream}; resetting would rewrite published history."
+    );
+    let range = if before.is_empty() {
+        "HEAD".to_string()
+    } else {
+        format!("{before}..HEAD")
+    };
+    if cli.dry_run {
+        say!("DRY RUN\nWould offer: git revert --no-edit {range}");
+        return Ok(());
+    }
+    if !cli.yes
+        && !confirm(
+            "revert",
+            &format!("Create revert commit(s) with `git revert {range}`?"),
+        )?
+    {
+        say!("Nothing changed. To revert later: git revert {range} && git push");
+        return Ok(());
+    }
+    git(&["revert", "--no-edit", &range])?;
+    fs::remove_file(record_path()?).ok();
+    say!("Reverted; push with `git push` when ready.");
+    Ok(())
+}
+
+fn short(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_record_reads_span_and_unborn_base() {
+        assert_eq!(
+            parse_record("before abc\nafter def\n"),
+            Some(("abc".to_string(), "def".to_string()))
+        );
+        assert_eq!(
+            parse_record("before \nafter def\n"),
+            Some((String::new(), "def".to_string()))
+        );
+        assert_eq!(parse_record("after def\n"), None);
+    }
+}
@@ -0,0 +1,207 @@
+//! `scommit watch`: automatic local checkpoints. The worktree is polled; once it has been
+//! quiet for `[watch] quiet_seconds`, its state is committed with a generated `wip:`
+//! message to a separate branch (`wip/<branch>` by default). The checked-out branch, the
+//! index and the files are never touched, and nothing is pushed unless asked.
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::Path;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::output::say;
+use crate::reword::CommitView;
+use crate::session;
+use crate::{
+    Cli, build_commit_message, collect_staged_changes, commit_message_text, compose_message,
+    compute_stats, config, current_branch, default_remote, git_output, git_path, secrets,
+    staged_added_lines,
+};
+
+/// How often the worktree is looked at.
+const POLL: Duration = Duration::from_secs(2);
+
+pub fn run(cli: &Cli, quiet: Option<u64>, once: bool, push: bool) -> Result<()> {
+    let config = &config::get().watch;
+    let quiet = Duration::from_secs(quiet.unwrap_or(config.quiet_seconds));
+    let push = push || config.push;
+    let index = git_path("scommit/watch-index")?;
+    if git_output(&["rev-parse", "--verify", "--quiet", "HEAD"]).is_err() {
+        bail!("scommit watch checkpoints on top of HEAD; make a first commit");
+    }
+    if once {
+        let tree = snapshot(&index)?;
+        if !checkpoint(cli, &tree, push)? {
+            say!("Nothing changed since the last checkpoint.");
+        }
+        return Ok(());
+    }
+
+    say!(
+        "Watching for changes; checkpoints go to {} after {}s of quiet (Ctrl-C to stop).",
+        wip_branch(&config.branch)?,
+        quiet.as_secs()
+    );
+    let mut seen = snapshot(&index)?;
+    let mut changed_at = Instant::now();
+    let mut saved: Option<String> = None;
+    loop {
+        thread::sleep(POLL);
+        let tree = match snapshot(&index) {
+            Ok(tree) => tree,
+            // A git command racing ours (e.g. a commit holding index.lock); try again.
+            Err(e) => {
+                tracing::debug!(%e, "snapshot failed");
+                continue;
+            }
+        };
+        if tree != seen {
+            seen = tree;
+            changed_at = Instant::now();
+        }
+        if saved.as_deref() != Some(seen.as_str()) && changed_at.elapsed() >= quiet {
+            if let Err(e) = checkpoint(cli, &seen, push) {
+                eprintln!("Checkpoint failed: {e:#}");
+            }
+            saved = Some(seen.clone());
+        }
+    }
+}
+
+/// The worktree as a tree object, via a private index so the real one is left alone.
+/// Untracked files are included; ignored ones aren't.
+pub fn snapshot(index: &Path) -> Result<String> {
+    let git = |args: &[&str]| -> Result<String> {
+        let out = session::command("git")
+            .args(args)
+            .env("GIT_INDEX_FILE", index)
+            .output()
+            .with_context(|| format!("running git {args:?}"))?;
+        if !out.status.success() {
+            bail!("git {args:?} failed");
+        }
+        Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+    };
+    if !index.exists() {
+        if let Some(dir) = index.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        // Start from HEAD's entries so the first `add` only hashes what changed.
+        git(&["read-tree", "HEAD"])?;
+    }
+    git(&["add", "--all", "--", "."])?;
+    git(&["write-tree"])
+}
+
+/// Commit `tree` to the wip branch unless it's already there (or matches HEAD). Returns
+/// whether a checkpoint was made.
+fn checkpoint(cli: &Cli, tree: &str, push: bool) -> Result<bool> {
+    let branch = wip_branch(&config::get().watch.branch)?;
+    let refname = format!("refs/heads/{branch}");
+    let head = rev(&["rev-parse", "HEAD"])?;
+    let tip = rev(&["rev-parse", "--verify", "--quiet", &refname]).ok();
+    // Checkpoints stack up while HEAD stays put; once the user commits, the next one
+    // starts from the new HEAD and keeps the old run reachable as a second parent.
+    let parents = match &tip {
+        Some(tip) if git_output(&["merge-base", "--is-ancestor", &head, tip]).is_ok() => {
+            vec![tip.clone()]
+        }
+        Some(tip) => vec![head, tip.clone()],
+        None => vec![head],
+    };
+    if rev(&["rev-parse", &format!("{}^{{tree}}", parents[0])])? == tree {
+        return Ok(false);
+    }
+
+    let (subject, body) = {
+        let _view = CommitView::enter(&parents[0], tree)?;
+        let changes = collect_staged_changes()?;
+        let stats = compute_stats(&changes);
+        // Same rule as the hooks: the diff only reaches the AI after the secrets check.
+        match secrets::check(&staged_added_lines()?, false) {
+            Ok(()) => compose_message(cli, &changes, &stats, None)?,
+            Err(_) => build_commit_message(&changes, &stats),
+        }
+    };
+    let subject = wip_subject(&subject);
+    if cli.dry_run {
+        say!("Would checkpoint to {branch}: {subject}");
+        return Ok(true);
+    }
+
+    let msg_path = git_path("scommit/WATCH_MSG")?;
+    fs::write(&msg_path, commit_message_text(&subject, &body))?;
+    let mut args = vec!["commit-tree", tree];
+    for parent in &parents {
+        args.extend(["-p", parent.as_str()]);
+    }
+    let msg_arg = msg_path.to_string_lossy();
+    args.extend(["-F", &msg_arg]);
+    let commit = rev(&args);
+    fs::remove_file(&msg_path).ok();
+    let commit = commit?;
+    let mut update = vec!["update-ref", "-m", "scommit watch", &refname, &commit];
+    if let Some(tip) = &tip {
+        update.push(tip);
+    }
+    git_output(&update)?;
+    say!(
+        "{} checkpoint {} on {branch}: {subject}",
+        chrono::Local::now().format("%H:%M:%S"),
+        &commit[..7]
+    );
+
+    if push {
+        match default_remote()? {
+            Some(remote) => {
+                git_output(&["push", "--quiet", &remote, &format!("{refname}:{refname}")])
+                    .with_context(|| format!("pushing {branch} to {remote}"))?;
+            }
+            None => eprintln!("No remote to push {branch} to; kept it local."),
+        }
+    }
+    Ok(true)
+}
+
+/// `[watch] branch` with `{branch}` filled in ("detached" on a detached HEAD).
+fn wip_branch(pattern: &str) -> Result<String> {
+    let current = current_branch()?.unwrap_or_else(|| "detached".to_string());
+    let branch = pattern.replace("{branch}", &current);
+    if branch == current {
+        bail!("[watch] branch must differ from the checked-out branch ({current})");
+    }
+    Ok(branch)
+}
+
+/// "wip: add parser" from "feat(cli): add parser"; other subjects just get the prefix.
+fn wip_subject(subject: &str) -> String {
+    let description = subject
+        .split_once(": ")
+        .filter(|(prefix, _)| !prefix.is_empty() && !prefix.contains(' '))
+        .map_or(subject, |(_, description)| description);
+    format!("wip: {description}")
+}
+
+fn rev(args: &[&str]) -> Result<String> {
+    Ok(git_output(args)?.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkpoint_subjects_drop_the_commit_type() {
+        assert_eq!(wip_subject("feat(cli): add parser"), "wip: add parser");
+        assert_eq!(
+            wip_subject("fix: handle empty input"),
+            "wip: handle empty input"
+        );
+        assert_eq!(
+            wip_subject("Update README: typo"),
+            "wip: Update README: typo"
+        );
+        assert_eq!(wip_subject("update README.md"), "wip: update README.md");
+    }
+}
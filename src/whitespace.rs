@@ -0,0 +1,90 @@
+//! Formatting-only changes, for `[diff] ignore_whitespace`: files whose staged content
+//! differs from HEAD only in whitespace and line breaks. A `cargo fmt` or `prettier` run
+//! is a `style:` commit, not the refactor its line counts suggest.
+
+use std::collections::HashSet;
+
+use crate::{FileChange, FileStatus, config, git_blobs};
+
+/// Larger files are assumed to have real changes rather than compared.
+const MAX_FILE_BYTES: usize = 1024 * 1024;
+
+/// Paths of the modified (or renamed) files whose changes are formatting only; empty
+/// unless the mode is on.
+pub fn formatting_only(changes: &[FileChange]) -> HashSet<String> {
+    if !config::get().diff.ignore_whitespace {
+        return HashSet::new();
+    }
+    let candidates: Vec<(&FileChange, &str)> = changes
+        .iter()
+        .filter_map(|c| match &c.status {
+            FileStatus::Modified => Some((c, c.path.as_str())),
+            FileStatus::Renamed { from, .. } => Some((c, from.as_str())),
+            _ => None,
+        })
+        .collect();
+    let specs: Vec<String> = candidates
+        .iter()
+        .flat_map(|(change, old)| [format!("HEAD:{old}"), format!(":{}", change.path)])
+        .collect();
+    let Ok(blobs) = git_blobs(&specs) else {
+        return HashSet::new();
+    };
+    candidates
+        .iter()
+        .zip(blobs.chunks_exact(2))
+        .filter(|(_, pair)| match pair {
+            [Some(old), Some(new)] => {
+                old.len().max(new.len()) <= MAX_FILE_BYTES && same_tokens(old, new)
+            }
+            _ => false,
+        })
+        .map(|((change, _), _)| change.path.clone())
+        .collect()
+}
+
+/// Equal once every whitespace byte is dropped.
+fn same_tokens(old: &[u8], new: &[u8]) -> bool {
+    let tokens = |text: &[u8]| {
+        text.iter()
+            .copied()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect::<Vec<u8>>()
+    };
+    old != new && tokens(old) == tokens(new)
+}
+
+/// "style: reformat a.rs & b.rs", for commits where every file is formatting only.
+pub fn subject(paths: &[&str]) -> String {
+    let names: Vec<String> = paths.iter().map(|p| crate::short_name(p)).collect();
+    let focus = match names.as_slice() {
+        [one] => one.clone(),
+        [a, b] => format!("{a} & {b}"),
+        _ => format!("{} files", names.len()),
+    };
+    format!("style: reformat {focus}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reflowed_code_is_formatting_only() {
+        assert!(same_tokens(
+            b"fn a(x: u8,\n     y: u8) {}\n",
+            b"fn a(x: u8, y: u8) {\n}\n"
+        ));
+        assert!(same_tokens(b"a\tb\r\n", b"a b\n"));
+        assert!(!same_tokens(b"fn a() {}\n", b"fn b() {}\n"));
+        assert!(!same_tokens(b"same\n", b"same\n"));
+        assert_eq!(
+            subject(&["src/a.rs", "src/b.rs"]),
+            "style: reformat a.rs & b.rs"
+        );
+        assert_eq!(
+            subject(&["a.rs", "b.rs", "c.rs"]),
+            "style: reformat 3 files"
+        );
+    }
+}